@@ -0,0 +1,113 @@
+//! Persistent cache of processed artifacts.
+//!
+//! Some imports are expensive – mesh optimization, texture compression – and redoing them at each
+//! launch slows iteration down. Set an `ArtifactCache` with `StoreOpt::set_artifact_cache` and
+//! loaders can store the result of their processing on disk, and get it back at the next run with
+//! `Storage::artifact_cache`:
+//!
+//! ```ignore
+//! let source = storage.read(key.as_path())?;
+//!
+//! if let Some(cache) = storage.artifact_cache() {
+//!   if let Some(artifact) = cache.get(key.as_path(), &source, "mesh-v2") {
+//!     return Ok(Mesh::from_optimized(&artifact).into());
+//!   }
+//! }
+//!
+//! let mesh = Mesh::optimize(&source);
+//!
+//! if let Some(cache) = storage.artifact_cache() {
+//!   let _ = cache.put(key.as_path(), &source, "mesh-v2", &mesh.to_bytes());
+//! }
+//! ```
+//!
+//! Artifacts are keyed by the path of their source, the hash of its content and the version of
+//! the loader – bump it whenever the processing changes, so that stale artifacts are not used.
+//! Artifacts are never evicted: clear the cache with `ArtifactCache::clear` when it grows too big.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// On-disk cache of processed artifacts.
+///
+/// See the documentation of the `artifact` module for further details.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArtifactCache {
+  dir: PathBuf,
+}
+
+impl ArtifactCache {
+  /// Create a cache storing its artifacts in a directory.
+  ///
+  /// The directory is created when the first artifact is stored.
+  pub fn new<P>(dir: P) -> Self
+  where P: AsRef<Path> {
+    ArtifactCache {
+      dir: dir.as_ref().to_owned(),
+    }
+  }
+
+  /// Directory the artifacts are stored in.
+  pub fn dir(&self) -> &Path {
+    &self.dir
+  }
+
+  /// Get the artifact processed from a source by a given version of a loader, if any.
+  pub fn get<P>(&self, source_path: P, source: &[u8], version: &str) -> Option<Vec<u8>>
+  where P: AsRef<Path> {
+    fs::read(self.artifact_path(source_path.as_ref(), source, version)).ok()
+  }
+
+  /// Store the artifact processed from a source by a given version of a loader.
+  pub fn put<P>(
+    &self,
+    source_path: P,
+    source: &[u8],
+    version: &str,
+    artifact: &[u8],
+  ) -> io::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let path = self.artifact_path(source_path.as_ref(), source, version);
+    let tmp_path = path.with_extension("tmp");
+
+    fs::create_dir_all(&self.dir)?;
+
+    // write aside first so that a crash never leaves a truncated artifact behind
+    fs::write(&tmp_path, artifact)?;
+    fs::rename(&tmp_path, &path)
+  }
+
+  /// Remove all the artifacts.
+  pub fn clear(&self) -> io::Result<()> {
+    match fs::remove_dir_all(&self.dir) {
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+      result => result,
+    }
+  }
+
+  /// Path of the artifact of a source.
+  fn artifact_path(&self, source_path: &Path, source: &[u8], version: &str) -> PathBuf {
+    let mut hash = fnv1a(FNV_OFFSET_BASIS, source_path.to_string_lossy().as_bytes());
+    hash = fnv1a(hash, &[0]);
+    hash = fnv1a(hash, &fnv1a(FNV_OFFSET_BASIS, source).to_le_bytes());
+    hash = fnv1a(hash, version.as_bytes());
+
+    self.dir.join(format!("{:016x}.artifact", hash))
+  }
+}
+
+/// Offset basis of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continue a 64-bit FNV-1a hash with some bytes.
+///
+/// Unlike the hashers of the standard library, it’s stable across builds, which is mandatory for
+/// artifacts to survive an update of the application.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+  bytes
+    .iter()
+    .fold(hash, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
@@ -0,0 +1,152 @@
+//! Resource cache backends.
+//!
+//! A `Storage` caches its resources in a *cache backend*. By default, `HashCache` is used, but you
+//! can provide your own backend – instrumented, bounded, etc. – by implementing `CacheBackend` and
+//! passing it to `StoreOpt::set_cache_backend`.
+//!
+//! Cached values are type-erased: they’re stored as `Box<Any>` and identified by a `CacheKey`,
+//! which is made of a `DepKey`, the `TypeId` of the resource and the `TypeId` of the method it was
+//! loaded with – a same file can back a resource per method.
+
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use key::DepKey;
+
+/// Key used to identify a value in a cache backend.
+#[derive(Clone, Debug)]
+pub struct CacheKey {
+  dep_key: DepKey,
+  type_id: TypeId,
+  method: TypeId,
+  method_name: &'static str,
+}
+
+impl CacheKey {
+  /// Create a cache key for a resource of type `T` living at `dep_key`, loaded with the default
+  /// method.
+  pub fn new<T>(dep_key: DepKey) -> Self
+  where T: 'static {
+    Self::with_method::<T, ()>(dep_key)
+  }
+
+  /// Create a cache key for a resource of type `T` living at `dep_key`, loaded with method `M`.
+  pub fn with_method<T, M>(dep_key: DepKey) -> Self
+  where
+    T: 'static,
+    M: 'static + ?Sized,
+  {
+    CacheKey {
+      dep_key,
+      type_id: TypeId::of::<T>(),
+      method: TypeId::of::<M>(),
+      method_name: type_name::<M>(),
+    }
+  }
+
+  /// Create a cache key for a resource living at `dep_key` whose type is given by `type_id`,
+  /// loaded with the method given by `method` and named `method_name`.
+  pub(crate) fn from_type_id(
+    dep_key: DepKey,
+    type_id: TypeId,
+    method: TypeId,
+    method_name: &'static str,
+  ) -> Self
+  {
+    CacheKey {
+      dep_key,
+      type_id,
+      method,
+      method_name,
+    }
+  }
+
+  /// Key of the resource.
+  pub fn dep_key(&self) -> &DepKey {
+    &self.dep_key
+  }
+
+  /// Type of the resource.
+  pub fn type_id(&self) -> TypeId {
+    self.type_id
+  }
+
+  /// Type of the method the resource was loaded with.
+  pub fn method(&self) -> TypeId {
+    self.method
+  }
+
+  /// Name of the method the resource was loaded with.
+  ///
+  /// Names are meant for diagnostics only: two methods might share the same name.
+  pub fn method_name(&self) -> &'static str {
+    self.method_name
+  }
+}
+
+// the name of the method is left out, as the type identifies it already
+impl PartialEq for CacheKey {
+  fn eq(&self, rhs: &Self) -> bool {
+    self.dep_key == rhs.dep_key && self.type_id == rhs.type_id && self.method == rhs.method
+  }
+}
+
+impl Eq for CacheKey {}
+
+impl Hash for CacheKey {
+  fn hash<H>(&self, state: &mut H)
+  where H: Hasher {
+    self.dep_key.hash(state);
+    self.type_id.hash(state);
+    self.method.hash(state);
+  }
+}
+
+/// Class of types that can be used to cache resources.
+pub trait CacheBackend {
+  /// Save a value in the cache, replacing any value previously stored at the same key.
+  fn save(&mut self, key: CacheKey, value: Box<Any>);
+
+  /// Get a value from the cache.
+  fn get(&self, key: &CacheKey) -> Option<&Any>;
+
+  /// Remove a value from the cache and return it.
+  fn remove(&mut self, key: &CacheKey) -> Option<Box<Any>>;
+
+  /// Iterate over all the keys of the cache.
+  fn keys<'a>(&'a self) -> Box<Iterator<Item = &'a CacheKey> + 'a>;
+}
+
+/// Default cache backend, backed by a `HashMap`.
+#[derive(Default)]
+pub struct HashCache {
+  items: HashMap<CacheKey, Box<Any>>,
+}
+
+impl HashCache {
+  /// Create an empty cache.
+  pub fn new() -> Self {
+    HashCache {
+      items: HashMap::new(),
+    }
+  }
+}
+
+impl CacheBackend for HashCache {
+  fn save(&mut self, key: CacheKey, value: Box<Any>) {
+    self.items.insert(key, value);
+  }
+
+  fn get(&self, key: &CacheKey) -> Option<&Any> {
+    self.items.get(key).map(|value| &**value)
+  }
+
+  fn remove(&mut self, key: &CacheKey) -> Option<Box<Any>> {
+    self.items.remove(key)
+  }
+
+  fn keys<'a>(&'a self) -> Box<Iterator<Item = &'a CacheKey> + 'a> {
+    Box::new(self.items.keys())
+  }
+}
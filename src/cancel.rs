@@ -0,0 +1,44 @@
+//! Cooperative cancellation.
+//!
+//! Loading or reloading a resource might take a while – think of multi-second asset imports. A
+//! `Storage` holds a `CancellationToken` that well-behaved loaders check periodically (via
+//! `Storage::cancellation_token`) in order to stop early on shutdown, level aborts or when their
+//! work gets superseded.
+//!
+//! The token can be cloned and sent to other threads; all clones share the same state. While it’s
+//! cancelled, the synchronizer also stops reloading resources and keeps them dirty, so that they
+//! get reloaded once the token is reset.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A shareable cancellation flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+  cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+  /// Create a new, non-cancelled token.
+  pub fn new() -> Self {
+    CancellationToken::default()
+  }
+
+  /// Request cancellation.
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::SeqCst);
+  }
+
+  /// Check whether cancellation was requested.
+  ///
+  /// Long-running loaders should call this function periodically and bail out if it returns
+  /// `true`.
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::SeqCst)
+  }
+
+  /// Clear a cancellation request, so that loading can resume.
+  pub fn reset(&self) {
+    self.cancelled.store(false, Ordering::SeqCst);
+  }
+}
@@ -0,0 +1,61 @@
+//! Time sources.
+//!
+//! A `Store` reads the time from a `Clock` to debounce reloads – see
+//! `StoreOpt::set_update_await_time_ms` – and to timestamp resources – see `Storage::info`. The
+//! default clock is the system one; set a `ManualClock`
+//! with `StoreOpt::set_clock` to test the debouncing without sleeping, advancing the time by hand.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// A source of time.
+pub trait Clock {
+  /// Current instant.
+  fn now(&self) -> Instant;
+}
+
+/// The system clock.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+}
+
+/// A clock that only moves when told to.
+///
+/// All the clones of a `ManualClock` share the same time: keep one around to advance the clock of
+/// the `Store` you gave another one to.
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+  now: Rc<Cell<Instant>>,
+}
+
+impl ManualClock {
+  /// Create a clock stopped at the current instant.
+  pub fn new() -> Self {
+    ManualClock {
+      now: Rc::new(Cell::new(Instant::now())),
+    }
+  }
+
+  /// Move the clock forward.
+  pub fn advance(&self, duration: Duration) {
+    self.now.set(self.now.get() + duration);
+  }
+}
+
+impl Default for ManualClock {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Clock for ManualClock {
+  fn now(&self) -> Instant {
+    self.now.get()
+  }
+}
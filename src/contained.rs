@@ -0,0 +1,107 @@
+//! Stores owning their context.
+//!
+//! When the context is global to the store anyway, threading `ctx: &mut C` through every call is
+//! noise. `Store::with_context` turns a `Store` into a `SelfContained` store, which owns the
+//! context and lends it to the `Store` on its own:
+//!
+//! ```ignore
+//! let mut store = Store::new(opt)?.with_context(gpu);
+//!
+//! let tex: Res<Texture> = store.get(&FSKey::new("/tex.png"))?;
+//! store.sync();
+//! ```
+//!
+//! The context is still available with `SelfContained::context` and `SelfContained::context_mut`,
+//! and `SelfContained::with` lends both the `Store` and the context for anything else.
+
+use std::ops::{Deref, DerefMut};
+
+use load::{Load, Store, StoreErrorOr, SyncReport};
+use res::Res;
+
+/// A `Store` owning its context.
+///
+/// See the documentation of the `contained` module for further details.
+pub struct SelfContained<C> {
+  store: Store<C>,
+  ctx: C,
+}
+
+impl<C> SelfContained<C> {
+  /// Create a store owning its context.
+  pub fn new(store: Store<C>, ctx: C) -> Self {
+    SelfContained { store, ctx }
+  }
+
+  /// The context of the store.
+  pub fn context(&self) -> &C {
+    &self.ctx
+  }
+
+  /// The context of the store.
+  pub fn context_mut(&mut self) -> &mut C {
+    &mut self.ctx
+  }
+
+  /// Lend both the `Store` and its context to a function.
+  pub fn with<F, A>(&mut self, f: F) -> A
+  where F: FnOnce(&mut Store<C>, &mut C) -> A {
+    f(&mut self.store, &mut self.ctx)
+  }
+
+  /// Get a resource from the store and return an error if its loading failed.
+  ///
+  /// This function uses the default loading method.
+  pub fn get<K, T>(&mut self, key: &K) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>, {
+    self.store.get(key, &mut self.ctx)
+  }
+
+  /// Get a resource from the store by using a specific method and return an error if its loading
+  /// failed.
+  pub fn get_by<K, T, M>(&mut self, key: &K, method: M) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    self.store.get_by(key, &mut self.ctx, method)
+  }
+
+  /// Synchronize the store.
+  ///
+  /// See `Store::sync` for further details.
+  pub fn sync(&mut self) -> SyncReport {
+    self.store.sync(&mut self.ctx)
+  }
+
+  /// Give the `Store` and the context back.
+  pub fn into_inner(self) -> (Store<C>, C) {
+    (self.store, self.ctx)
+  }
+}
+
+impl<C> Deref for SelfContained<C> {
+  type Target = Store<C>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.store
+  }
+}
+
+impl<C> DerefMut for SelfContained<C> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.store
+  }
+}
+
+impl<C> Store<C> {
+  /// Turn the store into a store owning its context.
+  ///
+  /// See the documentation of the `contained` module for further details.
+  pub fn with_context(self, ctx: C) -> SelfContained<C> {
+    SelfContained::new(self, ctx)
+  }
+}
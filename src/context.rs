@@ -0,0 +1,145 @@
+//! Type-map contexts.
+//!
+//! Libraries needing a value in the context usually constrain `C` with a trait of their own – see
+//! the documentation of the crate. Using several such libraries then requires a context type
+//! implementing all their traits, which must be written by hand. A `TypeMapContext` avoids that:
+//! it holds at most one value per type, and loaders constrained with `C: Fetch` get the value they
+//! need by type:
+//!
+//! ```
+//! use std::io;
+//! use warmy::{Fetch, Load, Loaded, LogicalKey, Storage, TypeMapContext};
+//!
+//! struct Counter(usize);
+//!
+//! struct Foo;
+//!
+//! impl<C> Load<C> for Foo where C: Fetch {
+//!   type Key = LogicalKey;
+//!
+//!   type Error = io::Error;
+//!
+//!   fn load(
+//!     _: Self::Key,
+//!     _: &mut Storage<C>,
+//!     ctx: &mut C
+//!   ) -> Result<Loaded<Self>, Self::Error> {
+//!     if let Some(counter) = ctx.fetch::<Counter>() {
+//!       counter.0 += 1;
+//!     }
+//!
+//!     Ok(Foo.into())
+//!   }
+//! }
+//!
+//! let ctx = TypeMapContext::new().with(Counter(0)).with("another library’s context");
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Contexts values can be fetched from by type.
+pub trait Fetch {
+  /// Fetch the value of type `T` of the context, if any.
+  fn fetch<T>(&mut self) -> Option<&mut T>
+  where T: 'static;
+}
+
+impl Fetch for () {
+  fn fetch<T>(&mut self) -> Option<&mut T>
+  where T: 'static {
+    None
+  }
+}
+
+/// A context holding at most one value per type.
+///
+/// See the documentation of the `context` module for further details.
+#[derive(Default)]
+pub struct TypeMapContext {
+  values: HashMap<TypeId, Box<Any>>,
+}
+
+impl TypeMapContext {
+  /// Create an empty context.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add a value to the context, replacing the value of the same type, if any.
+  pub fn with<T>(mut self, value: T) -> Self
+  where T: 'static {
+    self.insert(value);
+    self
+  }
+
+  /// Insert a value in the context and return the value of the same type it replaces, if any.
+  pub fn insert<T>(&mut self, value: T) -> Option<T>
+  where T: 'static {
+    self
+      .values
+      .insert(TypeId::of::<T>(), Box::new(value))
+      .and_then(|old| old.downcast().ok())
+      .map(|old| *old)
+  }
+
+  /// Remove the value of type `T` from the context and return it, if any.
+  pub fn remove<T>(&mut self) -> Option<T>
+  where T: 'static {
+    self
+      .values
+      .remove(&TypeId::of::<T>())
+      .and_then(|value| value.downcast().ok())
+      .map(|value| *value)
+  }
+
+  /// Check whether the context holds a value of type `T`.
+  pub fn contains<T>(&self) -> bool
+  where T: 'static {
+    self.values.contains_key(&TypeId::of::<T>())
+  }
+
+  /// Get the value of type `T` of the context, if any.
+  pub fn get<T>(&self) -> Option<&T>
+  where T: 'static {
+    self
+      .values
+      .get(&TypeId::of::<T>())
+      .and_then(|value| value.downcast_ref())
+  }
+
+  /// Get the value of type `T` of the context, if any.
+  pub fn get_mut<T>(&mut self) -> Option<&mut T>
+  where T: 'static {
+    self
+      .values
+      .get_mut(&TypeId::of::<T>())
+      .and_then(|value| value.downcast_mut())
+  }
+
+  /// Number of values in the context.
+  pub fn len(&self) -> usize {
+    self.values.len()
+  }
+
+  /// Whether the context holds no value.
+  pub fn is_empty(&self) -> bool {
+    self.values.is_empty()
+  }
+}
+
+impl Fetch for TypeMapContext {
+  fn fetch<T>(&mut self) -> Option<&mut T>
+  where T: 'static {
+    self.get_mut()
+  }
+}
+
+impl fmt::Debug for TypeMapContext {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("TypeMapContext")
+      .field("len", &self.values.len())
+      .finish()
+  }
+}
@@ -0,0 +1,205 @@
+//! Deferred and cancelable loads.
+//!
+//! `Storage::get` loads resources right away, which can stall a frame for a while. Instead,
+//! `Storage::request` queues the loading of a resource and gives back a `LoadHandle` to it. Queued
+//! loads happen when calling `Storage::load_requests`, a given number of them at a time –
+//! typically, a few per frame:
+//!
+//! ```ignore
+//! let level = store.request::<_, Level>(&FSKey::new("/levels/1.json"));
+//!
+//! loop {
+//!   store.load_requests(ctx, 4);
+//!
+//!   if player_backed_out {
+//!     level.cancel();
+//!   }
+//!
+//!   if let Some(level) = level.take() {
+//!     // …
+//!   }
+//! }
+//! ```
+//!
+//! Cancelling a handle removes its request from the queue. If it’s being loaded, the loader sees it
+//! with `Storage::load_cancelled` and can stop early. Either way, the handle never yields the
+//! resource. A resource that finished loading despite the cancellation stays in the `Storage` like
+//! any other one.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+use cancel::CancellationToken;
+use key::DepKey;
+use load::{Load, Storage, StoreErrorOr};
+use res::Res;
+
+/// State of a request.
+enum State<T, C, M>
+where T: Load<C, M> {
+  Pending,
+  Done(Result<Res<T>, StoreErrorOr<T, C, M>>),
+  Taken,
+  Cancelled,
+}
+
+/// Handle to a resource requested with `Storage::request` or `Storage::request_by`.
+///
+/// See the documentation of the `deferred` module for further details.
+pub struct LoadHandle<T, C, M = ()>
+where T: Load<C, M> {
+  key: DepKey,
+  token: CancellationToken,
+  state: Rc<RefCell<State<T, C, M>>>,
+}
+
+impl<T, C, M> LoadHandle<T, C, M>
+where T: Load<C, M>
+{
+  /// Key of the requested resource.
+  pub fn key(&self) -> &DepKey {
+    &self.key
+  }
+
+  /// Cancel the request.
+  ///
+  /// The request is removed from the queue of its `Storage` and the handle never yields the
+  /// resource.
+  pub fn cancel(&self) {
+    self.token.cancel();
+    *self.state.borrow_mut() = State::Cancelled;
+  }
+
+  /// Check whether the request was cancelled.
+  pub fn is_cancelled(&self) -> bool {
+    self.token.is_cancelled()
+  }
+
+  /// Check whether the resource is still waiting to be loaded.
+  pub fn is_pending(&self) -> bool {
+    match *self.state.borrow() {
+      State::Pending => !self.token.is_cancelled(),
+      _ => false,
+    }
+  }
+
+  /// The cancellation token of the request.
+  ///
+  /// Clone it to cancel the request from another place – another thread, for instance.
+  pub fn token(&self) -> &CancellationToken {
+    &self.token
+  }
+
+  /// Take the result of the loading, if it’s done.
+  ///
+  /// The result is given only once: later calls return `None`.
+  pub fn take(&self) -> Option<Result<Res<T>, StoreErrorOr<T, C, M>>> {
+    if self.token.is_cancelled() {
+      return None;
+    }
+
+    let mut state = self.state.borrow_mut();
+
+    match *state {
+      State::Done(_) => (),
+      _ => return None,
+    }
+
+    match mem::replace(&mut *state, State::Taken) {
+      State::Done(result) => Some(result),
+      _ => None,
+    }
+  }
+}
+
+impl<T, C, M> fmt::Debug for LoadHandle<T, C, M>
+where T: Load<C, M>
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("LoadHandle")
+      .field("key", &self.key)
+      .field("pending", &self.is_pending())
+      .field("cancelled", &self.is_cancelled())
+      .finish()
+  }
+}
+
+/// A queued load.
+pub(crate) struct Request<C> {
+  pub(crate) token: CancellationToken,
+  load: Box<FnOnce(&mut Storage<C>, &mut C)>,
+}
+
+impl<C> Storage<C>
+where C: 'static
+{
+  /// Queue the loading of a resource and get a handle to it.
+  ///
+  /// This function uses the default loading method. See the documentation of the `deferred` module
+  /// for further details.
+  pub fn request<K, T>(&mut self, key: &K) -> LoadHandle<T, C>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>, {
+    self.request_by(key, ())
+  }
+
+  /// Queue the loading of a resource by using a specific method and get a handle to it.
+  pub fn request_by<K, T, M>(&mut self, key: &K, method: M) -> LoadHandle<T, C, M>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    let key: T::Key = key.clone().into();
+    let token = CancellationToken::new();
+    let state = Rc::new(RefCell::new(State::Pending));
+
+    let handle = LoadHandle {
+      key: key.clone().into(),
+      token: token.clone(),
+      state: state.clone(),
+    };
+
+    let load = move |storage: &mut Storage<C>, ctx: &mut C| {
+      let result = storage.get_by::<T::Key, T, M>(&key, ctx, method);
+      let mut state = state.borrow_mut();
+
+      if let State::Pending = *state {
+        *state = State::Done(result);
+      }
+    };
+
+    self.push_request(Request {
+      token,
+      load: Box::new(load),
+    });
+
+    handle
+  }
+
+  /// Load at most `budget` requested resources, in the order they were requested, and return how
+  /// many were loaded.
+  ///
+  /// Cancelled requests are dropped and don’t count towards the budget.
+  pub fn load_requests(&mut self, ctx: &mut C, budget: usize) -> usize {
+    let mut loaded = 0;
+
+    while loaded < budget {
+      let request = match self.pop_request() {
+        Some(request) => request,
+        None => break,
+      };
+
+      let previous = self.set_current_request(Some(request.token.clone()));
+      (request.load)(self, ctx);
+      self.set_current_request(previous);
+
+      loaded += 1;
+    }
+
+    loaded
+  }
+}
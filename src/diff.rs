@@ -0,0 +1,101 @@
+//! Differences between generations of a storage.
+//!
+//! A `Storage` keeps a journal of the generations at which each resource was loaded, reloaded or
+//! evicted. `Storage::diff` uses it to report which keys changed, appeared or disappeared between
+//! two generations – typically, the generation you saved when starting a playtest and the current
+//! one. That’s what you need to show “changes since you started playtesting” or to revert them all.
+
+use std::collections::HashMap;
+
+use key::DepKey;
+
+/// Keys that changed, appeared or disappeared between two generations of a `Storage`.
+///
+/// Keys are sorted.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GenerationDiff {
+  /// Keys living in both generations that were reloaded in between.
+  pub changed: Vec<DepKey>,
+  /// Keys living in the newer generation only.
+  pub appeared: Vec<DepKey>,
+  /// Keys living in the older generation only.
+  pub disappeared: Vec<DepKey>,
+}
+
+impl GenerationDiff {
+  /// Whether nothing happened between the two generations.
+  pub fn is_empty(&self) -> bool {
+    self.changed.is_empty() && self.appeared.is_empty() && self.disappeared.is_empty()
+  }
+}
+
+/// Journal of the generations at which resources were loaded, reloaded or evicted.
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+  // for each key, generations at which the key was (re)loaded (true) or evicted (false), in order
+  entries: HashMap<DepKey, Vec<(u64, bool)>>,
+}
+
+impl Journal {
+  pub(crate) fn new() -> Self {
+    Journal::default()
+  }
+
+  /// Record that a resource was loaded or reloaded.
+  pub(crate) fn loaded(&mut self, key: &DepKey, generation: u64) {
+    self.entries.entry(key.clone()).or_insert(Vec::new()).push((generation, true));
+  }
+
+  /// Forget that a resource was reloaded at a given generation, because the reload got rolled back.
+  pub(crate) fn forget_reload(&mut self, key: &DepKey, generation: u64) {
+    if let Some(events) = self.entries.get_mut(key) {
+      events.retain(|&event| event != (generation, true));
+    }
+  }
+
+  /// Record that a resource was evicted.
+  pub(crate) fn evicted(&mut self, key: &DepKey, generation: u64) {
+    self.entries.entry(key.clone()).or_insert(Vec::new()).push((generation, false));
+  }
+
+  /// Compute the differences from generation `from` to generation `to`.
+  ///
+  /// If `from` is newer than `to`, the diff is reversed: appeared keys are the ones that got
+  /// evicted, and so on.
+  pub(crate) fn diff(&self, from: u64, to: u64) -> GenerationDiff {
+    let (older, newer) = if from <= to { (from, to) } else { (to, from) };
+    let mut diff = GenerationDiff::default();
+
+    for (key, events) in &self.entries {
+      let before = living_at(events, from);
+      let after = living_at(events, to);
+
+      match (before, after) {
+        (false, true) => diff.appeared.push(key.clone()),
+        (true, false) => diff.disappeared.push(key.clone()),
+        (true, true) => {
+          let touched = events.iter().any(|&(g, _)| g > older && g <= newer);
+
+          if touched {
+            diff.changed.push(key.clone());
+          }
+        }
+        (false, false) => (),
+      }
+    }
+
+    diff.changed.sort();
+    diff.appeared.sort();
+    diff.disappeared.sort();
+    diff
+  }
+}
+
+/// Whether a key was living in the storage at a given generation.
+fn living_at(events: &[(u64, bool)], generation: u64) -> bool {
+  events
+    .iter()
+    .take_while(|&&(g, _)| g <= generation)
+    .last()
+    .map_or(false, |&(_, living)| living)
+}
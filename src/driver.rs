@@ -0,0 +1,170 @@
+//! Asynchronous synchronization, for tokio runtimes.
+//!
+//! This module is only available with the `async` feature. Instead of calling `Store::sync` from a
+//! dedicated polling thread, servers can drive the synchronization from their runtime:
+//!
+//!   - `Store::sync_stream` returns a `Stream` of the reports of the synchronization passes that
+//!     reloaded something. It is woken up by the watcher as soon as filesystem events are pending,
+//!     so it doesn’t poll anything while nothing changes.
+//!   - `Store::run_sync_loop` returns a future synchronizing the store at a fixed interval, for
+//!     stores which resources aren’t watched – or when a bounded reload latency is enough.
+//!
+//! Both borrow the store and the context for as long as they run; select on them along with the
+//! rest of your work – with `tokio::select!`, for instance – and drop them to stop synchronizing.
+//!
+//! A `Store` isn’t `Send`, and neither are those futures: they cannot be given to `tokio::spawn`.
+//! Run them on the thread owning the store instead, with `Runtime::block_on` on a current-thread
+//! runtime or with `tokio::task::spawn_local` within a `LocalSet`.
+//!
+//! ```ignore
+//! let mut reports = store.sync_stream(&mut ctx);
+//!
+//! while let Some(report) = reports.next().await {
+//!   println!("reloaded {:?}", report.reloaded);
+//! }
+//! ```
+
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use tokio::time::{self, Interval, Sleep};
+
+use load::{Store, SyncReport};
+
+/// Waker of the task driving a store, if any, shared with the watcher thread.
+pub(crate) type TaskWaker = Arc<Mutex<Option<Waker>>>;
+
+/// Chain the waker of the task driving a store after a user-provided waker, if any.
+pub(crate) fn chain_waker(
+  waker: Option<Arc<Fn() + Send + Sync>>,
+  task_waker: TaskWaker,
+) -> Arc<Fn() + Send + Sync>
+{
+  Arc::new(move || {
+    if let Some(ref waker) = waker {
+      waker();
+    }
+
+    if let Ok(mut task_waker) = task_waker.lock() {
+      if let Some(task_waker) = task_waker.take() {
+        task_waker.wake();
+      }
+    }
+  })
+}
+
+/// Minimum delay before synchronizing again while dirty resources wait for their update await time.
+const SETTLE_DELAY_MS: u64 = 5;
+
+/// Stream of the reports of the synchronization passes of a `Store` that reloaded something.
+///
+/// See `Store::sync_stream` for further details. The stream never ends.
+pub struct SyncStream<'a, C: 'a> {
+  store: &'a mut Store<C>,
+  ctx: &'a mut C,
+  // timer waking the stream up while dirty resources settle
+  settle: Option<Pin<Box<Sleep>>>,
+}
+
+impl<'a, C> Stream for SyncStream<'a, C> {
+  type Item = SyncReport;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    let stream = self.get_mut();
+
+    loop {
+      // register before looking for work, so that no event is missed in between
+      if let Ok(mut task_waker) = stream.store.task_waker.lock() {
+        *task_waker = Some(cx.waker().clone());
+      }
+
+      if !stream.store.sync_needed() {
+        stream.settle = None;
+        return Poll::Pending;
+      }
+
+      let report = stream.store.sync(stream.ctx);
+
+      if report.reloads_attempted > 0 {
+        stream.settle = None;
+        return Poll::Ready(Some(report));
+      }
+
+      // nothing to reload yet: dirty resources are settling, so come back once they’re settled
+      let store = &mut stream.store;
+      let settle = stream.settle.get_or_insert_with(|| {
+        let now = store.now();
+        let delay = store
+          .next_sync_deadline()
+          .map_or(Duration::from_millis(0), |deadline| deadline.saturating_duration_since(now));
+
+        Box::pin(time::sleep(delay.max(Duration::from_millis(SETTLE_DELAY_MS))))
+      });
+
+      match settle.as_mut().poll(cx) {
+        Poll::Ready(()) => stream.settle = None,
+        Poll::Pending => return Poll::Pending,
+      }
+    }
+  }
+}
+
+/// Future synchronizing a `Store` at a fixed interval.
+///
+/// See `Store::run_sync_loop` for further details. The future never completes.
+pub struct SyncLoop<'a, C: 'a> {
+  store: &'a mut Store<C>,
+  ctx: &'a mut C,
+  period: Duration,
+  // created lazily, as it requires to run within a tokio runtime
+  interval: Option<Interval>,
+}
+
+impl<'a, C> Future for SyncLoop<'a, C> {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    let sync_loop = self.get_mut();
+    let period = sync_loop.period;
+    let interval = sync_loop.interval.get_or_insert_with(|| time::interval(period));
+
+    while interval.poll_tick(cx).is_ready() {
+      sync_loop.store.sync(sync_loop.ctx);
+    }
+
+    Poll::Pending
+  }
+}
+
+impl<C> Store<C> {
+  /// Stream the reports of the synchronization passes that reloaded something.
+  ///
+  /// The stream synchronizes the store whenever filesystem events are pending or resources were
+  /// marked dirty, and yields the report of every synchronization pass that attempted reloads. It
+  /// must be polled from within a tokio runtime, on the thread owning the store – see the
+  /// documentation of the `driver` module.
+  pub fn sync_stream<'a>(&'a mut self, ctx: &'a mut C) -> SyncStream<'a, C> {
+    SyncStream {
+      store: self,
+      ctx,
+      settle: None,
+    }
+  }
+
+  /// Synchronize the store every `period`, forever.
+  ///
+  /// The first synchronization happens right away. The returned future must be polled from within
+  /// a tokio runtime, on the thread owning the store – see the documentation of the `driver`
+  /// module. It never completes: drop it to stop synchronizing.
+  pub fn run_sync_loop<'a>(&'a mut self, ctx: &'a mut C, period: Duration) -> SyncLoop<'a, C> {
+    SyncLoop {
+      store: self,
+      ctx,
+      period,
+      interval: None,
+    }
+  }
+}
@@ -0,0 +1,101 @@
+//! Hot-reloadable dynamic libraries.
+//!
+//! This module is only available with the `libloading` feature. A `Dylib` is a dynamic library –
+//! `.so`, `.dylib` or `.dll` – loaded from the filesystem. Because most platforms don’t cope well
+//! with a library being overwritten while it’s loaded, the library is first copied to a temporary
+//! path and loaded from there. When the file changes, a new copy is made and loaded; the old
+//! library is only unloaded once the new one has replaced it, so that `Load::after_reload` can
+//! still migrate state out of it.
+//!
+//! Symbols must not outlive the library they come from: look them up again after each reload –
+//! typically in a `Res::on_reload` observer.
+
+use libloading::{Library, Symbol};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+
+/// Number of copies made so far, used to generate unique temporary paths.
+static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+/// A dynamic library.
+pub struct Dylib {
+  // always set; only taken when dropping so that the library is closed before removing its copy
+  lib: Option<Library>,
+  source: PathBuf,
+  copy: PathBuf,
+}
+
+impl Dylib {
+  /// Path of the library.
+  pub fn source(&self) -> &Path {
+    &self.source
+  }
+
+  /// Path of the copy the library was actually loaded from.
+  pub fn copy(&self) -> &Path {
+    &self.copy
+  }
+
+  /// Look up a symbol.
+  ///
+  /// # Safety
+  ///
+  /// The type `T` must match the actual type of the symbol, and the symbol must not be used after
+  /// the library gets reloaded.
+  pub unsafe fn get<T>(&self, symbol: &[u8]) -> io::Result<Symbol<T>> {
+    self.lib.as_ref().unwrap().get(symbol)
+  }
+}
+
+impl Drop for Dylib {
+  fn drop(&mut self) {
+    drop(self.lib.take());
+    let _ = fs::remove_file(&self.copy);
+  }
+}
+
+impl<C> Load<C> for Dylib {
+  type Key = FSKey;
+
+  type Error = io::Error;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let source = key.as_path().to_owned();
+    let file_name = source
+      .file_name()
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+
+    let copy = env::temp_dir().join(format!(
+      "warmy-{}-{}-{}",
+      process::id(),
+      COPIES.fetch_add(1, Ordering::SeqCst),
+      file_name.to_string_lossy()
+    ));
+
+    fs::copy(&source, &copy)?;
+
+    match Library::new(&copy) {
+      Ok(lib) => {
+        let dylib = Dylib {
+          lib: Some(lib),
+          source,
+          copy,
+        };
+
+        Ok(dylib.into())
+      }
+
+      Err(e) => {
+        let _ = fs::remove_file(&copy);
+        Err(e)
+      }
+    }
+  }
+}
@@ -0,0 +1,74 @@
+//! Resources embedded in the binary.
+//!
+//! Release builds might want a few critical resources – a default font, a fallback texture – to
+//! be baked into the binary, so that a broken installation still runs. An `EmbeddedSource` maps
+//! VFS paths to bytes, typically from `include_bytes!`. Give it to a `Store` with
+//! `StoreOpt::set_embedded_source`: `Storage::read` – and thus the built-in loaders – reads the
+//! embedded bytes of a path when its file doesn’t exist. Embedded resources never change, so they
+//! never reload – unless a file appears at their path.
+//!
+//! The `embedded_source!` macro builds a source from files, given their directory – relative to
+//! the file the macro is invoked in – and their VFS paths:
+//!
+//! ```ignore
+//! let embedded = embedded_source!("../assets", "/fonts/default.ttf", "/textures/missing.png");
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// VFS paths mapped to bytes embedded in the binary.
+///
+/// See the documentation of the `embedded` module for further details.
+#[derive(Clone, Debug, Default)]
+pub struct EmbeddedSource {
+  entries: HashMap<PathBuf, &'static [u8]>,
+}
+
+impl EmbeddedSource {
+  /// Create a source from a table of VFS paths and their bytes.
+  pub fn new(table: &[(&str, &'static [u8])]) -> Self {
+    let mut source = EmbeddedSource::default();
+
+    for &(path, bytes) in table {
+      source.insert(path, bytes);
+    }
+
+    source
+  }
+
+  /// Embed bytes at a VFS path, replacing the ones already embedded there, if any.
+  pub fn insert<P>(&mut self, path: P, bytes: &'static [u8])
+  where P: AsRef<Path> {
+    self.entries.insert(vfs_path(path.as_ref()), bytes);
+  }
+
+  /// Bytes embedded at a VFS path, if any.
+  pub fn get<P>(&self, path: P) -> Option<&'static [u8]>
+  where P: AsRef<Path> {
+    self.entries.get(&vfs_path(path.as_ref())).cloned()
+  }
+
+  /// Iterate over the VFS paths of the embedded entries.
+  pub fn paths<'a>(&'a self) -> impl Iterator<Item = &'a Path> + 'a {
+    self.entries.keys().map(PathBuf::as_path)
+  }
+}
+
+/// Make a VFS path absolute, so that `a.txt` and `/a.txt` are the same entry.
+fn vfs_path(path: &Path) -> PathBuf {
+  Path::new("/").join(path)
+}
+
+/// Build an `EmbeddedSource` from files.
+///
+/// The first argument is the directory the files live in, relative to the file the macro is
+/// invoked in; the next ones are the VFS paths of the files, starting with `/`.
+#[macro_export]
+macro_rules! embedded_source {
+  ($dir:expr, $($path:expr),+ $(,)*) => {
+    $crate::embedded::EmbeddedSource::new(&[
+      $(($path, &include_bytes!(concat!($dir, $path))[..])),+
+    ])
+  };
+}
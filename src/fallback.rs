@@ -0,0 +1,130 @@
+//! Chained stores.
+//!
+//! A `FallbackStore` chains several `Store`s – typically a directory of user overrides, then the
+//! game data directory. A resource is looked up in each store in turn and is taken from the first
+//! one in which it exists, that is:
+//!
+//!   - The resource already lives in the store.
+//!   - Or its key refers to a file – or directory – existing under the root of the store.
+//!
+//! Keys that don’t refer to the filesystem, such as logical keys, always exist. If the resource
+//! exists in none of the stores, the last store tries to load it – and most likely reports the
+//! missing file. A resource keeps living in the store it was taken from: adding an override after
+//! the fact only takes effect once the resource is evicted.
+//!
+//! Synchronizing a `FallbackStore` synchronizes all its stores.
+
+use load::{Load, Store, StoreErrorOr, SyncReport};
+use res::Res;
+
+/// A chain of stores looked up in order.
+///
+/// See the documentation of the `fallback` module for further details.
+pub struct FallbackStore<C> {
+  stores: Vec<Store<C>>,
+}
+
+impl<C> FallbackStore<C> {
+  /// Create a chain starting with a primary store.
+  pub fn new(primary: Store<C>) -> Self {
+    FallbackStore {
+      stores: vec![primary],
+    }
+  }
+
+  /// Add a store to fall back to, looked up after all the previous ones.
+  pub fn add_fallback(mut self, store: Store<C>) -> Self {
+    self.stores.push(store);
+    self
+  }
+
+  /// Stores of the chain, in lookup order.
+  pub fn stores(&self) -> &[Store<C>] {
+    &self.stores
+  }
+
+  /// Stores of the chain, in lookup order.
+  pub fn stores_mut(&mut self) -> &mut [Store<C>] {
+    &mut self.stores
+  }
+
+  /// Check whether a resource is living in any of the stores.
+  ///
+  /// This function never triggers any loading.
+  pub fn contains<T>(&self, key: &T::Key) -> bool
+  where T: Load<C> {
+    self.stores.iter().any(|store| store.contains::<T>(key))
+  }
+
+  /// Get a resource from the first store it exists in, and return an error if its loading failed.
+  ///
+  /// This function uses the default loading method.
+  pub fn get<K, T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>, {
+    self.get_by(key, ctx, ())
+  }
+
+  /// Get a resource from the first store it exists in by using a specific method, and return an
+  /// error if its loading failed.
+  pub fn get_by<K, T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    let last = self.stores.len() - 1;
+    let index = self
+      .stores
+      .iter()
+      .position(|store| exists::<K, T, M, C>(store, key))
+      .unwrap_or(last);
+
+    self.stores[index].get_by(key, ctx, method)
+  }
+
+  /// Synchronize all the stores, in lookup order, and merge their reports.
+  pub fn sync(&mut self, ctx: &mut C) -> SyncReport {
+    let mut merged = SyncReport::default();
+
+    for store in &mut self.stores {
+      let report = store.sync(ctx);
+
+      merged.events_dequeued += report.events_dequeued;
+      merged.keys_debounced += report.keys_debounced;
+      merged.reloads_attempted += report.reloads_attempted;
+      merged.reloads_succeeded += report.reloads_succeeded;
+      merged.reloads_failed += report.reloads_failed;
+      merged.reloads_rolled_back += report.reloads_rolled_back;
+      merged.reloaded.extend(report.reloaded);
+      merged.root_rewatched |= report.root_rewatched;
+      merged.rollbacks += report.rollbacks;
+      merged.reloads_coalesced += report.reloads_coalesced;
+      merged.duration += report.duration;
+    }
+
+    merged
+  }
+}
+
+/// Check whether a resource exists in a store.
+fn exists<K, T, M, C>(store: &Store<C>, key: &K) -> bool
+where
+  T: Load<C, M>,
+  K: Clone + Into<T::Key>,
+{
+  let key: T::Key = key.clone().into();
+  let dep_key = store.prepare(key).into();
+
+  if store.contains_key(&dep_key) {
+    return true;
+  }
+
+  dep_key.path().map_or(true, |path| path.exists())
+}
@@ -0,0 +1,94 @@
+//! Failure injection, for resilience testing.
+//!
+//! This module is only available with the `failure-injection` feature. It allows you to make
+//! specific keys fail to load or reload – or to delay their loading – so that you can test your
+//! proxy, fallback and retry paths against a real `Store` without corrupting actual files on disk.
+//!
+//! Faults are registered on a `Storage` with `Storage::set_fault` and removed with
+//! `Storage::clear_fault`.
+
+use std::any::Any;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+/// A fault to trigger when a key gets loaded or reloaded.
+pub struct Fault {
+  delay: Option<Duration>,
+  error: Option<Box<Fn() -> Box<Any>>>,
+  on_load: bool,
+  on_reload: bool,
+}
+
+impl Fault {
+  /// A fault that makes the loading or reloading fail with the provided error.
+  ///
+  /// The error type must be the `Load::Error` type of the resource living at the faulted key;
+  /// otherwise, no error is triggered.
+  pub fn error<E, F>(f: F) -> Self
+  where
+    E: Error + 'static,
+    F: 'static + Fn() -> E,
+  {
+    Fault {
+      delay: None,
+      error: Some(Box::new(move || Box::new(f()))),
+      on_load: true,
+      on_reload: true,
+    }
+  }
+
+  /// A fault that only delays the loading or reloading by the given amount of time.
+  pub fn delay(delay: Duration) -> Self {
+    Fault {
+      delay: Some(delay),
+      error: None,
+      on_load: true,
+      on_reload: true,
+    }
+  }
+
+  /// Add a delay to the fault, applied before the error (if any) is triggered.
+  pub fn with_delay(self, delay: Duration) -> Self {
+    Fault {
+      delay: Some(delay),
+      ..self
+    }
+  }
+
+  /// Only trigger the fault when the resource is loaded for the first time.
+  pub fn only_on_load(self) -> Self {
+    Fault {
+      on_load: true,
+      on_reload: false,
+      ..self
+    }
+  }
+
+  /// Only trigger the fault when the resource gets reloaded.
+  pub fn only_on_reload(self) -> Self {
+    Fault {
+      on_load: false,
+      on_reload: true,
+      ..self
+    }
+  }
+
+  /// Trigger the fault: wait for the delay, if any, and generate the error, if any.
+  pub(crate) fn trigger<E>(&self, reloading: bool) -> Option<E>
+  where E: 'static {
+    if (reloading && !self.on_reload) || (!reloading && !self.on_load) {
+      return None;
+    }
+
+    if let Some(delay) = self.delay {
+      thread::sleep(delay);
+    }
+
+    self
+      .error
+      .as_ref()
+      .and_then(|f| f().downcast::<E>().ok())
+      .map(|e| *e)
+  }
+}
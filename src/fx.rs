@@ -0,0 +1,342 @@
+//! Particle effect definitions.
+//!
+//! An `Fx` is a particle emitter described in a small text format, one property per line:
+//!
+//! ```text
+//! # sparks of a burning torch
+//! rate 40
+//! lifetime 1.5
+//! texture /textures/spark.png
+//! curve size /curves/spark_size.curve
+//! curve alpha /curves/fade.curve
+//! sub_emitter /fx/smoke.fx
+//! ```
+//!
+//! Textures, curves and sub-emitters live in other files, given by their VFS paths. Besides being
+//! usable as-is, this module is a reference for declaring dependencies, as each kind of file is
+//! depended on differently:
+//!
+//!   - Curves are loaded as `Curve` resources and baked into lookup tables when the effect loads,
+//!     so the effect must reload whenever a curve changes: they’re *strong* dependencies.
+//!   - Textures are not loaded by the effect – that’s the job of your renderer, which loads them as
+//!     resources of the same `Storage` – but it must know when to bind them again. They’re *weak*
+//!     dependencies: the effect doesn’t reload, it queues the texture, which you get back with
+//!     `Fx::take_stale_textures`.
+//!   - Sub-emitters are `Fx` resources themselves, shared through `Res`, so they’re always up to
+//!     date and don’t require the parent effect to reload. They’re weak dependencies as well, so
+//!     that the parent knows – through `Fx::take_changed_sub_emitters` – when to respawn them.
+//!
+//! A curve file contains one `t value` point per line, `t` going increasingly from `0` to `1`.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use key::{DepKey, FSKey};
+use load::{Load, Loaded, Storage, StoreError, StoreErrorOr};
+use res::Res;
+
+/// Number of samples curves are baked into.
+pub const CURVE_SAMPLES: usize = 32;
+
+thread_local! {
+  // keys of the effects being loaded on this thread, used to detect sub-emitter cycles
+  static LOADING: RefCell<Vec<DepKey>> = RefCell::new(Vec::new());
+}
+
+/// A piecewise linear curve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+  points: Vec<(f32, f32)>,
+}
+
+impl Curve {
+  /// Points of the curve, as `(t, value)` pairs sorted by `t`.
+  pub fn points(&self) -> &[(f32, f32)] {
+    &self.points
+  }
+
+  /// Value of the curve at `t`, clamped to the first and last points.
+  pub fn sample(&self, t: f32) -> f32 {
+    let (first, last) = match (self.points.first(), self.points.last()) {
+      (Some(&first), Some(&last)) => (first, last),
+      _ => return 0.,
+    };
+
+    if t <= first.0 {
+      return first.1;
+    }
+
+    for window in self.points.windows(2) {
+      let ((t0, v0), (t1, v1)) = (window[0], window[1]);
+
+      if t <= t1 {
+        return v0 + (v1 - v0) * (t - t0) / (t1 - t0);
+      }
+    }
+
+    last.1
+  }
+
+  /// Bake the curve into `CURVE_SAMPLES` samples evenly spread over `[0; 1]`.
+  pub fn bake(&self) -> Vec<f32> {
+    (0..CURVE_SAMPLES)
+      .map(|i| self.sample(i as f32 / (CURVE_SAMPLES - 1) as f32))
+      .collect()
+  }
+}
+
+impl<C> Load<C> for Curve {
+  type Key = FSKey;
+
+  type Error = FxError;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let content = storage.read_to_string(key.as_path()).map_err(FxError::Io)?;
+    let mut points: Vec<(f32, f32)> = Vec::new();
+
+    for line in meaningful_lines(&content) {
+      let mut words = line.split_whitespace();
+      let point = match (words.next(), words.next(), words.next()) {
+        (Some(t), Some(v), None) => t.parse().and_then(|t| v.parse().map(|v| (t, v))),
+        _ => return Err(FxError::Malformed(line.to_owned())),
+      };
+
+      match point {
+        Ok(point) if points.last().map_or(true, |last| last.0 < point.0) => points.push(point),
+        _ => return Err(FxError::Malformed(line.to_owned())),
+      }
+    }
+
+    Ok(Curve { points }.into())
+  }
+}
+
+/// A particle effect.
+#[derive(Debug)]
+pub struct Fx {
+  rate: f32,
+  lifetime: f32,
+  texture: Option<FSKey>,
+  curves: Vec<(String, Vec<f32>)>,
+  sub_emitters: Vec<Res<Fx>>,
+  // prepared keys of the texture and sub-emitters, to recognize them in dependency_reloaded
+  texture_dep: Option<DepKey>,
+  sub_emitter_deps: Vec<DepKey>,
+  stale_textures: Vec<FSKey>,
+  changed_sub_emitters: Vec<usize>,
+}
+
+impl Fx {
+  /// Number of particles emitted per second.
+  pub fn rate(&self) -> f32 {
+    self.rate
+  }
+
+  /// Lifetime of the particles, in seconds.
+  pub fn lifetime(&self) -> f32 {
+    self.lifetime
+  }
+
+  /// Key of the texture of the particles, if any.
+  pub fn texture(&self) -> Option<&FSKey> {
+    self.texture.as_ref()
+  }
+
+  /// Baked samples of a curve, by name.
+  pub fn curve(&self, name: &str) -> Option<&[f32]> {
+    self
+      .curves
+      .iter()
+      .find(|&&(ref curve_name, _)| curve_name == name)
+      .map(|&(_, ref samples)| samples.as_slice())
+  }
+
+  /// Sub-emitters of the effect, in declaration order.
+  pub fn sub_emitters(&self) -> &[Res<Fx>] {
+    &self.sub_emitters
+  }
+
+  /// Take the textures that changed since the last call and must be bound again.
+  pub fn take_stale_textures(&mut self) -> Vec<FSKey> {
+    self.stale_textures.drain(..).collect()
+  }
+
+  /// Take the indices of the sub-emitters that changed since the last call.
+  pub fn take_changed_sub_emitters(&mut self) -> Vec<usize> {
+    self.changed_sub_emitters.drain(..).collect()
+  }
+}
+
+impl<C> Load<C> for Fx {
+  type Key = FSKey;
+
+  type Error = FxError;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let dep_key = DepKey::from(key.clone());
+    let cyclic = LOADING.with(|loading| loading.borrow().contains(&dep_key));
+
+    if cyclic {
+      return Err(FxError::SubEmitterCycle(key.as_path().to_owned()));
+    }
+
+    LOADING.with(|loading| loading.borrow_mut().push(dep_key));
+    let loaded = load_fx(&key, storage, ctx);
+    LOADING.with(|loading| loading.borrow_mut().pop());
+
+    loaded
+  }
+
+  fn after_reload(&mut self, old: &Self, _: &mut Storage<C>, _: &mut C) {
+    // changes not taken yet survive the reload
+    self.stale_textures.extend(old.stale_textures.iter().cloned());
+    self.changed_sub_emitters.extend(old.changed_sub_emitters.iter().cloned());
+  }
+
+  fn dependency_reloaded(&mut self, dep: &DepKey, _: &mut Storage<C>, _: &mut C) {
+    if self.texture_dep.as_ref() == Some(dep) {
+      if let Some(ref texture) = self.texture {
+        if !self.stale_textures.contains(texture) {
+          self.stale_textures.push(texture.clone());
+        }
+      }
+    }
+
+    for (index, sub_emitter_dep) in self.sub_emitter_deps.iter().enumerate() {
+      if sub_emitter_dep == dep && !self.changed_sub_emitters.contains(&index) {
+        self.changed_sub_emitters.push(index);
+      }
+    }
+  }
+}
+
+fn load_fx<C>(key: &FSKey, storage: &mut Storage<C>, ctx: &mut C) -> Result<Loaded<Fx>, FxError> {
+  let content = storage.read_to_string(key.as_path()).map_err(FxError::Io)?;
+  let mut fx = Fx {
+    rate: 0.,
+    lifetime: 1.,
+    texture: None,
+    curves: Vec::new(),
+    sub_emitters: Vec::new(),
+    texture_dep: None,
+    sub_emitter_deps: Vec::new(),
+    stale_textures: Vec::new(),
+    changed_sub_emitters: Vec::new(),
+  };
+  let mut deps = Vec::new();
+  let mut weak_deps = Vec::new();
+
+  for line in meaningful_lines(&content) {
+    let mut words = line.split_whitespace();
+    let malformed = || FxError::Malformed(line.to_owned());
+
+    match (words.next(), words.next(), words.next(), words.next()) {
+      (Some("rate"), Some(rate), None, None) => {
+        fx.rate = rate.parse().map_err(|_| malformed())?;
+      }
+
+      (Some("lifetime"), Some(lifetime), None, None) => {
+        fx.lifetime = lifetime.parse().map_err(|_| malformed())?;
+      }
+
+      (Some("texture"), Some(path), None, None) => {
+        let texture = FSKey::new(path);
+        fx.texture_dep = Some(storage.prepare(texture.clone()).into());
+        fx.texture = Some(texture.clone());
+        weak_deps.push(texture);
+      }
+
+      (Some("curve"), Some(name), Some(path), None) => {
+        let curve_key = FSKey::new(path);
+        let curve: Res<Curve> = storage.get(&curve_key, ctx).map_err(store_error)?;
+        fx.curves.push((name.to_owned(), curve.borrow().bake()));
+        deps.push(curve_key.into());
+      }
+
+      (Some("sub_emitter"), Some(path), None, None) => {
+        let sub_key = FSKey::new(path);
+        let sub_emitter: Res<Fx> = storage.get(&sub_key, ctx).map_err(store_error)?;
+        fx.sub_emitters.push(sub_emitter);
+        fx.sub_emitter_deps.push(storage.prepare(sub_key.clone()).into());
+        weak_deps.push(sub_key);
+      }
+
+      _ => return Err(malformed()),
+    }
+  }
+
+  Ok(weak_deps.into_iter().fold(Loaded::with_deps(fx, deps), Loaded::with_weak_dep))
+}
+
+/// Turn the error of a curve or a sub-emitter into an effect error.
+fn store_error<T, C>(e: StoreErrorOr<T, C>) -> FxError
+where T: Load<C, Error = FxError> {
+  match e {
+    StoreErrorOr::StoreError(e) => FxError::Store(e),
+    StoreErrorOr::ResError(e) => e,
+  }
+}
+
+/// Lines that are neither blank nor comments, trimmed.
+fn meaningful_lines<'a>(content: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+  content
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Error that might occur while loading an effect or a curve.
+#[derive(Debug)]
+pub enum FxError {
+  /// A file couldn’t be read.
+  Io(io::Error),
+  /// A curve or a sub-emitter couldn’t be registered in the `Storage`.
+  Store(StoreError),
+  /// A line is not a valid property – or point, for curves.
+  Malformed(String),
+  /// An effect ends up being its own sub-emitter.
+  SubEmitterCycle(PathBuf),
+}
+
+impl fmt::Display for FxError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      FxError::Io(ref e) => write!(f, "I/O error: {}", e),
+      FxError::Store(ref e) => write!(f, "store error: {}", e),
+      FxError::Malformed(ref line) => write!(f, "malformed line: {}", line),
+      FxError::SubEmitterCycle(ref path) => write!(f, "sub-emitter cycle: {}", path.display()),
+    }
+  }
+}
+
+impl Error for FxError {
+  fn description(&self) -> &str {
+    match *self {
+      FxError::Io(_) => "I/O error",
+      FxError::Store(_) => "store error",
+      FxError::Malformed(_) => "malformed line",
+      FxError::SubEmitterCycle(_) => "sub-emitter cycle",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      FxError::Io(ref e) => Some(e),
+      FxError::Store(ref e) => Some(e),
+      _ => None,
+    }
+  }
+}
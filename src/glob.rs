@@ -0,0 +1,273 @@
+//! Sets of resources selected by glob patterns.
+//!
+//! A `GlobKey` selects all the files which VFS paths match a pattern – e.g.
+//! `/textures/**/*.png`. Patterns support the usual wildcards:
+//!
+//!   - `?` matches any single character of a path component.
+//!   - `*` matches any sequence of characters of a path component.
+//!   - `**` matches any sequence of path components, including none.
+//!
+//! `Storage::get_all` loads all the matching files as a `ResourceSet<T>`. Each file is a resource
+//! of its own living in the `Storage`, so that modifying a file only reloads that file. Creating or
+//! removing matching files makes the set reload, which only loads the new files and evicts the
+//! removed ones; the other members are left untouched.
+//!
+//! ```ignore
+//! let key = GlobKey::new("/textures/**/*.png");
+//! let textures: Res<ResourceSet<Texture>> = store.get_all(&key, ctx)?;
+//! ```
+
+use std::path::{Component, Path, PathBuf};
+
+use key::{vfs_substite_path, DepKey, DirKey, ErasedKey, FSKey, Key, PathNormalization};
+use load::{Load, Loaded, Storage, StoreErrorOr};
+use map::{find_files, ResourceMapError};
+use mount::Mounts;
+use res::Res;
+
+/// Key selecting all the files which VFS paths match a glob pattern.
+///
+/// See the documentation of the `glob` module for further details.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GlobKey {
+  pattern: PathBuf,
+}
+
+impl GlobKey {
+  /// Create a new `GlobKey` by providing a pattern over VFS paths – see `FSKey::new`.
+  pub fn new<P>(pattern: P) -> Self
+  where P: AsRef<Path> {
+    GlobKey {
+      pattern: pattern.as_ref().to_owned(),
+    }
+  }
+
+  /// Get the underlying pattern.
+  pub fn pattern(&self) -> &Path {
+    &self.pattern
+  }
+
+  /// The longest directory of the pattern without wildcards, under which all the selected files
+  /// live.
+  ///
+  /// A pattern without any wildcard selects a single file; its base is the parent directory of that
+  /// file.
+  pub fn base(&self) -> PathBuf {
+    let base: PathBuf = self
+      .pattern
+      .components()
+      .take_while(|component| !is_wildcard(component))
+      .collect();
+
+    if base == self.pattern {
+      self.pattern.parent().map_or(base.clone(), Path::to_owned)
+    } else {
+      base
+    }
+  }
+
+  /// Check whether a path matches the pattern.
+  pub fn matches(&self, path: &Path) -> bool {
+    let pattern: Vec<_> = self.pattern.components().collect();
+    let path: Vec<_> = path.components().collect();
+
+    match_components(&pattern, &path)
+  }
+}
+
+impl From<GlobKey> for DepKey {
+  fn from(key: GlobKey) -> Self {
+    DepKey::Typed(ErasedKey::new(key))
+  }
+}
+
+impl Key for GlobKey {
+  fn prepare_key(self, root: &Path) -> Self {
+    GlobKey {
+      pattern: vfs_substite_path(&self.pattern, root),
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    GlobKey::new(normalization.apply(&self.pattern)).prepare_key(root)
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    GlobKey {
+      pattern: mounts.route(&self.pattern),
+    }
+  }
+}
+
+/// Check whether a path component contains wildcards.
+fn is_wildcard(component: &Component) -> bool {
+  component
+    .as_os_str()
+    .to_string_lossy()
+    .contains(|c| c == '*' || c == '?')
+}
+
+/// Match path components against pattern components.
+fn match_components(pattern: &[Component], path: &[Component]) -> bool {
+  match pattern.split_first() {
+    None => path.is_empty(),
+
+    Some((first, rest)) if first.as_os_str() == "**" => {
+      (0..=path.len()).any(|skipped| match_components(rest, &path[skipped..]))
+    }
+
+    Some((first, rest)) => match path.split_first() {
+      Some((component, path_rest)) => {
+        let pattern: Vec<_> = first.as_os_str().to_string_lossy().chars().collect();
+        let component: Vec<_> = component.as_os_str().to_string_lossy().chars().collect();
+
+        match_chars(&pattern, &component) && match_components(rest, path_rest)
+      }
+
+      None => false,
+    },
+  }
+}
+
+/// Match the characters of a path component against the ones of a pattern component.
+fn match_chars(pattern: &[char], s: &[char]) -> bool {
+  match pattern.split_first() {
+    None => s.is_empty(),
+    Some((&'*', rest)) => (0..=s.len()).any(|skipped| match_chars(rest, &s[skipped..])),
+    Some((&'?', rest)) => !s.is_empty() && match_chars(rest, &s[1..]),
+    Some((c, rest)) => s.first() == Some(c) && match_chars(rest, &s[1..]),
+  }
+}
+
+/// A set of resources selected by a glob pattern.
+///
+/// Members are sorted by the VFS path of their file – e.g. `/textures/hero.png`.
+pub struct ResourceSet<T> {
+  members: Vec<(FSKey, Res<T>)>,
+}
+
+impl<T> ResourceSet<T> {
+  /// Get the member living at a given VFS path.
+  pub fn get(&self, key: &FSKey) -> Option<&Res<T>> {
+    self
+      .members
+      .iter()
+      .find(|&&(ref member, _)| member == key)
+      .map(|&(_, ref res)| res)
+  }
+
+  /// Iterate over all the members, sorted by VFS path.
+  pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a FSKey, &'a Res<T>)> + 'a {
+    self.members.iter().map(|&(ref key, ref res)| (key, res))
+  }
+
+  /// Number of members.
+  pub fn len(&self) -> usize {
+    self.members.len()
+  }
+
+  /// Whether the set has no member.
+  pub fn is_empty(&self) -> bool {
+    self.members.is_empty()
+  }
+}
+
+impl<C, T, M> Load<C, M> for ResourceSet<T>
+where
+  T: Load<C, M, Key = FSKey>,
+  M: 'static,
+{
+  type Key = GlobKey;
+
+  type Error = ResourceMapError<T::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    // a missing directory is just empty; files might appear in it later
+    let base = key.base();
+    let mut paths = Vec::new();
+
+    if base.is_dir() {
+      find_files(&base, &mut paths).map_err(ResourceMapError::Io)?;
+    }
+
+    // the pattern is normalized; so must be the paths it’s matched against
+    paths.retain(|path| key.matches(&storage.normalize_path(path)));
+    paths.sort();
+
+    let mut members = Vec::with_capacity(paths.len());
+
+    for path in paths {
+      let fs_key = storage.file_key(&path);
+
+      // members already living in the storage are just looked up
+      let res = storage
+        .get_with::<FSKey, T, M>(&fs_key, ctx)
+        .map_err(|e| match e {
+          StoreErrorOr::StoreError(e) => ResourceMapError::Store(path, e),
+          StoreErrorOr::ResError(e) => ResourceMapError::Entry(path, e),
+        })?;
+
+      members.push((fs_key, res));
+    }
+
+    let deps = vec![DirKey::new(storage.vfs_path(&base).unwrap_or(base)).into()];
+
+    Ok(Loaded::with_deps(ResourceSet { members }, deps))
+  }
+
+  fn reload(
+    &self,
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error>
+  {
+    let set = <Self as Load<C, M>>::load(key, storage, ctx)?.res;
+
+    // evict the members which files are gone
+    for &(ref fs_key, _) in &self.members {
+      if set.get(fs_key).is_none() {
+        storage.evict(&fs_key.clone().into());
+      }
+    }
+
+    Ok(set)
+  }
+}
+
+impl<C> Storage<C> {
+  /// Get all the resources which VFS paths match a glob pattern, as a set.
+  ///
+  /// This function uses the default loading method. See the documentation of the `glob` module
+  /// for further details.
+  pub fn get_all<T>(
+    &mut self,
+    key: &GlobKey,
+    ctx: &mut C,
+  ) -> Result<Res<ResourceSet<T>>, StoreErrorOr<ResourceSet<T>, C>>
+  where
+    T: Load<C, Key = FSKey>,
+  {
+    self.get(key, ctx)
+  }
+
+  /// Get all the resources which VFS paths match a glob pattern, as a set, by using a specific
+  /// method.
+  pub fn get_all_by<T, M>(
+    &mut self,
+    key: &GlobKey,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<ResourceSet<T>>, StoreErrorOr<ResourceSet<T>, C, M>>
+  where
+    T: Load<C, M, Key = FSKey>,
+    M: 'static,
+  {
+    self.get_by(key, ctx, method)
+  }
+}
@@ -0,0 +1,66 @@
+//! Dependency graph introspection.
+//!
+//! This module exposes a read-only view of the dependency graph a `Storage` maintains between its
+//! resources. It’s mostly useful for debugging purposes – for instance, to understand why a given
+//! reload cascaded into other resources.
+
+use std::fmt::Write;
+
+use key::DepKey;
+
+/// A snapshot of the dependency graph of a `Storage`.
+///
+/// Nodes are all the keys known by the `Storage` – either because they’re registered resources or
+/// because a resource declared them as dependencies. Edges are oriented from a dependency to its
+/// dependent resource: that is, the direction in which reloads cascade.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DependencyGraph {
+  nodes: Vec<DepKey>,
+  edges: Vec<(DepKey, DepKey)>,
+}
+
+impl DependencyGraph {
+  pub(crate) fn new(mut nodes: Vec<DepKey>, mut edges: Vec<(DepKey, DepKey)>) -> Self {
+    // sort everything so that the output is deterministic
+    nodes.sort();
+    nodes.dedup();
+    edges.sort();
+    edges.dedup();
+
+    DependencyGraph { nodes, edges }
+  }
+
+  /// All the keys living in the graph.
+  pub fn nodes(&self) -> &[DepKey] {
+    &self.nodes
+  }
+
+  /// All the edges of the graph, as `(dependency, dependent)` pairs.
+  pub fn edges(&self) -> &[(DepKey, DepKey)] {
+    &self.edges
+  }
+
+  /// Render the graph in the [DOT] format.
+  ///
+  /// [DOT]: https://www.graphviz.org/doc/info/lang.html
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::from("digraph warmy {\n");
+
+    for node in &self.nodes {
+      let _ = writeln!(dot, "  {};", dot_id(node));
+    }
+
+    for &(ref dep, ref dependent) in &self.edges {
+      let _ = writeln!(dot, "  {} -> {};", dot_id(dep), dot_id(dependent));
+    }
+
+    dot.push_str("}\n");
+    dot
+  }
+}
+
+/// Turn a key into a quoted DOT identifier.
+fn dot_id(key: &DepKey) -> String {
+  let label = key.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+  format!("\"{}\"", label)
+}
@@ -0,0 +1,144 @@
+//! Pre-resolved keys.
+//!
+//! Getting a resource from a `Storage` prepares its key – substituting the VFS root, checking it
+//! doesn’t escape it – and builds the key identifying it in the cache. That costs a few clones and
+//! allocations per call, which add up when fetching thousands of resources per frame.
+//!
+//! A `Handle` does that work once: get it with `Storage::handle` and fetch the resource with
+//! `Storage::get_handle` as many times as needed. Fetching a resource living in the `Storage`
+//! through a handle doesn’t allocate.
+//!
+//! ```ignore
+//! let tex = store.handle::<_, Texture>(&FSKey::new("/tex.png"))?;
+//!
+//! loop {
+//!   let tex = store.get_handle(&tex, ctx)?;
+//!   // …
+//! }
+//! ```
+
+use std::any::TypeId;
+use std::fmt;
+use std::marker::PhantomData;
+
+use cache::CacheKey;
+use key::Key;
+use load::{Load, Storage, StoreError, StoreErrorOr};
+use res::Res;
+
+/// A key resolved once and for all for resources of type `T`.
+///
+/// `K` is the type of key of `T`. See the documentation of the `handle` module for further details.
+pub struct Handle<T, K> {
+  key: K,
+  cache_key: CacheKey,
+  _res: PhantomData<fn() -> T>,
+}
+
+impl<T, K> Handle<T, K> {
+  /// The prepared key.
+  pub fn key(&self) -> &K {
+    &self.key
+  }
+
+  /// The key identifying the resource in the cache.
+  pub fn cache_key(&self) -> &CacheKey {
+    &self.cache_key
+  }
+}
+
+impl<T, K> Clone for Handle<T, K>
+where K: Clone
+{
+  fn clone(&self) -> Self {
+    Handle {
+      key: self.key.clone(),
+      cache_key: self.cache_key.clone(),
+      _res: PhantomData,
+    }
+  }
+}
+
+impl<T, K> fmt::Debug for Handle<T, K>
+where K: fmt::Debug
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("Handle")
+      .field("key", &self.key)
+      .field("cache_key", &self.cache_key)
+      .finish()
+  }
+}
+
+impl<C> Storage<C> {
+  /// Resolve a key for resources of type `T`, loaded with the default method.
+  ///
+  /// Fails with `StoreError::KeyOutsideRoot` if the key escapes the root – see
+  /// `StoreOpt::set_allow_outside_root`.
+  pub fn handle<K, T>(&self, key: &K) -> Result<Handle<T, T::Key>, StoreError>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>,
+  {
+    self.handle_by(key, ())
+  }
+
+  /// Resolve a key for resources of type `T`, loaded with a specific method.
+  ///
+  /// See `Storage::handle`.
+  pub fn handle_by<K, T, M>(&self, key: &K, _: M) -> Result<Handle<T, T::Key>, StoreError>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    let key = self.prepare_checked(key.clone().into())?;
+    let cache_key = CacheKey::with_method::<T, M>(key.clone().into());
+
+    Ok(Handle {
+      key,
+      cache_key,
+      _res: PhantomData,
+    })
+  }
+
+  /// Get a resource from the `Storage` through a handle and return an error if its loading failed.
+  ///
+  /// This function uses the default loading method.
+  pub fn get_handle<T>(
+    &mut self,
+    handle: &Handle<T, T::Key>,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+  {
+    self.get_handle_by(handle, ctx, ())
+  }
+
+  /// Get a resource from the `Storage` through a handle by using a specific method and return an
+  /// error if its loading failed.
+  ///
+  /// Fetching the resource doesn’t allocate if the handle was resolved for that method – see
+  /// `Storage::handle_by`.
+  pub fn get_handle_by<T, K, M>(
+    &mut self,
+    handle: &Handle<T, K>,
+    ctx: &mut C,
+    _: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M, Key = K>,
+    K: Key,
+    M: 'static,
+  {
+    let load = <T as Load<C, M>>::load;
+
+    if handle.cache_key.method() == TypeId::of::<M>() {
+      self.get_or_load_cached(&handle.key, &handle.cache_key, ctx, None, load)
+    } else {
+      let cache_key = CacheKey::with_method::<T, M>(handle.key.clone().into());
+      self.get_or_load_cached(&handle.key, &cache_key, ctx, None, load)
+    }
+  }
+}
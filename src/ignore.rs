@@ -0,0 +1,74 @@
+//! Ignore patterns for filesystem events.
+//!
+//! Editors and version control tools write a lot of files nobody loads – swap files, backups,
+//! `.git` objects – and each of them yields filesystem events. A `Store` drops the events about
+//! paths matching one of its ignore patterns before marking anything dirty. Add patterns with
+//! `StoreOpt::add_ignore_pattern`.
+//!
+//! Patterns are globs: `*` matches any sequence of characters but `/` and `?` matches any single
+//! character but `/`. A pattern without `/` is matched against every component of the path
+//! relative to the root – `.git` ignores everything under a `.git` directory, at any depth. A
+//! pattern with a `/` is matched against the whole path relative to the root, without leading `/`.
+
+use std::path::Path;
+
+/// Patterns ignored by default.
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+  "*.swp", "*.swx", "*~", ".#*", "#*#", ".git", ".hg", ".svn", ".DS_Store",
+];
+
+/// Check whether a glob pattern matches a text.
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+  let pattern = pattern.as_bytes();
+  let text = text.as_bytes();
+  let (mut p, mut t) = (0, 0);
+  // position of the last star in the pattern and of the text it started matching at
+  let mut backtrack = None;
+
+  while t < text.len() {
+    match pattern.get(p) {
+      Some(b'*') => {
+        backtrack = Some((p, t));
+        p += 1;
+      }
+
+      Some(&c) if (c == b'?' && text[t] != b'/') || c == text[t] => {
+        p += 1;
+        t += 1;
+      }
+
+      _ => match backtrack {
+        // let the last star eat one more character, unless it’s a separator
+        Some((star, start)) if text[start] != b'/' => {
+          backtrack = Some((star, start + 1));
+          p = star + 1;
+          t = start + 1;
+        }
+
+        _ => return false,
+      },
+    }
+  }
+
+  pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Check whether a path, relative to the root, is matched by any of the patterns.
+pub fn is_ignored<P>(patterns: &[P], relative: &Path) -> bool
+where P: AsRef<str> {
+  let components: Vec<_> = relative
+    .components()
+    .map(|c| c.as_os_str().to_string_lossy())
+    .collect();
+  let whole = components.join("/");
+
+  patterns.iter().any(|pattern| {
+    let pattern = pattern.as_ref();
+
+    if pattern.contains('/') {
+      glob_matches(pattern.trim_left_matches('/'), &whole)
+    } else {
+      components.iter().any(|c| glob_matches(pattern, c))
+    }
+  })
+}
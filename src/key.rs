@@ -1,30 +1,88 @@
 //! Module exporting all key types recognized by `warmy`.
 //!
-//! This module provides you with three main types:
+//! This module provides you with six main types:
 //!
-//!   - `FSKey`:
-//!   - `LogicalKey`.
-//!   - `DeyKep`.
+//!   - `FSKey`, a key to a resource living on the filesystem.
+//!   - `FSKeyWith`, a filesystem key carrying processing parameters.
+//!   - `DirKey`, a key to a whole directory of resources.
+//!   - `LogicalKey`, a key to a resource living in memory or computed on the fly.
+//!   - `TypedKey`, a key carrying a typed payload.
+//!   - `DepKey`, the type-erased key used to express dependencies.
 
-use any_cache::CacheKey;
+use std::any::{Any, TypeId};
+use std::cmp::Ordering;
+use std::fmt;
 use std::hash;
-use std::marker::PhantomData;
 use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
-use res::Res;
+use mount::Mounts;
 
 /// A dependency key, used to express dependency.
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum DepKey {
   /// A key to a resource living on the filesystem – akin to `FSKey`.
   Path(PathBuf),
+  /// A key to a resource living on the filesystem, loaded with a parameter – akin to `FSKeyWith`.
+  PathWith(PathBuf, ErasedKey),
+  /// A key to a resource living on the filesystem at an absolute path, bypassing the root – akin
+  /// to `FSKey::absolute`.
+  ///
+  /// Once prepared, such a key becomes a `DepKey::Path`.
+  HostPath(PathBuf),
   /// A key to a resource living in memory or computed on the fly – akin to `LogicalKey`.
   Logical(String),
+  /// A key to a resource living in memory or computed on the fly, carrying a typed payload – akin
+  /// to `TypedKey`.
+  Typed(ErasedKey),
+  /// A key to a directory living on the filesystem, which changes whenever a file is created,
+  /// removed or modified under it – akin to `DirKey`.
+  Dir(PathBuf),
+}
+
+impl fmt::Display for DepKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      DepKey::Path(ref path) | DepKey::HostPath(ref path) => write!(f, "{}", path.display()),
+      DepKey::Dir(ref path) => write!(f, "{}/", path.display()),
+      DepKey::PathWith(ref path, ref param) => write!(f, "{} ({:?})", path.display(), param),
+      DepKey::Logical(ref s) => f.write_str(s),
+      DepKey::Typed(ref key) => write!(f, "{:?}", key),
+    }
+  }
+}
+
+impl DepKey {
+  /// Path of the file the key refers to, if any.
+  pub fn path(&self) -> Option<&Path> {
+    match *self {
+      DepKey::Path(ref path)
+      | DepKey::PathWith(ref path, _)
+      | DepKey::HostPath(ref path)
+      | DepKey::Dir(ref path) => Some(path),
+      _ => None,
+    }
+  }
+}
+
+/// Kind of a dependency.
+///
+/// A *strong* dependency makes the dependent resource reload whenever the dependency reloads. A
+/// *weak* dependency only notifies the dependent resource – see `Load::dependency_reloaded`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DepKind {
+  /// The dependent resource is reloaded when the dependency reloads.
+  Strong,
+  /// The dependent resource is only notified when the dependency reloads.
+  Weak,
 }
 
 /// Filesystem key.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct FSKey(PathBuf);
+pub struct FSKey {
+  path: PathBuf,
+  absolute: bool,
+}
 
 impl FSKey {
   /// Create a new `FSKey` by providing a VFS path.
@@ -33,18 +91,115 @@ impl FSKey {
   /// get transformed by a `Store` when used by inspecting the `Store`’s root.
   pub fn new<P>(path: P) -> Self
   where P: AsRef<Path> {
-    FSKey(path.as_ref().to_owned())
+    FSKey {
+      path: path.as_ref().to_owned(),
+      absolute: false,
+    }
+  }
+
+  /// Create a new `FSKey` by providing an absolute path on the host filesystem.
+  ///
+  /// Unlike VFS paths, absolute paths are not transformed by the `Store`’s root and may refer to
+  /// files living anywhere – a user-supplied configuration file, for instance. Because you build
+  /// them explicitly, they’re trusted: they’re accepted even if `StoreOpt::allow_outside_root` is
+  /// not set. The `Store` watches the directories of such files as they get loaded.
+  pub fn absolute<P>(path: P) -> Self
+  where P: AsRef<Path> {
+    FSKey {
+      path: path.as_ref().to_owned(),
+      absolute: true,
+    }
   }
 
   /// Get the underlying path.
   pub fn as_path(&self) -> &Path {
-    self.0.as_path()
+    self.path.as_path()
+  }
+
+  /// Whether the key was created with `FSKey::absolute` and not prepared yet.
+  pub fn is_absolute(&self) -> bool {
+    self.absolute
   }
 }
 
 impl From<FSKey> for DepKey {
   fn from(key: FSKey) -> Self {
-    DepKey::Path(key.0)
+    if key.absolute {
+      DepKey::HostPath(key.path)
+    } else {
+      DepKey::Path(key.path)
+    }
+  }
+}
+
+/// Directory key.
+///
+/// A resource living at a directory – a level list enumerating all the files of `/levels`, for
+/// instance – reloads whenever a file is created, removed or modified anywhere under the directory.
+/// So do the resources depending on a directory key, whether a resource lives there or not.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DirKey {
+  path: PathBuf,
+}
+
+impl DirKey {
+  /// Create a new `DirKey` by providing the VFS path of a directory – see `FSKey::new`.
+  pub fn new<P>(path: P) -> Self
+  where P: AsRef<Path> {
+    DirKey {
+      path: path.as_ref().to_owned(),
+    }
+  }
+
+  /// Get the underlying path.
+  pub fn as_path(&self) -> &Path {
+    self.path.as_path()
+  }
+}
+
+impl From<DirKey> for DepKey {
+  fn from(key: DirKey) -> Self {
+    DepKey::Dir(key.path)
+  }
+}
+
+/// Filesystem key with a parameter.
+///
+/// Use it to load the same file several times with different parameters – a texture with different
+/// mipmap settings, a level with different difficulties, etc. Each parameter yields a distinct
+/// resource, yet all of them get reloaded whenever the file changes.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FSKeyWith<P> {
+  path: PathBuf,
+  param: P,
+}
+
+impl<P> FSKeyWith<P> {
+  /// Create a new `FSKeyWith` by providing a VFS path – see `FSKey::new` – and a parameter.
+  pub fn new<Q>(path: Q, param: P) -> Self
+  where Q: AsRef<Path> {
+    FSKeyWith {
+      path: path.as_ref().to_owned(),
+      param,
+    }
+  }
+
+  /// Get the underlying path.
+  pub fn as_path(&self) -> &Path {
+    self.path.as_path()
+  }
+
+  /// Get the parameter.
+  pub fn param(&self) -> &P {
+    &self.param
+  }
+}
+
+impl<P> From<FSKeyWith<P>> for DepKey
+where P: Clone + Eq + hash::Hash + fmt::Debug + Send + Sync + 'static
+{
+  fn from(key: FSKeyWith<P>) -> Self {
+    DepKey::PathWith(key.path, ErasedKey::new(key.param))
   }
 }
 
@@ -71,8 +226,146 @@ impl From<LogicalKey> for DepKey {
   }
 }
 
+/// Logical key carrying a typed payload.
+///
+/// Use it instead of `LogicalKey` when your keys are structured – tuples, enums, etc. – rather than
+/// serializing them into strings and parsing them back in `Load::load`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TypedKey<K>(K);
+
+impl<K> TypedKey<K> {
+  /// Create a new `TypedKey` by providing its payload.
+  pub fn new(payload: K) -> Self {
+    TypedKey(payload)
+  }
+
+  /// Get the payload the key holds.
+  pub fn payload(&self) -> &K {
+    &self.0
+  }
+
+  /// Get the payload back.
+  pub fn into_payload(self) -> K {
+    self.0
+  }
+}
+
+impl<K> From<TypedKey<K>> for DepKey
+where K: Clone + Eq + hash::Hash + fmt::Debug + Send + Sync + 'static
+{
+  fn from(key: TypedKey<K>) -> Self {
+    DepKey::Typed(ErasedKey::new(key.0))
+  }
+}
+
+/// Type-erased payload of a `TypedKey`, as held by `DepKey::Typed`.
+///
+/// Two erased keys are equal if their payloads have the same type and are equal. They’re ordered
+/// by type first, then by the `Debug` representation of their payloads.
+#[derive(Clone)]
+pub struct ErasedKey(Arc<Payload>);
+
+impl ErasedKey {
+  /// Erase a payload.
+  pub fn new<K>(payload: K) -> Self
+  where K: Clone + Eq + hash::Hash + fmt::Debug + Send + Sync + 'static {
+    ErasedKey(Arc::new(payload))
+  }
+
+  /// Check whether the payload is of type `K`.
+  pub fn is<K>(&self) -> bool
+  where K: 'static {
+    self.0.payload_type_id() == TypeId::of::<K>()
+  }
+
+  /// Get the payload if it’s of type `K`.
+  pub fn downcast_ref<K>(&self) -> Option<&K>
+  where K: 'static {
+    self.0.as_any().downcast_ref()
+  }
+}
+
+impl fmt::Debug for ErasedKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    self.0.fmt_payload(f)
+  }
+}
+
+impl PartialEq for ErasedKey {
+  fn eq(&self, rhs: &Self) -> bool {
+    self.0.eq_payload(rhs.0.as_any())
+  }
+}
+
+impl Eq for ErasedKey {}
+
+impl hash::Hash for ErasedKey {
+  fn hash<H>(&self, state: &mut H)
+  where H: hash::Hasher {
+    self.0.payload_type_id().hash(state);
+    self.0.hash_payload(state);
+  }
+}
+
+impl PartialOrd for ErasedKey {
+  fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for ErasedKey {
+  fn cmp(&self, rhs: &Self) -> Ordering {
+    if self == rhs {
+      return Ordering::Equal;
+    }
+
+    self
+      .0
+      .payload_type_id()
+      .cmp(&rhs.0.payload_type_id())
+      .then_with(|| format!("{:?}", self).cmp(&format!("{:?}", rhs)))
+  }
+}
+
+/// Object-safe interface of the payloads of `ErasedKey`.
+trait Payload: Send + Sync {
+  fn as_any(&self) -> &Any;
+
+  fn payload_type_id(&self) -> TypeId;
+
+  fn eq_payload(&self, rhs: &Any) -> bool;
+
+  fn hash_payload(&self, state: &mut hash::Hasher);
+
+  fn fmt_payload(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error>;
+}
+
+impl<K> Payload for K
+where K: Clone + Eq + hash::Hash + fmt::Debug + Send + Sync + 'static
+{
+  fn as_any(&self) -> &Any {
+    self
+  }
+
+  fn payload_type_id(&self) -> TypeId {
+    TypeId::of::<K>()
+  }
+
+  fn eq_payload(&self, rhs: &Any) -> bool {
+    rhs.downcast_ref::<K>().map_or(false, |rhs| self == rhs)
+  }
+
+  fn hash_payload(&self, mut state: &mut hash::Hasher) {
+    self.hash(&mut state);
+  }
+
+  fn fmt_payload(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    fmt::Debug::fmt(self, f)
+  }
+}
+
 /// Class of keys recognized by `warmy`.
-pub trait Key: Clone + hash::Hash + Into<DepKey> {
+pub trait Key: Clone + Eq + hash::Hash + Into<DepKey> {
   /// Prepare a key.
   ///
   /// If your key is akin to `FSKey`, it’s very likely you need to substitute its VFS path with the
@@ -83,20 +376,184 @@ pub trait Key: Clone + hash::Hash + Into<DepKey> {
   /// > `FSKey`. You’re **strongly advised** to implement `From<YourKey> for FSKey` instead, unless
   /// > you know exactly what you’re doing.
   fn prepare_key(self, root: &Path) -> Self;
+
+  /// Prepare a key, normalizing its VFS path first – see `StoreOpt::set_path_normalization`.
+  ///
+  /// The default implementation ignores the normalization. Keys akin to `FSKey` should apply it to
+  /// their VFS path with `PathNormalization::apply` before substituting it.
+  fn prepare_key_with(self, root: &Path, _normalization: PathNormalization) -> Self
+  where Self: Sized {
+    self.prepare_key(root)
+  }
+
+  /// Route a prepared key to the directories mounted with `StoreOpt::add_mount`.
+  ///
+  /// The default implementation leaves the key untouched. Keys akin to `FSKey` should route their
+  /// real path with `Mounts::route`.
+  fn mount_key(self, _mounts: &Mounts) -> Self
+  where Self: Sized {
+    self
+  }
+}
+
+/// How the VFS paths of keys are normalized when prepared by a `Store`.
+///
+/// Keys written differently – `\textures\hero.png` on Windows and `/textures/hero.png` elsewhere,
+/// or `/Textures/Hero.png` on a case-insensitive filesystem – refer to the same file. Normalized,
+/// they compare equal: the file is loaded once and filesystem events are matched against it. Host
+/// paths – see `FSKey::absolute` – are never normalized.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PathNormalization {
+  /// Turn backslashes into forward slashes.
+  pub separators: bool,
+  /// Lowercase paths. Only use it on case-insensitive filesystems: files which names have
+  /// uppercase letters cannot be opened otherwise.
+  pub case_folding: bool,
+}
+
+impl PathNormalization {
+  /// No normalization – the default.
+  pub fn none() -> Self {
+    Self::default()
+  }
+
+  /// Normalize both separators and case.
+  pub fn all() -> Self {
+    PathNormalization {
+      separators: true,
+      case_folding: true,
+    }
+  }
+
+  /// Whether the normalization does nothing.
+  pub fn is_none(&self) -> bool {
+    !self.separators && !self.case_folding
+  }
+
+  /// Normalize a VFS path.
+  pub fn apply(&self, path: &Path) -> PathBuf {
+    if self.is_none() {
+      return path.to_owned();
+    }
+
+    let mut path = path.to_string_lossy().into_owned();
+
+    if self.separators {
+      path = path.replace('\\', "/");
+    }
+
+    if self.case_folding {
+      path = path.to_lowercase();
+    }
+
+    path.into()
+  }
 }
 
 impl Key for DepKey {
   fn prepare_key(self, root: &Path) -> Self {
     match self {
       DepKey::Path(path) => DepKey::Path(vfs_substite_path(&path, root)),
+      DepKey::PathWith(path, param) => DepKey::PathWith(vfs_substite_path(&path, root), param),
+      DepKey::HostPath(path) => DepKey::Path(normalize(path.components())),
       DepKey::Logical(x) => DepKey::Logical(x),
+      DepKey::Typed(x) => DepKey::Typed(x),
+      DepKey::Dir(path) => DepKey::Dir(vfs_substite_path(&path, root)),
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    let key = match self {
+      DepKey::Path(path) => DepKey::Path(normalization.apply(&path)),
+      DepKey::PathWith(path, param) => DepKey::PathWith(normalization.apply(&path), param),
+      DepKey::Dir(path) => DepKey::Dir(normalization.apply(&path)),
+      key => key,
+    };
+
+    key.prepare_key(root)
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    match self {
+      DepKey::Path(path) => DepKey::Path(mounts.route(&path)),
+      DepKey::PathWith(path, param) => DepKey::PathWith(mounts.route(&path), param),
+      DepKey::Dir(path) => DepKey::Dir(mounts.route(&path)),
+      key => key,
     }
   }
 }
 
 impl Key for FSKey {
   fn prepare_key(self, root: &Path) -> Self {
-    FSKey(vfs_substite_path(self.as_path(), root))
+    let path = if self.absolute {
+      normalize(self.path.components())
+    } else {
+      vfs_substite_path(self.as_path(), root)
+    };
+
+    FSKey {
+      path,
+      absolute: false,
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    if self.absolute {
+      return self.prepare_key(root);
+    }
+
+    FSKey::new(normalization.apply(&self.path)).prepare_key(root)
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    FSKey {
+      path: mounts.route(&self.path),
+      ..self
+    }
+  }
+}
+
+impl<P> Key for FSKeyWith<P>
+where P: Clone + Eq + hash::Hash + fmt::Debug + Send + Sync + 'static
+{
+  fn prepare_key(self, root: &Path) -> Self {
+    FSKeyWith {
+      path: vfs_substite_path(self.as_path(), root),
+      ..self
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    FSKeyWith {
+      path: normalization.apply(&self.path),
+      ..self
+    }
+    .prepare_key(root)
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    FSKeyWith {
+      path: mounts.route(&self.path),
+      ..self
+    }
+  }
+}
+
+impl Key for DirKey {
+  fn prepare_key(self, root: &Path) -> Self {
+    DirKey {
+      path: vfs_substite_path(self.as_path(), root),
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    DirKey::new(normalization.apply(&self.path)).prepare_key(root)
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    DirKey {
+      path: mounts.route(&self.path),
+    }
   }
 }
 
@@ -106,38 +563,78 @@ impl Key for LogicalKey {
   }
 }
 
+impl<K> Key for TypedKey<K>
+where K: Clone + Eq + hash::Hash + fmt::Debug + Send + Sync + 'static
+{
+  fn prepare_key(self, _: &Path) -> Self {
+    self
+  }
+}
+
+impl DepKey {
+  /// Turn a prepared key back into a key expressed with a VFS path, so that it can be prepared
+  /// again.
+  pub(crate) fn unprepare_key(self, root: &Path, mounts: &Mounts) -> Self {
+    match self {
+      // paths outside of the root can only be reached through absolute keys
+      DepKey::Path(path) => match vfs_path(&path, root, mounts) {
+        Some(vfs_path) => DepKey::Path(vfs_path),
+        None => DepKey::HostPath(path),
+      },
+      DepKey::PathWith(path, param) => {
+        DepKey::PathWith(vfs_path(&path, root, mounts).unwrap_or(path), param)
+      }
+      DepKey::Dir(path) => DepKey::Dir(vfs_path(&path, root, mounts).unwrap_or(path)),
+      key => key,
+    }
+  }
+}
+
+/// Get the VFS path of a real path living under the root or in a mounted directory, if any.
+pub(crate) fn vfs_path(path: &Path, root: &Path, mounts: &Mounts) -> Option<PathBuf> {
+  let unrouted = mounts.unroute(path);
+  let path = unrouted.as_ref().map_or(path, PathBuf::as_path);
+
+  path
+    .strip_prefix(root)
+    .ok()
+    .map(|relative| Path::new("/").join(relative))
+}
+
 /// Substitute a VFS path by a real one.
-fn vfs_substite_path(path: &Path, root: &Path) -> PathBuf {
+///
+/// The resulting path is normalized: `.` and `..` components are resolved lexically, so that it
+/// can be checked against the root.
+pub(crate) fn vfs_substite_path(path: &Path, root: &Path) -> PathBuf {
   let mut components = path.components().peekable();
   let root_components = root.components();
 
-  match components.peek() {
+  let components: Vec<_> = match components.peek() {
     Some(&Component::RootDir) => {
       // drop the root component
       root_components.chain(components.skip(1)).collect()
     }
 
     _ => root_components.chain(components).collect(),
-  }
-}
-
-pub(crate) struct PrivateKey<T>(DepKey, PhantomData<T>);
+  };
 
-impl<T> PrivateKey<T> {
-  pub(crate) fn new(dep_key: DepKey) -> Self {
-    PrivateKey(dep_key, PhantomData)
-  }
+  normalize(components)
 }
 
-impl<T> hash::Hash for PrivateKey<T> {
-  fn hash<H>(&self, state: &mut H)
-  where H: hash::Hasher {
-    self.0.hash(state)
+/// Resolve the `.` and `..` components of a path lexically.
+pub(crate) fn normalize<'a, I>(components: I) -> PathBuf
+where I: IntoIterator<Item = Component<'a>> {
+  let mut normalized = PathBuf::new();
+
+  for component in components {
+    match component {
+      Component::CurDir => (),
+      Component::ParentDir => {
+        normalized.pop();
+      }
+      component => normalized.push(component.as_os_str()),
+    }
   }
-}
 
-impl<T> CacheKey for PrivateKey<T>
-where T: 'static
-{
-  type Target = Res<T>;
+  normalized
 }
@@ -87,6 +87,19 @@
 //! > expressed with a leading `'/'`. Both `FSKey::new("/zulu.json")` and `FSKey::new("zulu.json")`
 //! > refer to the exact same resource.
 //!
+//! Keys escaping the root – e.g. `FSKey::new("../../etc/passwd")` – are refused when loading,
+//! unless you allow them with `StoreOpt::set_allow_outside_root`. To load a file living outside of
+//! the root on purpose – a user-supplied configuration file, for instance – build its key with
+//! `FSKey::absolute`: absolute keys bypass the root and are hot-reloaded as well.
+//!
+//! If you need to load the same file several times with different parameters – say, a texture with
+//! different mipmap settings – use a [FSKeyWith] instead: each parameter yields a distinct
+//! resource, all of them reloaded whenever the file changes.
+//!
+//! A [DirKey] refers to a whole directory – say, `/levels`, to enumerate all the levels. A resource
+//! living at a directory, or depending on one, reloads whenever a file is created, removed or
+//! modified anywhere under it.
+//!
 //! ### Flexibility: `LogicalKey`, the memory key
 //!
 //! This type of key is a bit hard to wrap your finger around at first, because you might not need
@@ -103,8 +116,15 @@
 //! let my_key = LogicalKey::new("586e6452-4bac-11e8-842f-0ed5f89f718b");
 //! ```
 //!
-//! Logical keys are very simple to use and may contain any kind of information. However, for now,
-//! they must be encoded with strings.
+//! Logical keys are very simple to use and may contain any kind of information encoded as a
+//! string. If your keys are structured, use a [TypedKey] instead: it carries any payload that is
+//! `Clone + Eq + Hash + Debug`, such as a tuple, which you get back in `Load::load`.
+//!
+//! ```
+//! use warmy::TypedKey;
+//!
+//! let my_key = TypedKey::new(("level", 3));
+//! ```
 //!
 //! ### Special case: dependency key
 //!
@@ -112,7 +132,7 @@
 //! implements [Key] also implements `Into<DepKey>`, which comes in handy when you want to build
 //! heterogenous lists of dependency keys.
 //!
-//! [DepKey] is either akin to a [FSKey] or [LogicalKey].
+//! [DepKey] is either akin to a [FSKey], a [FSKeyWith], a [DirKey], a [LogicalKey] or a [TypedKey].
 //!
 //! ## `Load::Error`
 //!
@@ -288,6 +308,15 @@
 //!
 //! See the documentation of [Load::reload] for further details.
 //!
+//! Filesystem changes are detected with the [notify] crate, behind the `watcher` feature – enabled
+//! by default. On platforms without filesystem notification, such as `wasm32`, disable it: [Store]
+//! and its cache keep working, but resources only reload when marked dirty with
+//! [Store::mark_dirty].
+//!
+//! Enable the `log` feature to have `warmy` emit [log] records about what it does – loading and
+//! reloading resources, debouncing and ignoring filesystem events, failures. It helps diagnosing a
+//! resource that doesn’t reload with your usual logger.
+//!
 //! # Context
 //!
 //! A context is a special value you can access to via a mutable references when loading or
@@ -336,6 +365,9 @@
 //! }
 //! ```
 //!
+//! When several libraries each need their own value in the context, a [TypeMapContext] and the
+//! [Fetch] trait spare you from writing a context type implementing all their traits by hand.
+//!
 //! # Load methods
 //!
 //! `warmy` supports load methods. Those are used to specify several ways to load an object of a
@@ -355,12 +387,20 @@
 //! [Load::reload]: load/trait.Load.html#tymethod.reload
 //! [Key]: key/trait.Key.html
 //! [FSKey]: key/struct.FSKey.html
+//! [FSKeyWith]: key/struct.FSKeyWith.html
+//! [DirKey]: key/struct.DirKey.html
 //! [LogicalKey]: key/struct.LogicalKey.html
+//! [TypedKey]: key/struct.TypedKey.html
 //! [DepKey]: key/struct.DepKey.html
 //! [Store]: load/struct.Store.html
 //! [Store::get]: load/struct.Store.html#method.get
 //! [Store::get_proxied]: load/struct.Store.html#method.get_proxied
 //! [Store::sync]: load/struct.Store.html#method.sync
+//! [Store::mark_dirty]: load/struct.Store.html#method.mark_dirty
+//! [notify]: https://crates.io/crates/notify
+//! [TypeMapContext]: context/struct.TypeMapContext.html
+//! [Fetch]: context/trait.Fetch.html
+//! [log]: https://crates.io/crates/log
 //! [StoreOpt]: load/struct.StoreOpt.html
 //! [Storage]: load/struct.Storage.html
 //! [serde-json]: https://crates.io/crates/serde_json
@@ -368,14 +408,147 @@
 //! [methods]: methods/index.html
 //! [VFS]: https://en.wikipedia.org/wiki/Virtual_file_system
 
-extern crate any_cache;
+#[cfg(feature = "serde-bincode")]
+extern crate bincode;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "fluent")]
+extern crate fluent_bundle;
+#[cfg(feature = "async")]
+extern crate futures_core;
+#[cfg(feature = "image")]
+extern crate image;
+#[cfg(feature = "libloading")]
+extern crate libloading;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "watcher")]
 extern crate notify;
+#[cfg(feature = "rhai")]
+extern crate rhai;
+#[cfg(feature = "serde-msgpack")]
+extern crate rmp_serde;
+#[cfg(feature = "serde-ron")]
+extern crate ron;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde-json")]
+extern crate serde_json;
+#[cfg(feature = "serde-yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "serde-toml")]
+extern crate toml;
+#[cfg(feature = "fluent")]
+extern crate unic_langid;
+#[cfg(feature = "derive")]
+#[allow(unused_imports)]
+#[macro_use]
+extern crate warmy_derive;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
+/// Emit a log record at the given level – `trace`, `debug`, `info`, `warn` or `error`.
+#[cfg(feature = "log")]
+macro_rules! log_event {
+  ($level:ident, $($arg:tt)+) => {
+    $level!($($arg)+)
+  };
+}
+
+/// Emit a log record at the given level; records go nowhere without the `log` feature.
+#[cfg(not(feature = "log"))]
+macro_rules! log_event {
+  ($level:ident, $($arg:tt)+) => {
+    if false {
+      let _ = format!($($arg)+);
+    }
+  };
+}
 
+pub mod artifact;
+pub mod cache;
+pub mod cancel;
+pub mod clock;
+pub mod contained;
+pub mod context;
+pub mod deferred;
+pub mod diff;
+#[cfg(feature = "async")]
+pub mod driver;
+#[cfg(feature = "libloading")]
+pub mod dylib;
+pub mod embedded;
+pub mod fallback;
+#[cfg(feature = "failure-injection")]
+pub mod fault;
+pub mod fx;
+pub mod glob;
+pub mod graph;
+pub mod handle;
+pub mod ignore;
 pub mod key;
 pub mod load;
+pub mod manifest;
+pub mod map;
 pub mod methods;
+pub mod metrics;
+pub mod migrate;
+pub mod mount;
+pub mod preload;
+pub mod process;
+pub mod progress;
+pub mod provenance;
+pub mod registry;
+#[cfg(feature = "remote")]
+pub mod remote;
 pub mod res;
+pub mod scope;
+#[cfg(feature = "rhai")]
+pub mod script;
+pub mod shader;
+pub mod sink;
+pub mod snapshot;
+#[cfg(feature = "streaming")]
+pub mod streaming;
+pub mod transform;
 
-pub use key::{DepKey, FSKey, Key, LogicalKey};
-pub use load::{Load, Loaded, Storage, Store, StoreError, StoreErrorOr, StoreOpt};
-pub use res::Res;
+pub use artifact::ArtifactCache;
+pub use cache::CacheBackend;
+pub use cancel::CancellationToken;
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use contained::SelfContained;
+pub use context::{Fetch, TypeMapContext};
+pub use deferred::LoadHandle;
+pub use diff::GenerationDiff;
+pub use embedded::EmbeddedSource;
+pub use fallback::FallbackStore;
+pub use glob::{GlobKey, ResourceSet};
+pub use graph::DependencyGraph;
+pub use handle::Handle;
+pub use key::{
+  DepKey, DepKind, DirKey, FSKey, FSKeyWith, Key, LogicalKey, PathNormalization, TypedKey,
+};
+pub use load::{
+  DependencyCheck, Load, Loaded, ReloadCheckReport, ReloadErrorPolicy, ReloadEvent, ReloadFailure,
+  ReloadOutcome, Storage, Store, StoreError, StoreErrorOr, StoreOpt, SyncReport, WatchMode,
+  WatcherBackend,
+};
+pub use manifest::{Manifest, ManifestFile, PreflightReport};
+pub use map::{ResourceMap, ResourceMapKey};
+pub use metrics::{ReadCounts, ReadMetrics, StoreMetrics};
+pub use mount::Mounts;
+pub use process::{Process, ProcessParams};
+pub use progress::{LoadStatus, Progress, ProgressRead, ProgressReport, ResourceProgress};
+pub use provenance::{Provenance, ResInfo};
+pub use registry::LoaderRegistry;
+pub use res::{AnyRes, Res, ResState, ResView, ResWeak};
+pub use scope::{Scope, ScopedStore};
+pub use sink::{ReloadRecord, ReloadSink};
+pub use snapshot::StoreSnapshot;
+pub use transform::ByteTransform;
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub use warmy_derive::*;
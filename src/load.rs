@@ -2,19 +2,56 @@
 //!
 //! This module exposes traits, types and functions you need to use to load and reload objects.
 
-use any_cache::{Cache, HashCache};
-use notify::{op::WRITE, raw_watcher, Op, RawEvent, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
+use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+#[cfg(feature = "watcher")]
+use notify::op::{CREATE, REMOVE, RENAME, WRITE};
+#[cfg(feature = "watcher")]
+use notify::{
+  self, raw_watcher, Op, PollWatcher, RawEvent, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::env;
 use std::error::Error;
 use std::fmt;
+use std::fs;
 use std::hash;
+use std::io;
+use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{channel, Receiver};
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 
-use key::{self, DepKey, Key, PrivateKey};
-use res::Res;
+use artifact::ArtifactCache;
+use cache::{CacheBackend, CacheKey, HashCache};
+use cancel::CancellationToken;
+use clock::{Clock, SystemClock};
+use deferred::Request;
+use diff::{GenerationDiff, Journal};
+#[cfg(feature = "async")]
+use driver::{self, TaskWaker};
+use embedded::EmbeddedSource;
+#[cfg(feature = "failure-injection")]
+use fault::Fault;
+use graph::DependencyGraph;
+#[cfg(feature = "watcher")]
+use ignore;
+use ignore::DEFAULT_IGNORE_PATTERNS;
+use key::{self, DepKey, DepKind, FSKey, Key, PathNormalization};
+use metrics::{ReadMetrics, StoreMetrics};
+use mount::Mounts;
+use progress::{Progress, ProgressReport, ProgressTracker};
+use provenance::{self, Backend, Provenance, ResInfo};
+use registry::{FromDepKey, LoaderRegistry, RegistryError};
+use res::{AnyRes, Res, ResState};
+use sink::{ReloadRecord, ReloadSink};
+use snapshot::{self, Frozen, StoreSnapshot};
+use transform::{ByteTransform, ByteTransforms};
 
 /// Class of types that can be loaded and reloaded.
 ///
@@ -46,6 +83,10 @@ where Method: ?Sized {
   // FIXME: add support for redeclaring the dependencies?
   /// Function called when a resource must be reloaded.
   ///
+  /// The new value is built aside – *staged* – while the current one stays readable; it only
+  /// replaces the current value once fully built, which is the only moment the resource is
+  /// borrowed mutably. Do all the expensive work – IO, parsing, etc. – in this function.
+  ///
   /// The default implementation of that function calls `load` and returns its result.
   fn reload(
     &self,
@@ -56,6 +97,62 @@ where Method: ?Sized {
   {
     Self::load(key, storage, ctx).map(|lr| lr.res)
   }
+
+  /// Function called right before the resource gets reloaded.
+  ///
+  /// The default implementation does nothing.
+  fn before_reload(&self, _storage: &mut Storage<C>, _ctx: &mut C) {}
+
+  /// Function called right after the resource has been reloaded, with the previous value.
+  ///
+  /// This is the place to migrate state from the old value – external handles, for instance. It’s
+  /// called on the staged value, right before it replaces the previous one, which is still readable
+  /// at that time. The default implementation does nothing.
+  ///
+  /// Because the replacement hasn’t happened yet, getting the same resource from `storage` in this
+  /// function hands back the previous value, not `self`. Implementations that need the new value
+  /// must use `self` directly.
+  fn after_reload(&mut self, _old: &Self, _storage: &mut Storage<C>, _ctx: &mut C) {}
+
+  /// Function called when the resource gets evicted from its `Storage`.
+  ///
+  /// Use it to release external resources deterministically. The default implementation does
+  /// nothing.
+  fn unload(&mut self) {}
+
+  /// Memory footprint of the resource, in bytes.
+  ///
+  /// It’s accounted against the memory budget of the `Storage` – see
+  /// `StoreOpt::set_memory_budget`. The default implementation returns `0`.
+  fn memory_footprint(&self) -> usize {
+    0
+  }
+
+  /// Time to wait after the last change of the resource before reloading it.
+  ///
+  /// It overrides the update await time of the `Store` – see `StoreOpt::set_update_await_time_ms` –
+  /// for every resource of this type, which is handy for resources which files are written in
+  /// several steps, or which reloading is expensive. The default implementation returns `None`,
+  /// using the await time of the `Store`.
+  fn reload_delay() -> Option<Duration> {
+    None
+  }
+
+  /// Priority of the resources of this type when reloading.
+  ///
+  /// When several resources are dirty at once – think of a branch switch – the ones with the
+  /// highest priority reload first, so that critical resources such as shaders are up to date
+  /// before less important ones such as audio. It can be overridden per resource with
+  /// `Store::set_reload_priority_for`. The default implementation returns `0`.
+  fn reload_priority() -> i32 {
+    0
+  }
+
+  /// Function called when a weak dependency of the resource has been reloaded.
+  ///
+  /// Weak dependencies don’t make the resource reload; instead, this function is called with the
+  /// key of the dependency that has reloaded. The default implementation does nothing.
+  fn dependency_reloaded(&mut self, _dep: &DepKey, _storage: &mut Storage<C>, _ctx: &mut C) {}
 }
 
 /// Result of a resource loading.
@@ -69,6 +166,10 @@ pub struct Loaded<T> {
   pub res: T,
   /// The list of dependencies to listen for events.
   pub deps: Vec<DepKey>,
+  // weak dependencies, which don’t make the resource reload but call `Load::dependency_reloaded`
+  weak_deps: Vec<DepKey>,
+  // aspects of the context the resource is derived from, which `Store::invalidate_tag` reloads
+  ctx_tags: Vec<String>,
 }
 
 impl<T> Loaded<T> {
@@ -77,12 +178,60 @@ impl<T> Loaded<T> {
     Loaded {
       res,
       deps: Vec::new(),
+      weak_deps: Vec::new(),
+      ctx_tags: Vec::new(),
     }
   }
 
   /// Return a resource along with its dependencies.
   pub fn with_deps(res: T, deps: Vec<DepKey>) -> Self {
-    Loaded { res, deps }
+    Loaded {
+      res,
+      deps,
+      weak_deps: Vec::new(),
+      ctx_tags: Vec::new(),
+    }
+  }
+
+  /// Return a resource along with its weak dependencies.
+  ///
+  /// Weak dependencies won’t make the resource reload but will call `Load::dependency_reloaded`
+  /// instead.
+  pub fn with_weak_deps(res: T, weak_deps: Vec<DepKey>) -> Self {
+    Loaded {
+      res,
+      deps: Vec::new(),
+      weak_deps,
+      ctx_tags: Vec::new(),
+    }
+  }
+
+  /// Add a weak dependency to the resource – see `Loaded::with_weak_deps`.
+  pub fn with_weak_dep<K>(mut self, dep: K) -> Self
+  where K: Into<DepKey> {
+    self.weak_deps.push(dep.into());
+    self
+  }
+
+  /// Weak dependencies of the resource – see `Loaded::with_weak_deps`.
+  pub fn weak_deps(&self) -> &[DepKey] {
+    &self.weak_deps
+  }
+
+  /// Tag the resource with an aspect of the context it is derived from – a quality level, the
+  /// current locale, etc.
+  ///
+  /// Once the context changes, `Store::invalidate_tag` reloads all the resources tagged with that
+  /// aspect at once.
+  pub fn with_ctx_tag<S>(mut self, tag: S) -> Self
+  where S: Into<String> {
+    self.ctx_tags.push(tag.into());
+    self
+  }
+
+  /// Aspects of the context the resource is tagged with – see `Loaded::with_ctx_tag`.
+  pub fn ctx_tags(&self) -> &[String] {
+    &self.ctx_tags
   }
 }
 
@@ -94,17 +243,89 @@ impl<T> From<T> for Loaded<T> {
 
 /// Metadata about a resource.
 struct ResMetaData<C> {
+  /// Generation of the `Storage` at which the resource was registered.
+  ///
+  /// It identifies the resource among all the resources ever registered at the same key.
+  generation: u64,
+  /// Type of the resource.
+  type_id: TypeId,
+  /// Type of the method the resource was loaded with.
+  method: TypeId,
+  /// Name of the method the resource was loaded with.
+  method_name: &'static str,
+  /// Name of the type of the resource.
+  type_name: &'static str,
   /// Function to call each time the resource must be reloaded.
   on_reload: Box<Fn(&mut Storage<C>, &mut C) -> Result<(), Box<Error>>>,
+  /// Function to call each time a weak dependency of the resource has reloaded.
+  on_dep_reload: Box<Fn(&DepKey, &mut Storage<C>, &mut C)>,
+  /// Function to call when the resource gets evicted.
+  on_unload: Box<Fn()>,
+  /// Function replacing the resource with the proxy of its type, returning whether there was one.
+  on_proxy: Box<Fn(&mut Storage<C>) -> bool>,
+  /// Function setting the state of the source of the resource.
+  on_source_state: Box<Fn(ResState)>,
+  /// Time to wait before reloading the resource, overriding the one of the `Store`.
+  reload_delay: Option<Duration>,
+  /// Priority of the resource when reloading.
+  reload_priority: i32,
 }
 
 impl<C> ResMetaData<C> {
-  fn new<F>(f: F) -> Self
-  where F: 'static + Fn(&mut Storage<C>, &mut C) -> Result<(), Box<Error>> {
+  fn new<T, M, F, G, U, P, S>(
+    generation: u64,
+    on_reload: F,
+    on_dep_reload: G,
+    on_unload: U,
+    on_proxy: P,
+    on_source_state: S,
+    reload_delay: Option<Duration>,
+    reload_priority: i32,
+  ) -> Self
+  where
+    T: 'static,
+    M: ?Sized,
+    F: 'static + Fn(&mut Storage<C>, &mut C) -> Result<(), Box<Error>>,
+    G: 'static + Fn(&DepKey, &mut Storage<C>, &mut C),
+    U: 'static + Fn(),
+    P: 'static + Fn(&mut Storage<C>) -> bool,
+    S: 'static + Fn(ResState),
+    M: 'static,
+  {
     ResMetaData {
-      on_reload: Box::new(f),
+      generation,
+      type_id: TypeId::of::<T>(),
+      method: TypeId::of::<M>(),
+      method_name: type_name::<M>(),
+      type_name: type_name::<T>(),
+      on_reload: Box::new(on_reload),
+      on_dep_reload: Box::new(on_dep_reload),
+      on_unload: Box::new(on_unload),
+      on_proxy: Box::new(on_proxy),
+      on_source_state: Box::new(on_source_state),
+      reload_delay,
+      reload_priority,
     }
   }
+
+  /// Cache key of the resource, living at the given key.
+  fn cache_key(&self, dep_key: DepKey) -> CacheKey {
+    CacheKey::from_type_id(dep_key, self.type_id, self.method, self.method_name)
+  }
+}
+
+/// A resource depending on another one.
+#[derive(Clone, Debug)]
+struct Dependent {
+  /// Key of the dependent resource.
+  key: DepKey,
+  /// Kind of the dependency.
+  kind: DepKind,
+  /// Generation at which the dependent resource was registered.
+  ///
+  /// If no resource registered at that generation lives at the key anymore, the dependent
+  /// resource is dead – evicted or replaced – and the dependency must be ignored.
+  generation: u64,
 }
 
 /// Resource storage.
@@ -114,503 +335,4882 @@ impl<C> ResMetaData<C> {
 pub struct Storage<C> {
   // canonicalized root path (used for resources loaded from the file system)
   canon_root: PathBuf,
+  // other spellings of the root the watcher might report paths with
+  root_aliases: Vec<PathBuf>,
   // resource cache, containing all living resources
-  cache: HashCache,
-  // dependencies, mapping a dependency to its dependent resources
-  deps: HashMap<DepKey, Vec<DepKey>>,
-  // contains all metadata on resources (reload functions)
-  metadata: HashMap<DepKey, ResMetaData<C>>,
+  cache: Box<CacheBackend>,
+  // dependencies, mapping a dependency to its dependent resources along with the kind of dependency
+  deps: HashMap<DepKey, Vec<Dependent>>,
+  // context tags, mapping a tag to the resources derived from that aspect of the context
+  ctx_tags: HashMap<String, Vec<Dependent>>,
+  // named groups, mapping a group to its resources
+  groups: HashMap<String, HashSet<CacheKey>>,
+  // contains all metadata on resources (reload functions); a same key can back several resources,
+  // each of a different type
+  metadata: HashMap<DepKey, Vec<ResMetaData<C>>>,
+  // faults to trigger when loading or reloading resources
+  #[cfg(feature = "failure-injection")]
+  faults: HashMap<DepKey, Fault>,
+  // cancellation token loaders can check to stop early
+  cancellation_token: CancellationToken,
+  // loads queued with Storage::request, oldest first
+  requests: VecDeque<Request<C>>,
+  // cancellation token of the requested resource being loaded, if any
+  current_request: Option<CancellationToken>,
+  // generation of the storage, bumped each time a resource is loaded, reloaded or evicted
+  generation: u64,
+  // provenance of all the resources
+  provenance: HashMap<DepKey, Provenance>,
+  // backends and hashes of the files read since their resources last recorded their provenance
+  reads: RefCell<HashMap<PathBuf, (Backend, u64)>>,
+  // bookkeeping about the lifetime of all the resources
+  info: HashMap<CacheKey, ResInfo>,
+  // source of time of the bookkeeping, shared with the synchronizer
+  clock: Rc<Clock>,
+  // generations at which resources were loaded, reloaded or evicted
+  journal: Journal,
+  // loaders registered at runtime
+  registry: LoaderRegistry<C>,
+  // what to do with declared dependencies that don’t exist
+  dependency_check: DependencyCheck,
+  // whether filesystem keys may refer to files living outside of the root
+  allow_outside_root: bool,
+  // directories mounted at VFS directories
+  mounts: Mounts,
+  // whether resources failing to load are replaced by the defaults of their types
+  fallback_to_defaults: bool,
+  // normalization of the VFS paths of keys
+  path_normalization: PathNormalization,
+  // aliases, mapping a key to the key it stands for
+  aliases: HashMap<DepKey, DepKey>,
+  // aliases remapped since the last synchronization, which observers must reload
+  remapped_aliases: HashSet<DepKey>,
+  // watches on the filesystem, extended lazily with the directories of requested keys
+  watch: Watch,
+  // resolved targets of the paths of resources going through symlinks, mapped to those paths
+  symlinks: HashMap<PathBuf, HashSet<PathBuf>>,
+  // hard cap on the memory footprint of all the resources, if any
+  memory_budget: Option<usize>,
+  // memory footprint of all the resources
+  memory_used: usize,
+  // memory footprint of each resource
+  footprints: HashMap<CacheKey, usize>,
+  // hit and miss counts of reads, if enabled
+  read_metrics: Option<ReadMetrics>,
+  // progress of the loaded resources, if enabled
+  progress: Option<ProgressTracker>,
+  // bytes read when files don’t exist
+  embedded: EmbeddedSource,
+  // transforms applied to the bytes of files, by extension
+  transforms: ByteTransforms,
+  // stages applied to the bytes of all files, in order
+  read_middleware: Vec<Box<Fn(&DepKey, Vec<u8>) -> io::Result<Vec<u8>>>>,
+  // on-disk cache of processed artifacts, if enabled
+  artifact_cache: Option<ArtifactCache>,
+  // processing parameters of resources
+  process_params: HashMap<CacheKey, Box<Any>>,
+  // already prepared keys, as a HashMap<K, K> per type of key K
+  prepared_keys: HashMap<TypeId, Box<Any>>,
+  // number of reads served from the cache and of reads that had to load, whatever their types
+  cache_hits: u64,
+  cache_misses: u64,
+  // reload transaction in progress, if any – see StoreOpt::set_transactional_reloads
+  undo_log: Option<UndoLog<C>>,
+  // functions creating the proxies of resources, as a Box<Fn() -> T> per type T
+  proxies: HashMap<TypeId, Box<Any>>,
+  // functions erasing the type of cached resources, per type of resource
+  erasers: HashMap<TypeId, fn(&Any) -> Option<AnyRes>>,
+  // functions copying cached resources into snapshots, per freezable type of resource
+  freezers: HashMap<TypeId, snapshot::Freezer>,
+  // copies of the resources made by the last snapshot, reused until the resources change
+  frozen: RefCell<HashMap<CacheKey, Frozen>>,
+}
+
+/// Number of prepared keys of a given type remembered by a `Storage` before they’re forgotten.
+const PREPARED_KEYS_CAPACITY: usize = 4096;
+
+/// Reload transaction in progress – see `StoreOpt::set_transactional_reloads`.
+struct UndoLog<C> {
+  // functions restoring the values replaced by reloads, oldest first
+  undos: Vec<Box<FnOnce(&mut Storage<C>)>>,
+  // functions notifying the observers of reloaded resources, once the transaction is committed
+  notifications: Vec<Box<FnOnce()>>,
+}
+
+impl<C> UndoLog<C> {
+  fn new() -> Self {
+    UndoLog {
+      undos: Vec::new(),
+      notifications: Vec::new(),
+    }
+  }
+
+  /// Keep the reloads of the transaction and notify the observers of the reloaded resources.
+  fn commit(self) {
+    for notify in self.notifications {
+      notify();
+    }
+  }
+
+  /// Restore the values replaced by the reloads of the transaction, newest first. Observers of the
+  /// reloaded resources aren’t notified.
+  fn roll_back(self, storage: &mut Storage<C>) {
+    for undo in self.undos.into_iter().rev() {
+      undo(storage);
+    }
+  }
+}
+
+/// Settings of a `Storage`, gathered from a `StoreOpt` by `Store::new`.
+struct StorageConfig {
+  canon_root: PathBuf,
+  root_aliases: Vec<PathBuf>,
+  cache: Box<CacheBackend>,
+  dependency_check: DependencyCheck,
+  allow_outside_root: bool,
+  mounts: Mounts,
+  fallback_to_defaults: bool,
+  path_normalization: PathNormalization,
+  watch: Watch,
+  memory_budget: Option<usize>,
+  read_metrics: bool,
+  progress_tracking: bool,
+  embedded: EmbeddedSource,
+  transforms: ByteTransforms,
+  read_middleware: Vec<Box<Fn(&DepKey, Vec<u8>) -> io::Result<Vec<u8>>>>,
+  artifact_cache: Option<ArtifactCache>,
+  clock: Rc<Clock>,
 }
 
 impl<C> Storage<C> {
-  fn new(canon_root: PathBuf) -> Self {
+  fn new(config: StorageConfig) -> Self {
+    let StorageConfig {
+      canon_root,
+      root_aliases,
+      cache,
+      dependency_check,
+      allow_outside_root,
+      mounts,
+      fallback_to_defaults,
+      path_normalization,
+      watch,
+      memory_budget,
+      read_metrics,
+      progress_tracking,
+      embedded,
+      transforms,
+      read_middleware,
+      artifact_cache,
+      clock,
+    } = config;
+
     Storage {
       canon_root,
-      cache: HashCache::new(),
+      root_aliases,
+      cache,
       deps: HashMap::new(),
+      ctx_tags: HashMap::new(),
+      groups: HashMap::new(),
       metadata: HashMap::new(),
+      #[cfg(feature = "failure-injection")]
+      faults: HashMap::new(),
+      cancellation_token: CancellationToken::new(),
+      requests: VecDeque::new(),
+      current_request: None,
+      generation: 0,
+      provenance: HashMap::new(),
+      reads: RefCell::new(HashMap::new()),
+      info: HashMap::new(),
+      clock,
+      journal: Journal::new(),
+      registry: LoaderRegistry::new(),
+      dependency_check,
+      allow_outside_root,
+      mounts,
+      fallback_to_defaults,
+      path_normalization,
+      aliases: HashMap::new(),
+      remapped_aliases: HashSet::new(),
+      watch,
+      symlinks: HashMap::new(),
+      memory_budget,
+      memory_used: 0,
+      footprints: HashMap::new(),
+      read_metrics: if read_metrics { Some(ReadMetrics::new()) } else { None },
+      progress: if progress_tracking { Some(ProgressTracker::new()) } else { None },
+      embedded,
+      transforms,
+      read_middleware,
+      artifact_cache,
+      process_params: HashMap::new(),
+      prepared_keys: HashMap::new(),
+      cache_hits: 0,
+      cache_misses: 0,
+      undo_log: None,
+      proxies: HashMap::new(),
+      erasers: HashMap::new(),
+      freezers: HashMap::new(),
+      frozen: RefCell::new(HashMap::new()),
     }
   }
 
-  /// The canonicalized root the `Storage` is configured with.
+  /// The root the `Storage` is configured with – canonicalized, unless disabled with
+  /// `StoreOpt::set_canonicalize`.
   pub fn root(&self) -> &Path {
     &self.canon_root
   }
 
-  /// Inject a new resource in the store.
+  /// The mount points of the `Storage` – see `StoreOpt::add_mount`.
+  pub fn mounts(&self) -> &Mounts {
+    &self.mounts
+  }
+
+  /// The VFS path of a real path living under the root or in a mounted directory, if any.
+  pub(crate) fn vfs_path(&self, path: &Path) -> Option<PathBuf> {
+    key::vfs_path(path, self.root(), &self.mounts)
+  }
+
+  /// The key of a file found on the filesystem: its VFS path if it has one, the host path
+  /// otherwise – see `FSKey::absolute`.
+  pub(crate) fn file_key(&self, path: &Path) -> FSKey {
+    match self.vfs_path(path) {
+      Some(vfs_path) => FSKey::new(vfs_path),
+      None => FSKey::absolute(path),
+    }
+  }
+
+  /// The cancellation token of the `Storage`.
   ///
-  /// The resource might be refused for several reasons. Further information in the documentation of
-  /// the `StoreError` error type.
-  fn inject<T, M>(
+  /// Long-running loaders should check it periodically and stop early when it’s cancelled. Clone
+  /// it to cancel loading from another place – another thread, for instance.
+  pub fn cancellation_token(&self) -> &CancellationToken {
+    &self.cancellation_token
+  }
+
+  /// Check whether loading should stop early.
+  ///
+  /// That’s the case when the cancellation token of the `Storage` is cancelled or when the request
+  /// of the resource being loaded, if any, is cancelled – see `LoadHandle::cancel`.
+  pub fn load_cancelled(&self) -> bool {
+    self.cancellation_token.is_cancelled()
+      || self
+        .current_request
+        .as_ref()
+        .map_or(false, CancellationToken::is_cancelled)
+  }
+
+  /// Number of requested resources waiting to be loaded.
+  ///
+  /// See `Storage::request`.
+  pub fn pending_requests(&self) -> usize {
+    self
+      .requests
+      .iter()
+      .filter(|request| !request.token.is_cancelled())
+      .count()
+  }
+
+  /// Queue a request.
+  pub(crate) fn push_request(&mut self, request: Request<C>) {
+    self.requests.retain(|request| !request.token.is_cancelled());
+    self.requests.push_back(request);
+  }
+
+  /// Take the oldest request that isn’t cancelled out of the queue, dropping the cancelled ones.
+  pub(crate) fn pop_request(&mut self) -> Option<Request<C>> {
+    while let Some(request) = self.requests.pop_front() {
+      if !request.token.is_cancelled() {
+        return Some(request);
+      }
+    }
+
+    None
+  }
+
+  /// Set the cancellation token of the requested resource being loaded and return the previous one.
+  pub(crate) fn set_current_request(
     &mut self,
-    key: T::Key,
-    resource: T,
-    deps: Vec<DepKey>,
-  ) -> Result<Res<T>, StoreError>
-  where
-    T: Load<C, M>,
-    T::Key: Clone + hash::Hash + Into<DepKey>,
+    token: Option<CancellationToken>,
+  ) -> Option<CancellationToken>
   {
-    let dep_key = key.clone().into();
+    mem::replace(&mut self.current_request, token)
+  }
 
-    // we forbid having two resources sharing the same key
-    if self.metadata.contains_key(&dep_key) {
-      return Err(StoreError::AlreadyRegisteredKey(dep_key));
+  /// The progress reporter of the resource being loaded, if progress tracking is enabled.
+  ///
+  /// Call it from `Load::load` to report the bytes read and the stage of the loading. See the
+  /// `progress` module for further details.
+  pub fn load_progress(&self) -> Option<Progress> {
+    self.progress.as_ref().and_then(ProgressTracker::current)
+  }
+
+  /// Generation of the `Storage`.
+  ///
+  /// It’s bumped each time a resource is loaded, reloaded or evicted.
+  pub fn generation(&self) -> u64 {
+    self.generation
+  }
+
+  /// Memory footprint of all the resources living in the `Storage`, in bytes.
+  ///
+  /// See `Load::memory_footprint`.
+  pub fn memory_used(&self) -> usize {
+    self.memory_used
+  }
+
+  /// Hard cap on the memory footprint of all the resources, in bytes, if any.
+  pub fn memory_budget(&self) -> Option<usize> {
+    self.memory_budget
+  }
+
+  /// Read the whole content of a file, given its real path – typically, the one of a prepared key.
+  ///
+  /// If the file doesn’t exist, the bytes embedded at its VFS path are read instead, if any – see
+  /// `StoreOpt::set_embedded_source`. The bytes go through the read middleware – see
+  /// `StoreOpt::add_read_middleware` – then the bytes of files with an extension registered with
+  /// `StoreOpt::add_byte_transform` are transformed; if the file still doesn’t exist, its variants
+  /// with a registered extension appended are tried. Loaders should read their files with this
+  /// function rather than with `std::fs` so that they support embedded and transformed resources.
+  pub fn read<P>(&self, path: P) -> io::Result<Vec<u8>>
+  where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let read = match self.read_transformed(path) {
+      Err(e) => {
+        let variant = if e.kind() == io::ErrorKind::NotFound {
+          self
+            .transforms
+            .variants(path)
+            .map(|variant| self.read_transformed(&variant))
+            .find(|read| match *read {
+              Err(ref e) => e.kind() != io::ErrorKind::NotFound,
+              Ok(_) => true,
+            })
+        } else {
+          None
+        };
+
+        variant.unwrap_or(Err(e))
+      }
+
+      read => read,
+    };
+
+    // remember what was read, so that the provenance of the resource states it
+    let (bytes, backend) = read?;
+    let content_hash = provenance::content_hash(&bytes);
+    self.reads.borrow_mut().insert(path.to_owned(), (backend, content_hash));
+
+    Ok(bytes)
+  }
+
+  /// Read the whole content of a file – on the filesystem or embedded – pass it through the read
+  /// middleware and transform it according to its extension.
+  fn read_transformed(&self, path: &Path) -> io::Result<(Vec<u8>, Backend)> {
+    let (mut bytes, backend) = self.read_source(path)?;
+
+    if !self.read_middleware.is_empty() {
+      let dep_key = DepKey::Path(path.to_owned());
+
+      for stage in &self.read_middleware {
+        bytes = stage(&dep_key, bytes)?;
+      }
     }
 
-    // wrap the resource to make it shared mutably
-    let res = Res::new(resource);
+    let bytes = match self.transforms.get(path) {
+      Some(transform) => transform.transform(bytes)?,
+      None => bytes,
+    };
 
-    // create the metadata for the resource
-    let res_ = res.clone();
-    let key_ = key.clone();
-    let metadata = ResMetaData::new(move |storage, ctx| {
-      let reloaded = <T as Load<C, M>>::reload(&res_.borrow(), key_.clone(), storage, ctx);
+    Ok((bytes, backend))
+  }
 
-      match reloaded {
-        Ok(r) => {
-          // replace the current resource with the freshly loaded one
-          *res_.borrow_mut() = r;
-          Ok(())
+  /// Read the whole content of a file, falling back to the embedded bytes of its VFS path.
+  fn read_source(&self, path: &Path) -> io::Result<(Vec<u8>, Backend)> {
+    match fs::read(path) {
+      Ok(bytes) => Ok((bytes, Backend::Filesystem)),
+      Err(e) => {
+        let embedded = path
+          .strip_prefix(self.root())
+          .ok()
+          .and_then(|relative| self.embedded.get(relative));
+
+        match embedded {
+          Some(bytes) if e.kind() == io::ErrorKind::NotFound => {
+            Ok((bytes.to_owned(), Backend::Embedded))
+          }
+          _ => Err(e),
         }
-        Err(e) => Err(Box::new(e)),
       }
-    });
+    }
+  }
 
-    self.metadata.insert(dep_key.clone(), metadata);
+  /// Check whether the source of a file resource is there – on the filesystem or embedded, as is
+  /// or transformed.
+  fn source_exists(&self, path: &Path) -> bool {
+    let exists = |path: &Path| {
+      path.exists()
+        || path
+          .strip_prefix(self.root())
+          .ok()
+          .map_or(false, |relative| self.embedded.get(relative).is_some())
+    };
 
-    // register the resource as an observer of its dependencies in the dependencies graph
-    let root = &self.canon_root;
-    for dep in deps {
-      self
-        .deps
-        .entry(dep.clone().prepare_key(root))
-        .or_insert(Vec::new())
-        .push(dep_key.clone());
-    }
+    exists(path) || self.transforms.variants(path).any(|variant| exists(&variant))
+  }
 
-    // wrap the key in our private key so that we can use it in the cache
-    let pkey = PrivateKey::new(dep_key);
+  /// Read the whole content of a file as a string, given its real path.
+  ///
+  /// See `Storage::read` for further details.
+  pub fn read_to_string<P>(&self, path: P) -> io::Result<String>
+  where P: AsRef<Path> {
+    let bytes = self.read(path)?;
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
 
-    // cache the resource
-    self.cache.save(pkey, res.clone());
+  /// On-disk cache of processed artifacts, if enabled with `StoreOpt::set_artifact_cache`.
+  ///
+  /// See the `artifact` module for further details.
+  pub fn artifact_cache(&self) -> Option<&ArtifactCache> {
+    self.artifact_cache.as_ref()
+  }
 
-    Ok(res)
+  /// Hit and miss counts of the reads, if enabled with `StoreOpt::set_read_metrics`.
+  pub fn read_metrics(&self) -> Option<&ReadMetrics> {
+    self.read_metrics.as_ref()
   }
 
-  /// Get a resource from the `Storage` and return an error if its loading failed.
+  /// Forget the hit and miss counts of the reads.
+  pub fn clear_read_metrics(&mut self) {
+    if let Some(ref mut read_metrics) = self.read_metrics {
+      read_metrics.clear();
+    }
+  }
+
+  /// Keys that changed, appeared or disappeared from generation `from` to generation `to`.
   ///
-  /// This function uses the default loading method.
-  pub fn get<K, T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C>>
+  /// Save the current generation at some point and diff it later against the current generation
+  /// to know what happened in between. If `from` is newer than `to`, the diff is reversed.
+  pub fn diff(&self, from: u64, to: u64) -> GenerationDiff {
+    self.journal.diff(from, to)
+  }
+
+  /// Where the resource living at the given key came from, if any.
+  pub fn provenance(&self, key: &DepKey) -> Option<&Provenance> {
+    let dep_key = self.prepare_dep_key(key);
+    self.provenance.get(&dep_key)
+  }
+
+  /// Bookkeeping about the resource of type `T`, loaded with the default method, living at the
+  /// given key, if any.
+  ///
+  /// See the documentation of `ResInfo` for further details.
+  pub fn info<K, T>(&self, key: &K) -> Option<&ResInfo>
   where
     T: Load<C>,
     K: Clone + Into<T::Key>, {
-    self.get_by(key, ctx, ())
+    self.info_by::<K, T, ()>(key, ())
   }
 
-  /// Get a resource from the `Storage` by using a specific method and return and error if its
-  /// loading failed.
-  pub fn get_by<K, T, M>(
-    &mut self,
-    key: &K,
-    ctx: &mut C,
-    _: M,
-  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  /// Bookkeeping about the resource of type `T`, loaded with a specific method, living at the
+  /// given key, if any.
+  ///
+  /// See the documentation of `ResInfo` for further details.
+  pub fn info_by<K, T, M>(&self, key: &K, _: M) -> Option<&ResInfo>
   where
     T: Load<C, M>,
     K: Clone + Into<T::Key>,
+    M: 'static,
   {
-    let key_ = key.clone().into().prepare_key(self.root());
-    let dep_key = key_.clone().into();
-    let pkey = PrivateKey::<T>::new(dep_key);
+    let dep_key = self.prepare::<T::Key>(key.clone().into()).into();
+    self.info.get(&CacheKey::with_method::<T, M>(dep_key))
+  }
 
-    let x: Option<Res<T>> = self.cache.get(&pkey).cloned();
+  /// Bump the generation and record the provenance of a freshly (re)loaded resource.
+  fn record_provenance<T, M>(&mut self, dep_key: &DepKey, reloading: bool)
+  where
+    T: 'static,
+    M: 'static,
+  {
+    self.generation += 1;
 
-    match x {
-      Some(resource) => Ok(resource),
-      None => {
-        let loaded =
-          <T as Load<C, M>>::load(key_.clone(), self, ctx).map_err(StoreErrorOr::ResError)?;
-        self
-          .inject::<T, M>(key_, loaded.res, loaded.deps)
-          .map_err(StoreErrorOr::StoreError)
-      }
+    let read = dep_key
+      .path()
+      .and_then(|path| self.reads.borrow_mut().remove(path));
+    let provenance = Provenance::record::<T, M>(dep_key, self.generation, read);
+    self.provenance.insert(dep_key.clone(), provenance);
+
+    let now = self.clock.now();
+    let cache_key = CacheKey::with_method::<T, M>(dep_key.clone());
+    let reloaded = reloading
+      && self
+        .info
+        .get_mut(&cache_key)
+        .map(|info| info.reloaded(now))
+        .is_some();
+
+    if !reloaded {
+      self.info.insert(cache_key, ResInfo::loaded::<M>(dep_key, now));
     }
+
+    self.journal.loaded(dep_key, self.generation);
   }
 
-  /// Get a resource from the `Storage` for the given key. If it fails, a proxied version is used,
-  /// which will get replaced by the resource once it’s available and reloaded.
-  ///
-  /// This function uses the default loading method.
-  pub fn get_proxied<K, T, P>(
-    &mut self,
-    key: &K,
-    proxy: P,
-    ctx: &mut C,
-  ) -> Result<Res<T>, StoreError>
-  where
-    T: Load<C>,
-    K: Clone + Into<T::Key>,
-    P: FnOnce() -> T,
+  /// Provenance and bookkeeping of a resource, as recorded by its last (re)load.
+  fn provenance_of(
+    &self,
+    dep_key: &DepKey,
+    cache_key: &CacheKey,
+  ) -> (Option<Provenance>, Option<ResInfo>)
   {
-    self
-      .get(key, ctx)
-      .or_else(|_| self.inject::<T, ()>(key.clone().into(), proxy(), Vec::new()))
+    (self.provenance.get(dep_key).cloned(), self.info.get(cache_key).cloned())
   }
 
-  /// Get a resource from the `Storage` for the given key by using a specific method. If it fails, a
-  /// proxied version is used, which will get replaced by the resource once it’s available and
-  /// reloaded.
-  pub fn get_proxied_by<K, T, M, P>(
+  /// Restore the provenance and bookkeeping a resource had before a reload, recorded at the given
+  /// generation, got rolled back.
+  fn restore_provenance(
     &mut self,
-    key: &K,
-    proxy: P,
-    ctx: &mut C,
-    method: M,
-  ) -> Result<Res<T>, StoreError>
+    dep_key: &DepKey,
+    cache_key: &CacheKey,
+    (provenance, info): (Option<Provenance>, Option<ResInfo>),
+    generation: u64,
+  )
+  {
+    match provenance {
+      Some(provenance) => self.provenance.insert(dep_key.clone(), provenance),
+      None => self.provenance.remove(dep_key),
+    };
+
+    match info {
+      Some(info) => self.info.insert(cache_key.clone(), info),
+      None => self.info.remove(cache_key),
+    };
+
+    self.journal.forget_reload(dep_key, generation);
+  }
+
+  /// Check whether a resource of type `T`, loaded with the default method, is living in the
+  /// `Storage` at the given key.
+  ///
+  /// This function never triggers any loading. Resources loaded with other methods are ignored:
+  /// see `Storage::contains_by`.
+  pub fn contains<T>(&self, key: &T::Key) -> bool
+  where T: Load<C> {
+    self.contains_by::<T::Key, T, ()>(key, ())
+  }
+
+  /// Check whether a resource of type `T`, loaded with a specific method, is living in the
+  /// `Storage` at the given key.
+  ///
+  /// This function never triggers any loading.
+  pub fn contains_by<K, T, M>(&self, key: &K, _: M) -> bool
   where
     T: Load<C, M>,
     K: Clone + Into<T::Key>,
-    P: FnOnce() -> T,
+    M: 'static,
   {
-    self
-      .get_by(key, ctx, method)
-      .or_else(|_| self.inject::<T, M>(key.clone().into(), proxy(), Vec::new()))
+    let dep_key = self.prepare::<T::Key>(key.clone().into()).into();
+    self.cache.get(&CacheKey::with_method::<T, M>(dep_key)).is_some()
   }
-}
 
-/// Error that might happen when handling a resource store around.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum StoreError {
-  /// The root path for a filesystem resource was not found.
-  RootDoesDotExit(PathBuf),
-  /// The key associated with a resource already exists in the `Store`.
+  /// Get a type-erased resource living in the `Storage`, given its key and the `TypeId` of its
+  /// type.
   ///
-  /// > Note: it is not currently possible to have two resources living in a `Store` and using an
-  /// > identical key at the same time.
-  AlreadyRegisteredKey(DepKey),
-}
+  /// This is meant for code that cannot be generic over the type of resources – scripting
+  /// bridges, for instance. This function never triggers any loading: the key must be prepared
+  /// already, as the keys returned by `Storage::keys` are.
+  ///
+  /// If resources of that type were loaded at that key with several methods, the one loaded with
+  /// the default method is preferred.
+  pub fn get_dyn(&self, key: &DepKey, type_id: TypeId) -> Option<AnyRes> {
+    let metadata = self
+      .metadata
+      .get(key)?
+      .iter()
+      .filter(|metadata| metadata.type_id == type_id)
+      .min_by_key(|metadata| metadata.method != TypeId::of::<()>())?;
 
-impl fmt::Display for StoreError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    f.write_str(self.description())
+    self.get_cached_dyn(&metadata.cache_key(key.clone()))
   }
-}
 
-impl Error for StoreError {
-  fn description(&self) -> &str {
-    match *self {
-      StoreError::RootDoesDotExit(_) => "root doesn’t exist",
-      StoreError::AlreadyRegisteredKey(_) => "already registered key",
-    }
+  /// Get a type-erased resource living in the `Storage`, given its cache key.
+  fn get_cached_dyn(&self, cache_key: &CacheKey) -> Option<AnyRes> {
+    let erase = self.erasers.get(&cache_key.type_id())?;
+    self.cache.get(cache_key).and_then(erase)
   }
-}
 
-/// Either a store error or a resource loading error.
-pub enum StoreErrorOr<T, C, M = ()>
-where T: Load<C, M> {
-  /// A store error.
-  StoreError(StoreError),
-  /// A resource error.
-  ResError(T::Error),
-}
+  /// Get all the type-erased resources living in the `Storage` at the given key, whatever their
+  /// types and methods.
+  ///
+  /// See `Storage::get_dyn`.
+  pub fn get_all_dyn(&self, key: &DepKey) -> Vec<AnyRes> {
+    self
+      .metadata
+      .get(key)
+      .into_iter()
+      .flat_map(|metadata| metadata.iter())
+      .filter_map(|metadata| self.get_cached_dyn(&metadata.cache_key(key.clone())))
+      .collect()
+  }
+
+  /// Check whether any resource is living in the `Storage` at the given key, regardless of its
+  /// type.
+  ///
+  /// This function never triggers any loading. The key can be expressed with a VFS path or be one
+  /// returned by `Storage::keys`.
+  pub fn contains_key(&self, key: &DepKey) -> bool {
+    let dep_key = self.prepare_dep_key(key);
+    self.metadata.contains_key(&dep_key)
+  }
+
+  /// Keys of the resources living on the filesystem at the given path, whatever their parameters.
+  #[cfg(feature = "watcher")]
+  fn keys_at_path<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a DepKey> + 'a {
+    let dep_key = DepKey::Path(path.to_owned());
+    let plain = self.metadata.get_key_value(&dep_key).map(|(key, _)| key);
+    let parameterized = self.metadata.keys().filter(move |key| match **key {
+      DepKey::PathWith(ref p, _) => p == path,
+      _ => false,
+    });
+
+    plain.into_iter().chain(parameterized)
+  }
+
+  /// Evict the resources living at the given key from the `Storage`, regardless of their types.
+  ///
+  /// The resources get unloaded (see `Load::unload`) and won’t be reloaded anymore. Handles to them
+  /// that you still hold remain valid. Return `true` if any resource was evicted.
+  ///
+  /// Just like with `Storage::contains_key`, the key can be one returned by `Storage::keys`.
+  pub fn evict(&mut self, key: &DepKey) -> bool {
+    let dep_key = self.prepare_dep_key(key);
+    self.evict_prepared(dep_key)
+  }
+
+  /// Same as `evict`, with an already prepared key.
+  fn evict_prepared(&mut self, dep_key: DepKey) -> bool {
+    match self.metadata.remove(&dep_key) {
+      Some(metadata) => {
+        for metadata in metadata {
+          self.unload_resource(&dep_key, metadata);
+        }
+
+        self.forget_key(&dep_key);
+        true
+      }
+
+      None => false,
+    }
+  }
+
+  /// Evict a single resource, given its cache key which key is already prepared. Other resources
+  /// living at the same key are kept.
+  fn evict_resource(&mut self, cache_key: &CacheKey) -> bool {
+    let dep_key = cache_key.dep_key();
+
+    let mut metadata = match self.metadata.remove(dep_key) {
+      Some(metadata) => metadata,
+      None => return false,
+    };
+
+    let position = metadata
+      .iter()
+      .position(|metadata| metadata.cache_key(dep_key.clone()) == *cache_key);
+
+    if let Some(i) = position {
+      let evicted = metadata.remove(i);
+      self.unload_resource(dep_key, evicted);
+    }
+
+    if metadata.is_empty() {
+      self.forget_key(dep_key);
+    } else {
+      self.metadata.insert(dep_key.clone(), metadata);
+    }
+
+    position.is_some()
+  }
+
+  /// Unload a resource which metadata was taken out of the `Storage` and forget about it.
+  fn unload_resource(&mut self, dep_key: &DepKey, metadata: ResMetaData<C>) {
+    (metadata.on_unload)();
+
+    let cache_key = metadata.cache_key(dep_key.clone());
+    self.cache.remove(&cache_key);
+    self.memory_used -= self.footprints.remove(&cache_key).unwrap_or(0);
+    self.info.remove(&cache_key);
+
+    for members in self.groups.values_mut() {
+      members.remove(&cache_key);
+    }
+
+    // the resource doesn’t observe its dependencies nor the context anymore
+    let generation = metadata.generation;
+
+    for dependents in self.deps.values_mut().chain(self.ctx_tags.values_mut()) {
+      dependents
+        .retain(|dependent| dependent.key != *dep_key || dependent.generation != generation);
+    }
+  }
+
+  /// Forget about a key which resources were all evicted.
+  fn forget_key(&mut self, dep_key: &DepKey) {
+    self.provenance.remove(dep_key);
+    self.generation += 1;
+    self.journal.evicted(dep_key, self.generation);
+  }
+
+  /// Keys of the resources of a group – see `Storage::get_in_group` – sorted.
+  ///
+  /// A key backing several resources of the group is only yielded once.
+  pub fn group_keys(&self, group: &str) -> Vec<DepKey> {
+    let mut keys: Vec<_> = self
+      .groups
+      .get(group)
+      .into_iter()
+      .flatten()
+      .map(|cache_key| cache_key.dep_key().clone())
+      .collect();
+
+    keys.sort();
+    keys.dedup();
+    keys
+  }
+
+  /// Evict the resources of a group from the `Storage` and forget about the group – see
+  /// `Storage::get_in_group`.
+  ///
+  /// Resources that are also part of other groups are kept – assets shared by two levels, for
+  /// instance – and so are the resources of other types living at the same keys. Return the
+  /// number of evicted resources.
+  pub fn evict_group(&mut self, group: &str) -> usize {
+    let members = match self.groups.remove(group) {
+      Some(members) => members,
+      None => return 0,
+    };
+
+    let unshared: Vec<_> = members
+      .into_iter()
+      .filter(|cache_key| !self.groups.values().any(|members| members.contains(cache_key)))
+      .collect();
+
+    unshared
+      .into_iter()
+      .filter(|cache_key| self.evict_resource(cache_key))
+      .count()
+  }
+
+  /// Iterate over the keys of all the resources living in the `Storage`.
+  ///
+  /// A key backing several resources of different types is only yielded once. The keys are
+  /// prepared – their paths are real ones – and can be passed back to the functions taking a
+  /// `DepKey`, such as `Storage::evict`.
+  pub fn keys<'a>(&'a self) -> impl Iterator<Item = &'a DepKey> + 'a {
+    self.metadata.keys()
+  }
+
+  /// Iterate over the keys of all the resources of type `T` living in the `Storage`.
+  pub fn keys_of<'a, T>(&'a self) -> impl Iterator<Item = &'a DepKey> + 'a
+  where T: 'static {
+    let type_id = TypeId::of::<T>();
+
+    self
+      .metadata
+      .iter()
+      .filter(move |&(_, metadata)| metadata.iter().any(|metadata| metadata.type_id == type_id))
+      .map(|(key, _)| key)
+  }
+
+  /// Reload every resource living in the `Storage` from the current content of its files and report
+  /// all the failures at once.
+  ///
+  /// This exercises the reload code path of every resource as if its file had just been touched,
+  /// so that CI catches resources which load fine but fail to reload before anyone hits it live.
+  /// Resources are reloaded in key order; their dependent resources are not reloaded in cascade.
+  /// Successfully reloaded resources are replaced by their new values.
+  pub fn check_reloads(&mut self, ctx: &mut C) -> ReloadCheckReport {
+    let mut keys: Vec<_> = self.metadata.keys().cloned().collect();
+    keys.sort();
+
+    self.reload_keys(keys, |_| true, ctx)
+  }
+
+  /// Reload every resource living in the `Storage`, dependencies first, and report all the failures
+  /// at once.
+  ///
+  /// Use it when filesystem events can’t be trusted anymore – after switching branches or pulling
+  /// from version control, for instance. Every resource is reloaded once, after all the resources
+  /// it depends on; resources depending on each other in a cycle are reloaded last, in key order.
+  pub fn reload_all(&mut self, ctx: &mut C) -> ReloadCheckReport {
+    let keys = self.keys_in_dependency_order();
+    self.reload_keys(keys, |_| true, ctx)
+  }
+
+  /// Reload every resource of type `T` living in the `Storage`, dependencies first, and report all
+  /// the failures at once.
+  ///
+  /// Resources of other types, including the ones depending on the reloaded resources, are not
+  /// reloaded.
+  pub fn reload_type<T>(&mut self, ctx: &mut C) -> ReloadCheckReport
+  where T: 'static {
+    let keys = self.keys_in_dependency_order();
+    let type_id = TypeId::of::<T>();
+    self.reload_keys(keys, |cache_key| cache_key.type_id() == type_id, ctx)
+  }
+
+  /// Reload every resource of a group – see `Storage::get_in_group` – dependencies first, and
+  /// report all the failures at once.
+  ///
+  /// Resources out of the group, including the ones depending on the reloaded resources and the
+  /// ones of other types living at the same keys, are not reloaded.
+  pub fn reload_group(&mut self, group: &str, ctx: &mut C) -> ReloadCheckReport {
+    let members = match self.groups.get(group) {
+      Some(members) => members.clone(),
+      None => return ReloadCheckReport::default(),
+    };
+
+    let keys = self
+      .keys_in_dependency_order()
+      .into_iter()
+      .filter(|key| members.iter().any(|cache_key| cache_key.dep_key() == key))
+      .collect();
+
+    self.reload_keys(keys, |cache_key| members.contains(cache_key), ctx)
+  }
+
+  /// Keys of the resources living in the `Storage`, each after the keys it depends on.
+  fn keys_in_dependency_order(&self) -> Vec<DepKey> {
+    let mut keys: Vec<_> = self.metadata.keys().cloned().collect();
+    keys.sort();
+
+    self.in_dependency_order(keys)
+  }
+
+  /// Order keys so that each one comes after the keys it depends on, keeping their relative order
+  /// otherwise.
+  ///
+  /// This is a topological sort, linear in the number of keys and dependencies. Keys caught in
+  /// dependency cycles are kept in their relative order.
+  fn in_dependency_order(&self, keys: Vec<DepKey>) -> Vec<DepKey> {
+    let index: HashMap<&DepKey, usize> = keys.iter().enumerate().map(|(i, key)| (key, i)).collect();
+    // number of dependencies of each key still waiting to be ordered, and dependents of each key
+    let mut waiting = vec![0usize; keys.len()];
+    let mut dependents = vec![Vec::new(); keys.len()];
+
+    for (i, key) in keys.iter().enumerate() {
+      for dependent in self.deps.get(key).into_iter().flatten() {
+        match index.get(&dependent.key) {
+          Some(&j) if j != i => {
+            waiting[j] += 1;
+            dependents[i].push(j);
+          }
+
+          _ => (),
+        }
+      }
+    }
+
+    // ready keys, first given first
+    let mut ready: BinaryHeap<_> = (0..keys.len())
+      .filter(|&i| waiting[i] == 0)
+      .map(Reverse)
+      .collect();
+    let mut placed = vec![false; keys.len()];
+    let mut order = Vec::with_capacity(keys.len());
+    let mut next_unplaced = 0;
+
+    while order.len() < keys.len() {
+      let i = match ready.pop() {
+        Some(Reverse(i)) => i,
+
+        // only cycles remain; break the first one
+        None => {
+          while placed[next_unplaced] {
+            next_unplaced += 1;
+          }
+
+          next_unplaced
+        }
+      };
+
+      if placed[i] {
+        continue;
+      }
+
+      placed[i] = true;
+      order.push(i);
+
+      for &j in &dependents[i] {
+        waiting[j] = waiting[j].saturating_sub(1);
+
+        if waiting[j] == 0 && !placed[j] {
+          ready.push(Reverse(j));
+        }
+      }
+    }
+
+    let mut keys: Vec<_> = keys.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| keys[i].take()).collect()
+  }
+
+  /// Reload the resources living at the given keys, in order, optionally only the ones of a given
+  /// type.
+  fn reload_keys<F>(&mut self, keys: Vec<DepKey>, selected: F, ctx: &mut C) -> ReloadCheckReport
+  where F: Fn(&CacheKey) -> bool {
+    let mut report = ReloadCheckReport::default();
+
+    for dep_key in keys {
+      if let Some(metadata) = self.metadata.remove(&dep_key) {
+        let selected = metadata
+          .iter()
+          .filter(|metadata| selected(&metadata.cache_key(dep_key.clone())));
+
+        for metadata in selected {
+          match (metadata.on_reload)(self, ctx) {
+            Ok(_) => report.reloaded += 1,
+            Err(e) => report.failures.push(ReloadFailure {
+              key: dep_key.clone(),
+              type_name: metadata.type_name,
+              error: e.to_string(),
+            }),
+          }
+        }
+
+        self.insert_metadata(dep_key, metadata);
+      }
+    }
+
+    report
+  }
+
+  /// Take a snapshot of the dependency graph of the `Storage`.
+  ///
+  /// See the documentation of `DependencyGraph` for further details.
+  pub fn dependency_graph(&self) -> DependencyGraph {
+    let mut nodes: Vec<DepKey> = self.metadata.keys().cloned().collect();
+    let mut edges = Vec::new();
+
+    for (dep, dependents) in &self.deps {
+      nodes.push(dep.clone());
+
+      for dependent in dependents {
+        edges.push((dep.clone(), dependent.key.clone()));
+      }
+    }
+
+    DependencyGraph::new(nodes, edges)
+  }
+
+  /// Keys of the resources directly depending on the resource living at the given key – the ones
+  /// reloaded when it changes – sorted.
+  ///
+  /// The key can be expressed with a VFS path or be one returned by the `Storage`: call this
+  /// function again on the returned keys to walk all the resources a change ripples through.
+  pub fn dependents_of(&self, key: &DepKey) -> Vec<DepKey> {
+    let dep_key = self.prepare_dep_key(key);
+    let mut dependents: Vec<_> = self
+      .deps
+      .get(&dep_key)
+      .into_iter()
+      .flat_map(|dependents| dependents)
+      .filter(|dependent| self.is_live(dependent))
+      .map(|dependent| dependent.key.clone())
+      .collect();
+
+    dependents.sort();
+    dependents.dedup();
+    dependents
+  }
+
+  /// Keys the resources living at the given key directly depend on, sorted.
+  ///
+  /// Just like with `Storage::dependents_of`, the returned keys can be queried in turn.
+  pub fn dependencies_of(&self, key: &DepKey) -> Vec<DepKey> {
+    let dep_key = self.prepare_dep_key(key);
+    let mut dependencies: Vec<_> = self
+      .deps
+      .iter()
+      .filter(|&(_, dependents)| {
+        dependents
+          .iter()
+          .any(|dependent| dependent.key == dep_key && self.is_live(dependent))
+      })
+      .map(|(dep, _)| dep.clone())
+      .collect();
+
+    dependencies.sort();
+    dependencies
+  }
+
+  /// Keys of the resources tagged with an aspect of the context – see `Loaded::with_ctx_tag`.
+  ///
+  /// Keys are sorted and only yielded once.
+  pub fn tagged(&self, tag: &str) -> Vec<DepKey> {
+    let mut keys: Vec<_> = self.ctx_tags.get(tag).map_or(Vec::new(), |dependents| {
+      dependents
+        .iter()
+        .filter(|dependent| self.is_live(dependent))
+        .map(|dependent| dependent.key.clone())
+        .collect()
+    });
+
+    keys.sort();
+    keys.dedup();
+    keys
+  }
+
+  /// Check whether a dependent resource is still living in the `Storage`.
+  fn is_live(&self, dependent: &Dependent) -> bool {
+    self.metadata.get(&dependent.key).map_or(false, |metadata| {
+      metadata.iter().any(|metadata| metadata.generation == dependent.generation)
+    })
+  }
+
+  /// Register a fault to trigger whenever the resource living at the given key gets loaded or
+  /// reloaded.
+  ///
+  /// Any previous fault registered for that key is replaced.
+  #[cfg(feature = "failure-injection")]
+  pub fn set_fault<K>(&mut self, key: K, fault: Fault)
+  where K: Key {
+    let dep_key = self.prepare(key).into();
+    self.faults.insert(dep_key, fault);
+  }
+
+  /// Remove the fault registered for the given key, if any.
+  #[cfg(feature = "failure-injection")]
+  pub fn clear_fault<K>(&mut self, key: K)
+  where K: Key {
+    let dep_key = self.prepare(key).into();
+    self.faults.remove(&dep_key);
+  }
+
+  /// Trigger the fault registered for a key, if any, and return the error it generates.
+  #[cfg(feature = "failure-injection")]
+  fn trigger_fault<E>(&self, dep_key: &DepKey, reloading: bool) -> Option<E>
+  where E: 'static {
+    self
+      .faults
+      .get(dep_key)
+      .and_then(|fault| fault.trigger(reloading))
+  }
+
+  #[cfg(not(feature = "failure-injection"))]
+  #[inline(always)]
+  fn trigger_fault<E>(&self, _: &DepKey, _: bool) -> Option<E>
+  where E: 'static {
+    None
+  }
+
+  /// Inject a new resource in the store.
+  ///
+  /// The resource might be refused for several reasons. Further information in the documentation of
+  /// the `StoreError` error type.
+  fn inject<T, M>(&mut self, key: T::Key, loaded: Loaded<T>) -> Result<Res<T>, StoreError>
+  where
+    T: Load<C, M>,
+    T::Key: Clone + hash::Hash + Into<DepKey>,
+    M: 'static,
+  {
+    let dep_key = key.clone().into();
+
+    // we forbid having two resources of the same type, loaded with the same method, sharing the
+    // same key
+    let cache_key = CacheKey::with_method::<T, M>(dep_key.clone());
+
+    if self.contains_resource(&cache_key) {
+      return Err(StoreError::AlreadyRegisteredKey(dep_key));
+    }
+
+    let Loaded {
+      res: mut resource,
+      deps,
+      weak_deps,
+      ctx_tags,
+    } = loaded;
+
+    let footprint = <T as Load<C, M>>::memory_footprint(&resource);
+
+    if let Err(e) = self.check_budget(0, footprint) {
+      <T as Load<C, M>>::unload(&mut resource);
+      return Err(e);
+    }
+
+    self.check_dependencies(&dep_key, deps.iter().chain(&weak_deps))?;
+
+    log_event!(debug, "loaded {} ({})", dep_key, type_name::<T>());
+
+    // wrap the resource to make it shared mutably
+    let res = Res::new(resource);
+    self
+      .erasers
+      .entry(TypeId::of::<T>())
+      .or_insert(AnyRes::erase::<T>);
+
+    // create the metadata for the resource
+    let res_ = res.clone();
+    let key_ = key.clone();
+    let dep_key_ = dep_key.clone();
+    let on_reload = move |storage: &mut Storage<C>, ctx: &mut C| -> Result<(), Box<Error>> {
+      if let Some(e) = storage.trigger_fault::<T::Error>(&dep_key_, true) {
+        return Err(Box::new(e));
+      }
+
+      <T as Load<C, M>>::before_reload(&res_.borrow(), storage, ctx);
+      let reloaded = <T as Load<C, M>>::reload(&res_.borrow(), key_.clone(), storage, ctx);
+
+      match reloaded {
+        Ok(mut r) => {
+          // the new value replaces the current one, so only the difference must fit in the budget
+          let cache_key = CacheKey::with_method::<T, M>(dep_key_.clone());
+          let current = storage.footprints.get(&cache_key).cloned().unwrap_or(0);
+          let footprint = <T as Load<C, M>>::memory_footprint(&r);
+
+          if let Err(e) = storage.check_budget(current, footprint) {
+            <T as Load<C, M>>::unload(&mut r);
+            return Err(Box::new(e));
+          }
+
+          // finish staging the new value while the current one is still readable; the resource
+          // is only borrowed mutably for the time of the swap
+          <T as Load<C, M>>::after_reload(&mut r, &res_.borrow(), storage, ctx);
+          let old = res_.replace(r);
+          storage.memory_used = storage.memory_used - current + footprint;
+          storage.footprints.insert(cache_key.clone(), footprint);
+          let provenance = storage.provenance_of(&dep_key_, &cache_key);
+          storage.record_provenance::<T, M>(&dep_key_, true);
+
+          match storage.undo_log {
+            // keep the old value around in case the transaction gets rolled back, and only notify
+            // the observers once it’s committed
+            Some(ref mut undo_log) => {
+              let res = res_.clone();
+              let dep_key = dep_key_.clone();
+              let generation = storage.generation;
+              let undo = move |storage: &mut Storage<C>| {
+                let new = res.restore(old);
+                storage.memory_used = storage.memory_used - footprint + current;
+                storage.footprints.insert(cache_key.clone(), current);
+                storage.restore_provenance(&dep_key, &cache_key, provenance, generation);
+                drop(new);
+              };
+
+              let res = res_.clone();
+              undo_log.undos.push(Box::new(undo));
+              undo_log.notifications.push(Box::new(move || res.notify_reloaded()));
+            }
+
+            // the old value is only dropped once the new one is in place
+            None => {
+              res_.notify_reloaded();
+              drop(old);
+            }
+          }
+
+          Ok(())
+        }
+        Err(e) => Err(Box::new(e)),
+      }
+    };
+
+    let res_ = res.clone();
+    let on_dep_reload = move |dep: &DepKey, storage: &mut Storage<C>, ctx: &mut C| {
+      <T as Load<C, M>>::dependency_reloaded(&mut res_.borrow_mut(), dep, storage, ctx);
+    };
+
+    let res_ = res.clone();
+    let on_unload = move || <T as Load<C, M>>::unload(&mut res_.borrow_mut());
+
+    let res_ = res.clone();
+    let dep_key_ = dep_key.clone();
+    let on_proxy = move |storage: &mut Storage<C>| match storage.proxy::<T>() {
+      Some(proxy) => {
+        let cache_key = CacheKey::with_method::<T, M>(dep_key_.clone());
+        let current = storage.footprints.get(&cache_key).cloned().unwrap_or(0);
+        let footprint = <T as Load<C, M>>::memory_footprint(&proxy);
+        let old = res_.replace(proxy);
+
+        storage.memory_used = storage.memory_used - current + footprint;
+        storage.footprints.insert(cache_key, footprint);
+        res_.notify_reloaded();
+        drop(old);
+        true
+      }
+
+      None => false,
+    };
+
+    let res_ = res.clone();
+    let on_source_state = move |state| res_.set_state(state);
+
+    self.record_provenance::<T, M>(&dep_key, false);
+    let generation = self.generation;
+    let reload_delay = <T as Load<C, M>>::reload_delay();
+    let reload_priority = <T as Load<C, M>>::reload_priority();
+    let metadata = ResMetaData::new::<T, M, _, _, _, _, _>(
+      generation,
+      on_reload,
+      on_dep_reload,
+      on_unload,
+      on_proxy,
+      on_source_state,
+      reload_delay,
+      reload_priority,
+    );
+    self.insert_metadata(dep_key.clone(), vec![metadata]);
+
+    // register the resource as an observer of its dependencies in the dependencies graph
+    let deps = deps.into_iter().map(|dep| (dep, DepKind::Strong));
+    let weak_deps = weak_deps.into_iter().map(|dep| (dep, DepKind::Weak));
+
+    for (dep, kind) in deps.chain(weak_deps) {
+      let dep = self.prepare(dep);
+
+      if let Some(path) = dep.path() {
+        self.watch_path(path);
+      }
+
+      self
+        .deps
+        .entry(dep)
+        .or_insert(Vec::new())
+        .push(Dependent {
+          key: dep_key.clone(),
+          kind,
+          generation,
+        });
+    }
+
+    if let Some(path) = dep_key.path() {
+      self.watch_path(path);
+    }
+
+    for tag in ctx_tags {
+      self.ctx_tags.entry(tag).or_insert(Vec::new()).push(Dependent {
+        key: dep_key.clone(),
+        kind: DepKind::Strong,
+        generation,
+      });
+    }
+
+    // cache the resource
+    self.memory_used += footprint;
+    self.footprints.insert(cache_key.clone(), footprint);
+    self.cache.save(cache_key, Box::new(res.clone()));
+
+    Ok(res)
+  }
+
+  /// Check whether a resource of a given type, loaded with a given method, lives at the given key,
+  /// already prepared.
+  fn contains_resource(&self, cache_key: &CacheKey) -> bool {
+    self.metadata.get(cache_key.dep_key()).map_or(false, |metadata| {
+      metadata.iter().any(|metadata| metadata.cache_key(cache_key.dep_key().clone()) == *cache_key)
+    })
+  }
+
+  /// Insert metadata for resources living at the given key, already prepared, keeping the metadata
+  /// of other resources living there.
+  fn insert_metadata(&mut self, dep_key: DepKey, metadata: Vec<ResMetaData<C>>) {
+    self.metadata.entry(dep_key).or_insert(Vec::new()).extend(metadata);
+  }
+
+  /// Processing parameters of a resource, if set.
+  pub(crate) fn process_params(&self, cache_key: &CacheKey) -> Option<&Any> {
+    self.process_params.get(cache_key).map(|params| &**params)
+  }
+
+  /// Set the processing parameters of a resource.
+  pub(crate) fn insert_process_params(&mut self, cache_key: CacheKey, params: Box<Any>) {
+    self.process_params.insert(cache_key, params);
+  }
+
+  /// Reload a single resource, if it’s living in the `Storage`.
+  ///
+  /// Neither the other resources living at the same key nor the dependents are reloaded.
+  pub(crate) fn reload_resource(
+    &mut self,
+    cache_key: &CacheKey,
+    ctx: &mut C,
+  ) -> Option<Result<(), Box<Error>>>
+  {
+    let dep_key = cache_key.dep_key();
+    let metadata = self.metadata.remove(dep_key)?;
+    let outcome = metadata
+      .iter()
+      .find(|metadata| metadata.cache_key(dep_key.clone()) == *cache_key)
+      .map(|metadata| (metadata.on_reload)(self, ctx));
+
+    self.insert_metadata(dep_key.clone(), metadata);
+    outcome
+  }
+
+  /// Keys through which the resources living at the given key, already prepared, are observed:
+  /// the key itself and the aliases standing for it.
+  fn observed_keys(&self, dep_key: &DepKey) -> Vec<DepKey> {
+    let aliases = self
+      .aliases
+      .iter()
+      .filter(|&(_, target)| target == dep_key)
+      .map(|(alias, _)| alias.clone());
+
+    Some(dep_key.clone()).into_iter().chain(aliases).collect()
+  }
+
+  /// Forget about the dead resources depending on the resource living at the given key.
+  fn prune_dependents(&mut self, dep_key: &DepKey) {
+    let metadata = &self.metadata;
+
+    if let Some(dependents) = self.deps.get_mut(dep_key) {
+      dependents.retain(|dependent| {
+        metadata.get(&dependent.key).map_or(false, |metadata| {
+          metadata.iter().any(|metadata| metadata.generation == dependent.generation)
+        })
+      });
+    }
+  }
+
+  /// Check that a resource which footprint is `requested` fits in the memory budget, once a
+  /// resource which footprint is `current` is released.
+  fn check_budget(&self, current: usize, requested: usize) -> Result<(), StoreError> {
+    match self.memory_budget {
+      Some(budget) => {
+        let available = budget.saturating_sub(self.memory_used - current);
+
+        if requested > available {
+          Err(StoreError::BudgetExceeded {
+            requested,
+            available,
+          })
+        } else {
+          Ok(())
+        }
+      }
+
+      None => Ok(()),
+    }
+  }
+
+  /// Check that a prepared key doesn’t escape the root, unless allowed.
+  fn check_root(&self, dep_key: &DepKey) -> Result<(), StoreError> {
+    match dep_key.path() {
+      Some(path)
+        if !self.allow_outside_root
+          && !path.starts_with(self.root())
+          && !self.mounts.contains(path) =>
+      {
+        Err(StoreError::KeyOutsideRoot(dep_key.clone()))
+      }
+
+      _ => Ok(()),
+    }
+  }
+
+  /// Check that declared dependencies exist, according to the configured `DependencyCheck`.
+  fn check_dependencies<'a, I>(&self, dep_key: &DepKey, deps: I) -> Result<(), StoreError>
+  where I: Iterator<Item = &'a DepKey> {
+    if self.dependency_check == DependencyCheck::Ignore {
+      return Ok(());
+    }
+
+    for dep in deps {
+      let dep = self.prepare(dep.clone());
+      let exists = self.aliases.contains_key(&dep)
+        || match dep.path() {
+          // resources inserted by hand live at paths that might not exist
+          Some(path) => path.exists() || self.metadata.contains_key(&dep),
+          None => self.metadata.contains_key(&dep),
+        };
+
+      if exists {
+        continue;
+      }
+
+      match self.dependency_check {
+        DependencyCheck::Warn => {
+          log_event!(warn, "{} depends on {}, which doesn’t exist", dep_key, dep);
+        }
+
+        _ => return Err(StoreError::MissingDependency(dep_key.clone(), dep)),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Make a key stand for another one.
+  ///
+  /// Looking a resource up with `Storage::get_via` at the alias key yields the resource living at
+  /// the target key – the very same `Res<T>`, reloading the same way. Resources declaring a
+  /// dependency on the alias key are reloaded whenever the target reloads.
+  ///
+  /// Remapping an existing alias makes the resources depending on it reload during the next
+  /// synchronization, so that they switch to the new target at once. Aliasing an alias binds the
+  /// new alias to the current target of the aliased one.
+  ///
+  /// A key that backs resources cannot be used as an alias.
+  pub fn alias<A, K>(&mut self, alias: A, target: K) -> Result<(), StoreError>
+  where
+    A: Into<DepKey>,
+    K: Into<DepKey>,
+  {
+    let alias = self.prepare(alias.into());
+    let target = self.resolve(&target.into());
+
+    if self.metadata.contains_key(&alias) {
+      return Err(StoreError::AlreadyRegisteredKey(alias));
+    }
+
+    if let Some(previous) = self.aliases.insert(alias.clone(), target.clone()) {
+      if previous != target {
+        self.remapped_aliases.insert(alias);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Remove an alias and return the key it stood for, if any.
+  ///
+  /// The resources depending on the alias get reloaded during the next synchronization.
+  pub fn unalias(&mut self, alias: &DepKey) -> Option<DepKey> {
+    let alias = self.prepare(alias.clone());
+    let target = self.aliases.remove(&alias);
+
+    if target.is_some() {
+      self.remapped_aliases.insert(alias);
+    }
+
+    target
+  }
+
+  /// Resolve a key, following its alias if it’s one.
+  pub fn resolve(&self, key: &DepKey) -> DepKey {
+    let dep_key = self.prepare(key.clone());
+    self.aliases.get(&dep_key).cloned().unwrap_or(dep_key)
+  }
+
+  /// Get a resource from the `Storage` by following aliases, and return an error if its loading
+  /// failed.
+  ///
+  /// This function uses the default loading method. See `Storage::alias` for further details.
+  pub fn get_via<T>(&mut self, key: &DepKey, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    T::Key: FromDepKey,
+  {
+    let dep_key = self.resolve(key).unprepare_key(self.root(), &self.mounts);
+    let key = <T::Key as FromDepKey>::from_dep_key(&dep_key)
+      .ok_or_else(|| StoreErrorOr::StoreError(StoreError::KeyMismatch(dep_key.clone())))?;
+
+    self.get::<T::Key, T>(&key, ctx)
+  }
+
+  /// Get a resource from the `Storage` and return an error if its loading failed.
+  ///
+  /// This function uses the default loading method.
+  pub fn get<K, T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>, {
+    self.get_by(key, ctx, ())
+  }
+
+  /// Get a resource from the `Storage`, tagging the read with a label in the read metrics.
+  ///
+  /// This function uses the default loading method. See `StoreOpt::set_read_metrics`.
+  pub fn get_labeled<K, T>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    label: &'static str,
+  ) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>,
+  {
+    self.get_by_labeled(key, ctx, (), label)
+  }
+
+  /// Get a resource from the `Storage` by using a specific method and return and error if its
+  /// loading failed.
+  pub fn get_by<K, T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    _: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    self.get_with::<K, T, M>(key, ctx)
+  }
+
+  /// Get a resource from the `Storage` by using a specific method, tagging the read with a label
+  /// in the read metrics.
+  pub fn get_by_labeled<K, T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    _: M,
+    label: &'static str,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    self.get_or_load_labeled(key, ctx, Some(label), <T as Load<C, M>>::load)
+  }
+
+  /// Get a resource from the `Storage` and add it to a named group.
+  ///
+  /// Groups make it possible to reload or evict a bunch of resources at once – the assets of a
+  /// level, for instance – with `Storage::reload_group` and `Storage::evict_group`. A resource can
+  /// be part of several groups. This function uses the default loading method.
+  pub fn get_in_group<K, T>(
+    &mut self,
+    group: &str,
+    key: &K,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>,
+  {
+    self.get_in_group_by(group, key, ctx, ())
+  }
+
+  /// Get a resource from the `Storage` by using a specific method and add it to a named group.
+  ///
+  /// See `Storage::get_in_group`.
+  pub fn get_in_group_by<K, T, M>(
+    &mut self,
+    group: &str,
+    key: &K,
+    ctx: &mut C,
+    _: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    let key_ = self
+      .prepare_memoized(key.clone().into())
+      .map_err(StoreErrorOr::StoreError)?;
+    let res = self.get_or_load_prepared(key_.clone(), ctx, None, <T as Load<C, M>>::load)?;
+
+    self
+      .groups
+      .entry(group.to_owned())
+      .or_insert_with(HashSet::new)
+      .insert(CacheKey::with_method::<T, M>(key_.into()));
+
+    Ok(res)
+  }
+
+  /// Get a resource from the `Storage` by using a specific method, without requiring a value of
+  /// the method.
+  pub(crate) fn get_with<K, T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    self.get_or_load(key, ctx, <T as Load<C, M>>::load)
+  }
+
+  /// Get a resource from the `Storage`, loading it with the given function if it’s not cached yet.
+  pub(crate) fn get_or_load<K, T, M, F>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    load: F,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    F: FnOnce(T::Key, &mut Self, &mut C) -> Result<Loaded<T>, T::Error>,
+    M: 'static,
+  {
+    self.get_or_load_labeled(key, ctx, None, load)
+  }
+
+  /// Same as `get_or_load`, tagging the read with a label in the read metrics.
+  fn get_or_load_labeled<K, T, M, F>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    label: Option<&'static str>,
+    load: F,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    F: FnOnce(T::Key, &mut Self, &mut C) -> Result<Loaded<T>, T::Error>,
+    M: 'static,
+  {
+    let key_ = self
+      .prepare_memoized(key.clone().into())
+      .map_err(StoreErrorOr::StoreError)?;
+
+    self.get_or_load_prepared(key_, ctx, label, load)
+  }
+
+  /// Get a resource from the `Storage` given its prepared key – typically, the one a loader is
+  /// given – with its default loading function.
+  pub(crate) fn get_prepared<T, M>(
+    &mut self,
+    key: T::Key,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    M: 'static,
+  {
+    self.get_or_load_prepared(key, ctx, None, <T as Load<C, M>>::load)
+  }
+
+  /// Same as `get_or_load_labeled`, with an already prepared key.
+  fn get_or_load_prepared<T, M, F>(
+    &mut self,
+    key_: T::Key,
+    ctx: &mut C,
+    label: Option<&'static str>,
+    load: F,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    F: FnOnce(T::Key, &mut Self, &mut C) -> Result<Loaded<T>, T::Error>,
+    M: 'static,
+  {
+    let cache_key = CacheKey::with_method::<T, M>(key_.clone().into());
+    self.get_or_load_cached(&key_, &cache_key, ctx, label, load)
+  }
+
+  /// Same as `get_or_load_prepared`, with the cache key of the resource already computed.
+  ///
+  /// Cache hits don’t allocate: the prepared key is only cloned if the resource must be loaded.
+  pub(crate) fn get_or_load_cached<T, M, F>(
+    &mut self,
+    key_: &T::Key,
+    cache_key: &CacheKey,
+    ctx: &mut C,
+    label: Option<&'static str>,
+    load: F,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    F: FnOnce(T::Key, &mut Self, &mut C) -> Result<Loaded<T>, T::Error>,
+    M: 'static,
+  {
+    let x: Option<Res<T>> = self
+      .cache
+      .get(cache_key)
+      .and_then(|cached| cached.downcast_ref::<Res<T>>())
+      .cloned();
+
+    if x.is_some() {
+      self.cache_hits += 1;
+    } else {
+      self.cache_misses += 1;
+    }
+
+    if let Some(ref mut read_metrics) = self.read_metrics {
+      read_metrics.record(type_name::<T>(), label, x.is_some());
+    }
+
+    match x {
+      Some(resource) => Ok(resource),
+      None => {
+        if let Some(e) = self.trigger_fault(cache_key.dep_key(), false) {
+          return Err(StoreErrorOr::ResError(e));
+        }
+
+        log_event!(trace, "cache miss on {} ({})", cache_key.dep_key(), type_name::<T>());
+
+        if let Some(ref mut progress) = self.progress {
+          progress.start(cache_key.dep_key().clone(), type_name::<T>());
+        }
+
+        let loaded = load(key_.clone(), self, ctx);
+
+        if let Some(ref mut progress) = self.progress {
+          progress.finish(loaded.is_ok());
+        }
+
+        let loaded = match loaded {
+          Ok(loaded) => loaded,
+          Err(e) => {
+            log_event!(warn, "cannot load {} ({}): {}", cache_key.dep_key(), type_name::<T>(), e);
+
+            // the default stands in for the resource until its source is fixed
+            match self.fallback_default::<T>() {
+              Some(default) => default.into(),
+              None => return Err(StoreErrorOr::ResError(e)),
+            }
+          }
+        };
+
+        self
+          .inject::<T, M>(key_.clone(), loaded)
+          .map_err(StoreErrorOr::StoreError)
+      }
+    }
+  }
+
+  /// Same as `prepare_checked`, remembering the prepared key so that preparing the same key again
+  /// is a mere lookup.
+  fn prepare_memoized<K>(&mut self, key: K) -> Result<K, StoreError>
+  where K: Key + 'static {
+    let type_id = TypeId::of::<K>();
+    let prepared = self
+      .prepared_keys
+      .get(&type_id)
+      .and_then(|keys| keys.downcast_ref::<HashMap<K, K>>())
+      .and_then(|keys| keys.get(&key))
+      .cloned();
+
+    if let Some(prepared) = prepared {
+      return Ok(prepared);
+    }
+
+    let prepared = self.prepare_checked(key.clone())?;
+    let keys = self
+      .prepared_keys
+      .entry(type_id)
+      .or_insert_with(|| Box::new(HashMap::<K, K>::new()));
+
+    if let Some(keys) = keys.downcast_mut::<HashMap<K, K>>() {
+      // forget everything rather than growing without bounds with ever-changing keys
+      if keys.len() >= PREPARED_KEYS_CAPACITY {
+        keys.clear();
+      }
+
+      keys.insert(key, prepared.clone());
+    }
+
+    Ok(prepared)
+  }
+
+  /// Normalization of the VFS paths of keys.
+  pub fn path_normalization(&self) -> PathNormalization {
+    self.path_normalization
+  }
+
+  /// Make sure changes to the path of a resource are watched.
+  ///
+  /// Paths going through symlinks are watched at their targets as well, since watchers report
+  /// events on the targets while keys keep the links.
+  fn watch_path(&mut self, path: &Path) {
+    self.watch.cover(path);
+
+    let target = match fs::canonicalize(path) {
+      Ok(target) => target,
+      Err(_) => return,
+    };
+
+    // the target is spelled with the canonicalized root, which might not be the one of the key;
+    // events are matched against normalized paths
+    let normalized = self.normalize_path(&target);
+
+    if normalized != path {
+      self.watch.cover(&target);
+      self
+        .symlinks
+        .entry(normalized)
+        .or_insert_with(HashSet::new)
+        .insert(path.to_owned());
+    }
+  }
+
+  /// Paths of resources a real path stands for through symlinks.
+  #[cfg_attr(not(feature = "watcher"), allow(dead_code))]
+  fn linked_paths(&self, path: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    for (target, links) in &self.symlinks {
+      if let Ok(relative) = path.strip_prefix(target) {
+        for link in links {
+          if relative.as_os_str().is_empty() {
+            paths.push(link.clone());
+          } else {
+            paths.push(link.join(relative));
+          }
+        }
+      }
+    }
+
+    paths
+  }
+
+  /// Normalize a real path the same way the VFS paths of keys are, spelling it with the root of the
+  /// `Storage` if it lives under one of its aliases.
+  pub(crate) fn normalize_path(&self, path: &Path) -> PathBuf {
+    let rebased = self
+      .root_aliases
+      .iter()
+      .filter_map(|alias| path.strip_prefix(alias).ok())
+      .next()
+      .map(|relative| self.root().join(relative));
+    let path = rebased.as_ref().map_or(path, PathBuf::as_path);
+
+    match path.strip_prefix(self.root()) {
+      Ok(relative) if !self.path_normalization.is_none() => {
+        self.root().join(self.path_normalization.apply(relative))
+      }
+      _ => path.to_owned(),
+    }
+  }
+
+  /// Prepare a key with the root and the path normalization of the `Storage`.
+  pub(crate) fn prepare<K>(&self, key: K) -> K
+  where K: Key {
+    self.mounts.prepare_key(key, self.root(), self.path_normalization)
+  }
+
+  /// Prepare a key that might have been handed out by the `Storage` already – see
+  /// `Storage::keys`.
+  ///
+  /// Such keys are prepared: their paths live under the root or in a mounted directory, and
+  /// preparing them again would prefix them with the root a second time.
+  pub(crate) fn prepare_dep_key(&self, key: &DepKey) -> DepKey {
+    let path = match *key {
+      DepKey::Path(ref path) | DepKey::PathWith(ref path, _) | DepKey::Dir(ref path) => path,
+      _ => return self.prepare(key.clone()),
+    };
+
+    if path.starts_with(self.root()) || self.mounts.contains(path) {
+      key.clone()
+    } else {
+      self.prepare(key.clone())
+    }
+  }
+
+  /// Prepare a key, checking that it doesn’t escape the root unless allowed.
+  pub(crate) fn prepare_checked<K>(&self, key: K) -> Result<K, StoreError>
+  where K: Key {
+    // absolute keys are built explicitly and are trusted
+    let absolute = match key.clone().into() {
+      DepKey::HostPath(_) => true,
+      _ => false,
+    };
+
+    let key = self.prepare(key);
+
+    if !absolute {
+      self.check_root(&key.clone().into())?;
+    }
+
+    Ok(key)
+  }
+
+  /// The runtime loader registry of the `Storage`.
+  pub fn registry(&self) -> &LoaderRegistry<C> {
+    &self.registry
+  }
+
+  /// The runtime loader registry of the `Storage`, to register new loaders.
+  pub fn registry_mut(&mut self) -> &mut LoaderRegistry<C> {
+    &mut self.registry
+  }
+
+  /// Get a type-erased resource from the `Storage` by using a loader registered at runtime.
+  pub fn get_any(
+    &mut self,
+    loader: &str,
+    key: &DepKey,
+    ctx: &mut C,
+  ) -> Result<AnyRes, RegistryError>
+  {
+    let loader = self
+      .registry
+      .loader(loader)
+      .ok_or_else(|| RegistryError::UnknownLoader(loader.to_owned()))?;
+
+    loader(key, self, ctx)
+  }
+
+  /// Get a type-erased resource from the `Storage` by using the loader registered at runtime for
+  /// the extension of its path.
+  ///
+  /// The path is a VFS path, as in `FSKey::new`. See `LoaderRegistry::register_extension`.
+  pub fn get_any_path<P>(&mut self, path: P, ctx: &mut C) -> Result<AnyRes, RegistryError>
+  where P: AsRef<Path> {
+    let path = path.as_ref();
+    let loader = self
+      .registry
+      .loader_for_path(path)
+      .ok_or_else(|| RegistryError::UnknownExtension(path.to_owned()))?;
+
+    loader(&FSKey::new(path).into(), self, ctx)
+  }
+
+  /// Get a resource from the `Storage` for the given key. If it fails, a proxied version is used,
+  /// which will get replaced by the resource once it’s available and reloaded.
+  ///
+  /// This function uses the default loading method.
+  pub fn get_proxied<K, T, P>(
+    &mut self,
+    key: &K,
+    proxy: P,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreError>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>,
+    P: FnOnce() -> T,
+  {
+    self
+      .get(key, ctx)
+      .or_else(|_| self.inject::<T, ()>(key.clone().into(), proxy().into()))
+  }
+
+  /// Get a resource from the `Storage` for the given key by using a specific method. If it fails, a
+  /// proxied version is used, which will get replaced by the resource once it’s available and
+  /// reloaded.
+  pub fn get_proxied_by<K, T, M, P>(
+    &mut self,
+    key: &K,
+    proxy: P,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<T>, StoreError>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    P: FnOnce() -> T,
+    M: 'static,
+  {
+    self
+      .get_by(key, ctx, method)
+      .or_else(|_| self.inject::<T, M>(key.clone().into(), proxy().into()))
+  }
+
+  /// Load a resource once, without keeping it in the `Storage`.
+  ///
+  /// The resource isn’t cached, watched nor reloaded: it’s handed over as-is – think of a save file
+  /// read at startup. Its dependencies are ignored, but the resources its loader gets from the
+  /// `Storage` are cached as usual.
+  ///
+  /// This function uses the default loading method.
+  pub fn load_once<K, T>(&mut self, key: &K, ctx: &mut C) -> Result<T, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>, {
+    self.load_once_by(key, ctx, ())
+  }
+
+  /// Load a resource once by using a specific method, without keeping it in the `Storage`.
+  pub fn load_once_by<K, T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    _: M,
+  ) -> Result<T, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+  {
+    let key = self
+      .prepare_memoized(key.clone().into())
+      .map_err(StoreErrorOr::StoreError)?;
+
+    <T as Load<C, M>>::load(key, self, ctx)
+      .map(|loaded| loaded.res)
+      .map_err(StoreErrorOr::ResError)
+  }
+
+  /// Insert a resource computed by other means – a procedurally generated mesh, for instance – in
+  /// the `Storage`, along with its dependencies.
+  ///
+  /// The resource is then handled as if it had been loaded with the default method: other resources
+  /// can depend on it and it’s reloaded with `Load::reload` when its key or its dependencies
+  /// change. A resource of the same type already living at the key makes this function fail with
+  /// `StoreError::AlreadyRegisteredKey`.
+  pub fn insert<K, T>(&mut self, key: &K, loaded: Loaded<T>) -> Result<Res<T>, StoreError>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>, {
+    self.insert_by(key, loaded, ())
+  }
+
+  /// Insert a resource computed by other means in the `Storage`, reloaded with a specific method.
+  pub fn insert_by<K, T, M>(
+    &mut self,
+    key: &K,
+    loaded: Loaded<T>,
+    _: M,
+  ) -> Result<Res<T>, StoreError>
+  where
+    T: Load<C, M>,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    let key = self.prepare_memoized(key.clone().into())?;
+    self.inject::<T, M>(key, loaded)
+  }
+
+  /// Get a resource from the `Storage` if it’s living there, or insert the one computed by a
+  /// function otherwise.
+  ///
+  /// The resource is never loaded. See `Storage::insert` for further details.
+  pub fn get_or_insert_with<K, T, F>(&mut self, key: &K, f: F) -> Result<Res<T>, StoreError>
+  where
+    T: Load<C>,
+    K: Clone + Into<T::Key>,
+    F: FnOnce() -> Loaded<T>,
+  {
+    let key = self.prepare_memoized(key.clone().into())?;
+    let cache_key = CacheKey::new::<T>(key.clone().into());
+    let cached = self
+      .cache
+      .get(&cache_key)
+      .and_then(|cached| cached.downcast_ref::<Res<T>>())
+      .cloned();
+
+    match cached {
+      Some(resource) => Ok(resource),
+      None => self.inject::<T, ()>(key, f()),
+    }
+  }
+
+  /// Register the proxy of the resources of type `T`, replacing the one already registered, if
+  /// any.
+  ///
+  /// This is the same as `Storage::register_default`, which is the preferred name.
+  pub fn set_proxy<T, F>(&mut self, proxy: F)
+  where
+    T: 'static,
+    F: 'static + Fn() -> T,
+  {
+    self.register_default(proxy);
+  }
+
+  /// Register the default value – or proxy – of the resources of type `T`, replacing the one
+  /// already registered, if any.
+  ///
+  /// The default replaces resources failing to reload when the reload error policy is
+  /// `ReloadErrorPolicy::ReplaceWithProxy`, and resources failing to load when
+  /// `StoreOpt::set_fallback_to_defaults` is enabled, so that call sites don’t have to pass it to
+  /// `Storage::get_proxied` each time.
+  pub fn register_default<T, F>(&mut self, default: F)
+  where
+    T: 'static,
+    F: 'static + Fn() -> T,
+  {
+    let default: Box<Fn() -> T> = Box::new(default);
+    self.proxies.insert(TypeId::of::<T>(), Box::new(default));
+  }
+
+  /// Create a value with the default of the resources of type `T` if resources failing to load
+  /// fall back to it.
+  fn fallback_default<T>(&self) -> Option<T>
+  where T: 'static {
+    if self.fallback_to_defaults {
+      self.proxy()
+    } else {
+      None
+    }
+  }
+
+  /// Create a value with the proxy of the resources of type `T`, if any.
+  fn proxy<T>(&self) -> Option<T>
+  where T: 'static {
+    self
+      .proxies
+      .get(&TypeId::of::<T>())
+      .and_then(|proxy| proxy.downcast_ref::<Box<Fn() -> T>>())
+      .map(|proxy| proxy())
+  }
+
+  /// Make the resources of type `T` part of the snapshots taken with `Storage::freeze`.
+  pub fn set_freezable<T>(&mut self)
+  where T: 'static + Clone + Send + Sync {
+    self.freezers.insert(TypeId::of::<T>(), snapshot::freeze::<T>);
+  }
+
+  /// Take a snapshot of the resources living in the `Storage` which types are freezable – see
+  /// `Storage::set_freezable`.
+  ///
+  /// The snapshot can be sent to and shared between other threads. It holds copies of the
+  /// resources: reloads happening afterwards only show in the next snapshots. A resource is only
+  /// copied again once it has changed, so that successive snapshots share their unchanged copies.
+  pub fn freeze(&self) -> StoreSnapshot {
+    let mut resources = HashMap::new();
+    let mut previous = self.frozen.borrow_mut();
+    let mut frozen = HashMap::with_capacity(previous.len());
+
+    for (dep_key, metadata) in &self.metadata {
+      for metadata in metadata {
+        let cache_key = metadata.cache_key(dep_key.clone());
+        let (freeze, cached) = match (
+          self.freezers.get(&metadata.type_id),
+          self.cache.get(&cache_key),
+        ) {
+          (Some(freeze), Some(cached)) => (freeze, cached),
+          _ => continue,
+        };
+
+        let previous = previous.remove(&cache_key);
+
+        if let Some(copy) = freeze(cached, metadata.generation, previous) {
+          resources.insert(cache_key.clone(), copy.resource.clone());
+          frozen.insert(cache_key, copy);
+        }
+      }
+    }
+
+    // forget about the copies of the resources that went away
+    *previous = frozen;
+
+    StoreSnapshot::new(
+      self.canon_root.clone(),
+      self.path_normalization,
+      self.mounts.clone(),
+      self.generation,
+      resources,
+    )
+  }
+
+  /// Handle a resource that failed to reload according to a policy.
+  fn apply_reload_error_policy(
+    &mut self,
+    policy: ReloadErrorPolicy,
+    cache_key: &CacheKey,
+  )
+  {
+    let dep_key = cache_key.dep_key();
+
+    match policy {
+      ReloadErrorPolicy::KeepPrevious => (),
+
+      ReloadErrorPolicy::ReplaceWithProxy => {
+        if let Some(metadata) = self.metadata.remove(dep_key) {
+          let failed = metadata
+            .iter()
+            .filter(|metadata| metadata.cache_key(dep_key.clone()) == *cache_key);
+
+          for metadata in failed {
+            if !(metadata.on_proxy)(self) {
+              log_event!(warn, "no proxy for {} ({})", dep_key, metadata.type_name);
+            }
+          }
+
+          self.insert_metadata(dep_key.clone(), metadata);
+        }
+      }
+
+      ReloadErrorPolicy::Evict => {
+        log_event!(debug, "evicting {}", dep_key);
+        self.evict_prepared(dep_key.clone());
+      }
+    }
+  }
+}
+
+/// Error that might happen when handling a resource store around.
+///
+/// Errors carrying an underlying error expose it with `Error::source`. They compare equal if their
+/// underlying errors are of the same kind.
+#[derive(Clone, Debug)]
+pub enum StoreError {
+  /// The root path for a filesystem resource was not found.
+  RootNotFound {
+    /// The root path, as configured.
+    path: PathBuf,
+    /// Why it was not found.
+    source: Arc<io::Error>,
+  },
+  /// The directory mounted at a VFS directory was not found – see `StoreOpt::add_mount`.
+  MountNotFound {
+    /// The mounted directory, as configured.
+    path: PathBuf,
+    /// Why it was not found.
+    source: Arc<io::Error>,
+  },
+  /// The filesystem watcher cannot be initialized.
+  #[cfg(feature = "watcher")]
+  WatcherInit(Arc<notify::Error>),
+  /// The thread waking the application up on filesystem events cannot be spawned – see
+  /// `StoreOpt::set_waker`.
+  WakerThread(Arc<io::Error>),
+  /// The key associated with a resource already exists in the `Store` for a resource of the same
+  /// type.
+  ///
+  /// > Note: a same key can back several resources of different types, but not two resources of
+  /// > the same type.
+  AlreadyRegisteredKey(DepKey),
+  /// A resource (first key) declares a dependency (second key) that doesn’t exist.
+  ///
+  /// Only reported with `DependencyCheck::Deny`.
+  MissingDependency(DepKey, DepKey),
+  /// A key cannot be converted to the type of key the resource expects.
+  KeyMismatch(DepKey),
+  /// A filesystem key refers to a file living outside of the root – e.g. `"../../etc/passwd"`.
+  ///
+  /// Such keys are allowed with `StoreOpt::set_allow_outside_root`.
+  KeyOutsideRoot(DepKey),
+  /// Loading or reloading a resource would exceed the memory budget.
+  ///
+  /// Only reported with `StoreOpt::set_memory_budget`.
+  BudgetExceeded {
+    /// Memory footprint of the resource, in bytes.
+    requested: usize,
+    /// Memory still available in the budget, in bytes.
+    available: usize,
+  },
+  /// Preparing a resource panicked on a worker thread – see `Storage::get_many`.
+  PreparePanicked(DepKey),
+}
+
+impl PartialEq for StoreError {
+  fn eq(&self, rhs: &Self) -> bool {
+    match (self, rhs) {
+      (
+        &StoreError::RootNotFound {
+          path: ref a,
+          source: ref e,
+        },
+        &StoreError::RootNotFound {
+          path: ref b,
+          source: ref f,
+        },
+      ) => a == b && e.kind() == f.kind(),
+      (
+        &StoreError::MountNotFound {
+          path: ref a,
+          source: ref e,
+        },
+        &StoreError::MountNotFound {
+          path: ref b,
+          source: ref f,
+        },
+      ) => a == b && e.kind() == f.kind(),
+      #[cfg(feature = "watcher")]
+      (&StoreError::WatcherInit(ref a), &StoreError::WatcherInit(ref b)) => {
+        a.to_string() == b.to_string()
+      }
+      (&StoreError::WakerThread(ref a), &StoreError::WakerThread(ref b)) => a.kind() == b.kind(),
+      (&StoreError::AlreadyRegisteredKey(ref a), &StoreError::AlreadyRegisteredKey(ref b)) => {
+        a == b
+      }
+      (
+        &StoreError::MissingDependency(ref a, ref c),
+        &StoreError::MissingDependency(ref b, ref d),
+      ) => a == b && c == d,
+      (&StoreError::KeyMismatch(ref a), &StoreError::KeyMismatch(ref b)) => a == b,
+      (&StoreError::KeyOutsideRoot(ref a), &StoreError::KeyOutsideRoot(ref b)) => a == b,
+      (&StoreError::PreparePanicked(ref a), &StoreError::PreparePanicked(ref b)) => a == b,
+      (
+        &StoreError::BudgetExceeded {
+          requested: a,
+          available: c,
+        },
+        &StoreError::BudgetExceeded {
+          requested: b,
+          available: d,
+        },
+      ) => a == b && c == d,
+      _ => false,
+    }
+  }
+}
+
+impl Eq for StoreError {}
+
+impl fmt::Display for StoreError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      StoreError::RootNotFound { ref path, .. } => write!(f, "root {} not found", path.display()),
+      StoreError::MountNotFound { ref path, .. } => {
+        write!(f, "mounted directory {} not found", path.display())
+      }
+      _ => f.write_str(self.description()),
+    }
+  }
+}
+
+impl Error for StoreError {
+  fn description(&self) -> &str {
+    match *self {
+      StoreError::RootNotFound { .. } => "root not found",
+      StoreError::MountNotFound { .. } => "mounted directory not found",
+      #[cfg(feature = "watcher")]
+      StoreError::WatcherInit(_) => "cannot initialize the filesystem watcher",
+      StoreError::WakerThread(_) => "cannot spawn the waker thread",
+      StoreError::AlreadyRegisteredKey(_) => "already registered key",
+      StoreError::MissingDependency(..) => "missing dependency",
+      StoreError::KeyMismatch(_) => "key mismatch",
+      StoreError::KeyOutsideRoot(_) => "key outside root",
+      StoreError::BudgetExceeded { .. } => "memory budget exceeded",
+      StoreError::PreparePanicked(_) => "preparation panicked",
+    }
+  }
+
+  fn source(&self) -> Option<&(Error + 'static)> {
+    match *self {
+      StoreError::RootNotFound { ref source, .. } => Some(&**source),
+      StoreError::MountNotFound { ref source, .. } => Some(&**source),
+      #[cfg(feature = "watcher")]
+      StoreError::WatcherInit(ref e) => Some(&**e),
+      StoreError::WakerThread(ref e) => Some(&**e),
+      _ => None,
+    }
+  }
+}
+
+/// Error of a resource which file was deleted.
+#[derive(Debug)]
+struct SourceMissing(PathBuf);
+
+impl fmt::Display for SourceMissing {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{} was deleted", self.0.display())
+  }
+}
+
+impl Error for SourceMissing {
+  fn description(&self) -> &str {
+    "source missing"
+  }
+}
+
+/// Either a store error or a resource loading error.
+pub enum StoreErrorOr<T, C, M = ()>
+where T: Load<C, M> {
+  /// A store error.
+  StoreError(StoreError),
+  /// A resource error.
+  ResError(T::Error),
+}
+
+impl<T, C, M> Clone for StoreErrorOr<T, C, M>
+where
+  T: Load<C, M>,
+  T::Error: Clone,
+{
+  fn clone(&self) -> Self {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => StoreErrorOr::StoreError(e.clone()),
+      StoreErrorOr::ResError(ref e) => StoreErrorOr::ResError(e.clone()),
+    }
+  }
+}
+
+impl<T, C, M> Eq for StoreErrorOr<T, C, M>
+where
+  T: Load<C, M>,
+  T::Error: Eq,
+{
+}
+
+impl<T, C, M> PartialEq for StoreErrorOr<T, C, M>
+where
+  T: Load<C, M>,
+  T::Error: PartialEq,
+{
+  fn eq(&self, rhs: &Self) -> bool {
+    match (self, rhs) {
+      (&StoreErrorOr::StoreError(ref a), &StoreErrorOr::StoreError(ref b)) => a == b,
+      (&StoreErrorOr::ResError(ref a), &StoreErrorOr::ResError(ref b)) => a == b,
+      _ => false,
+    }
+  }
+}
+
+impl<T, C, M> fmt::Debug for StoreErrorOr<T, C, M>
+where
+  T: Load<C, M>,
+  T::Error: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => f.debug_tuple("StoreError").field(e).finish(),
+      StoreErrorOr::ResError(ref e) => f.debug_tuple("ResError").field(e).finish(),
+    }
+  }
+}
+
+impl<T, C, M> fmt::Display for StoreErrorOr<T, C, M>
+where
+  T: Load<C, M>,
+  T::Error: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str(self.description())
+  }
+}
+
+impl<T, C, M> Error for StoreErrorOr<T, C, M>
+where
+  T: Load<C, M>,
+  T::Error: fmt::Debug,
+{
+  fn description(&self) -> &str {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => e.description(),
+      StoreErrorOr::ResError(ref e) => e.description(),
+    }
+  }
+
+  fn source(&self) -> Option<&(Error + 'static)> {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => e.source(),
+      StoreErrorOr::ResError(ref e) => e.source(),
+    }
+  }
+}
+
+/// Resource synchronizer.
+///
+/// An object of this type is responsible to synchronize resources living in a store. It keeps in
+/// internal, optimized state to perform correct and efficient synchronization.
+struct Synchronizer {
+  // all the resources that must be reloaded; they’re mapped to the instants they were found updated
+  dirties: HashMap<DepKey, Dirty>,
+  // watcher receiver part of the channel
+  watcher_rx: Receiver<WatchEvent>,
+  // events dequeued since the last synchronization, outside of it
+  events_dequeued: usize,
+  // whether the watch on the root is alive; it dies if the root gets deleted
+  root_watched: bool,
+  // time in milleseconds to wait before actually invoking the reloading function on a given
+  // resource; the wait is done between the current time and the last time the resource was touched
+  // by the event loop
+  update_await_time_ms: u64,
+  // if set, the update await time is learned per resource and capped to that value
+  adaptive_await_time_ms: Option<u64>,
+  // learned update await times, in milliseconds
+  learned_await_times_ms: HashMap<DepKey, u64>,
+  // update await times set per resource, in milliseconds; they take precedence over anything else
+  await_time_overrides_ms: HashMap<DepKey, u64>,
+  // reload priorities set per resource; they take precedence over the ones of the types
+  reload_priority_overrides: HashMap<DepKey, i32>,
+  // subscribers to reload events
+  event_subscribers: Vec<Sender<ReloadEvent>>,
+  // sink receiving a record of every reload
+  reload_sink: Option<Box<ReloadSink>>,
+  // identifier of the build, attached to reload records
+  build_id: Option<String>,
+  // domains, mapping their names to the directories they cover
+  domains: Vec<(String, PathBuf)>,
+  // patterns of the paths which events are dropped
+  #[cfg_attr(not(feature = "watcher"), allow(dead_code))]
+  ignore_patterns: Vec<String>,
+  // whether reloading is paused; dirty resources pile up meanwhile
+  paused: bool,
+  // whether the next pass reloads every dirty resource without waiting, after a pause
+  flush: bool,
+  // source of time of the debouncing
+  clock: Rc<Clock>,
+  // number of reloads that succeeded and failed since the creation of the store
+  reloads_succeeded: u64,
+  reloads_failed: u64,
+  // duration of the last reload of each key
+  last_reload_durations: HashMap<DepKey, Duration>,
+  // whether reload cascades are rolled back if any of their reloads fails
+  transactional: bool,
+  // whether dependents are reloaded once per pass, after all their changed dependencies
+  coalesce_reloads: bool,
+  // what to do with resources failing to reload
+  reload_error_policy: ReloadErrorPolicy,
+  // keys of the resources which files were found deleted
+  missing_sources: HashSet<DepKey>,
+}
+
+/// Time in milliseconds to sleep between two synchronizations while waiting for dirty resources to
+/// settle.
+const WAIT_STEP_MS: u64 = 5;
+
+/// Update await time (milliseconds) of a dirty key, along with its override, if any.
+///
+/// The override of the key comes first, then the one of the type of its resources, then the learned
+/// await time if adaptive, and finally the default one.
+fn await_time_ms<C>(
+  dep_key: &DepKey,
+  storage: &Storage<C>,
+  overrides_ms: &HashMap<DepKey, u64>,
+  learned_ms: &HashMap<DepKey, u64>,
+  update_await_time_ms: u64,
+  adaptive_await_time_ms: Option<u64>,
+) -> (Option<u64>, u64)
+{
+  let overridden_ms = overrides_ms.get(dep_key).cloned().or_else(|| {
+    storage
+      .metadata
+      .get(dep_key)
+      .and_then(|metadata| metadata.iter().filter_map(|m| m.reload_delay).max())
+      .map(|delay| delay.as_secs() * 1000 + (delay.subsec_nanos() / 1_000_000) as u64)
+  });
+
+  let await_time_ms = match (overridden_ms, adaptive_await_time_ms) {
+    (Some(overridden_ms), _) => overridden_ms,
+    (None, Some(_)) => *learned_ms.get(dep_key).unwrap_or(&update_await_time_ms),
+    (None, None) => update_await_time_ms,
+  };
+
+  (overridden_ms, await_time_ms)
+}
+
+/// A resource that was found updated and is waiting to be reloaded.
+struct Dirty {
+  // first time the resource was touched in the current burst of writes
+  first: Instant,
+  // last time the resource was touched in the current burst of writes
+  last: Instant,
+}
+
+impl Dirty {
+  /// Mark a resource as touched in a set of dirty resources.
+  fn touch(dirties: &mut HashMap<DepKey, Dirty>, dep_key: DepKey, now: Instant) {
+    dirties
+      .entry(dep_key)
+      .and_modify(|dirty| dirty.last = now)
+      .or_insert(Dirty {
+        first: now,
+        last: now,
+      });
+  }
+}
+
+/// Settings of a `Synchronizer`, gathered from a `StoreOpt` by `Store::new`.
+struct SyncConfig {
+  watcher_rx: Receiver<WatchEvent>,
+  update_await_time_ms: u64,
+  adaptive_await_time_ms: Option<u64>,
+  reload_sink: Option<Box<ReloadSink>>,
+  build_id: Option<String>,
+  domains: Vec<(String, PathBuf)>,
+  ignore_patterns: Vec<String>,
+  clock: Rc<Clock>,
+  transactional: bool,
+  coalesce_reloads: bool,
+  reload_error_policy: ReloadErrorPolicy,
+}
+
+impl Synchronizer {
+  fn new(config: SyncConfig) -> Self {
+    let SyncConfig {
+      watcher_rx,
+      update_await_time_ms,
+      adaptive_await_time_ms,
+      reload_sink,
+      build_id,
+      domains,
+      ignore_patterns,
+      clock,
+      transactional,
+      coalesce_reloads,
+      reload_error_policy,
+    } = config;
+
+    Synchronizer {
+      dirties: HashMap::new(),
+      watcher_rx,
+      events_dequeued: 0,
+      root_watched: true,
+      update_await_time_ms,
+      adaptive_await_time_ms,
+      learned_await_times_ms: HashMap::new(),
+      await_time_overrides_ms: HashMap::new(),
+      reload_priority_overrides: HashMap::new(),
+      event_subscribers: Vec::new(),
+      reload_sink,
+      build_id,
+      domains,
+      ignore_patterns,
+      paused: false,
+      flush: false,
+      clock,
+      reloads_succeeded: 0,
+      reloads_failed: 0,
+      last_reload_durations: HashMap::new(),
+      transactional,
+      coalesce_reloads,
+      reload_error_policy,
+      missing_sources: HashSet::new(),
+    }
+  }
+
+  /// Dequeue any file system events and check whether a synchronization has work to do.
+  fn sync_needed<C>(&mut self, storage: &Storage<C>) -> bool {
+    self.events_dequeued += self.dequeue_fs_events(storage);
+    !self.dirties.is_empty() || !storage.remapped_aliases.is_empty()
+  }
+
+  /// Dequeue any file system events and compute the earliest time at which a synchronization
+  /// will have something to reload, if any.
+  fn next_deadline<C>(&mut self, storage: &Storage<C>) -> Option<Instant> {
+    self.events_dequeued += self.dequeue_fs_events(storage);
+
+    if self.paused {
+      return None;
+    }
+
+    // remapped aliases get touched by the next synchronization
+    let aliases = if storage.remapped_aliases.is_empty() {
+      None
+    } else {
+      Some(self.clock.now() + Duration::from_millis(self.update_await_time_ms))
+    };
+
+    self
+      .dirties
+      .iter()
+      .map(|(dep_key, dirty)| {
+        let (_, await_time_ms) = await_time_ms(
+          dep_key,
+          storage,
+          &self.await_time_overrides_ms,
+          &self.learned_await_times_ms,
+          self.update_await_time_ms,
+          self.adaptive_await_time_ms,
+        );
+        let await_time_ms = if self.flush { 0 } else { await_time_ms };
+
+        dirty.last + Duration::from_millis(await_time_ms)
+      })
+      .chain(aliases)
+      .min()
+  }
+
+  /// Mark a resource as touched.
+  fn touch(&mut self, dep_key: DepKey) {
+    Dirty::touch(&mut self.dirties, dep_key, self.clock.now());
+  }
+
+  /// Dequeue any file system events and return how many of them were dequeued.
+  fn dequeue_fs_events<C>(&mut self, storage: &Storage<C>) -> usize {
+    let events: Vec<_> = self.watcher_rx.try_iter().collect();
+    let dequeued = events.len();
+
+    for event in events {
+      self.handle_fs_event(storage, event);
+    }
+
+    dequeued
+  }
+
+  /// Block until a file system event arrives or the timeout elapses, and handle it.
+  fn wait_fs_event<C>(&mut self, storage: &Storage<C>, timeout: Duration) {
+    match self.watcher_rx.recv_timeout(timeout) {
+      Ok(event) => self.handle_fs_event(storage, event),
+      Err(RecvTimeoutError::Timeout) => (),
+      // nothing watches the filesystem anymore – or never did
+      Err(RecvTimeoutError::Disconnected) => thread::sleep(timeout),
+    }
+  }
+
+  /// Mark dirty the resources concerned by a file system event.
+  #[cfg(feature = "watcher")]
+  fn handle_fs_event<C>(&mut self, storage: &Storage<C>, event: WatchEvent) {
+    match event {
+      RawEvent {
+        path: Some(ref path),
+        op: Ok(op),
+        ..
+      } if op | WRITE != Op::empty() =>
+      {
+        // keys are normalized; so must be the paths they’re matched against
+        let path = &storage.normalize_path(path);
+
+        if op.contains(REMOVE) && path == storage.root() {
+          // the root is gone and so is the watch on it
+          log_event!(warn, "the root {} was removed", path.display());
+          self.root_watched = false;
+          return;
+        }
+
+        // drop the noise of editors and version control tools
+        if let Ok(relative) = path.strip_prefix(storage.root()) {
+          if ignore::is_ignored(&self.ignore_patterns, relative) {
+            log_event!(trace, "ignored event on {}", path.display());
+            return;
+          }
+        }
+
+        // events on the targets of symlinks concern the resources living at the links
+        let mut paths = vec![path.clone()];
+        paths.extend(storage.linked_paths(path));
+
+        // events on transformed variants concern the resources living at the plain paths
+        let plain_paths: Vec<_> = paths
+          .iter()
+          .filter_map(|path| storage.transforms.plain_path(path))
+          .collect();
+        paths.extend(plain_paths);
+
+        let now = self.clock.now();
+
+        for path in &paths {
+          self.touch_path(storage, path, op, now);
+        }
+      }
+
+      _ => (),
+    }
+  }
+
+  /// Mark dirty the resources concerned by a change at a path.
+  #[cfg(feature = "watcher")]
+  fn touch_path<C>(&mut self, storage: &Storage<C>, path: &Path, op: Op, now: Instant) {
+    let mut keys = storage.keys_at_path(path).peekable();
+
+    if keys.peek().is_none() {
+      log_event!(trace, "no resource at {}", path.display());
+    }
+
+    for dep_key in keys {
+      log_event!(trace, "{} changed", dep_key);
+      Dirty::touch(&mut self.dirties, dep_key.clone(), now);
+    }
+
+    // any change under a directory changes the directory keys covering it – the resources living
+    // there as well as the ones depending on them
+    for dir in path.ancestors().take_while(|dir| dir.starts_with(storage.root())) {
+      let dep_key = DepKey::Dir(dir.to_owned());
+
+      if storage.metadata.contains_key(&dep_key) || storage.deps.contains_key(&dep_key) {
+        log_event!(trace, "{} changed", dep_key);
+        Dirty::touch(&mut self.dirties, dep_key, now);
+      }
+    }
+
+    // files appearing or disappearing also change the directories containing them
+    if op.intersects(CREATE | REMOVE | RENAME) {
+      let dirs = path.ancestors().skip(1);
+
+      for dir in dirs.take_while(|dir| dir.starts_with(storage.root())) {
+        for dep_key in storage.keys_at_path(dir) {
+          Dirty::touch(&mut self.dirties, dep_key.clone(), now);
+        }
+      }
+    }
+  }
+
+  /// Mark dirty the resources concerned by a file system event; there’s none without the `watcher`
+  /// feature.
+  #[cfg(not(feature = "watcher"))]
+  fn handle_fs_event<C>(&mut self, _: &Storage<C>, event: WatchEvent) {
+    match event {}
+  }
+
+  /// Re-establish the watch on the root if it was deleted and has been recreated since then, and
+  /// return whether it was.
+  ///
+  /// Because changes might have been missed in between, all the resources living on the filesystem
+  /// are marked dirty.
+  fn rewatch_root<C>(&mut self, storage: &mut Storage<C>) -> bool {
+    if !storage.root().is_dir() {
+      self.root_watched = false;
+      return false;
+    }
+
+    if self.root_watched {
+      return false;
+    }
+
+    // the watch is tried again at the next synchronization if it cannot be re-established
+    self.watcher_rx = match storage.watch.rewatch() {
+      Ok(watcher_rx) => watcher_rx,
+      Err(e) => {
+        log_event!(warn, "cannot watch the root again: {}", e);
+        return false;
+      }
+    };
+    self.root_watched = true;
+
+    let now = self.clock.now();
+    let dep_keys: Vec<_> = storage
+      .metadata
+      .keys()
+      .filter(|dep_key| dep_key.path().is_some())
+      .cloned()
+      .collect();
+
+    for dep_key in dep_keys {
+      if let Some(path) = dep_key.path() {
+        storage.watch_path(path);
+      }
+
+      Dirty::touch(&mut self.dirties, dep_key, now);
+    }
+
+    true
+  }
+
+  /// Reload any dirty resource that fulfill its time predicate.
+  fn reload_dirties<C>(
+    &mut self,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+    domain: Option<&str>,
+    report: &mut SyncReport,
+  ) {
+    // keys of the other domains are left dirty
+    let skipped: HashSet<DepKey> = match domain {
+      Some(domain) => self
+        .dirties
+        .keys()
+        .filter(|dep_key| self.domain_of(dep_key) != Some(domain))
+        .cloned()
+        .collect(),
+      None => HashSet::new(),
+    };
+
+    // resources that piled up during a pause are reloaded at once, in a single pass
+    let flush = mem::replace(&mut self.flush, false);
+    let update_await_time_ms = self.update_await_time_ms;
+    let adaptive_await_time_ms = self.adaptive_await_time_ms;
+    let learned_await_times_ms = &mut self.learned_await_times_ms;
+    let await_time_overrides_ms = &self.await_time_overrides_ms;
+    let transactional = self.transactional;
+    // transactional cascades are rolled back per dirty key, so they can’t be coalesced
+    let coalesce = self.coalesce_reloads && !transactional;
+    // dependents to reload once all the dirty keys are processed, along with their generations
+    let mut coalesced: Vec<(DepKey, u64)> = Vec::new();
+    let mut coalesced_set: HashSet<(DepKey, u64)> = HashSet::new();
+    // dirty keys reloaded in this pass, which are up to date once all the dirty keys are processed
+    let mut reloaded_dirties: HashSet<DepKey> = HashSet::new();
+    let mut reloads_coalesced = 0;
+    let reload_error_policy = self.reload_error_policy;
+    let missing_sources = &mut self.missing_sources;
+    let mut rollbacks = 0;
+    let now = self.clock.now();
+    let mut events = Vec::new();
+    let mut reporter = ReloadReporter {
+      report,
+      events: &mut events,
+      reload_sink: &mut self.reload_sink,
+      build_id: &self.build_id,
+      last_reload_durations: &mut self.last_reload_durations,
+      held: None,
+    };
+
+    // reload the resources with the highest priorities first, in key order for a same priority
+    let reload_priority_overrides = &self.reload_priority_overrides;
+    let mut dirties: Vec<_> = self.dirties.drain().collect();
+    dirties.sort_by(|&(ref a, _), &(ref b, _)| {
+      let priority = |dep_key: &DepKey| {
+        reload_priority_overrides.get(dep_key).cloned().unwrap_or_else(|| {
+          storage
+            .metadata
+            .get(dep_key)
+            .and_then(|metadata| metadata.iter().map(|m| m.reload_priority).max())
+            .unwrap_or(0)
+        })
+      };
+
+      priority(b).cmp(&priority(a)).then_with(|| a.cmp(b))
+    });
+
+    // dirty dependents are reloaded after their dirty dependencies, so that they’re up to date
+    // without being reloaded again with the coalesced dependents
+    if coalesce {
+      let keys = dirties.iter().map(|&(ref dep_key, _)| dep_key.clone()).collect();
+      let mut by_key: HashMap<_, _> = dirties.drain(..).collect();
+
+      dirties = storage
+        .in_dependency_order(keys)
+        .into_iter()
+        .filter_map(|dep_key| by_key.remove(&dep_key).map(|dirty| (dep_key, dirty)))
+        .collect();
+    }
+
+    dirties.retain(|&(ref dep_key, ref dirty)| {
+      if skipped.contains(dep_key) {
+        return true;
+      }
+
+      let (overridden_ms, await_time_ms) = await_time_ms(
+        dep_key,
+        storage,
+        await_time_overrides_ms,
+        learned_await_times_ms,
+        update_await_time_ms,
+        adaptive_await_time_ms,
+      );
+      let await_time_ms = if flush { 0 } else { await_time_ms };
+
+      // don’t start any new reload while cancelled; the resource stays dirty
+      if storage.cancellation_token.is_cancelled() {
+        log_event!(debug, "not reloading {}: cancelled", dep_key);
+        return true;
+      }
+
+      // check whether we’ve waited enough to actually invoke the reloading code
+      if now.duration_since(dirty.last) >= Duration::from_millis(await_time_ms) {
+        // the burst of writes is over; learn from its duration so that the next burst of the same
+        // resource gets a settle window of about twice that duration
+        if let (None, Some(max_ms)) = (overridden_ms, adaptive_await_time_ms) {
+          let burst = dirty.last.duration_since(dirty.first);
+          let burst_ms = burst.as_secs() * 1000 + (burst.subsec_nanos() / 1_000_000) as u64;
+          let learned_ms = ((await_time_ms + 2 * burst_ms) / 2).min(max_ms);
+          learned_await_times_ms.insert(dep_key.clone(), learned_ms);
+        }
+
+        // we’ve waited enough; reload all the resources living at that key
+        let metadata = storage.metadata.remove(&dep_key);
+
+        if coalesce {
+          reloaded_dirties.insert(dep_key.clone());
+        }
+
+        let mut reloaded = false;
+        // cache keys of the resources that failed to reload
+        let mut failed = Vec::new();
+
+        if transactional {
+          storage.undo_log = Some(UndoLog::new());
+          reporter.hold();
+        }
+
+        match metadata {
+          Some(ref metadata) => {
+            // resources which files were deleted cannot reload; they keep their values, flagged,
+            // until the files come back
+            let missing = dep_key
+              .path()
+              .filter(|path| !storage.source_exists(path))
+              .map(Path::to_owned);
+
+            for metadata in metadata {
+              let started = Instant::now();
+
+              if let Some(ref path) = missing {
+                (metadata.on_source_state)(ResState::SourceMissing);
+                let outcome: Result<(), Box<Error>> = Err(Box::new(SourceMissing(path.clone())));
+                reporter.record(dep_key, metadata, started, outcome);
+                failed.push(metadata.cache_key(dep_key.clone()));
+                continue;
+              }
+
+              (metadata.on_source_state)(ResState::Live);
+              let outcome = (metadata.on_reload)(storage, ctx);
+              if reporter.record(dep_key, metadata, started, outcome) {
+                reloaded = true;
+              } else {
+                failed.push(metadata.cache_key(dep_key.clone()));
+              }
+            }
+
+            match (missing, dep_key.path()) {
+              (Some(_), _) => {
+                missing_sources.insert(dep_key.clone());
+              }
+
+              // symlinks might have been retargeted
+              (None, path) => {
+                missing_sources.remove(dep_key);
+
+                if let Some(path) = path {
+                  storage.watch_path(path);
+                }
+              }
+            }
+          }
+
+          // a remapped alias or a directory might have no resource of its own; only its observers
+          // are concerned then
+          None => {
+            reloaded = match *dep_key {
+              DepKey::Dir(_) => true,
+              _ => storage.remapped_aliases.remove(dep_key),
+            }
+          }
+        }
+
+        if reloaded {
+          // if we have successfully reloaded the resource, notify the observers that this
+          // dependency has changed – including the ones observing it through an alias
+          for observed in storage.observed_keys(dep_key) {
+            if let Some(dependents) = storage.deps.get(&observed).cloned() {
+              for dependent in dependents {
+                let Dependent {
+                  key: dep,
+                  kind,
+                  generation,
+                } = dependent;
+
+                if let Some(obs_metadata) = storage.metadata.remove(&dep) {
+                  // only the very resource that declared the dependency is concerned; resources
+                  // registered at the same key afterwards are not
+                  let observers = obs_metadata
+                    .iter()
+                    .filter(|obs_metadata| obs_metadata.generation == generation);
+
+                  for obs_metadata in observers {
+                    match kind {
+                      DepKind::Strong if coalesce => {
+                        let pending = (dep.clone(), generation);
+
+                        if coalesced_set.insert(pending.clone()) {
+                          coalesced.push(pending);
+                        } else {
+                          reloads_coalesced += 1;
+                        }
+                      }
+
+                      DepKind::Strong => {
+                        // FIXME: decide what to do with the result (error?)
+                        let started = Instant::now();
+                        let outcome = (obs_metadata.on_reload)(storage, ctx);
+                        if !reporter.record(&dep, obs_metadata, started, outcome) {
+                          failed.push(obs_metadata.cache_key(dep.clone()));
+                        }
+                      }
+
+                      DepKind::Weak => (obs_metadata.on_dep_reload)(dep_key, storage, ctx),
+                    }
+                  }
+
+                  // reinject the dependency once afterwards
+                  storage.insert_metadata(dep, obs_metadata);
+                }
+              }
+
+              storage.prune_dependents(&observed);
+            }
+          }
+        }
+
+        if let Some(metadata) = metadata {
+          storage.insert_metadata(dep_key.clone(), metadata);
+        }
+
+        // restore the old values of the whole cascade, newest first
+        if let Some(undo_log) = storage.undo_log.take() {
+          let rolled_back = !failed.is_empty() && !undo_log.undos.is_empty();
+
+          if rolled_back {
+            log_event!(warn, "rolling back the reload of {}", dep_key);
+            undo_log.roll_back(storage);
+            rollbacks += 1;
+          } else {
+            undo_log.commit();
+          }
+
+          reporter.release(rolled_back);
+        }
+
+        for cache_key in failed {
+          storage.apply_reload_error_policy(reload_error_policy, &cache_key);
+        }
+
+        false
+      } else {
+        log_event!(trace, "{} is settling, waiting for {} ms", dep_key, await_time_ms);
+        true
+      }
+    });
+
+    // reload the coalesced dependents once each, now that all their changed dependencies are
+    // reloaded; reloads are never coalesced in transactions, so those are never rolled back
+    for (dep, generation) in coalesced {
+      if reloaded_dirties.contains(&dep) {
+        reloads_coalesced += 1;
+        continue;
+      }
+
+      if let Some(obs_metadata) = storage.metadata.remove(&dep) {
+        let mut failed = Vec::new();
+
+        for obs_metadata in obs_metadata.iter().filter(|m| m.generation == generation) {
+          let started = Instant::now();
+          let outcome = (obs_metadata.on_reload)(storage, ctx);
+          if !reporter.record(&dep, obs_metadata, started, outcome) {
+            failed.push(obs_metadata.cache_key(dep.clone()));
+          }
+        }
+
+        storage.insert_metadata(dep, obs_metadata);
+
+        for cache_key in failed {
+          storage.apply_reload_error_policy(reload_error_policy, &cache_key);
+        }
+      }
+    }
+
+    self.dirties.extend(dirties);
+
+    report.rollbacks += rollbacks;
+    report.reloads_coalesced += reloads_coalesced;
+
+    // dispatch the events to the subscribers, forgetting about the ones that went away
+    self.event_subscribers.retain(|subscriber| {
+      events
+        .iter()
+        .all(|event| subscriber.send(event.clone()).is_ok())
+    });
+  }
+
+  /// Domain a key belongs to, if any.
+  ///
+  /// Only filesystem keys belong to domains; the domain with the longest matching directory wins.
+  fn domain_of(&self, dep_key: &DepKey) -> Option<&str> {
+    let path = dep_key.path()?;
+
+    self
+      .domains
+      .iter()
+      .filter(|&&(_, ref dir)| path.starts_with(dir))
+      .max_by_key(|&&(_, ref dir)| dir.components().count())
+      .map(|&(ref name, _)| name.as_str())
+  }
+
+  /// Synchronize the `Storage` by updating the resources that ought to.
+  ///
+  /// If a domain is given, only the resources belonging to it are updated; the other ones stay
+  /// dirty until their domain gets synchronized.
+  fn sync<C>(&mut self, storage: &mut Storage<C>, ctx: &mut C, domain: Option<&str>) -> SyncReport {
+    let start = Instant::now();
+    let mut report = SyncReport::default();
+
+    report.events_dequeued =
+      mem::replace(&mut self.events_dequeued, 0) + self.dequeue_fs_events(storage);
+
+    // touching an alias again would restart its update await time, so it’s only touched once
+    for alias in &storage.remapped_aliases {
+      if !self.dirties.contains_key(alias) {
+        self.touch(alias.clone());
+      }
+    }
+
+    report.root_rewatched = self.rewatch_root(storage);
+
+    if self.paused {
+      log_event!(trace, "reloading paused, {} keys dirty", self.dirties.len());
+    } else {
+      self.reload_dirties(storage, ctx, domain, &mut report);
+      self.reloads_succeeded += report.reloads_succeeded as u64;
+      self.reloads_failed += report.reloads_failed as u64;
+    }
+
+    // files read by loaders without being resources of their own are forgotten about
+    storage.reads.borrow_mut().clear();
+
+    report.keys_debounced = self
+      .dirties
+      .keys()
+      .filter(|dep_key| domain.map_or(true, |domain| self.domain_of(dep_key) == Some(domain)))
+      .count();
+    report.duration = start.elapsed();
+
+    report
+  }
+}
+
+/// Summary of a single synchronization pass.
+///
+/// A `SyncReport` is returned by `Store::sync` and gives you statistics about what happened during
+/// the synchronization. It’s handy to monitor reload failures.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncReport {
+  /// Number of filesystem events dequeued.
+  pub events_dequeued: usize,
+  /// Number of dirty keys still waiting for their update await time to elapse.
+  pub keys_debounced: usize,
+  /// Number of reloads attempted, including the ones of dependent resources.
+  pub reloads_attempted: usize,
+  /// Number of reloads that succeeded.
+  pub reloads_succeeded: usize,
+  /// Number of reloads that failed.
+  pub reloads_failed: usize,
+  /// Number of reloads that succeeded but were rolled back – see
+  /// `StoreOpt::set_transactional_reloads`.
+  pub reloads_rolled_back: usize,
+  /// Keys of the resources that were successfully reloaded, in reloading order.
+  pub reloaded: Vec<DepKey>,
+  /// Whether the watch on the root was re-established because the root got recreated.
+  pub root_rewatched: bool,
+  /// Number of reload cascades rolled back – see `StoreOpt::set_transactional_reloads`.
+  pub rollbacks: usize,
+  /// Number of dependent reloads saved by coalescing – see `StoreOpt::set_coalesce_reloads`.
+  pub reloads_coalesced: usize,
+  /// Time spent synchronizing.
+  pub duration: Duration,
+}
+
+/// Report of a reload check.
+///
+/// See `Storage::check_reloads` for further details.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReloadCheckReport {
+  /// Number of resources that were successfully reloaded.
+  pub reloaded: usize,
+  /// All the failures that occurred.
+  pub failures: Vec<ReloadFailure>,
+}
+
+impl ReloadCheckReport {
+  /// Whether all the resources were successfully reloaded.
+  pub fn is_ok(&self) -> bool {
+    self.failures.is_empty()
+  }
+}
+
+/// A resource that failed to reload during a reload check.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReloadFailure {
+  /// Key of the resource.
+  pub key: DepKey,
+  /// Name of the type of the resource.
+  pub type_name: &'static str,
+  /// Error message.
+  pub error: String,
+}
+
+impl fmt::Display for ReloadFailure {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{} ({}): {}", self.key, self.type_name, self.error)
+  }
+}
+
+impl SyncReport {
+  /// Record the outcome of a reload.
+  fn record(&mut self, dep_key: &DepKey, outcome: &ReloadOutcome) {
+    self.reloads_attempted += 1;
+
+    match *outcome {
+      ReloadOutcome::Reloaded => {
+        self.reloads_succeeded += 1;
+        self.reloaded.push(dep_key.clone());
+      }
+
+      ReloadOutcome::RolledBack => self.reloads_rolled_back += 1,
+      _ => self.reloads_failed += 1,
+    }
+  }
+}
+
+/// Reporter of the reloads of a synchronization pass – to its `SyncReport`, its events and the
+/// reload sink.
+///
+/// Reloads done in a transaction are held back until the transaction is over, so that they’re
+/// reported as rolled back if it is.
+struct ReloadReporter<'a> {
+  report: &'a mut SyncReport,
+  events: &'a mut Vec<ReloadEvent>,
+  reload_sink: &'a mut Option<Box<ReloadSink>>,
+  build_id: &'a Option<String>,
+  last_reload_durations: &'a mut HashMap<DepKey, Duration>,
+  // reloads of the transaction in progress, if any, along with the instants they finished at
+  held: Option<Vec<(ReloadRecord, Instant)>>,
+}
+
+impl<'a> ReloadReporter<'a> {
+  /// Record the outcome of a reload and return whether it succeeded.
+  fn record<C>(
+    &mut self,
+    dep_key: &DepKey,
+    metadata: &ResMetaData<C>,
+    started: Instant,
+    outcome: Result<(), Box<Error>>,
+  ) -> bool
+  {
+    let duration = started.elapsed();
+    let type_name = metadata.type_name;
+
+    match outcome {
+      Ok(_) => log_event!(debug, "reloaded {} ({}) in {:?}", dep_key, type_name, duration),
+      Err(ref e) => log_event!(warn, "cannot reload {} ({}): {}", dep_key, type_name, e),
+    }
+
+    self.last_reload_durations.insert(dep_key.clone(), duration);
+
+    let record = ReloadRecord {
+      key: dep_key.clone(),
+      type_name,
+      duration,
+      outcome: ReloadOutcome::of(&outcome),
+      build_id: self.build_id.clone(),
+    };
+
+    match self.held {
+      Some(ref mut held) => held.push((record, Instant::now())),
+      None => self.report(record, Instant::now()),
+    }
+
+    outcome.is_ok()
+  }
+
+  /// Hold the reloads back until `ReloadReporter::release` is called.
+  fn hold(&mut self) {
+    self.held = Some(Vec::new());
+  }
+
+  /// Report the reloads held back, as rolled back if asked to.
+  fn release(&mut self, rolled_back: bool) {
+    for (mut record, time) in self.held.take().unwrap_or_default() {
+      if rolled_back && record.outcome == ReloadOutcome::Reloaded {
+        record.outcome = ReloadOutcome::RolledBack;
+      }
+
+      self.report(record, time);
+    }
+  }
+
+  fn report(&mut self, record: ReloadRecord, time: Instant) {
+    if let Some(ref mut sink) = *self.reload_sink {
+      sink.record(&record);
+    }
+
+    self.report.record(&record.key, &record.outcome);
+    self.events.push(ReloadEvent {
+      key: record.key,
+      outcome: record.outcome,
+      time,
+    });
+  }
+}
+
+/// Event emitted each time a resource is reloaded – or fails to.
+///
+/// Those events are emitted during `Store::sync` and can be received with `Store::events`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReloadEvent {
+  /// Key of the resource.
+  pub key: DepKey,
+  /// Outcome of the reload.
+  pub outcome: ReloadOutcome,
+  /// Instant at which the reload finished.
+  pub time: Instant,
+}
+
+/// Outcome of a reload.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ReloadOutcome {
+  /// The resource was successfully reloaded.
+  Reloaded,
+  /// The resource failed to reload; the error message is attached.
+  Failed(String),
+  /// The file backing the resource was deleted – see `Res::state`.
+  SourceMissing,
+  /// The resource was reloaded, but its reload was rolled back because another reload of the same
+  /// cascade failed – see `StoreOpt::set_transactional_reloads`.
+  RolledBack,
+}
+
+impl ReloadOutcome {
+  fn of(outcome: &Result<(), Box<Error>>) -> Self {
+    match *outcome {
+      Ok(_) => ReloadOutcome::Reloaded,
+      Err(ref e) if e.is::<SourceMissing>() => ReloadOutcome::SourceMissing,
+      Err(ref e) => ReloadOutcome::Failed(e.to_string()),
+    }
+  }
+}
+
+/// Resource store. Responsible for holding and presenting resources.
+pub struct Store<C> {
+  storage: Storage<C>,
+  synchronizer: Synchronizer,
+  // hook called before each synchronization pass
+  before_sync: Option<Box<Fn(&mut C)>>,
+  // hook called after each synchronization pass
+  after_sync: Option<Box<Fn(&mut C)>>,
+  // most recent synchronization reports, oldest first
+  sync_history: VecDeque<SyncReport>,
+  // maximum number of reports kept in the history
+  sync_history_len: usize,
+  // waker of the task driving the store, woken up by the watcher
+  #[cfg(feature = "async")]
+  pub(crate) task_waker: TaskWaker,
+}
+
+/// How directories are watched for changes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WatchMode {
+  /// Watch the directories along with all their subdirectories.
+  Recursive,
+  /// Watch the directories only, not their subdirectories.
+  NonRecursive,
+}
+
+/// Backend used to watch the filesystem.
+///
+/// Without the `watcher` feature, nothing is watched whatever the backend.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WatcherBackend {
+  /// The recommended backend of the platform – inotify, FSEvents, etc.
+  Recommended,
+  /// Poll the watched directories at the given interval.
+  ///
+  /// Use it on filesystems the recommended backend doesn’t get events from, such as NFS or some
+  /// Docker volumes.
+  Poll(Duration),
+}
+
+/// Event sent by a running watcher.
+#[cfg(feature = "watcher")]
+type WatchEvent = RawEvent;
+
+/// Event sent by a running watcher, which never happens without the `watcher` feature.
+#[cfg(not(feature = "watcher"))]
+enum WatchEvent {}
+
+/// A running watcher.
+#[cfg(feature = "watcher")]
+enum RunningWatcher {
+  Recommended(RecommendedWatcher),
+  Poll(PollWatcher),
+}
+
+#[cfg(feature = "watcher")]
+impl RunningWatcher {
+  fn new(backend: WatcherBackend, tx: Sender<WatchEvent>) -> Result<Self, StoreError> {
+    let watcher = match backend {
+      WatcherBackend::Recommended => raw_watcher(tx).map(RunningWatcher::Recommended),
+      WatcherBackend::Poll(interval) => {
+        let ms = interval.as_secs() * 1000 + u64::from(interval.subsec_millis());
+        let ms = ms.min(u64::from(u32::max_value())) as u32;
+        PollWatcher::with_delay_ms(tx, ms.max(1)).map(RunningWatcher::Poll)
+      }
+    };
+
+    watcher.map_err(|e| StoreError::WatcherInit(Arc::new(e)))
+  }
+
+  fn watch(&mut self, dir: &Path, mode: WatchMode) -> bool {
+    let mode = match mode {
+      WatchMode::Recursive => RecursiveMode::Recursive,
+      WatchMode::NonRecursive => RecursiveMode::NonRecursive,
+    };
 
-impl<T, C, M> Clone for StoreErrorOr<T, C, M>
-where
-  T: Load<C, M>,
-  T::Error: Clone,
-{
-  fn clone(&self) -> Self {
     match *self {
-      StoreErrorOr::StoreError(ref e) => StoreErrorOr::StoreError(e.clone()),
-      StoreErrorOr::ResError(ref e) => StoreErrorOr::ResError(e.clone()),
+      RunningWatcher::Recommended(ref mut watcher) => watcher.watch(dir, mode).is_ok(),
+      RunningWatcher::Poll(ref mut watcher) => watcher.watch(dir, mode).is_ok(),
+    }
+  }
+}
+
+/// A running watcher, which doesn’t watch anything without the `watcher` feature.
+#[cfg(not(feature = "watcher"))]
+struct RunningWatcher;
+
+#[cfg(not(feature = "watcher"))]
+impl RunningWatcher {
+  fn new(_: WatcherBackend, _: Sender<WatchEvent>) -> Result<Self, StoreError> {
+    Ok(RunningWatcher)
+  }
+
+  fn watch(&mut self, _: &Path, _: WatchMode) -> bool {
+    false
+  }
+}
+
+/// Function called from the watcher thread when filesystem events are pending.
+type Waker = Arc<Fn() + Send + Sync>;
+
+/// Watches on the filesystem.
+struct Watch {
+  // keep the watcher around so that we don’t have it disconnected
+  watcher: RunningWatcher,
+  // function called whenever an event is sent, if any
+  waker: Option<Waker>,
+  // backend of the watcher
+  backend: WatcherBackend,
+  // how the configured directories are watched
+  mode: WatchMode,
+  // directories configured to be watched
+  dirs: Vec<PathBuf>,
+  // directories actually watched recursively
+  recursive: Vec<PathBuf>,
+  // directories actually watched non-recursively
+  flat: HashSet<PathBuf>,
+}
+
+impl Watch {
+  /// Watch directories, returning the watch and the receiving part of its event channel.
+  fn new(
+    dirs: Vec<PathBuf>,
+    mode: WatchMode,
+    backend: WatcherBackend,
+    waker: Option<Waker>,
+  ) -> Result<(Self, Receiver<WatchEvent>), StoreError>
+  {
+    // create the mpsc channel to communicate with the file watcher
+    let (wsx, wrx) = channel();
+
+    // forward the events through a thread waking the application up, if needed
+    let wsx = match waker {
+      Some(ref waker) => {
+        let (fsx, frx) = channel();
+        let waker = waker.clone();
+
+        let forward = move || {
+          for event in frx {
+            if wsx.send(event).is_err() {
+              break;
+            }
+
+            waker();
+          }
+        };
+
+        thread::Builder::new()
+          .spawn(forward)
+          .map_err(|e| StoreError::WakerThread(Arc::new(e)))?;
+
+        fsx
+      }
+
+      None => wsx,
+    };
+
+    let mut watch = Watch {
+      watcher: RunningWatcher::new(backend, wsx)?,
+      waker,
+      backend,
+      mode,
+      dirs,
+      recursive: Vec::new(),
+      flat: HashSet::new(),
+    };
+
+    for dir in watch.dirs.clone() {
+      watch.add(dir, mode);
+    }
+
+    Ok((watch, wrx))
+  }
+
+  /// Watch the configured directories again with a new watcher, forgetting about the lazily
+  /// watched ones, and return the receiving part of its event channel.
+  fn rewatch(&mut self) -> Result<Receiver<WatchEvent>, StoreError> {
+    let (watch, wrx) = Watch::new(self.dirs.clone(), self.mode, self.backend, self.waker.clone())?;
+    *self = watch;
+    Ok(wrx)
+  }
+
+  /// Watch a directory.
+  fn add(&mut self, dir: PathBuf, mode: WatchMode) {
+    if self.watcher.watch(&dir, mode) {
+      match mode {
+        WatchMode::Recursive => self.recursive.push(dir),
+        WatchMode::NonRecursive => {
+          self.flat.insert(dir);
+        }
+      }
+    }
+  }
+
+  /// Make sure changes to a path are watched, lazily watching the directory containing it – or the
+  /// path itself if it’s a directory – if needed.
+  fn cover(&mut self, path: &Path) {
+    let dir = if path.is_dir() {
+      path
+    } else {
+      match path.parent() {
+        Some(parent) => parent,
+        None => return,
+      }
+    };
+
+    let covered = self.flat.contains(dir) || self.recursive.iter().any(|r| dir.starts_with(r));
+
+    if !covered {
+      self.add(dir.to_owned(), WatchMode::NonRecursive);
+    }
+  }
+}
+
+impl<C> Store<C> {
+  /// Create a new store.
+  ///
+  /// # Failures
+  ///
+  /// This function will fail if the root path in the `StoreOpt` isn’t an existing directory – or
+  /// cannot be created, with `StoreOpt::set_create_root`.
+  pub fn new(opt: StoreOpt<C>) -> Result<Self, StoreError> {
+    let root = &opt.root;
+    let root_missing = |source| StoreError::RootNotFound {
+      path: root.to_owned(),
+      source: Arc::new(source),
+    };
+
+    if opt.create_root {
+      fs::create_dir_all(root).map_err(root_missing)?;
+    }
+
+    // the root is lexically normalized – relative to the current directory – unless canonicalized
+    let lexical_root =
+      env::current_dir().map(|current| key::normalize(current.join(root).components()));
+
+    // canonicalize the root because some platforms won’t correctly report file changes otherwise
+    let (canon_root, other_root) = if opt.canonicalize {
+      (root.canonicalize().map_err(root_missing)?, lexical_root.ok())
+    } else {
+      let lexical_root = lexical_root.map_err(root_missing)?;
+      let metadata = fs::metadata(&lexical_root).map_err(root_missing)?;
+
+      if !metadata.is_dir() {
+        let source = io::Error::new(io::ErrorKind::Other, "not a directory");
+        return Err(root_missing(source));
+      }
+
+      let other_root = lexical_root.canonicalize().ok();
+      (lexical_root, other_root)
+    };
+
+    // watchers might report paths with the other spelling of the root
+    let root_aliases = other_root
+      .into_iter()
+      .filter(|other_root| *other_root != canon_root)
+      .collect();
+
+    // domains and watched directories are given as VFS paths
+    let normalization = opt.path_normalization;
+    let prepare_dir = |dir: PathBuf| {
+      let key = FSKey::new(dir).prepare_key_with(&canon_root, normalization);
+      key.as_path().to_owned()
+    };
+    let domains = opt
+      .domains
+      .into_iter()
+      .map(|(name, dir)| (name, prepare_dir(dir)))
+      .collect();
+
+    let mut watched_dirs = if opt.watched_dirs.is_empty() {
+      vec![canon_root.clone()]
+    } else {
+      opt
+        .watched_dirs
+        .into_iter()
+        .map(&prepare_dir)
+        .collect()
+    };
+
+    // mounted directories are spelled like the root and watched as well
+    let mut mounts = Vec::new();
+
+    for (point, dir) in opt.mounts {
+      let mount_missing = |source| StoreError::MountNotFound {
+        path: dir.clone(),
+        source: Arc::new(source),
+      };
+
+      let dir = if opt.canonicalize {
+        dir.canonicalize().map_err(mount_missing)?
+      } else {
+        let lexical_dir = env::current_dir()
+          .map(|current| key::normalize(current.join(&dir).components()))
+          .map_err(mount_missing)?;
+
+        if !fs::metadata(&lexical_dir).map_err(mount_missing)?.is_dir() {
+          let source = io::Error::new(io::ErrorKind::Other, "not a directory");
+          return Err(mount_missing(source));
+        }
+
+        lexical_dir
+      };
+
+      watched_dirs.push(dir.clone());
+      mounts.push((prepare_dir(point), dir));
+    }
+
+    // the task driving the store, if any, is woken up along with the application
+    #[cfg(feature = "async")]
+    let task_waker = TaskWaker::default();
+    #[cfg(feature = "async")]
+    let waker = Some(driver::chain_waker(opt.waker, task_waker.clone()));
+    #[cfg(not(feature = "async"))]
+    let waker = opt.waker;
+
+    // watch the root – or the configured directories – for changes
+    let (watch, wrx) = Watch::new(watched_dirs, opt.watch_mode, opt.watcher_backend, waker)?;
+
+    // the storage and the synchronizer share the same time
+    let clock: Rc<Clock> = opt.clock.into();
+
+    // create the storage
+    let storage = Storage::new(StorageConfig {
+      canon_root,
+      root_aliases,
+      cache: opt.cache,
+      dependency_check: opt.dependency_check,
+      allow_outside_root: opt.allow_outside_root,
+      mounts: Mounts::new(mounts),
+      fallback_to_defaults: opt.fallback_to_defaults,
+      path_normalization: opt.path_normalization,
+      watch,
+      memory_budget: opt.memory_budget,
+      read_metrics: opt.read_metrics,
+      progress_tracking: opt.progress_tracking,
+      embedded: opt.embedded,
+      transforms: opt.transforms,
+      read_middleware: opt.read_middleware,
+      artifact_cache: opt.artifact_cache,
+      clock: clock.clone(),
+    });
+
+    // create the synchronizer
+    let synchronizer = Synchronizer::new(SyncConfig {
+      watcher_rx: wrx,
+      update_await_time_ms: opt.update_await_time_ms,
+      adaptive_await_time_ms: opt.adaptive_await_time_ms,
+      reload_sink: opt.reload_sink,
+      build_id: opt.build_id,
+      domains,
+      ignore_patterns: opt.ignore_patterns,
+      clock,
+      transactional: opt.transactional_reloads,
+      coalesce_reloads: opt.coalesce_reloads,
+      reload_error_policy: opt.reload_error_policy,
+    });
+
+    let store = Store {
+      storage,
+      synchronizer,
+      before_sync: opt.before_sync,
+      after_sync: opt.after_sync,
+      sync_history: VecDeque::with_capacity(opt.sync_history_len),
+      sync_history_len: opt.sync_history_len,
+      #[cfg(feature = "async")]
+      task_waker,
+    };
+
+    Ok(store)
+  }
+
+  /// Check whether a call to `Store::sync` has work to do.
+  ///
+  /// Pending filesystem events are dequeued, so this function is cheap enough to be called on
+  /// every iteration of an event loop. Some of the resources to reload might still be waiting for
+  /// their update await time to elapse. See also `StoreOpt::set_waker`.
+  pub fn sync_needed(&mut self) -> bool {
+    self.synchronizer.sync_needed(&self.storage)
+  }
+
+  /// Earliest time at which a call to `Store::sync` will have something to reload, if any.
+  ///
+  /// The waker set with `StoreOpt::set_waker` only fires on filesystem events: resources touched by
+  /// an event are reloaded once their update await time has elapsed, which no further event
+  /// signals. After a synchronization, event-driven applications should schedule a wake-up at that
+  /// deadline – with `ControlFlow::WaitUntil` in winit, for instance. A deadline in the past means
+  /// that a synchronization is due right away. The deadline is measured with the clock of the store
+  /// – see `StoreOpt::set_clock`.
+  ///
+  /// Pending filesystem events are dequeued, just like with `Store::sync_needed`. No deadline is
+  /// returned while reloading is paused.
+  pub fn next_sync_deadline(&mut self) -> Option<Instant> {
+    self.synchronizer.next_deadline(&self.storage)
+  }
+
+  /// Current instant, according to the clock of the store.
+  #[cfg(feature = "async")]
+  pub(crate) fn now(&self) -> Instant {
+    self.synchronizer.clock.now()
+  }
+
+  /// Mark the resource living at the given key as dirty, as if its file had changed.
+  ///
+  /// The resource will get reloaded by a future call to `Store::sync`. Keys that don’t refer to any
+  /// resource living in the `Store` are ignored.
+  pub fn mark_dirty<K>(&mut self, key: K)
+  where K: Key {
+    let dep_key = self.storage.prepare(key).into();
+
+    if self.storage.metadata.contains_key(&dep_key) {
+      self.synchronizer.touch(dep_key);
+    }
+  }
+
+  /// Mark all the resources tagged with an aspect of the context as dirty – see
+  /// `Loaded::with_ctx_tag`.
+  ///
+  /// Call it whenever that aspect of the context changes: the resources will get reloaded by a
+  /// future call to `Store::sync`, along with the resources depending on them.
+  pub fn invalidate_tag(&mut self, tag: &str) {
+    for dep_key in self.storage.tagged(tag) {
+      self.synchronizer.touch(dep_key);
+    }
+  }
+
+  /// Override the update await time of a resource, in milliseconds.
+  ///
+  /// The override takes precedence over both the await time of the `Store` and the one of the type
+  /// of the resource – see `Load::reload_delay`. Pass `None` to remove it.
+  pub fn set_await_time_for<K>(&mut self, key: K, await_time_ms: Option<u64>)
+  where K: Key {
+    let dep_key = self.storage.prepare(key).into();
+    let overrides = &mut self.synchronizer.await_time_overrides_ms;
+
+    match await_time_ms {
+      Some(await_time_ms) => {
+        overrides.insert(dep_key, await_time_ms);
+      }
+
+      None => {
+        overrides.remove(&dep_key);
+      }
+    }
+  }
+
+  /// Keys of the resources which files were deleted, sorted.
+  ///
+  /// Those resources are `ResState::SourceMissing` – see `Res::state`. A key leaves the list once
+  /// a file appears at its path again or its resources are evicted.
+  pub fn missing_sources(&self) -> Vec<DepKey> {
+    let mut keys: Vec<_> = self
+      .synchronizer
+      .missing_sources
+      .iter()
+      .filter(|dep_key| self.storage.metadata.contains_key(dep_key))
+      .cloned()
+      .collect();
+    keys.sort();
+    keys
+  }
+
+  /// Override the reload priority of a resource.
+  ///
+  /// The override takes precedence over the priority of the type of the resource – see
+  /// `Load::reload_priority`. Pass `None` to remove it.
+  pub fn set_reload_priority_for<K>(&mut self, key: K, priority: Option<i32>)
+  where K: Key {
+    let dep_key = self.storage.prepare(key).into();
+    let overrides = &mut self.synchronizer.reload_priority_overrides;
+
+    match priority {
+      Some(priority) => {
+        overrides.insert(dep_key, priority);
+      }
+
+      None => {
+        overrides.remove(&dep_key);
+      }
+    }
+  }
+
+  /// Synchronize the `Store` until a resource has reloaded, or a timeout elapses.
+  ///
+  /// This function blocks on filesystem events and waits for the update await time of dirty
+  /// resources to elapse, so that tests and tools don’t have to guess how long to sleep. It returns
+  /// whether the resource was successfully reloaded in time; a failed reload doesn’t count.
+  pub fn wait_for_reload<K>(&mut self, key: K, timeout: Duration, ctx: &mut C) -> bool
+  where K: Key {
+    let dep_key = self.storage.prepare(key).into();
+    let deadline = Instant::now() + timeout;
+
+    loop {
+      if self.sync(ctx).reloaded.contains(&dep_key) {
+        return true;
+      }
+
+      let now = Instant::now();
+
+      if now >= deadline {
+        return false;
+      }
+
+      let remaining = deadline - now;
+
+      if self.synchronizer.dirties.is_empty() {
+        self.synchronizer.wait_fs_event(&self.storage, remaining);
+      } else {
+        // let the dirty resources settle
+        thread::sleep(remaining.min(Duration::from_millis(WAIT_STEP_MS)));
+      }
+    }
+  }
+
+  /// Pause the reloading of resources.
+  ///
+  /// `Store::sync` keeps dequeuing filesystem events while paused but doesn’t reload anything: the
+  /// changed resources pile up as dirty, each of them once however many times it changed. Use it
+  /// around a batch regeneration of assets, then call `Store::resume_sync`.
+  pub fn pause_sync(&mut self) {
+    self.synchronizer.paused = true;
+  }
+
+  /// Resume the reloading of resources.
+  ///
+  /// The resources that changed during the pause are all reloaded by the next call to
+  /// `Store::sync`, without waiting for the update await time. Does nothing if not paused.
+  pub fn resume_sync(&mut self) {
+    if self.synchronizer.paused {
+      self.synchronizer.paused = false;
+      self.synchronizer.flush = true;
+    }
+  }
+
+  /// Check whether the reloading of resources is paused.
+  pub fn is_sync_paused(&self) -> bool {
+    self.synchronizer.paused
+  }
+
+  /// Subscribe to reload events.
+  ///
+  /// The returned receiver gets a `ReloadEvent` for every reload attempted during `Store::sync`,
+  /// including the ones of dependent resources. You can subscribe as many times as you want;
+  /// dropping the receiver unsubscribes.
+  pub fn events(&mut self) -> Receiver<ReloadEvent> {
+    let (sx, rx) = channel();
+    self.synchronizer.event_subscribers.push(sx);
+    rx
+  }
+
+  /// Synchronize the `Store` by updating the resources that ought to with a provided context.
+  ///
+  /// A summary of what happened during the synchronization is returned.
+  pub fn sync(&mut self, ctx: &mut C) -> SyncReport {
+    self.sync_domains(ctx, None)
+  }
+
+  /// Synchronize a single domain of the `Store`.
+  ///
+  /// Only the resources belonging to the domain – see `StoreOpt::add_domain` – are reloaded; the
+  /// dirty resources of the other domains wait for their own domain to be synchronized, or for
+  /// `Store::sync`. Resources depending on reloaded resources are reloaded as well, whatever their
+  /// domains.
+  pub fn sync_domain(&mut self, domain: &str, ctx: &mut C) -> SyncReport {
+    self.sync_domains(ctx, Some(domain))
+  }
+
+  /// Domain a key belongs to, if any.
+  pub fn domain_of(&self, key: &DepKey) -> Option<&str> {
+    let dep_key = self.storage.prepare_dep_key(key);
+    self.synchronizer.domain_of(&dep_key)
+  }
+
+  /// Synchronize a given domain, or all of them.
+  fn sync_domains(&mut self, ctx: &mut C, domain: Option<&str>) -> SyncReport {
+    if let Some(ref before_sync) = self.before_sync {
+      before_sync(ctx);
+    }
+
+    let report = self.synchronizer.sync(&mut self.storage, ctx, domain);
+
+    if let Some(ref after_sync) = self.after_sync {
+      after_sync(ctx);
+    }
+
+    if self.sync_history_len > 0 {
+      if self.sync_history.len() == self.sync_history_len {
+        self.sync_history.pop_front();
+      }
+
+      self.sync_history.push_back(report.clone());
+    }
+
+    report
+  }
+
+  /// Take a snapshot of the metrics of the `Store`.
+  ///
+  /// See the documentation of `StoreMetrics` for further details.
+  pub fn metrics(&self) -> StoreMetrics {
+    StoreMetrics {
+      resident: self.storage.cache.keys().count(),
+      cache_hits: self.storage.cache_hits,
+      cache_misses: self.storage.cache_misses,
+      reloads_succeeded: self.synchronizer.reloads_succeeded,
+      reloads_failed: self.synchronizer.reloads_failed,
+      last_reload_durations: self.synchronizer.last_reload_durations.clone(),
+    }
+  }
+
+  /// Take a snapshot of the progress of the resources loaded since the last call to
+  /// `Store::clear_progress`.
+  ///
+  /// The report is empty unless enabled with `StoreOpt::set_progress_tracking`. See the `progress`
+  /// module for further details.
+  pub fn progress(&self) -> ProgressReport {
+    self
+      .storage
+      .progress
+      .as_ref()
+      .map(ProgressTracker::report)
+      .unwrap_or_default()
+  }
+
+  /// Forget about the progress of the resources that are done loading.
+  pub fn clear_progress(&mut self) {
+    if let Some(ref mut progress) = self.storage.progress {
+      progress.clear();
+    }
+  }
+
+  /// Iterate over the most recent synchronization reports, from the oldest to the newest.
+  ///
+  /// The number of reports kept is set with `StoreOpt::set_sync_history_len`.
+  pub fn recent_syncs<'a>(&'a self) -> impl Iterator<Item = &'a SyncReport> + 'a {
+    self.sync_history.iter()
+  }
+}
+
+impl<C> Deref for Store<C> {
+  type Target = Storage<C>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.storage
+  }
+}
+
+impl<C> DerefMut for Store<C> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.storage
+  }
+}
+
+/// What to do when a resource declares a dependency that doesn’t exist.
+///
+/// A dependency exists if it’s a file living under the root, the key of a resource already living
+/// in the `Storage` or an alias. A dependency that doesn’t exist – typically, a typo’d path – never
+/// triggers any reload.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DependencyCheck {
+  /// Dependencies are not checked.
+  Ignore,
+  /// Missing dependencies are logged as warnings – see the `log` feature.
+  Warn,
+  /// Missing dependencies make the loading fail with `StoreError::MissingDependency`.
+  Deny,
+}
+
+/// What to do with a resource that fails to reload – including because its file was deleted.
+///
+/// The policy applies to reloads done while synchronizing a `Store`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ReloadErrorPolicy {
+  /// The resource keeps its previous value.
+  KeepPrevious,
+  /// The resource is replaced by a value created by the proxy of its type – see
+  /// `Storage::register_default`. Resources which type has no proxy keep their previous values.
+  ReplaceWithProxy,
+  /// The resources living at the key of the resource are evicted from the `Store` – see
+  /// `Storage::evict`.
+  Evict,
+}
+
+/// Various options to customize a `Store`.
+///
+/// Feel free to inspect all of its declared methods for further information.
+///
+/// The type variable, `C`, is the context of the `Store` the options are meant for. It only
+/// matters for options involving the context, such as synchronization hooks.
+pub struct StoreOpt<C = ()> {
+  root: PathBuf,
+  update_await_time_ms: u64,
+  adaptive_await_time_ms: Option<u64>,
+  cache: Box<CacheBackend>,
+  before_sync: Option<Box<Fn(&mut C)>>,
+  after_sync: Option<Box<Fn(&mut C)>>,
+  sync_history_len: usize,
+  dependency_check: DependencyCheck,
+  reload_sink: Option<Box<ReloadSink>>,
+  build_id: Option<String>,
+  domains: Vec<(String, PathBuf)>,
+  mounts: Vec<(PathBuf, PathBuf)>,
+  allow_outside_root: bool,
+  fallback_to_defaults: bool,
+  path_normalization: PathNormalization,
+  canonicalize: bool,
+  create_root: bool,
+  ignore_patterns: Vec<String>,
+  watch_mode: WatchMode,
+  watched_dirs: Vec<PathBuf>,
+  watcher_backend: WatcherBackend,
+  memory_budget: Option<usize>,
+  read_metrics: bool,
+  progress_tracking: bool,
+  waker: Option<Waker>,
+  clock: Box<Clock>,
+  embedded: EmbeddedSource,
+  transforms: ByteTransforms,
+  read_middleware: Vec<Box<Fn(&DepKey, Vec<u8>) -> io::Result<Vec<u8>>>>,
+  artifact_cache: Option<ArtifactCache>,
+  transactional_reloads: bool,
+  coalesce_reloads: bool,
+  reload_error_policy: ReloadErrorPolicy,
+}
+
+impl<C> Default for StoreOpt<C> {
+  fn default() -> Self {
+    StoreOpt {
+      root: PathBuf::from("."),
+      update_await_time_ms: 50,
+      adaptive_await_time_ms: None,
+      cache: Box::new(HashCache::new()),
+      before_sync: None,
+      after_sync: None,
+      sync_history_len: 16,
+      dependency_check: DependencyCheck::Ignore,
+      reload_sink: None,
+      build_id: None,
+      domains: Vec::new(),
+      mounts: Vec::new(),
+      allow_outside_root: false,
+      fallback_to_defaults: false,
+      path_normalization: PathNormalization::none(),
+      canonicalize: true,
+      create_root: false,
+      ignore_patterns: DEFAULT_IGNORE_PATTERNS.iter().map(|&p| p.to_owned()).collect(),
+      watch_mode: WatchMode::Recursive,
+      watched_dirs: Vec::new(),
+      watcher_backend: WatcherBackend::Recommended,
+      memory_budget: None,
+      read_metrics: false,
+      progress_tracking: false,
+      waker: None,
+      clock: Box::new(SystemClock),
+      embedded: EmbeddedSource::default(),
+      transforms: ByteTransforms::default(),
+      read_middleware: Vec::new(),
+      artifact_cache: None,
+      transactional_reloads: false,
+      coalesce_reloads: false,
+      reload_error_policy: ReloadErrorPolicy::KeepPrevious,
     }
   }
 }
 
-impl<T, C, M> Eq for StoreErrorOr<T, C, M>
-where
-  T: Load<C, M>,
-  T::Error: Eq,
-{
-}
+impl<C> StoreOpt<C> {
+  /// Change the update await time (milliseconds) used to determine whether a resource should be
+  /// reloaded or not.
+  ///
+  /// A `Store` will wait that amount of time before deciding an resource should be reloaded after
+  /// it has changed on the filesystem. That is required in order to cope with write streaming, that
+  /// generates a lot of write event.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `50` milliseconds.
+  #[inline]
+  pub fn set_update_await_time_ms(self, ms: u64) -> Self {
+    StoreOpt {
+      update_await_time_ms: ms,
+      ..self
+    }
+  }
 
-impl<T, C, M> PartialEq for StoreErrorOr<T, C, M>
-where
-  T: Load<C, M>,
-  T::Error: PartialEq,
-{
-  fn eq(&self, rhs: &Self) -> bool {
-    match (self, rhs) {
-      (&StoreErrorOr::StoreError(ref a), &StoreErrorOr::StoreError(ref b)) => a == b,
-      (&StoreErrorOr::ResError(ref a), &StoreErrorOr::ResError(ref b)) => a == b,
-      _ => false,
+  /// Get the update await time (milliseconds).
+  #[inline]
+  pub fn update_await_time_ms(&self) -> u64 {
+    self.update_await_time_ms
+  }
+
+  /// Make the update await time adaptive, capped to the given value (milliseconds).
+  ///
+  /// When enabled, the `Store` learns, per resource, how long bursts of writes last and adapts the
+  /// time it waits before reloading that resource accordingly: small files written at once reload
+  /// almost instantly while large, stream-written files get longer settle windows. The update
+  /// await time is used for resources that haven’t been reloaded yet. Pass `None` to disable.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `None`.
+  #[inline]
+  pub fn set_adaptive_await_time_ms(self, max_ms: Option<u64>) -> Self {
+    StoreOpt {
+      adaptive_await_time_ms: max_ms,
+      ..self
     }
   }
-}
 
-impl<T, C, M> fmt::Debug for StoreErrorOr<T, C, M>
-where
-  T: Load<C, M>,
-  T::Error: fmt::Debug,
-{
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    match *self {
-      StoreErrorOr::StoreError(ref e) => f.debug_tuple("StoreError").field(e).finish(),
-      StoreErrorOr::ResError(ref e) => f.debug_tuple("ResError").field(e).finish(),
+  /// Get the maximum adaptive await time (milliseconds), if the adaptive mode is enabled.
+  #[inline]
+  pub fn adaptive_await_time_ms(&self) -> Option<u64> {
+    self.adaptive_await_time_ms
+  }
+
+  /// Change the root directory from which the `Store` will be watching file changes.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `"."`.
+  #[inline]
+  pub fn set_root<P>(self, root: P) -> Self
+  where P: AsRef<Path> {
+    StoreOpt {
+      root: root.as_ref().to_owned(),
+      ..self
+    }
+  }
+
+  /// Get root directory.
+  #[inline]
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  /// Canonicalize the root directory.
+  ///
+  /// Some platforms won’t correctly report file changes under a root that isn’t canonicalized, yet
+  /// canonicalized paths can get in the way – on Windows, they’re prefixed with `\\?\`, which
+  /// confuses tools comparing paths and some watcher backends. When disabled, the root is only
+  /// normalized lexically: made absolute, with its `.` and `..` components resolved. Events
+  /// reported with either spelling of the root are matched against the keys.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `true`.
+  #[inline]
+  pub fn set_canonicalize(self, canonicalize: bool) -> Self {
+    StoreOpt {
+      canonicalize,
+      ..self
+    }
+  }
+
+  /// Check whether the root directory is canonicalized.
+  #[inline]
+  pub fn canonicalize(&self) -> bool {
+    self.canonicalize
+  }
+
+  /// Create the root directory – along with its missing parents – if it doesn’t exist.
+  ///
+  /// Handy for directories that are empty on first run, such as user configuration ones. If the
+  /// root cannot be created, `Store::new` fails with `StoreError::RootNotFound`.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_create_root(self, create_root: bool) -> Self {
+    StoreOpt {
+      create_root,
+      ..self
+    }
+  }
+
+  /// Check whether the root directory is created if missing.
+  #[inline]
+  pub fn create_root(&self) -> bool {
+    self.create_root
+  }
+
+  /// Change the cache backend the `Store` will hold its resources in.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `HashCache`.
+  #[inline]
+  pub fn set_cache_backend<B>(self, cache: B) -> Self
+  where B: 'static + CacheBackend {
+    StoreOpt {
+      cache: Box::new(cache),
+      ..self
+    }
+  }
+
+  /// Set the resources embedded in the binary, read when their files don’t exist.
+  ///
+  /// See the `embedded` module for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no embedded resource.
+  #[inline]
+  pub fn set_embedded_source(self, embedded: EmbeddedSource) -> Self {
+    StoreOpt { embedded, ..self }
+  }
+
+  /// Get the resources embedded in the binary.
+  #[inline]
+  pub fn embedded_source(&self) -> &EmbeddedSource {
+    &self.embedded
+  }
+
+  /// Add a transform applied to the bytes of the files with a given extension – without the dot –
+  /// before they reach loaders, replacing the one already added for that extension, if any.
+  ///
+  /// Files are also looked up with that extension appended when they don’t exist: with a transform
+  /// added for `gz`, the key `/config.json` loads `config.json.gz` if `config.json` doesn’t exist.
+  /// See the `transform` module for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no transform.
+  #[inline]
+  pub fn add_byte_transform<E, T>(mut self, extension: E, transform: T) -> Self
+  where
+    E: Into<String>,
+    T: 'static + ByteTransform,
+  {
+    self.transforms.insert(extension.into(), Box::new(transform));
+    self
+  }
+
+  /// Iterate over the extensions with a byte transform.
+  #[inline]
+  pub fn byte_transform_extensions<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.transforms.extensions()
+  }
+
+  /// Add a stage to the read middleware, run on the bytes of every file read by `Storage::read`.
+  ///
+  /// Stages run in the order they were added, each one given the key of the file – a prepared
+  /// `DepKey::Path` – and the output of the previous one. They run before byte transforms, on the
+  /// bytes as stored. Use them to decrypt assets, verify their signatures or apply binary patches
+  /// once for all loaders; an error fails the read, and thus the load or reload of the resource.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no middleware.
+  #[inline]
+  pub fn add_read_middleware<F>(mut self, stage: F) -> Self
+  where F: 'static + Fn(&DepKey, Vec<u8>) -> io::Result<Vec<u8>> {
+    self.read_middleware.push(Box::new(stage));
+    self
+  }
+
+  /// Number of stages in the read middleware.
+  #[inline]
+  pub fn read_middleware_len(&self) -> usize {
+    self.read_middleware.len()
+  }
+
+  /// Set the on-disk cache loaders store their processed artifacts in.
+  ///
+  /// See the `artifact` module for further details. Pass `None` to disable.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `None`.
+  #[inline]
+  pub fn set_artifact_cache(self, artifact_cache: Option<ArtifactCache>) -> Self {
+    StoreOpt {
+      artifact_cache,
+      ..self
+    }
+  }
+
+  /// Get the on-disk cache of processed artifacts, if any.
+  #[inline]
+  pub fn artifact_cache(&self) -> Option<&ArtifactCache> {
+    self.artifact_cache.as_ref()
+  }
+
+  /// Change the clock used to debounce reloads and to timestamp resources – see `Storage::info`.
+  ///
+  /// Give it a `ManualClock` to test the debouncing deterministically. See the `clock` module for
+  /// further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `SystemClock`.
+  #[inline]
+  pub fn set_clock<K>(self, clock: K) -> Self
+  where K: 'static + Clock {
+    StoreOpt {
+      clock: Box::new(clock),
+      ..self
+    }
+  }
+
+  /// Change the number of synchronization reports the `Store` keeps around.
+  ///
+  /// They can be inspected after the fact with `Store::recent_syncs` – from a debug console, for
+  /// instance. Set it to `0` to disable the history.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `16`.
+  #[inline]
+  pub fn set_sync_history_len(self, len: usize) -> Self {
+    StoreOpt {
+      sync_history_len: len,
+      ..self
+    }
+  }
+
+  /// Get the number of synchronization reports kept around.
+  #[inline]
+  pub fn sync_history_len(&self) -> usize {
+    self.sync_history_len
+  }
+
+  /// Make reloads transactional.
+  ///
+  /// When a resource changes, it’s reloaded along with all the resources depending on it. If any of
+  /// those reloads fails, all the resources of the cascade are rolled back to the values they had
+  /// before the synchronization, so that they never end up in a mixed state – a new texture with
+  /// a half-broken material. Rolled back cascades are counted in `SyncReport::rollbacks`.
+  ///
+  /// A rolled back reload never happened as far as observers are concerned: the resource gets its
+  /// generation and provenance back, its `Res::on_reload` observers aren’t notified and it’s
+  /// reported as `ReloadOutcome::RolledBack` – in the `SyncReport`, the reload events and the
+  /// reload sink. Observers of the reloads of a cascade are only notified once the whole cascade
+  /// succeeded. Reloads are never coalesced in transactions – see `StoreOpt::set_coalesce_reloads`
+  /// – so that every dependent is reloaded within the cascade of its dependency.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`: successfully reloaded resources keep their new values.
+  #[inline]
+  pub fn set_transactional_reloads(self, transactional_reloads: bool) -> Self {
+    StoreOpt {
+      transactional_reloads,
+      ..self
+    }
+  }
+
+  /// Check whether reloads are transactional.
+  #[inline]
+  pub fn transactional_reloads(&self) -> bool {
+    self.transactional_reloads
+  }
+
+  /// Coalesce the reloads of dependent resources within a synchronization pass.
+  ///
+  /// Exporters often rewrite several related files at once – a mesh, its material and its
+  /// textures. Without coalescing, a resource depending on all of them is reloaded once per
+  /// changed dependency. With coalescing, it’s reloaded once per pass, after all the dirty keys of
+  /// the pass have been reloaded – or, if it’s dirty itself, right after its dirty dependencies.
+  /// Saved reloads are counted in `SyncReport::reloads_coalesced`.
+  ///
+  /// Files written within the update await time of each other are reloaded in the same pass. Weak
+  /// dependents are still notified once per changed dependency, and transactional reloads – see
+  /// `StoreOpt::set_transactional_reloads` – are never coalesced.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`: dependents are reloaded right after each of their dependencies.
+  #[inline]
+  pub fn set_coalesce_reloads(self, coalesce_reloads: bool) -> Self {
+    StoreOpt {
+      coalesce_reloads,
+      ..self
+    }
+  }
+
+  /// Check whether the reloads of dependent resources are coalesced.
+  #[inline]
+  pub fn coalesce_reloads(&self) -> bool {
+    self.coalesce_reloads
+  }
+
+  /// Change what to do with resources failing to reload.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `ReloadErrorPolicy::KeepPrevious`.
+  #[inline]
+  pub fn set_reload_error_policy(self, reload_error_policy: ReloadErrorPolicy) -> Self {
+    StoreOpt {
+      reload_error_policy,
+      ..self
+    }
+  }
+
+  /// Get what to do with resources failing to reload.
+  #[inline]
+  pub fn reload_error_policy(&self) -> ReloadErrorPolicy {
+    self.reload_error_policy
+  }
+
+  /// Change how the dependencies declared by resources are checked when they get loaded.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `DependencyCheck::Ignore`.
+  #[inline]
+  pub fn set_dependency_check(self, check: DependencyCheck) -> Self {
+    StoreOpt {
+      dependency_check: check,
+      ..self
+    }
+  }
+
+  /// Get how declared dependencies are checked.
+  #[inline]
+  pub fn dependency_check(&self) -> DependencyCheck {
+    self.dependency_check
+  }
+
+  /// Set a sink receiving a record of every reload.
+  ///
+  /// See the documentation of the `sink` module for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no sink.
+  #[inline]
+  pub fn set_reload_sink<S>(self, sink: S) -> Self
+  where S: 'static + ReloadSink {
+    StoreOpt {
+      reload_sink: Some(Box::new(sink)),
+      ..self
+    }
+  }
+
+  /// Change the identifier of the build, attached to reload records.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no identifier.
+  #[inline]
+  pub fn set_build_id<S>(self, build_id: S) -> Self
+  where S: Into<String> {
+    StoreOpt {
+      build_id: Some(build_id.into()),
+      ..self
     }
   }
-}
 
-impl<T, C, M> fmt::Display for StoreErrorOr<T, C, M>
-where
-  T: Load<C, M>,
-  T::Error: fmt::Debug,
-{
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    f.write_str(self.description())
+  /// Get the identifier of the build.
+  #[inline]
+  pub fn build_id(&self) -> Option<&str> {
+    self.build_id.as_ref().map(String::as_str)
   }
-}
 
-impl<T, C, M> Error for StoreErrorOr<T, C, M>
-where
-  T: Load<C, M>,
-  T::Error: fmt::Debug,
-{
-  fn description(&self) -> &str {
-    match *self {
-      StoreErrorOr::StoreError(ref e) => e.description(),
-      StoreErrorOr::ResError(ref e) => e.description(),
-    }
+  /// Add a domain covering all the files living under a directory, given by its VFS path.
+  ///
+  /// Domains partition the resources of a `Store` so that they can be synchronized independently
+  /// with `Store::sync_domain` – for instance, configuration every second but art only in
+  /// development builds – while sharing a single watcher. A file belongs to the domain with the
+  /// most specific directory containing it, if any.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no domain.
+  #[inline]
+  pub fn add_domain<N, P>(mut self, name: N, dir: P) -> Self
+  where
+    N: Into<String>,
+    P: AsRef<Path>,
+  {
+    self.domains.push((name.into(), dir.as_ref().to_owned()));
+    self
   }
 
-  fn cause(&self) -> Option<&Error> {
-    match *self {
-      StoreErrorOr::StoreError(ref e) => e.cause(),
-      StoreErrorOr::ResError(ref e) => e.cause(),
-    }
+  /// Iterate over the domains, along with the directories they cover.
+  #[inline]
+  pub fn domains<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a Path)> + 'a {
+    self
+      .domains
+      .iter()
+      .map(|&(ref name, ref dir)| (name.as_str(), dir.as_path()))
   }
-}
-
-/// Resource synchronizer.
-///
-/// An object of this type is responsible to synchronize resources living in a store. It keeps in
-/// internal, optimized state to perform correct and efficient synchronization.
-struct Synchronizer {
-  // all the resources that must be reloaded; they’re mapped to the instant they were found updated
-  dirties: HashMap<DepKey, Instant>,
-  // keep the watcher around so that we don’t have it disconnected
-  #[allow(dead_code)]
-  watcher: RecommendedWatcher,
-  // watcher receiver part of the channel
-  watcher_rx: Receiver<RawEvent>,
-  // time in milleseconds to wait before actually invoking the reloading function on a given
-  // resource; the wait is done between the current time and the last time the resource was touched
-  // by the event loop
-  update_await_time_ms: u64,
-}
 
-impl Synchronizer {
-  fn new(
-    watcher: RecommendedWatcher,
-    watcher_rx: Receiver<RawEvent>,
-    update_await_time_ms: u64,
-  ) -> Self
+  /// Mount a directory living anywhere on the filesystem at a VFS directory.
+  ///
+  /// Filesystem keys living under the VFS directory are routed to the mounted directory instead of
+  /// the root – the deepest mount point wins – and the mounted directory is watched for changes. A
+  /// relative mounted directory is taken relative to the current directory. See the `mount` module
+  /// for further details. If the mounted directory doesn’t exist, `Store::new` fails with
+  /// `StoreError::MountNotFound`.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no mount point.
+  #[inline]
+  pub fn add_mount<V, P>(mut self, vfs_dir: V, dir: P) -> Self
+  where
+    V: AsRef<Path>,
+    P: AsRef<Path>,
   {
-    Synchronizer {
-      dirties: HashMap::new(),
-      watcher,
-      watcher_rx,
-      update_await_time_ms,
-    }
+    self
+      .mounts
+      .push((vfs_dir.as_ref().to_owned(), dir.as_ref().to_owned()));
+    self
   }
 
-  /// Dequeue any file system events.
-  fn dequeue_fs_events<C>(&mut self, storage: &Storage<C>) {
-    for event in self.watcher_rx.try_iter() {
-      match event {
-        RawEvent {
-          path: Some(ref path),
-          op: Ok(op),
-          ..
-        } if op | WRITE != Op::empty() =>
-        {
-          let dep_key = DepKey::Path(path.to_owned());
-
-          if storage.metadata.contains_key(&dep_key) {
-            self.dirties.insert(dep_key, Instant::now());
-          }
-        }
+  /// Iterate over the mount points, along with the directories mounted there.
+  #[inline]
+  pub fn mounts<'a>(&'a self) -> impl Iterator<Item = (&'a Path, &'a Path)> + 'a {
+    self
+      .mounts
+      .iter()
+      .map(|&(ref vfs_dir, ref dir)| (vfs_dir.as_path(), dir.as_path()))
+  }
 
-        _ => (),
-      }
+  /// Allow filesystem keys to refer to files living outside of the root.
+  ///
+  /// Keys are normalized – `.` and `..` are resolved – and by default, loading a resource which key
+  /// escapes the root fails with `StoreError::KeyOutsideRoot`. That protects you from keys coming
+  /// from untrusted content. Only allow it if all your keys are trusted.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_allow_outside_root(self, allow: bool) -> Self {
+    StoreOpt {
+      allow_outside_root: allow,
+      ..self
     }
   }
 
-  /// Reload any dirty resource that fulfill its time predicate.
-  fn reload_dirties<C>(&mut self, storage: &mut Storage<C>, ctx: &mut C) {
-    let update_await_time_ms = self.update_await_time_ms;
-
-    self.dirties.retain(|dep_key, dirty_instant| {
-      let now = Instant::now();
+  /// Check whether filesystem keys may refer to files living outside of the root.
+  #[inline]
+  pub fn allow_outside_root(&self) -> bool {
+    self.allow_outside_root
+  }
 
-      // check whether we’ve waited enough to actually invoke the reloading code
-      if now.duration_since(dirty_instant.clone()) >= Duration::from_millis(update_await_time_ms) {
-        // we’ve waited enough; reload
-        if let Some(metadata) = storage.metadata.remove(&dep_key) {
-          if (metadata.on_reload)(storage, ctx).is_ok() {
-            // if we have successfully reloaded the resource, notify the observers that this
-            // dependency has changed
-            if let Some(deps) = storage.deps.get(&dep_key).cloned() {
-              for dep in deps {
-                if let Some(obs_metadata) = storage.metadata.remove(&dep) {
-                  // FIXME: decide what to do with the result (error?)
-                  let _ = (obs_metadata.on_reload)(storage, ctx);
+  /// Replace resources failing to load by the defaults of their types – see
+  /// `Storage::register_default`.
+  ///
+  /// Instead of failing, getting a resource which loading fails then yields the default of its
+  /// type, which gets replaced by the actual resource once its source is fixed and it reloads – as
+  /// with `Storage::get_proxied`. Resources which type has no default still fail to load.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_fallback_to_defaults(self, fallback: bool) -> Self {
+    StoreOpt {
+      fallback_to_defaults: fallback,
+      ..self
+    }
+  }
 
-                  // reinject the dependency once afterwards
-                  storage.metadata.insert(dep, obs_metadata);
-                }
-              }
-            }
-          }
+  /// Check whether resources failing to load are replaced by the defaults of their types.
+  #[inline]
+  pub fn fallback_to_defaults(&self) -> bool {
+    self.fallback_to_defaults
+  }
 
-          storage.metadata.insert(dep_key.clone(), metadata);
-        }
+  /// Normalize the VFS paths of keys, so that keys written differently across platforms refer to
+  /// the same resource – see `PathNormalization`.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `PathNormalization::none()`.
+  #[inline]
+  pub fn set_path_normalization(self, path_normalization: PathNormalization) -> Self {
+    StoreOpt {
+      path_normalization,
+      ..self
+    }
+  }
 
-        false
-      } else {
-        true
-      }
-    });
+  /// Get the normalization of the VFS paths of keys.
+  #[inline]
+  pub fn path_normalization(&self) -> PathNormalization {
+    self.path_normalization
   }
 
-  /// Synchronize the `Storage` by updating the resources that ought to.
-  fn sync<C>(&mut self, storage: &mut Storage<C>, ctx: &mut C) {
-    self.dequeue_fs_events(storage);
-    self.reload_dirties(storage, ctx);
+  /// Set a hard cap, in bytes, on the memory footprint of all the resources.
+  ///
+  /// Loading a resource – or reloading it with a bigger value – that would exceed the budget fails
+  /// with `StoreError::BudgetExceeded` instead of growing the memory usage, which is what you want
+  /// on platforms where an overshoot means a crash later. The footprint of a resource is given by
+  /// `Load::memory_footprint`. Pass `None` to disable.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `None`.
+  #[inline]
+  pub fn set_memory_budget(self, budget: Option<usize>) -> Self {
+    StoreOpt {
+      memory_budget: budget,
+      ..self
+    }
   }
-}
 
-/// Resource store. Responsible for holding and presenting resources.
-pub struct Store<C> {
-  storage: Storage<C>,
-  synchronizer: Synchronizer,
-}
+  /// Get the memory budget, in bytes, if any.
+  #[inline]
+  pub fn memory_budget(&self) -> Option<usize> {
+    self.memory_budget
+  }
 
-impl<C> Store<C> {
-  /// Create a new store.
+  /// Count the hits and misses of the reads, per type of resource and label.
   ///
-  /// # Failures
+  /// The counts are available with `Storage::read_metrics`. See the `metrics` module for further
+  /// details.
   ///
-  /// This function will fail if the root path in the `StoreOpt` doesn’t resolve to a correct
-  /// canonicalized path.
-  pub fn new(opt: StoreOpt) -> Result<Self, StoreError> {
-    // canonicalize the root because some platforms won’t correctly report file changes otherwise
-    let root = &opt.root;
-    let canon_root = root
-      .canonicalize()
-      .map_err(|_| StoreError::RootDoesDotExit(root.to_owned()))?;
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_read_metrics(self, read_metrics: bool) -> Self {
+    StoreOpt {
+      read_metrics,
+      ..self
+    }
+  }
 
-    // create the mpsc channel to communicate with the file watcher
-    let (wsx, wrx) = channel();
-    let mut watcher = raw_watcher(wsx).unwrap();
+  /// Check whether the hits and misses of the reads are counted.
+  #[inline]
+  pub fn read_metrics(&self) -> bool {
+    self.read_metrics
+  }
 
-    // spawn a new thread in which we look for events
-    let _ = watcher.watch(&canon_root, RecursiveMode::Recursive);
+  /// Track the progress of the resources being loaded.
+  ///
+  /// Loaders report their progress with `Storage::load_progress` and it’s aggregated by
+  /// `Store::progress`. See the `progress` module for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_progress_tracking(self, progress_tracking: bool) -> Self {
+    StoreOpt {
+      progress_tracking,
+      ..self
+    }
+  }
 
-    // create the storage
-    let storage = Storage::new(canon_root);
+  /// Check whether the progress of the resources being loaded is tracked.
+  #[inline]
+  pub fn progress_tracking(&self) -> bool {
+    self.progress_tracking
+  }
 
-    // create the synchronizer
-    let synchronizer = Synchronizer::new(watcher, wrx, opt.update_await_time_ms);
+  /// Set a function called from the watcher thread each time a filesystem event is pending.
+  ///
+  /// Event-driven applications can use it to wake their event loop up – with a user event, for
+  /// instance – and only call `Store::sync` when there’s work to do, instead of on every frame. See
+  /// also `Store::sync_needed`.
+  ///
+  /// The waker isn’t called again once the update await time of the touched resources elapses:
+  /// schedule a wake-up at `Store::next_sync_deadline` after each synchronization.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no waker.
+  #[inline]
+  pub fn set_waker<F>(self, waker: F) -> Self
+  where F: 'static + Fn() + Send + Sync {
+    StoreOpt {
+      waker: Some(Arc::new(waker)),
+      ..self
+    }
+  }
 
-    let store = Store {
-      storage,
-      synchronizer,
-    };
+  /// Check whether a waker is set.
+  #[inline]
+  pub fn has_waker(&self) -> bool {
+    self.waker.is_some()
+  }
 
-    Ok(store)
+  /// Add a pattern of paths which filesystem events are ignored.
+  ///
+  /// Events about matching paths are dropped before any resource is marked dirty, so that swap
+  /// files, backups and version control churn don’t flood the `Store`. See the `ignore` module for
+  /// the pattern syntax.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `ignore::DEFAULT_IGNORE_PATTERNS`.
+  #[inline]
+  pub fn add_ignore_pattern<G>(mut self, glob: G) -> Self
+  where G: Into<String> {
+    self.ignore_patterns.push(glob.into());
+    self
   }
 
-  /// Synchronize the `Store` by updating the resources that ought to with a provided context.
-  pub fn sync(&mut self, ctx: &mut C) {
-    self.synchronizer.sync(&mut self.storage, ctx);
+  /// Remove all the ignore patterns, including the default ones.
+  #[inline]
+  pub fn clear_ignore_patterns(mut self) -> Self {
+    self.ignore_patterns.clear();
+    self
   }
-}
 
-impl<C> Deref for Store<C> {
-  type Target = Storage<C>;
+  /// Iterate over the ignore patterns.
+  #[inline]
+  pub fn ignore_patterns<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.ignore_patterns.iter().map(String::as_str)
+  }
 
-  fn deref(&self) -> &Self::Target {
-    &self.storage
+  /// Change how the watched directories are watched.
+  ///
+  /// Watching recursively a root containing a lot of unrelated data is expensive. With
+  /// `WatchMode::NonRecursive`, only the watched directories themselves are watched, and the
+  /// directories containing the resources actually requested – and their dependencies – are
+  /// watched lazily, as they’re loaded.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `WatchMode::Recursive`.
+  #[inline]
+  pub fn set_watch_mode(self, watch_mode: WatchMode) -> Self {
+    StoreOpt { watch_mode, ..self }
   }
-}
 
-impl<C> DerefMut for Store<C> {
-  fn deref_mut(&mut self) -> &mut Self::Target {
-    &mut self.storage
+  /// Get how the watched directories are watched.
+  #[inline]
+  pub fn watch_mode(&self) -> WatchMode {
+    self.watch_mode
   }
-}
 
-/// Various options to customize a `Store`.
-///
-/// Feel free to inspect all of its declared methods for further information.
-pub struct StoreOpt {
-  root: PathBuf,
-  update_await_time_ms: u64,
-}
+  /// Watch a directory, given by its VFS path, instead of the whole root.
+  ///
+  /// Whatever the directories watched, the directories containing the resources actually requested
+  /// are watched lazily if they’re not covered yet.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no directory, which watches the root.
+  #[inline]
+  pub fn add_watched_dir<P>(mut self, dir: P) -> Self
+  where P: AsRef<Path> {
+    self.watched_dirs.push(dir.as_ref().to_owned());
+    self
+  }
 
-impl Default for StoreOpt {
-  fn default() -> Self {
-    StoreOpt {
-      root: PathBuf::from("."),
-      update_await_time_ms: 50,
-    }
+  /// Iterate over the watched directories.
+  #[inline]
+  pub fn watched_dirs<'a>(&'a self) -> impl Iterator<Item = &'a Path> + 'a {
+    self.watched_dirs.iter().map(PathBuf::as_path)
   }
-}
 
-impl StoreOpt {
-  /// Change the update await time (milliseconds) used to determine whether a resource should be
-  /// reloaded or not.
+  /// Change the backend used to watch the filesystem.
   ///
-  /// A `Store` will wait that amount of time before deciding an resource should be reloaded after
-  /// it has changed on the filesystem. That is required in order to cope with write streaming, that
-  /// generates a lot of write event.
+  /// On NFS and some Docker volumes, the recommended backend never gets any event; use
+  /// `WatcherBackend::Poll` there so that the same code works in containerized environments.
   ///
   /// # Default
   ///
-  /// Defaults to `50` milliseconds.
+  /// Defaults to `WatcherBackend::Recommended`.
   #[inline]
-  pub fn set_update_await_time_ms(self, ms: u64) -> Self {
+  pub fn set_watcher_backend(self, watcher_backend: WatcherBackend) -> Self {
     StoreOpt {
-      update_await_time_ms: ms,
+      watcher_backend,
       ..self
     }
   }
 
-  /// Get the update await time (milliseconds).
+  /// Get the backend used to watch the filesystem.
   #[inline]
-  pub fn update_await_time_ms(&self) -> u64 {
-    self.update_await_time_ms
+  pub fn watcher_backend(&self) -> WatcherBackend {
+    self.watcher_backend
   }
 
-  /// Change the root directory from which the `Store` will be watching file changes.
+  /// Set a hook called with the context right before each synchronization pass.
+  ///
+  /// Use it for per-synchronization setup – beginning a GPU upload command buffer, entering a
+  /// profiling scope, etc. – instead of duplicating it in every `Load::reload` implementation.
   ///
   /// # Default
   ///
-  /// Defaults to `"."`.
+  /// Defaults to no hook.
   #[inline]
-  pub fn set_root<P>(self, root: P) -> Self
-  where P: AsRef<Path> {
+  pub fn set_before_sync<F>(self, f: F) -> Self
+  where F: 'static + Fn(&mut C) {
     StoreOpt {
-      root: root.as_ref().to_owned(),
+      before_sync: Some(Box::new(f)),
       ..self
     }
   }
 
-  /// Get root directory.
+  /// Set a hook called with the context right after each synchronization pass.
+  ///
+  /// This is the counterpart of `StoreOpt::set_before_sync`, handy to tear down what it set up.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no hook.
   #[inline]
-  pub fn root(&self) -> &Path {
-    &self.root
+  pub fn set_after_sync<F>(self, f: F) -> Self
+  where F: 'static + Fn(&mut C) {
+    StoreOpt {
+      after_sync: Some(Box::new(f)),
+      ..self
+    }
   }
 }
@@ -0,0 +1,389 @@
+//! Resource manifests and preflight checks.
+//!
+//! A `Manifest` is a list of resources you expect to be loadable, along with their types and
+//! loading methods. It can be checked at once with `Storage::preflight`, which attempts to load
+//! every declared resource and aggregates all the failures into a single `PreflightReport` – handy
+//! to validate release content in CI instead of discovering missing or corrupt assets one by one.
+//!
+//! A manifest can also be loaded in parallel at startup with `Storage::preload` – see the `preload`
+//! module.
+//!
+//! # Manifest files
+//!
+//! The list of resources can also come from content instead of code: a `ManifestFile` is a text
+//! file listing one VFS path per line, optionally prefixed with the name of the loader to use, a
+//! colon and a space – a colon alone doesn’t delimit a loader, so that paths may contain colons.
+//! Paths without a loader are loaded with the loader registered for their extension. Blank lines
+//! and lines starting with `#` are ignored:
+//!
+//! ```text
+//! # textures
+//! /textures/hero.png
+//! /textures/villain.png
+//!
+//! # models
+//! gltf-binary: /models/hero.glb
+//! ```
+//!
+//! `Storage::preload_manifest` loads a manifest file and every resource it lists through the
+//! `LoaderRegistry` of the `Storage`, and reports the outcome of each entry. The manifest file is a
+//! regular resource: editing it reloads it, which loads the newly listed resources.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use key::{DepKey, FSKey, PathNormalization};
+use load::{Load, Loaded, Storage, StoreErrorOr};
+use mount::Mounts;
+use preload::Prepare;
+use res::{AnyRes, Res};
+
+/// A list of resources to load.
+pub struct Manifest<C> {
+  pub(crate) entries: Vec<ManifestEntry<C>>,
+  pub(crate) threads: usize,
+  pub(crate) type_limits: HashMap<TypeId, usize>,
+}
+
+/// A single entry in a `Manifest`.
+pub(crate) struct ManifestEntry<C> {
+  pub(crate) key: DepKey,
+  pub(crate) type_id: TypeId,
+  // keys of the resources of the manifest this resource depends on
+  pub(crate) deps: Vec<DepKey>,
+  pub(crate) load: Box<Fn(&mut Storage<C>, &mut C) -> Result<(), String>>,
+  // split loading, for resources which loading can be partly done on worker threads
+  pub(crate) prepared: Option<PreparedEntry<C>>,
+}
+
+/// The half of the loading of a resource implementing `Prepare` that runs on worker threads, given
+/// the root, the path normalization and the mount points of the `Storage`.
+pub(crate) type PrepareFn =
+  Fn(&Path, PathNormalization, &Mounts) -> Result<Box<Any + Send>, String> + Send + Sync;
+
+/// The two halves of the loading of a resource implementing `Prepare`.
+pub(crate) struct PreparedEntry<C> {
+  pub(crate) prepare: Arc<PrepareFn>,
+  pub(crate) finish: Box<Fn(Box<Any + Send>, &mut Storage<C>, &mut C) -> Result<(), String>>,
+}
+
+impl<C> Manifest<C> {
+  /// Create an empty manifest.
+  pub fn new() -> Self {
+    Manifest {
+      entries: Vec::new(),
+      threads: 4,
+      type_limits: HashMap::new(),
+    }
+  }
+
+  /// Declare a resource of type `T` living at the given key, loaded with the default method.
+  pub fn add<K, T>(self, key: K) -> Self
+  where
+    T: Load<C>,
+    K: 'static + Clone + Into<T::Key>,
+  {
+    self.add_by::<K, T, ()>(key, ())
+  }
+
+  /// Declare a resource of type `T` living at the given key, loaded with the given method.
+  pub fn add_by<K, T, M>(mut self, key: K, method: M) -> Self
+  where
+    T: Load<C, M>,
+    K: 'static + Clone + Into<T::Key>,
+    M: 'static + Clone,
+  {
+    let dep_key = key.clone().into().into();
+
+    let load = move |storage: &mut Storage<C>, ctx: &mut C| {
+      storage
+        .get_by::<K, T, M>(&key, ctx, method.clone())
+        .map(|_| ())
+        .map_err(|e| match e {
+          StoreErrorOr::StoreError(e) => e.to_string(),
+          StoreErrorOr::ResError(e) => e.to_string(),
+        })
+    };
+
+    self.entries.push(ManifestEntry {
+      key: dep_key,
+      type_id: TypeId::of::<T>(),
+      deps: Vec::new(),
+      load: Box::new(load),
+      prepared: None,
+    });
+
+    self
+  }
+
+  /// Declare a resource of type `T` living at the given key, which loading can be partly done on
+  /// worker threads when preloading – see `Prepare`.
+  pub fn add_prepared<K, T>(self, key: K) -> Self
+  where
+    T: Prepare<C>,
+    K: 'static + Clone + Send + Sync + Into<T::Key>,
+  {
+    let key_ = key.clone();
+    let prepare = move |root: &Path,
+                        normalization: PathNormalization,
+                        mounts: &Mounts|
+          -> Result<Box<Any + Send>, String> {
+      let key = mounts.prepare_key(key_.clone().into(), root, normalization);
+      let prepared = T::prepare(&key).map_err(|e| e.to_string())?;
+      Ok(Box::new(prepared))
+    };
+
+    let key_ = key.clone();
+    let finish = move |prepared: Box<Any + Send>, storage: &mut Storage<C>, ctx: &mut C| {
+      let prepared = *prepared
+        .downcast::<T::Prepared>()
+        .map_err(|_| "mismatching prepared data".to_owned())?;
+
+      storage
+        .get_or_load::<K, T, (), _>(&key_, ctx, move |key, storage, ctx| {
+          T::finish(prepared, key, storage, ctx)
+        })
+        .map(|_| ())
+        .map_err(|e| match e {
+          StoreErrorOr::StoreError(e) => e.to_string(),
+          StoreErrorOr::ResError(e) => e.to_string(),
+        })
+    };
+
+    let mut manifest = self.add::<K, T>(key);
+
+    if let Some(entry) = manifest.entries.last_mut() {
+      entry.prepared = Some(PreparedEntry {
+        prepare: Arc::new(prepare),
+        finish: Box::new(finish),
+      });
+    }
+
+    manifest
+  }
+
+  /// Declare that the resource living at `key` depends on the one living at `dep`, both declared
+  /// in the manifest.
+  ///
+  /// When preloading, dependencies are loaded before their dependent resources. Declaring them is
+  /// not needed for correctness – a composite loader loads its dependencies anyway – but it keeps
+  /// them from being loaded on the thread owning the `Storage` while they could be prepared on
+  /// worker threads.
+  pub fn add_dep<K, D>(mut self, key: K, dep: D) -> Self
+  where
+    K: Into<DepKey>,
+    D: Into<DepKey>,
+  {
+    let key = key.into();
+
+    if let Some(entry) = self.entries.iter_mut().find(|entry| entry.key == key) {
+      entry.deps.push(dep.into());
+    }
+
+    self
+  }
+
+  /// Change the number of worker threads used when preloading.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `4`.
+  pub fn set_preload_threads(self, threads: usize) -> Self {
+    Manifest {
+      threads: threads.max(1),
+      ..self
+    }
+  }
+
+  /// Limit how many resources of type `T` are prepared at once when preloading – to cap the memory
+  /// used by decoding buffers, for instance.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no limit, other than the number of worker threads.
+  pub fn set_type_limit<T>(mut self, limit: usize) -> Self
+  where T: 'static {
+    self.type_limits.insert(TypeId::of::<T>(), limit.max(1));
+    self
+  }
+
+  /// Number of resources declared in the manifest.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Whether the manifest declares no resource.
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
+
+impl<C> Default for Manifest<C> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Report of a preflight check.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PreflightReport {
+  /// Number of resources that were successfully loaded.
+  pub loaded: usize,
+  /// All the failures that occurred.
+  pub failures: Vec<PreflightFailure>,
+}
+
+impl PreflightReport {
+  /// Whether all the resources were successfully loaded.
+  pub fn is_ok(&self) -> bool {
+    self.failures.is_empty()
+  }
+}
+
+/// A resource that failed to load during a preflight check.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PreflightFailure {
+  /// Key of the resource, as declared in the manifest.
+  pub key: DepKey,
+  /// Error message.
+  pub error: String,
+}
+
+impl fmt::Display for PreflightFailure {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}: {}", self.key, self.error)
+  }
+}
+
+impl<C> Storage<C> {
+  /// Attempt to load every resource declared in a manifest and report all the failures at once.
+  ///
+  /// Successfully loaded resources stay in the `Storage`.
+  pub fn preflight(&mut self, manifest: &Manifest<C>, ctx: &mut C) -> PreflightReport {
+    let mut report = PreflightReport::default();
+
+    for entry in &manifest.entries {
+      match (entry.load)(self, ctx) {
+        Ok(_) => report.loaded += 1,
+        Err(error) => report.failures.push(PreflightFailure {
+          key: entry.key.clone(),
+          error,
+        }),
+      }
+    }
+
+    report
+  }
+}
+
+/// A manifest file, along with the resources it lists.
+///
+/// See the documentation of the `manifest` module for further details.
+#[derive(Debug)]
+pub struct ManifestFile {
+  entries: Vec<ManifestFileEntry>,
+}
+
+/// An entry of a `ManifestFile`, along with the outcome of its loading.
+#[derive(Debug)]
+pub struct ManifestFileEntry {
+  /// VFS path of the resource.
+  pub path: PathBuf,
+  /// Name of the loader to use, if given; the loader registered for the extension of the path is
+  /// used otherwise.
+  pub loader: Option<String>,
+  /// The loaded resource, or the error message if it failed to load.
+  pub outcome: Result<AnyRes, String>,
+}
+
+impl ManifestFile {
+  /// All the entries of the manifest file, in the order they’re listed.
+  pub fn entries(&self) -> &[ManifestFileEntry] {
+    &self.entries
+  }
+
+  /// The entries that failed to load.
+  pub fn failures<'a>(&'a self) -> impl Iterator<Item = &'a ManifestFileEntry> + 'a {
+    self.entries.iter().filter(|entry| entry.outcome.is_err())
+  }
+
+  /// Whether all the entries were successfully loaded.
+  pub fn is_ok(&self) -> bool {
+    self.failures().next().is_none()
+  }
+
+  /// Parse the lines of a manifest file into pairs of paths and optional loader names.
+  fn parse(source: &str) -> Vec<(PathBuf, Option<String>)> {
+    source
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .map(|line| match line.find(": ") {
+        Some(colon) => {
+          let loader = line[..colon].trim().to_owned();
+          (PathBuf::from(line[colon + 2..].trim()), Some(loader))
+        }
+
+        None => (PathBuf::from(line), None),
+      })
+      .collect()
+  }
+}
+
+impl<C> Load<C> for ManifestFile
+where C: 'static
+{
+  type Key = FSKey;
+
+  type Error = io::Error;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let source = storage.read_to_string(key.as_path())?;
+
+    let entries = ManifestFile::parse(&source)
+      .into_iter()
+      .map(|(path, loader)| {
+        let outcome = match loader {
+          Some(ref loader) => storage.get_any(loader, &FSKey::new(&path).into(), ctx),
+          None => storage.get_any_path(&path, ctx),
+        };
+
+        ManifestFileEntry {
+          path,
+          loader,
+          outcome: outcome.map_err(|e| e.to_string()),
+        }
+      })
+      .collect();
+
+    Ok(ManifestFile { entries }.into())
+  }
+}
+
+impl<C> Storage<C>
+where C: 'static
+{
+  /// Load a manifest file and every resource it lists, and report the outcome of each entry.
+  ///
+  /// The resources are loaded with the loaders of the `LoaderRegistry` of the `Storage`. The
+  /// manifest file is watched like any other resource: when it changes, the newly listed resources
+  /// get loaded and the report is updated. See the documentation of the `manifest` module for
+  /// further details.
+  pub fn preload_manifest(
+    &mut self,
+    key: &FSKey,
+    ctx: &mut C,
+  ) -> Result<Res<ManifestFile>, StoreErrorOr<ManifestFile, C>>
+  {
+    self.get(key, ctx)
+  }
+}
@@ -0,0 +1,253 @@
+//! Directories of resources loaded as a single map.
+//!
+//! A `ResourceMap<T>` is a resource holding every file living under a directory – recursively,
+//! optionally filtered by extension – loaded as `T`. Each file is a resource of its own living in
+//! the `Storage`, so that modifying a file only reloads that file. Creating or removing files makes
+//! the map reload, which only loads the new files and evicts the removed ones; the other entries
+//! are left untouched.
+//!
+//! The map is loaded with the same method as its entries:
+//!
+//! ```ignore
+//! let key = ResourceMapKey::new("/configs").with_extension("json");
+//! let configs: Res<ResourceMap<Config>> = store.get_by(&key, ctx, Json)?;
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use key::{DepKey, FSKey, Key, PathNormalization};
+use load::{Load, Loaded, Storage, StoreError, StoreErrorOr};
+use mount::Mounts;
+use res::Res;
+
+/// Key of a `ResourceMap`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ResourceMapKey {
+  dir: FSKey,
+  extension: Option<String>,
+}
+
+impl ResourceMapKey {
+  /// Create a key selecting all the files living under a directory, given by its VFS path.
+  pub fn new<P>(dir: P) -> Self
+  where P: AsRef<Path> {
+    ResourceMapKey {
+      dir: FSKey::new(dir),
+      extension: None,
+    }
+  }
+
+  /// Only select files with the given extension.
+  pub fn with_extension<E>(self, extension: E) -> Self
+  where E: Into<String> {
+    ResourceMapKey {
+      extension: Some(extension.into()),
+      ..self
+    }
+  }
+
+  /// The directory the files are selected under.
+  pub fn dir(&self) -> &Path {
+    self.dir.as_path()
+  }
+
+  /// The extension files must have, if any.
+  pub fn extension(&self) -> Option<&str> {
+    self.extension.as_ref().map(String::as_str)
+  }
+
+  /// Check whether a file is selected by the key.
+  fn selects(&self, path: &Path) -> bool {
+    match self.extension {
+      Some(ref extension) => path.extension().map_or(false, |ext| ext == extension.as_str()),
+      None => true,
+    }
+  }
+}
+
+impl From<FSKey> for ResourceMapKey {
+  fn from(dir: FSKey) -> Self {
+    ResourceMapKey {
+      dir,
+      extension: None,
+    }
+  }
+}
+
+impl From<ResourceMapKey> for DepKey {
+  fn from(key: ResourceMapKey) -> Self {
+    key.dir.into()
+  }
+}
+
+impl Key for ResourceMapKey {
+  fn prepare_key(self, root: &Path) -> Self {
+    ResourceMapKey {
+      dir: self.dir.prepare_key(root),
+      ..self
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    ResourceMapKey {
+      dir: self.dir.prepare_key_with(root, normalization),
+      ..self
+    }
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    ResourceMapKey {
+      dir: self.dir.mount_key(mounts),
+      ..self
+    }
+  }
+}
+
+/// A map of resources living under a directory.
+///
+/// Entries are indexed by the VFS path of their file – e.g. `/configs/player.json`.
+pub struct ResourceMap<T> {
+  entries: HashMap<FSKey, Res<T>>,
+}
+
+impl<T> ResourceMap<T> {
+  /// Get the entry living at a given VFS path.
+  pub fn get(&self, key: &FSKey) -> Option<&Res<T>> {
+    self.entries.get(key)
+  }
+
+  /// Iterate over all the entries.
+  pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a FSKey, &'a Res<T>)> + 'a {
+    self.entries.iter()
+  }
+
+  /// Number of entries.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Whether the map has no entry.
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
+
+/// Error that might occur while loading a `ResourceMap`.
+#[derive(Debug)]
+pub enum ResourceMapError<E> {
+  /// The directory couldn’t be read.
+  Io(io::Error),
+  /// An entry couldn’t be registered in the `Storage`.
+  Store(PathBuf, StoreError),
+  /// An entry failed to load.
+  Entry(PathBuf, E),
+}
+
+impl<E> fmt::Display for ResourceMapError<E>
+where E: Error
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      ResourceMapError::Io(ref e) => write!(f, "I/O error: {}", e),
+      ResourceMapError::Store(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+      ResourceMapError::Entry(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+    }
+  }
+}
+
+impl<E> Error for ResourceMapError<E>
+where E: Error
+{
+  fn description(&self) -> &str {
+    match *self {
+      ResourceMapError::Io(_) => "I/O error",
+      ResourceMapError::Store(..) => "store error",
+      ResourceMapError::Entry(..) => "entry error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      ResourceMapError::Io(ref e) => Some(e),
+      ResourceMapError::Store(_, ref e) => Some(e),
+      ResourceMapError::Entry(_, ref e) => Some(e),
+    }
+  }
+}
+
+impl<C, T, M> Load<C, M> for ResourceMap<T>
+where
+  T: Load<C, M, Key = FSKey>,
+  M: 'static,
+{
+  type Key = ResourceMapKey;
+
+  type Error = ResourceMapError<T::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let mut paths = Vec::new();
+    find_files(key.dir(), &mut paths).map_err(ResourceMapError::Io)?;
+
+    let mut entries = HashMap::new();
+
+    for path in paths.into_iter().filter(|path| key.selects(path)) {
+      let fs_key = storage.file_key(&path);
+
+      // entries already living in the storage are just looked up
+      let res = storage
+        .get_with::<FSKey, T, M>(&fs_key, ctx)
+        .map_err(|e| match e {
+          StoreErrorOr::StoreError(e) => ResourceMapError::Store(path, e),
+          StoreErrorOr::ResError(e) => ResourceMapError::Entry(path, e),
+        })?;
+
+      entries.insert(fs_key, res);
+    }
+
+    Ok(ResourceMap { entries }.into())
+  }
+
+  fn reload(
+    &self,
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error>
+  {
+    let map = <Self as Load<C, M>>::load(key, storage, ctx)?.res;
+
+    // evict the entries which files are gone
+    for fs_key in self.entries.keys() {
+      if !map.entries.contains_key(fs_key) {
+        storage.evict(&fs_key.clone().into());
+      }
+    }
+
+    Ok(map)
+  }
+}
+
+/// Recursively find all the files living under a directory.
+pub(crate) fn find_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+
+    if path.is_dir() {
+      find_files(&path, paths)?;
+    } else {
+      paths.push(path);
+    }
+  }
+
+  Ok(())
+}
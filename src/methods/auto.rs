@@ -0,0 +1,120 @@
+//! Format dispatch by file extension.
+
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{Auto, FormatError};
+
+/// Error that might occur while decoding a resource with the `Auto` method.
+#[derive(Debug)]
+pub enum AutoError {
+  /// The extension of the file doesn’t match any enabled format.
+  UnsupportedExtension(String),
+  /// JSON decoding error.
+  #[cfg(feature = "serde-json")]
+  Json(::serde_json::Error),
+  /// RON decoding error.
+  #[cfg(feature = "serde-ron")]
+  Ron(::ron::de::Error),
+  /// TOML decoding error.
+  #[cfg(feature = "serde-toml")]
+  Toml(::toml::de::Error),
+  /// YAML decoding error.
+  #[cfg(feature = "serde-yaml")]
+  Yaml(::serde_yaml::Error),
+}
+
+impl fmt::Display for AutoError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      AutoError::UnsupportedExtension(ref ext) => write!(f, "unsupported extension: {:?}", ext),
+      #[cfg(feature = "serde-json")]
+      AutoError::Json(ref e) => write!(f, "JSON error: {}", e),
+      #[cfg(feature = "serde-ron")]
+      AutoError::Ron(ref e) => write!(f, "RON error: {}", e),
+      #[cfg(feature = "serde-toml")]
+      AutoError::Toml(ref e) => write!(f, "TOML error: {}", e),
+      #[cfg(feature = "serde-yaml")]
+      AutoError::Yaml(ref e) => write!(f, "YAML error: {}", e),
+    }
+  }
+}
+
+impl Error for AutoError {
+  fn description(&self) -> &str {
+    match *self {
+      AutoError::UnsupportedExtension(_) => "unsupported extension",
+      #[cfg(feature = "serde-json")]
+      AutoError::Json(_) => "JSON error",
+      #[cfg(feature = "serde-ron")]
+      AutoError::Ron(_) => "RON error",
+      #[cfg(feature = "serde-toml")]
+      AutoError::Toml(_) => "TOML error",
+      #[cfg(feature = "serde-yaml")]
+      AutoError::Yaml(_) => "YAML error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      AutoError::UnsupportedExtension(_) => None,
+      #[cfg(feature = "serde-json")]
+      AutoError::Json(ref e) => Some(e),
+      #[cfg(feature = "serde-ron")]
+      AutoError::Ron(ref e) => Some(e),
+      #[cfg(feature = "serde-toml")]
+      AutoError::Toml(ref e) => Some(e),
+      #[cfg(feature = "serde-yaml")]
+      AutoError::Yaml(ref e) => Some(e),
+    }
+  }
+}
+
+/// Decode bytes according to a file extension.
+fn decode<T>(ext: &str, bytes: &[u8]) -> Result<T, AutoError>
+where T: DeserializeOwned {
+  match ext {
+    #[cfg(feature = "serde-json")]
+    "json" => ::serde_json::from_slice(bytes).map_err(AutoError::Json),
+    #[cfg(feature = "serde-ron")]
+    "ron" => ::ron::de::from_bytes(bytes).map_err(AutoError::Ron),
+    #[cfg(feature = "serde-toml")]
+    "toml" => ::toml::from_slice(bytes).map_err(AutoError::Toml),
+    #[cfg(feature = "serde-yaml")]
+    "yaml" | "yml" => ::serde_yaml::from_slice(bytes).map_err(AutoError::Yaml),
+    _ => {
+      let _ = bytes;
+      Err(AutoError::UnsupportedExtension(ext.to_owned()))
+    }
+  }
+}
+
+impl<C, T> Load<C, Auto> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<AutoError>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let ext = key
+      .as_path()
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .unwrap_or("")
+      .to_lowercase();
+
+    let bytes = storage.read(key.as_path())?;
+    let res = decode(&ext, &bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
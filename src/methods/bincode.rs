@@ -0,0 +1,28 @@
+//! Bincode loading, via serde.
+
+use bincode;
+use serde::de::DeserializeOwned;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, Bincode};
+
+impl<C, T> Load<C, Bincode> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<bincode::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let res = bincode::deserialize(&bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
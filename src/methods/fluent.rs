@@ -0,0 +1,231 @@
+//! Localization bundles loading, via the fluent-bundle crate.
+
+use fluent_bundle::{FluentBundle, FluentResource};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use unic_langid::LanguageIdentifier;
+
+use key::{vfs_substite_path, DepKey, DirKey, ErasedKey, FSKey, Key, PathNormalization};
+use load::{Load, Loaded, Storage, StoreError, StoreErrorOr};
+use methods::Fluent;
+use mount::Mounts;
+
+/// The source of the file of a locale.
+///
+/// Files are loaded as resources of their own and declared as dependencies of the bundles of the
+/// chains they’re part of, so that editing a file reloads all of them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FluentSource(pub String);
+
+impl<C> Load<C, Fluent> for FluentSource {
+  type Key = FSKey;
+
+  type Error = io::Error;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let source = storage.read_to_string(key.as_path())?;
+    Ok(FluentSource(source).into())
+  }
+}
+
+/// Key of a localization bundle, made of the same file taken in a chain of locales.
+///
+/// The file of a locale lives at `<dir>/<locale>/<file>`. Locales are sorted from the most
+/// preferred one to the last resort fallback: messages missing in a locale are taken from the next
+/// ones of the chain. Locales which file doesn’t exist are skipped; the bundle then depends on the
+/// whole `<dir>` directory, so that it reloads once their files get created.
+///
+/// ```ignore
+/// let key = FluentKey::new("/locales", "main.ftl", &["fr-CA", "fr", "en-US"]);
+/// let bundle: Res<FluentBundle<FluentResource>> = store.get_by(&key, ctx, Fluent)?;
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FluentKey {
+  dir: PathBuf,
+  file: PathBuf,
+  locales: Vec<String>,
+}
+
+impl FluentKey {
+  /// Create a new `FluentKey` by providing the VFS path of the directory holding a directory per
+  /// locale – see `FSKey::new` – the path of the file relative to those, and the chain of locales.
+  pub fn new<D, F, L>(dir: D, file: F, locales: &[L]) -> Self
+  where
+    D: AsRef<Path>,
+    F: AsRef<Path>,
+    L: AsRef<str>,
+  {
+    FluentKey {
+      dir: dir.as_ref().to_owned(),
+      file: file.as_ref().to_owned(),
+      locales: locales.iter().map(|locale| locale.as_ref().to_owned()).collect(),
+    }
+  }
+
+  /// Chain of locales, from the most preferred one to the last resort fallback.
+  pub fn locales(&self) -> &[String] {
+    &self.locales
+  }
+
+  /// Path of the file of a given locale.
+  pub fn path(&self, locale: &str) -> PathBuf {
+    self.dir.join(locale).join(&self.file)
+  }
+}
+
+impl From<FluentKey> for DepKey {
+  fn from(key: FluentKey) -> Self {
+    DepKey::Typed(ErasedKey::new(key))
+  }
+}
+
+impl Key for FluentKey {
+  fn prepare_key(self, root: &Path) -> Self {
+    FluentKey {
+      dir: vfs_substite_path(&self.dir, root),
+      ..self
+    }
+  }
+
+  fn prepare_key_with(self, root: &Path, normalization: PathNormalization) -> Self {
+    let key = FluentKey {
+      dir: normalization.apply(&self.dir),
+      file: normalization.apply(&self.file),
+      ..self
+    };
+
+    key.prepare_key(root)
+  }
+
+  fn mount_key(self, mounts: &Mounts) -> Self {
+    FluentKey {
+      dir: mounts.route(&self.dir),
+      ..self
+    }
+  }
+}
+
+impl<C> Load<C, Fluent> for FluentBundle<FluentResource> {
+  type Key = FluentKey;
+
+  type Error = LocalizationError;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let langids = key
+      .locales
+      .iter()
+      .map(|locale| {
+        locale
+          .parse::<LanguageIdentifier>()
+          .map_err(|_| LocalizationError::Locale(locale.clone()))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let mut bundle = FluentBundle::new(langids);
+    let mut deps = Vec::new();
+    let mut missing = false;
+
+    // fallbacks first, so that the messages of the preferred locales override theirs
+    for locale in key.locales.iter().rev() {
+      let path = key.path(locale);
+      let fs_key = FSKey::absolute(&path);
+
+      let source = match storage.get_by::<FSKey, FluentSource, Fluent>(&fs_key, ctx, Fluent) {
+        Ok(source) => source.borrow().0.clone(),
+        Err(StoreErrorOr::ResError(ref e)) if e.kind() == io::ErrorKind::NotFound => {
+          missing = true;
+          continue;
+        }
+        Err(StoreErrorOr::ResError(e)) => return Err(LocalizationError::Io(path, e)),
+        Err(StoreErrorOr::StoreError(e)) => return Err(LocalizationError::Store(path, e)),
+      };
+
+      let resource = FluentResource::try_new(source).map_err(|(_, errors)| {
+        let errors = errors.iter().map(|e| e.to_string()).collect();
+        LocalizationError::Parse(path.clone(), errors)
+      })?;
+
+      bundle.add_resource_overriding(resource);
+      deps.push(fs_key.into());
+    }
+
+    if deps.is_empty() {
+      return Err(LocalizationError::NoResource(key));
+    }
+
+    // missing files have no resource to depend on; their directories might not exist either
+    if missing {
+      let dir = storage.vfs_path(&key.dir).unwrap_or_else(|| key.dir.clone());
+
+      deps.push(DirKey::new(dir).into());
+    }
+
+    Ok(Loaded::with_deps(bundle, deps))
+  }
+}
+
+/// Error that might occur while loading a localization bundle.
+#[derive(Debug)]
+pub enum LocalizationError {
+  /// A locale of the chain isn’t a valid language identifier.
+  Locale(String),
+  /// The file of a locale couldn’t be read.
+  Io(PathBuf, io::Error),
+  /// The file of a locale couldn’t be stored.
+  Store(PathBuf, StoreError),
+  /// The file of a locale isn’t valid Fluent; all the syntax errors are reported.
+  Parse(PathBuf, Vec<String>),
+  /// No locale of the chain has a file.
+  NoResource(FluentKey),
+}
+
+impl fmt::Display for LocalizationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      LocalizationError::Locale(ref locale) => write!(f, "invalid locale: {}", locale),
+      LocalizationError::Io(ref path, ref e) => write!(f, "{}: I/O error: {}", path.display(), e),
+      LocalizationError::Store(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+      LocalizationError::Parse(ref path, ref errors) => {
+        write!(f, "{}: syntax errors: {}", path.display(), errors.join("; "))
+      }
+      LocalizationError::NoResource(ref key) => write!(
+        f,
+        "no file for {} in any of the locales {}",
+        key.file.display(),
+        key.locales.join(", ")
+      ),
+    }
+  }
+}
+
+impl Error for LocalizationError {
+  fn description(&self) -> &str {
+    match *self {
+      LocalizationError::Locale(_) => "invalid locale",
+      LocalizationError::Io(..) => "I/O error",
+      LocalizationError::Store(..) => "store error",
+      LocalizationError::Parse(..) => "syntax errors",
+      LocalizationError::NoResource(_) => "no localization resource",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      LocalizationError::Io(_, ref e) => Some(e),
+      LocalizationError::Store(_, ref e) => Some(e),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,50 @@
+//! Image loading, via the image crate.
+
+use image::{self, ImageError, ImageFormat, RgbaImage};
+use std::path::Path;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, ImageRgba8};
+
+impl<C> Load<C, ImageRgba8> for RgbaImage {
+  type Key = FSKey;
+
+  type Error = FormatError<ImageError>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+
+    // formats without magic bytes – TGA, for instance – can only be told apart by their extension
+    let image = match format_from_path(key.as_path()) {
+      Some(format) => image::load_from_memory_with_format(&bytes, format),
+      None => image::load_from_memory(&bytes),
+    };
+
+    Ok(Loaded::without_dep(image.map_err(FormatError::Format)?.to_rgba()))
+  }
+}
+
+/// Guess the format of an image from the extension of its path, if known.
+fn format_from_path(path: &Path) -> Option<ImageFormat> {
+  let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+  match extension.as_str() {
+    "jpg" | "jpeg" => Some(ImageFormat::JPEG),
+    "png" => Some(ImageFormat::PNG),
+    "gif" => Some(ImageFormat::GIF),
+    "webp" => Some(ImageFormat::WEBP),
+    "tif" | "tiff" => Some(ImageFormat::TIFF),
+    "tga" => Some(ImageFormat::TGA),
+    "bmp" => Some(ImageFormat::BMP),
+    "ico" => Some(ImageFormat::ICO),
+    "hdr" => Some(ImageFormat::HDR),
+    "pbm" | "pam" | "ppm" | "pgm" => Some(ImageFormat::PNM),
+    _ => None,
+  }
+}
@@ -0,0 +1,71 @@
+//! JSON loading, via serde.
+
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, Json, VersionedJson};
+use migrate::{Versioned, VersionedError};
+
+impl<C, T> Load<C, Json> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<serde_json::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let res = serde_json::from_slice(&bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
+
+impl<C, T> Load<C, VersionedJson> for T
+where T: 'static + DeserializeOwned + Versioned<Value>
+{
+  type Key = FSKey;
+
+  type Error = FormatError<VersionedError<serde_json::Error, T::Error>>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let doc: Value = serde_json::from_slice(&bytes)
+      .map_err(|e| FormatError::Format(VersionedError::Format(e)))?;
+
+    // documents predating versioning don’t have any version field
+    let version = match doc.get("version") {
+      Some(version) => version
+        .as_u64()
+        .filter(|&version| version <= u64::from(u32::max_value()))
+        .ok_or(FormatError::Format(VersionedError::InvalidVersion))? as u32,
+      None => 0,
+    };
+
+    let migrations = T::migrations();
+    let mut doc = migrations
+      .migrate(version, doc)
+      .map_err(|e| FormatError::Format(VersionedError::Migration(e)))?;
+
+    if let Some(fields) = doc.as_object_mut() {
+      fields.insert("version".to_owned(), migrations.current_version().into());
+    }
+
+    let res = serde_json::from_value(doc)
+      .map_err(|e| FormatError::Format(VersionedError::Format(e)))?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
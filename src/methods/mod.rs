@@ -0,0 +1,187 @@
+//! Recognized methods by warmy.
+//!
+//! > Disclaimer: those methods are just there for indications for now. It’s very likely that they
+//! > will get moved in separate crates when time comes.
+//!
+//! Some methods come with blanket implementations of `Load` when their matching feature is enabled:
+//!
+//!   - `Auto`, with any of the `serde-json`, `serde-ron`, `serde-toml` or `serde-yaml` features:
+//!     dispatches to one of the enabled text formats according to the extension of the file
+//!     (`json`, `ron`, `toml`, `yaml` or `yml`), so that a same type can be authored in any of them.
+//!   - `Bincode`, with the `serde-bincode` feature: any type implementing
+//!     `serde::de::DeserializeOwned` can be loaded from a bincode-encoded file.
+//!   - `Fluent`, with the `fluent` feature: a `fluent_bundle::FluentBundle` can be loaded from the
+//!     `.ftl` files of a chain of locales – see `FluentKey`. Editing the file of any locale of the
+//!     chain reloads the bundle, so that translators see their edits live.
+//!   - `ImageRgba8`, with the `image` feature: PNG, JPEG, TGA and the other formats supported by
+//!     the image crate can be loaded into an `image::RgbaImage`.
+//!   - `Json`, with the `serde-json` feature: any type implementing `serde::de::DeserializeOwned`
+//!     can be loaded from a JSON file.
+//!   - `MessagePack`, with the `serde-msgpack` feature: same thing, from a MessagePack file.
+//!   - `Ron`, with the `serde-ron` feature: same thing, from a RON file.
+//!   - `Sniff`: dispatches to one of the candidates of a type implementing `Sniffable` according to
+//!     the content of the file – typically, its magic bytes – so that files can be renamed freely,
+//!     like the hashed file names of packs.
+//!   - `Toml`, with the `serde-toml` feature: same thing, from a TOML file.
+//!   - `VersionedJson`, with the `serde-json` feature: same as `Json`, for types implementing
+//!     `migrate::Versioned`: documents authored against an older version are migrated first.
+//!   - `Yaml`, with the `serde-yaml` feature: same thing, from a YAML file.
+//!
+//! A couple of resource types are also built in, loadable with the default method in any context,
+//! for the common case of grabbing the raw content of a file:
+//!
+//!   - `Bytes`: the raw bytes of a file.
+//!   - `Text`: the content of a UTF-8 text file.
+//!
+//! With the `derive` feature, `#[derive(Load)]` along with a `#[warmy(format = "…")]` attribute
+//! implements `Load<C>` – the default method – by delegating to one of those methods.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[cfg(any(
+  feature = "serde-json",
+  feature = "serde-ron",
+  feature = "serde-toml",
+  feature = "serde-yaml"
+))]
+mod auto;
+#[cfg(feature = "serde-bincode")]
+mod bincode;
+#[cfg(feature = "fluent")]
+mod fluent;
+#[cfg(feature = "image")]
+mod image;
+#[cfg(feature = "serde-json")]
+mod json;
+#[cfg(feature = "serde-msgpack")]
+mod msgpack;
+mod raw;
+#[cfg(feature = "serde-ron")]
+mod ron;
+mod sniff;
+#[cfg(feature = "serde-toml")]
+mod toml;
+#[cfg(feature = "serde-yaml")]
+mod yaml;
+
+/// Automatic method, picking a format according to the extension of the file.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Auto;
+
+/// Bincode method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Bincode;
+
+/// Fluent localization method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Fluent;
+
+/// RGBA image method, with 8 bits per channel.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ImageRgba8;
+
+/// JSON method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Json;
+
+/// JSON method.
+#[deprecated(note = "use Json instead")]
+pub type JSON = Json;
+
+/// JSON method.
+#[deprecated(note = "use Json instead")]
+pub const JSON: Json = Json;
+
+/// MessagePack method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MessagePack;
+
+/// RON method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Ron;
+
+/// Automatic method, picking a candidate according to the content of the file.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Sniff;
+
+/// TOML method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Toml;
+
+/// JSON method, migrating documents authored against an older version.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VersionedJson;
+
+/// YAML method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Yaml;
+
+/// YAML method.
+#[deprecated(note = "use Yaml instead")]
+pub type YAML = Yaml;
+
+/// YAML method.
+#[deprecated(note = "use Yaml instead")]
+pub const YAML: Yaml = Yaml;
+
+/// XML method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct XML;
+
+#[cfg(any(
+  feature = "serde-json",
+  feature = "serde-ron",
+  feature = "serde-toml",
+  feature = "serde-yaml"
+))]
+pub use self::auto::AutoError;
+#[cfg(feature = "fluent")]
+pub use self::fluent::{FluentKey, FluentSource, LocalizationError};
+pub use self::raw::{Bytes, Text};
+pub use self::sniff::{Candidate, SniffError, Sniffable};
+
+/// Error that might occur while loading a resource encoded with a given format.
+#[derive(Debug)]
+pub enum FormatError<E> {
+  /// The resource couldn’t be read.
+  Io(io::Error),
+  /// The resource couldn’t be decoded.
+  Format(E),
+}
+
+impl<E> fmt::Display for FormatError<E>
+where E: Error
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      FormatError::Io(ref e) => write!(f, "I/O error: {}", e),
+      FormatError::Format(ref e) => write!(f, "format error: {}", e),
+    }
+  }
+}
+
+impl<E> Error for FormatError<E>
+where E: Error
+{
+  fn description(&self) -> &str {
+    match *self {
+      FormatError::Io(_) => "I/O error",
+      FormatError::Format(_) => "format error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      FormatError::Io(ref e) => Some(e),
+      FormatError::Format(ref e) => Some(e),
+    }
+  }
+}
+
+impl<E> From<io::Error> for FormatError<E> {
+  fn from(e: io::Error) -> Self {
+    FormatError::Io(e)
+  }
+}
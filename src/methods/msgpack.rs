@@ -0,0 +1,28 @@
+//! MessagePack loading, via serde.
+
+use rmp_serde;
+use serde::de::DeserializeOwned;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, MessagePack};
+
+impl<C, T> Load<C, MessagePack> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<rmp_serde::decode::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let res = rmp_serde::from_slice(&bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
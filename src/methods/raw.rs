@@ -0,0 +1,54 @@
+//! Raw file contents.
+
+use std::io;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+
+/// The raw bytes of a file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl<C> Load<C> for Bytes {
+  type Key = FSKey;
+
+  type Error = io::Error;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    Ok(Bytes(bytes).into())
+  }
+
+  fn memory_footprint(&self) -> usize {
+    self.0.len()
+  }
+}
+
+/// The content of a UTF-8 text file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Text(pub String);
+
+impl<C> Load<C> for Text {
+  type Key = FSKey;
+
+  type Error = io::Error;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let text = storage.read_to_string(key.as_path())?;
+    Ok(Text(text).into())
+  }
+
+  fn memory_footprint(&self) -> usize {
+    self.0.len()
+  }
+}
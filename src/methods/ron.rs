@@ -0,0 +1,28 @@
+//! RON loading, via serde.
+
+use ron;
+use serde::de::DeserializeOwned;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, Ron};
+
+impl<C, T> Load<C, Ron> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<ron::de::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let res = ron::de::from_bytes(&bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
@@ -0,0 +1,120 @@
+//! Format dispatch by content.
+
+use std::error::Error;
+use std::fmt;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, Sniff};
+
+/// Types that can be loaded with the `Sniff` method.
+///
+/// The content of the file is matched against the candidates in order; the first candidate
+/// recognizing it decodes it.
+pub trait Sniffable: 'static + Sized {
+  /// Candidates to load the type with.
+  fn candidates() -> Vec<Candidate<Self>>;
+}
+
+/// A way to decode a type, along with a predicate recognizing the content it can decode.
+pub struct Candidate<T> {
+  name: &'static str,
+  sniff: Box<Fn(&[u8]) -> bool>,
+  decode: fn(&[u8]) -> Result<T, Box<Error>>,
+}
+
+impl<T> Candidate<T> {
+  /// Create a candidate recognizing content with a predicate.
+  ///
+  /// `name` is used to report decoding errors.
+  pub fn new<S>(
+    name: &'static str,
+    sniff: S,
+    decode: fn(&[u8]) -> Result<T, Box<Error>>,
+  ) -> Self
+  where
+    S: 'static + Fn(&[u8]) -> bool,
+  {
+    Candidate {
+      name,
+      sniff: Box::new(sniff),
+      decode,
+    }
+  }
+
+  /// Create a candidate recognizing content starting with the given magic bytes – e.g.
+  /// `b"\x89PNG\r\n\x1a\n"` for PNG.
+  pub fn with_magic(
+    name: &'static str,
+    magic: &'static [u8],
+    decode: fn(&[u8]) -> Result<T, Box<Error>>,
+  ) -> Self
+  {
+    Self::new(name, move |bytes: &[u8]| bytes.starts_with(magic), decode)
+  }
+
+  /// Name of the candidate.
+  pub fn name(&self) -> &'static str {
+    self.name
+  }
+}
+
+/// Error that might occur while decoding a resource with the `Sniff` method.
+#[derive(Debug)]
+pub enum SniffError {
+  /// No candidate recognized the content.
+  Unrecognized,
+  /// The candidate recognizing the content, which name is attached, failed to decode it.
+  Decode(&'static str, Box<Error>),
+}
+
+impl fmt::Display for SniffError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      SniffError::Unrecognized => f.write_str("unrecognized content"),
+      SniffError::Decode(name, ref e) => write!(f, "{} error: {}", name, e),
+    }
+  }
+}
+
+impl Error for SniffError {
+  fn description(&self) -> &str {
+    match *self {
+      SniffError::Unrecognized => "unrecognized content",
+      SniffError::Decode(..) => "decoding error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      SniffError::Unrecognized => None,
+      SniffError::Decode(_, ref e) => Some(e.as_ref()),
+    }
+  }
+}
+
+impl<C, T> Load<C, Sniff> for T
+where T: Sniffable
+{
+  type Key = FSKey;
+
+  type Error = FormatError<SniffError>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let candidate = T::candidates()
+      .into_iter()
+      .find(|candidate| (candidate.sniff)(&bytes))
+      .ok_or(FormatError::Format(SniffError::Unrecognized))?;
+
+    let res = (candidate.decode)(&bytes)
+      .map_err(|e| FormatError::Format(SniffError::Decode(candidate.name, e)))?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
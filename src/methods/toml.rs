@@ -0,0 +1,28 @@
+//! TOML loading, via serde.
+
+use serde::de::DeserializeOwned;
+use toml;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, Toml};
+
+impl<C, T> Load<C, Toml> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<toml::de::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let res = toml::from_slice(&bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
@@ -0,0 +1,28 @@
+//! YAML loading, via serde.
+
+use serde::de::DeserializeOwned;
+use serde_yaml;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+use methods::{FormatError, Yaml};
+
+impl<C, T> Load<C, Yaml> for T
+where T: 'static + DeserializeOwned
+{
+  type Key = FSKey;
+
+  type Error = FormatError<serde_yaml::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let bytes = storage.read(key.as_path())?;
+    let res = serde_yaml::from_slice(&bytes).map_err(FormatError::Format)?;
+
+    Ok(Loaded::without_dep(res))
+  }
+}
@@ -0,0 +1,132 @@
+//! Read-through metrics.
+//!
+//! When enabled with `StoreOpt::set_read_metrics`, every `Storage::get` and `Storage::get_by`
+//! counts as a hit if the resource was already cached and as a miss if it had to be loaded. Counts
+//! are kept per type of resource and per label: pass one with `Storage::get_labeled` or
+//! `Storage::get_by_labeled` to tell call sites apart. A type constantly missed is a good
+//! candidate for preloading – see `Storage::preload` – or for being kept alive.
+//!
+//! Whatever the options, a `Store` also keeps a few global metrics – resident resources, cache
+//! hits and misses, reloads and their durations – available as a `StoreMetrics` snapshot with
+//! `Store::metrics`, to graph the health of hot-reloading over time.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use key::DepKey;
+
+/// Hit and miss counts.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ReadCounts {
+  /// Number of reads served from the cache.
+  pub hits: u64,
+  /// Number of reads that had to load the resource.
+  pub misses: u64,
+}
+
+impl ReadCounts {
+  /// Total number of reads.
+  pub fn reads(&self) -> u64 {
+    self.hits + self.misses
+  }
+
+  /// Ratio of reads served from the cache, or `None` if there was no read.
+  pub fn hit_ratio(&self) -> Option<f64> {
+    match self.reads() {
+      0 => None,
+      reads => Some(self.hits as f64 / reads as f64),
+    }
+  }
+}
+
+/// Read counts of a `Storage`, per type of resource and label.
+#[derive(Clone, Debug, Default)]
+pub struct ReadMetrics {
+  counts: HashMap<(&'static str, Option<&'static str>), ReadCounts>,
+}
+
+impl ReadMetrics {
+  /// Create empty metrics.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record a read of a resource of a given type.
+  pub(crate) fn record(&mut self, type_name: &'static str, label: Option<&'static str>, hit: bool) {
+    let counts = self.counts.entry((type_name, label)).or_default();
+
+    if hit {
+      counts.hits += 1;
+    } else {
+      counts.misses += 1;
+    }
+  }
+
+  /// Iterate over the counts, as `(type name, label, counts)` triples.
+  ///
+  /// Reads done without a label have a `None` label.
+  pub fn iter<'a>(
+    &'a self,
+  ) -> impl Iterator<Item = (&'static str, Option<&'static str>, ReadCounts)> + 'a {
+    self
+      .counts
+      .iter()
+      .map(|(&(type_name, label), &counts)| (type_name, label, counts))
+  }
+
+  /// Counts of a type, whatever the labels.
+  pub fn by_type(&self, type_name: &str) -> ReadCounts {
+    self
+      .counts
+      .iter()
+      .filter(|&(&(name, _), _)| name == type_name)
+      .fold(ReadCounts::default(), |acc, (_, counts)| ReadCounts {
+        hits: acc.hits + counts.hits,
+        misses: acc.misses + counts.misses,
+      })
+  }
+
+  /// Counts of a type for a given label.
+  pub fn by_label(&self, type_name: &'static str, label: Option<&'static str>) -> ReadCounts {
+    self
+      .counts
+      .get(&(type_name, label))
+      .cloned()
+      .unwrap_or_default()
+  }
+
+  /// Forget all the counts.
+  pub fn clear(&mut self) {
+    self.counts.clear();
+  }
+}
+
+/// Snapshot of the global metrics of a `Store`.
+///
+/// Counts are accumulated since the creation of the `Store`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StoreMetrics {
+  /// Number of resources living in the `Store`.
+  pub resident: usize,
+  /// Number of reads served from the cache.
+  pub cache_hits: u64,
+  /// Number of reads that had to load the resource.
+  pub cache_misses: u64,
+  /// Number of reloads that succeeded, including the ones of dependent resources.
+  pub reloads_succeeded: u64,
+  /// Number of reloads that failed.
+  pub reloads_failed: u64,
+  /// Duration of the last reload – successful or not – of each key.
+  pub last_reload_durations: HashMap<DepKey, Duration>,
+}
+
+impl StoreMetrics {
+  /// Ratio of reads served from the cache, or `None` if there was no read.
+  pub fn hit_ratio(&self) -> Option<f64> {
+    ReadCounts {
+      hits: self.cache_hits,
+      misses: self.cache_misses,
+    }
+    .hit_ratio()
+  }
+}
@@ -0,0 +1,189 @@
+//! Resource migrations between schema versions.
+//!
+//! Long-lived resources (user content, saved configurations, etc.) often outlive the structure
+//! they were authored against. Instead of erroring out when the on-disk format is older than the
+//! current version, a loader can register *migrations* – transform functions from a version to the
+//! next one – and apply them to the raw representation before building its resource. That keeps
+//! old content hot-reloading across releases.
+//!
+//! The raw representation is up to you: a `String`, a `serde_json::Value`, a TOML table, etc.
+//!
+//! Serde-backed resources can have their documents migrated on load by implementing `Versioned`:
+//! with the `serde-json` feature, the `VersionedJson` method reads the version of a JSON document,
+//! applies the migrations to its `serde_json::Value` and only then deserializes it.
+//!
+//! ```
+//! use warmy::migrate::Migrations;
+//!
+//! // version 0 used "colour", version 1 renamed it to "color", version 2 upper-cases values
+//! let migrations = Migrations::<String, ()>::new(2)
+//!   .register(0, |raw| Ok(raw.replace("colour", "color")))
+//!   .register(1, |raw| Ok(raw.to_uppercase()));
+//!
+//! let migrated = migrations.migrate(0, "colour = red".to_owned()).unwrap();
+//! assert_eq!(migrated, "COLOR = RED");
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Resources which documents are versioned and migrated on load.
+///
+/// Documents carry their version in a top-level `version` field; documents without one predate
+/// versioning and are at version `0`. Once migrated, the `version` field of a document is set to
+/// the current version before it gets deserialized. `R` is the raw representation of documents –
+/// `serde_json::Value` for the `VersionedJson` method.
+pub trait Versioned<R> {
+  /// Type of error a migration step might fail with.
+  type Error: fmt::Debug + 'static;
+
+  /// Migrations upgrading documents to the current version.
+  fn migrations() -> Migrations<R, Self::Error>;
+}
+
+/// A chain of migrations, upgrading a raw representation `R` to the current version.
+///
+/// `E` is the type of error a migration step might fail with.
+pub struct Migrations<R, E = ()> {
+  current: u32,
+  steps: BTreeMap<u32, Box<Fn(R) -> Result<R, E>>>,
+}
+
+impl<R, E> Migrations<R, E> {
+  /// Create an empty chain of migrations targeting the `current` version.
+  pub fn new(current: u32) -> Self {
+    Migrations {
+      current,
+      steps: BTreeMap::new(),
+    }
+  }
+
+  /// Register a migration step from version `from` to version `from + 1`.
+  ///
+  /// Registering a step twice for the same version replaces the former one.
+  pub fn register<F>(mut self, from: u32, f: F) -> Self
+  where F: 'static + Fn(R) -> Result<R, E> {
+    self.steps.insert(from, Box::new(f));
+    self
+  }
+
+  /// The version migrations upgrade to.
+  pub fn current_version(&self) -> u32 {
+    self.current
+  }
+
+  /// Migrate a raw representation authored with the given `version` to the current version.
+  ///
+  /// If `version` is already the current one, `raw` is returned untouched.
+  pub fn migrate(&self, version: u32, raw: R) -> Result<R, MigrationError<E>> {
+    if version > self.current {
+      return Err(MigrationError::FromTheFuture {
+        version,
+        current: self.current,
+      });
+    }
+
+    let mut raw = raw;
+
+    for from in version..self.current {
+      let step = self
+        .steps
+        .get(&from)
+        .ok_or(MigrationError::MissingStep(from))?;
+      raw = step(raw).map_err(|error| MigrationError::StepFailed { from, error })?;
+    }
+
+    Ok(raw)
+  }
+}
+
+/// Error that might occur while migrating a resource.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MigrationError<E> {
+  /// The resource was authored with a version more recent than the current one.
+  FromTheFuture {
+    /// Version of the resource.
+    version: u32,
+    /// Current version.
+    current: u32,
+  },
+  /// No migration step was registered to upgrade from the given version.
+  MissingStep(u32),
+  /// A migration step failed.
+  StepFailed {
+    /// Version the failing step was upgrading from.
+    from: u32,
+    /// Error the step failed with.
+    error: E,
+  },
+}
+
+impl<E> fmt::Display for MigrationError<E>
+where E: fmt::Debug
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      MigrationError::FromTheFuture { version, current } => write!(
+        f,
+        "version {} is more recent than the current version {}",
+        version, current
+      ),
+      MigrationError::MissingStep(from) => write!(f, "no migration from version {}", from),
+      MigrationError::StepFailed { from, ref error } => {
+        write!(f, "migration from version {} failed: {:?}", from, error)
+      }
+    }
+  }
+}
+
+impl<E> Error for MigrationError<E>
+where E: fmt::Debug
+{
+  fn description(&self) -> &str {
+    match *self {
+      MigrationError::FromTheFuture { .. } => "resource version from the future",
+      MigrationError::MissingStep(_) => "missing migration step",
+      MigrationError::StepFailed { .. } => "migration step failed",
+    }
+  }
+}
+
+/// Error that might occur while decoding a versioned document – see `Versioned`.
+#[derive(Debug)]
+pub enum VersionedError<F, E> {
+  /// The document couldn’t be decoded.
+  Format(F),
+  /// The `version` field of the document isn’t an unsigned integer.
+  InvalidVersion,
+  /// The document couldn’t be migrated to the current version.
+  Migration(MigrationError<E>),
+}
+
+impl<F, E> fmt::Display for VersionedError<F, E>
+where
+  F: Error,
+  E: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      VersionedError::Format(ref e) => write!(f, "format error: {}", e),
+      VersionedError::InvalidVersion => f.write_str("invalid version"),
+      VersionedError::Migration(ref e) => write!(f, "migration error: {}", e),
+    }
+  }
+}
+
+impl<F, E> Error for VersionedError<F, E>
+where
+  F: Error,
+  E: fmt::Debug,
+{
+  fn description(&self) -> &str {
+    match *self {
+      VersionedError::Format(_) => "format error",
+      VersionedError::InvalidVersion => "invalid version",
+      VersionedError::Migration(_) => "migration error",
+    }
+  }
+}
@@ -0,0 +1,107 @@
+//! Mount points, mapping VFS directories to physical directories.
+//!
+//! By default, every filesystem key lives under the root of the store. `StoreOpt::add_mount` maps
+//! a VFS directory – a prefix of keys – to a directory living anywhere on the filesystem instead,
+//! so that a single store spans assets scattered across several places while keys stay stable and
+//! portable:
+//!
+//! ```ignore
+//! let opt = StoreOpt::default()
+//!   .set_root("./assets")
+//!   .add_mount("/textures", "/mnt/art/exports")
+//!   .add_mount("/config", "./config");
+//!
+//! // loads /mnt/art/exports/hero.png
+//! let hero: Res<Texture> = store.get(&FSKey::new("/textures/hero.png"), ctx)?;
+//! ```
+//!
+//! Keys are routed to the deepest mount point they live under. Mounted directories are watched for
+//! changes just like the root, and keys routed to them aren’t considered outside of the root. Host
+//! paths – see `FSKey::absolute` – are never routed.
+
+use std::path::{Path, PathBuf};
+
+use key::{DepKey, Key, PathNormalization};
+
+/// Mount points of a store, resolved against its root.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Mounts {
+  // real path each mount point would have under the root, along with the mounted directory;
+  // deepest mount points first
+  mounts: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Mounts {
+  /// Create the mount points from their real paths under the root and their mounted directories.
+  pub(crate) fn new(mut mounts: Vec<(PathBuf, PathBuf)>) -> Self {
+    mounts.sort_by(|a, b| b.0.components().count().cmp(&a.0.components().count()));
+    Mounts { mounts }
+  }
+
+  /// Check whether there’s no mount point at all.
+  pub fn is_empty(&self) -> bool {
+    self.mounts.is_empty()
+  }
+
+  /// Iterate over the mounted directories.
+  pub fn dirs<'a>(&'a self) -> impl Iterator<Item = &'a Path> + 'a {
+    self.mounts.iter().map(|&(_, ref dir)| dir.as_path())
+  }
+
+  /// Route a prepared path to the mounted directory of the deepest mount point it lives under, if
+  /// any.
+  pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+    self.mounts.iter().find_map(|&(ref point, ref dir)| {
+      let rest = path.strip_prefix(point).ok()?;
+
+      if rest.as_os_str().is_empty() {
+        Some(dir.clone())
+      } else {
+        Some(dir.join(rest))
+      }
+    })
+  }
+
+  /// Route a prepared path, keeping it as-is if it lives under no mount point.
+  pub fn route(&self, path: &Path) -> PathBuf {
+    self.resolve(path).unwrap_or_else(|| path.to_owned())
+  }
+
+  /// Map a real path living in a mounted directory back to the real path it would have under the
+  /// root, if any – the reverse of `Mounts::resolve`.
+  pub fn unroute(&self, path: &Path) -> Option<PathBuf> {
+    self
+      .mounts
+      .iter()
+      .filter_map(|&(ref point, ref dir)| Some((point, dir, path.strip_prefix(dir).ok()?)))
+      .max_by_key(|&(_, dir, _)| dir.components().count())
+      .map(|(point, _, rest)| {
+        if rest.as_os_str().is_empty() {
+          point.clone()
+        } else {
+          point.join(rest)
+        }
+      })
+  }
+
+  /// Check whether a real path lives in a mounted directory.
+  pub fn contains(&self, path: &Path) -> bool {
+    self.dirs().any(|dir| path.starts_with(dir))
+  }
+
+  /// Prepare a key against a root and route it to the mounted directory it lives under, if any.
+  pub(crate) fn prepare_key<K>(&self, key: K, root: &Path, normalization: PathNormalization) -> K
+  where K: Key {
+    let prepared = key.clone().prepare_key_with(root, normalization);
+
+    if self.is_empty() {
+      return prepared;
+    }
+
+    // host paths are never routed to mounted directories
+    match key.into() {
+      DepKey::HostPath(_) => prepared,
+      _ => prepared.mount_key(self),
+    }
+  }
+}
@@ -0,0 +1,446 @@
+//! Parallel preloading.
+//!
+//! Loading all the resources of an asset-heavy application one after the other makes for long
+//! cold starts. `Storage::preload` loads the resources declared in a `Manifest` with the help of a
+//! pool of worker threads:
+//!
+//!   - Resources implementing `Prepare` – declared with `Manifest::add_prepared` – get the heavy
+//!     lifting of their loading – reading files, decoding, etc. – done on the worker threads, as
+//!     soon as possible. The number of resources of a given type prepared at once can be limited
+//!     with `Manifest::set_type_limit`.
+//!   - The loading of resources is finished on the thread owning the `Storage`, dependencies
+//!     first: a resource is only finished once all its dependencies declared with
+//!     `Manifest::add_dep` are loaded. Composite loaders thus find their dependencies in the
+//!     `Storage` instead of loading them on their own.
+//!
+//! Resources not implementing `Prepare` are fully loaded on the thread owning the `Storage`, in
+//! dependency order as well.
+//!
+//! Resources of a single type can also be fetched at once with `Storage::get_many`, which prepares
+//! the ones not living in the `Storage` yet on worker threads.
+//!
+//! A preparation panicking on a worker thread is reported as a failure of its resource.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use key::DepKey;
+use load::{Load, Loaded, Storage, StoreError, StoreErrorOr};
+use manifest::{Manifest, ManifestEntry, PreflightFailure, PreflightReport, PrepareFn};
+use res::Res;
+
+/// Resources which loading can be partly done on worker threads.
+///
+/// Loading is split in two halves:
+///
+///   - `prepare` runs on a worker thread, without access to the `Storage`. It does the heavy
+///     lifting: reading files, decoding images, etc.
+///   - `finish` runs on the thread owning the `Storage` and turns the prepared data into the
+///     resource, declaring its dependencies.
+///
+/// Only `Storage::preload` splits loading; the rest of the time, resources are loaded with
+/// `Load::load`, which you can implement in terms of both halves.
+pub trait Prepare<C>: Load<C> {
+  /// Data prepared on a worker thread.
+  type Prepared: Send + 'static;
+
+  /// Prepare the resource living at the given key.
+  fn prepare(key: &Self::Key) -> Result<Self::Prepared, Self::Error>;
+
+  /// Finish loading the resource from its prepared data.
+  fn finish(
+    prepared: Self::Prepared,
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>;
+}
+
+/// Loading status of an entry of a manifest.
+enum Status {
+  /// Not started yet.
+  Pending,
+  /// Being prepared on a worker thread.
+  Preparing,
+  /// Prepared, waiting for its dependencies to be loaded.
+  Prepared(Box<Any + Send>),
+  /// Loaded.
+  Loaded,
+  /// Failed to load.
+  Failed,
+}
+
+/// Result of a preparation run on a worker thread.
+type Prepared = Result<Box<Any + Send>, String>;
+
+/// Pool of worker threads running jobs, identified by an index, and sending their results back.
+///
+/// Jobs panicking are caught, so that a worker thread survives them.
+struct WorkerPool<J, R> {
+  jobs: Option<Sender<(usize, J)>>,
+  results: Receiver<(usize, thread::Result<R>)>,
+  workers: Vec<JoinHandle<()>>,
+}
+
+impl<J, R> WorkerPool<J, R>
+where
+  J: 'static + Send,
+  R: 'static + Send,
+{
+  /// Spawn `threads` worker threads running `work` on the jobs.
+  fn new<F>(threads: usize, work: F) -> Self
+  where F: 'static + Fn(J) -> R + Send + Sync {
+    let (job_sx, job_rx) = channel::<(usize, J)>();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (result_sx, results) = channel();
+    let work = Arc::new(work);
+
+    let workers = (0..threads)
+      .map(|_| {
+        let job_rx = job_rx.clone();
+        let result_sx = result_sx.clone();
+        let work = work.clone();
+
+        thread::spawn(move || loop {
+          let job = job_rx.lock().unwrap().recv();
+
+          match job {
+            Ok((i, job)) => {
+              let result = panic::catch_unwind(AssertUnwindSafe(|| work(job)));
+
+              if result_sx.send((i, result)).is_err() {
+                break;
+              }
+            }
+
+            Err(_) => break,
+          }
+        })
+      })
+      .collect();
+
+    // only the worker threads may send results, so that we know when they’re all gone
+    drop(result_sx);
+
+    WorkerPool {
+      jobs: Some(job_sx),
+      results,
+      workers,
+    }
+  }
+
+  /// Queue a job.
+  fn submit(&self, i: usize, job: J) {
+    if let Some(ref jobs) = self.jobs {
+      let _ = jobs.send((i, job));
+    }
+  }
+
+  /// Stop accepting jobs: the worker threads exit once the queued ones are done.
+  fn close(&mut self) {
+    self.jobs = None;
+  }
+
+  /// Wait for the result of a job.
+  ///
+  /// Return `None` once all the worker threads are gone – after `WorkerPool::close`, once all the
+  /// queued jobs are done.
+  fn recv(&self) -> Option<(usize, thread::Result<R>)> {
+    self.results.recv().ok()
+  }
+}
+
+impl<J, R> Drop for WorkerPool<J, R> {
+  fn drop(&mut self) {
+    self.jobs = None;
+
+    for worker in self.workers.drain(..) {
+      let _ = worker.join();
+    }
+  }
+}
+
+impl<C> Storage<C> {
+  /// Get several resources of type `T` at once, and return the outcome of their loading in the
+  /// order of the keys.
+  ///
+  /// Resources not living in the `Storage` yet are prepared in parallel on `threads` worker threads
+  /// and finished on the calling thread – see `Prepare`. Dependencies between them are not known
+  /// beforehand: a resource depending on another one of the batch loads it on its own when it’s
+  /// finished. Declare them in a `Manifest` and use `Storage::preload` if that matters.
+  ///
+  /// Resources failing to prepare are not loaded again: the error of their preparation is
+  /// returned. A preparation panicking is reported with `StoreError::PreparePanicked`.
+  pub fn get_many<K, T>(
+    &mut self,
+    keys: &[K],
+    threads: usize,
+    ctx: &mut C,
+  ) -> Vec<Result<Res<T>, StoreErrorOr<T, C>>>
+  where
+    T: Prepare<C>,
+    T::Error: Send,
+    K: 'static + Clone + Send + Sync + Into<T::Key>,
+  {
+    // keys escaping the root are refused when getting them
+    let missing: Vec<_> = keys
+      .iter()
+      .enumerate()
+      .filter(|&(_, key)| !self.contains_by::<K, T, ()>(key, ()))
+      .filter(|&(_, key)| self.prepare_checked(key.clone().into()).is_ok())
+      .map(|(i, key)| (i, key.clone()))
+      .collect();
+
+    let root = self.root().to_owned();
+    let normalization = self.path_normalization();
+    let mounts = self.mounts().clone();
+    let mut pool = WorkerPool::new(threads.max(1).min(missing.len()), move |key: K| {
+      let key = mounts.prepare_key(key.into(), &root, normalization);
+      T::prepare(&key)
+    });
+
+    for (i, key) in missing {
+      pool.submit(i, key);
+    }
+
+    pool.close();
+
+    // finish the resources as they get prepared
+    let mut results: Vec<_> = keys.iter().map(|_| None).collect();
+
+    while let Some((i, prepared)) = pool.recv() {
+      let key = &keys[i];
+
+      results[i] = Some(match prepared {
+        Ok(Ok(prepared)) => self.get_or_load::<K, T, (), _>(key, ctx, |key, storage, ctx| {
+          T::finish(prepared, key, storage, ctx)
+        }),
+        Ok(Err(e)) => Err(StoreErrorOr::ResError(e)),
+        Err(_) => {
+          let dep_key = self.prepare::<T::Key>(key.clone().into()).into();
+          Err(StoreErrorOr::StoreError(StoreError::PreparePanicked(dep_key)))
+        }
+      });
+    }
+
+    drop(pool);
+
+    keys
+      .iter()
+      .zip(results)
+      .map(|(key, result)| result.unwrap_or_else(|| self.get(key, ctx)))
+      .collect()
+  }
+
+  /// Load every resource declared in a manifest in parallel, dependencies first, and report all the
+  /// failures at once.
+  ///
+  /// See the documentation of the `preload` module for further details. A resource which
+  /// dependency fails to load fails as well. Successfully loaded resources stay in the `Storage`.
+  pub fn preload(&mut self, manifest: &Manifest<C>, ctx: &mut C) -> PreflightReport {
+    let entries = &manifest.entries;
+    let mut report = PreflightReport::default();
+    let mut failures = Vec::new();
+    let mut status: Vec<_> = entries.iter().map(|_| Status::Pending).collect();
+
+    // prepared keys, refusing the ones escaping the root
+    let keys: Vec<_> = entries
+      .iter()
+      .map(|entry| self.prepare_checked(entry.key.clone()))
+      .collect();
+
+    for (i, key) in keys.iter().enumerate() {
+      if let Err(ref e) = *key {
+        status[i] = Status::Failed;
+        failures.push((i, e.to_string()));
+      }
+    }
+
+    let index: HashMap<DepKey, usize> = keys
+      .iter()
+      .enumerate()
+      .filter_map(|(i, key)| key.as_ref().ok().map(|key| (key.clone(), i)))
+      .collect();
+    let root = self.root().to_owned();
+    let normalization = self.path_normalization();
+    let mounts = self.mounts().clone();
+    let deps: Vec<Vec<usize>> = entries
+      .iter()
+      .map(|entry| {
+        entry
+          .deps
+          .iter()
+          .filter_map(|dep| index.get(&self.prepare(dep.clone())).cloned())
+          .collect()
+      })
+      .collect();
+
+    let workers = entries.iter().filter(|entry| entry.prepared.is_some()).count();
+    let mut pool = WorkerPool::new(
+      manifest.threads.min(workers),
+      move |prepare: Arc<PrepareFn>| prepare(&root, normalization, &mounts),
+    );
+
+    let mut preparing: HashMap<_, usize> = HashMap::new();
+
+    loop {
+      let mut progressed = true;
+
+      while progressed {
+        progressed = false;
+
+        for i in 0..entries.len() {
+          let entry = &entries[i];
+
+          // start preparing as soon as possible, within the limit of the type
+          if let (&Status::Pending, &Some(ref prepared)) = (&status[i], &entry.prepared) {
+            let limit = manifest.type_limits.get(&entry.type_id).cloned();
+            let count = preparing.entry(entry.type_id).or_insert(0);
+
+            if limit.map_or(true, |limit| *count < limit) {
+              *count += 1;
+              status[i] = Status::Preparing;
+              pool.submit(i, prepared.prepare.clone());
+              progressed = true;
+            }
+
+            continue;
+          }
+
+          let ready = match status[i] {
+            Status::Pending | Status::Prepared(_) => true,
+            _ => false,
+          };
+
+          if !ready {
+            continue;
+          }
+
+          // a failed dependency fails its dependent resources
+          if let Some(&dep) = deps[i].iter().find(|&&dep| is_failed(&status[dep])) {
+            status[i] = Status::Failed;
+            failures.push((i, format!("dependency failed: {}", entries[dep].key)));
+            progressed = true;
+            continue;
+          }
+
+          if !deps[i].iter().all(|&dep| is_loaded(&status[dep])) {
+            continue;
+          }
+
+          let loaded = match mem::replace(&mut status[i], Status::Pending) {
+            Status::Prepared(data) => match entry.prepared {
+              Some(ref prepared) => (prepared.finish)(data, self, ctx),
+              None => (entry.load)(self, ctx),
+            },
+            _ => (entry.load)(self, ctx),
+          };
+
+          status[i] = match loaded {
+            Ok(_) => {
+              report.loaded += 1;
+              Status::Loaded
+            }
+
+            Err(error) => {
+              failures.push((i, error));
+              Status::Failed
+            }
+          };
+
+          progressed = true;
+        }
+      }
+
+      let in_flight = status.iter().any(|status| match *status {
+        Status::Preparing => true,
+        _ => false,
+      });
+
+      if !in_flight {
+        break;
+      }
+
+      receive_prepared(&pool, entries, &mut status, &mut preparing, &mut failures);
+    }
+
+    // entries still waiting depend on each other
+    for (i, status) in status.iter().enumerate() {
+      match *status {
+        Status::Pending | Status::Prepared(_) => failures.push((i, "dependency cycle".to_owned())),
+        _ => (),
+      }
+    }
+
+    pool.close();
+    drop(pool);
+
+    failures.sort_by_key(|&(i, _)| i);
+    report.failures = failures
+      .into_iter()
+      .map(|(i, error)| PreflightFailure {
+        key: entries[i].key.clone(),
+        error,
+      })
+      .collect();
+
+    report
+  }
+}
+
+/// Wait for a worker thread to finish preparing an entry.
+fn receive_prepared<C>(
+  pool: &WorkerPool<Arc<PrepareFn>, Prepared>,
+  entries: &[ManifestEntry<C>],
+  status: &mut [Status],
+  preparing: &mut HashMap<TypeId, usize>,
+  failures: &mut Vec<(usize, String)>,
+)
+{
+  match pool.recv() {
+    Some((i, prepared)) => {
+      if let Some(count) = preparing.get_mut(&entries[i].type_id) {
+        *count -= 1;
+      }
+
+      let prepared = prepared.unwrap_or_else(|_| Err("panicked while preparing".to_owned()));
+
+      status[i] = match prepared {
+        Ok(data) => Status::Prepared(data),
+        Err(error) => {
+          failures.push((i, error));
+          Status::Failed
+        }
+      };
+    }
+
+    // all the worker threads are gone
+    None => {
+      for (i, status) in status.iter_mut().enumerate() {
+        if let Status::Preparing = *status {
+          *status = Status::Failed;
+          failures.push((i, "worker thread gone".to_owned()));
+        }
+      }
+    }
+  }
+}
+
+fn is_loaded(status: &Status) -> bool {
+  match *status {
+    Status::Loaded => true,
+    _ => false,
+  }
+}
+
+fn is_failed(status: &Status) -> bool {
+  match *status {
+    Status::Failed => true,
+    _ => false,
+  }
+}
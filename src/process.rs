@@ -0,0 +1,152 @@
+//! Two-stage import and processing.
+//!
+//! Real asset pipelines split the making of a resource in two stages: the *import* parses the
+//! file into an intermediate representation – a mesh as a list of triangles, say – and the
+//! *processing* turns it into the runtime resource – an optimized vertex buffer – according to
+//! some parameters – the optimization level. Parsing is often the expensive part, so it shouldn’t
+//! be redone when only the parameters change.
+//!
+//! Implement `ProcessParams` and `Process` for the runtime resource and load it with the
+//! `Processed` method:
+//!
+//!   - The intermediate representation is a resource of its own, loaded with its default method
+//!     and living at the same key.
+//!   - When the file changes, the intermediate representation is imported again, then processed.
+//!   - When the parameters change – see `Storage::set_process_params` – only the processing runs
+//!     again, from the intermediate representation already living in the `Storage`.
+
+use std::error::Error;
+use std::fmt;
+
+use cache::CacheKey;
+use load::{Load, Loaded, Storage, StoreErrorOr};
+use res::Res;
+
+/// Method loading a resource by importing its intermediate representation and processing it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Processed;
+
+/// Parameters of the processing of a resource.
+///
+/// They don’t depend on the context, hence they’re declared apart from `Process`.
+pub trait ProcessParams: 'static + Sized {
+  /// Parameters of the processing.
+  ///
+  /// Resources which parameters were never set are processed with the default ones.
+  type Params: 'static + Clone + Default;
+}
+
+/// Resources made by processing an intermediate representation.
+///
+/// See the documentation of the `process` module for further details.
+pub trait Process<C>: ProcessParams {
+  /// Intermediate representation the resource is processed from.
+  type Intermediate: Load<C>;
+
+  /// Type of error that might happen while processing.
+  type Error: Error + 'static;
+
+  /// Process the intermediate representation into the resource.
+  fn process(
+    intermediate: &Self::Intermediate,
+    params: &Self::Params,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error>;
+}
+
+impl<C, T> Load<C, Processed> for T
+where T: Process<C>
+{
+  type Key = <T::Intermediate as Load<C>>::Key;
+
+  type Error = ProcessError<T::Error>;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    // the key is already prepared
+    let intermediate: Res<T::Intermediate> =
+      storage.get_prepared(key.clone(), ctx).map_err(|e| match e {
+        StoreErrorOr::StoreError(e) => ProcessError::Import(Box::new(e)),
+        StoreErrorOr::ResError(e) => ProcessError::Import(Box::new(e)),
+      })?;
+
+    let cache_key = CacheKey::with_method::<T, Processed>(key.into());
+    let params = storage
+      .process_params(&cache_key)
+      .and_then(|params| params.downcast_ref::<T::Params>())
+      .cloned()
+      .unwrap_or_default();
+
+    let res = T::process(&intermediate.borrow(), &params, storage, ctx)
+      .map_err(ProcessError::Process)?;
+
+    Ok(res.into())
+  }
+}
+
+impl<C> Storage<C> {
+  /// Set the processing parameters of a resource.
+  ///
+  /// If the resource is living in the `Storage`, it’s processed again with the new parameters, from
+  /// its current intermediate representation, and the outcome of the processing is returned.
+  /// Otherwise, the parameters are used when it gets loaded.
+  pub fn set_process_params<K, T>(
+    &mut self,
+    key: &K,
+    params: T::Params,
+    ctx: &mut C,
+  ) -> Result<(), Box<Error>>
+  where
+    T: Process<C>,
+    K: Clone + Into<<T::Intermediate as Load<C>>::Key>,
+  {
+    let key = self.prepare_checked(key.clone().into())?;
+    let cache_key = CacheKey::with_method::<T, Processed>(key.into());
+
+    self.insert_process_params(cache_key.clone(), Box::new(params));
+    self.reload_resource(&cache_key, ctx).unwrap_or(Ok(()))
+  }
+}
+
+/// Error that might occur while loading a resource with the `Processed` method.
+#[derive(Debug)]
+pub enum ProcessError<E> {
+  /// The intermediate representation couldn’t be imported.
+  Import(Box<Error>),
+  /// The intermediate representation couldn’t be processed.
+  Process(E),
+}
+
+impl<E> fmt::Display for ProcessError<E>
+where E: fmt::Display
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      ProcessError::Import(ref e) => write!(f, "import error: {}", e),
+      ProcessError::Process(ref e) => write!(f, "processing error: {}", e),
+    }
+  }
+}
+
+impl<E> Error for ProcessError<E>
+where E: Error
+{
+  fn description(&self) -> &str {
+    match *self {
+      ProcessError::Import(_) => "import error",
+      ProcessError::Process(_) => "processing error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      ProcessError::Import(ref e) => Some(e.as_ref()),
+      ProcessError::Process(ref e) => Some(e),
+    }
+  }
+}
@@ -0,0 +1,280 @@
+//! Load progress.
+//!
+//! Big resources take a while to load. When enabled with `StoreOpt::set_progress_tracking`, the
+//! `Storage` tracks every resource it loads. From `Load::load`, a loader gets a `Progress` reporter
+//! for the resource it’s loading with `Storage::load_progress`. It then reports the bytes it has
+//! read and the stage it’s in:
+//!
+//! ```ignore
+//! fn load(
+//!   key: Self::Key,
+//!   storage: &mut Storage<C>,
+//!   _: &mut C
+//! ) -> Result<Loaded<Self>, Self::Error> {
+//!   let mut file = File::open(key.as_path())?;
+//!
+//!   let mut bytes = Vec::new();
+//!   match storage.load_progress() {
+//!     Some(progress) => {
+//!       progress.set_total(file.metadata()?.len());
+//!       progress.reader(file).read_to_end(&mut bytes)?;
+//!       progress.set_stage("decoding");
+//!     }
+//!
+//!     None => { file.read_to_end(&mut bytes)?; }
+//!   }
+//!
+//!   // …
+//! }
+//! ```
+//!
+//! `Store::progress` aggregates the reports of all the resources loaded since the last
+//! `Store::clear_progress` into a `ProgressReport`, to show per-resource and overall progress.
+//! `Progress` can be cloned and sent to other threads – decoding workers, for instance.
+
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+use key::DepKey;
+
+/// Status of a resource being tracked.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LoadStatus {
+  /// The resource is being loaded.
+  Loading,
+  /// The resource was loaded.
+  Loaded,
+  /// The resource failed to load.
+  Failed,
+}
+
+/// Progress of a single resource.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResourceProgress {
+  /// Key of the resource.
+  pub key: DepKey,
+  /// Name of the type of the resource.
+  pub type_name: &'static str,
+  /// Last stage reported by the loader, if any.
+  pub stage: Option<String>,
+  /// Number of bytes read so far.
+  pub bytes_read: u64,
+  /// Number of bytes to read, if known.
+  pub bytes_total: Option<u64>,
+  /// Status of the resource.
+  pub status: LoadStatus,
+}
+
+impl ResourceProgress {
+  /// Ratio of the resource that is loaded, between `0` and `1`.
+  ///
+  /// Finished resources – loaded or failed – are complete. Resources being loaded are only known
+  /// to progress if their loader reported a total number of bytes.
+  pub fn fraction(&self) -> Option<f64> {
+    match (self.status, self.bytes_total) {
+      (LoadStatus::Loading, Some(total)) if total > 0 => {
+        Some((self.bytes_read as f64 / total as f64).min(1.))
+      }
+      (LoadStatus::Loading, _) => None,
+      _ => Some(1.),
+    }
+  }
+}
+
+/// Reporter of the progress of a resource being loaded.
+///
+/// See the documentation of the `progress` module for further details.
+#[derive(Clone, Debug)]
+pub struct Progress {
+  state: Arc<Mutex<ResourceProgress>>,
+}
+
+impl Progress {
+  fn new(key: DepKey, type_name: &'static str) -> Self {
+    let state = ResourceProgress {
+      key,
+      type_name,
+      stage: None,
+      bytes_read: 0,
+      bytes_total: None,
+      status: LoadStatus::Loading,
+    };
+
+    Progress {
+      state: Arc::new(Mutex::new(state)),
+    }
+  }
+
+  fn update<F>(&self, f: F)
+  where F: FnOnce(&mut ResourceProgress) {
+    if let Ok(mut state) = self.state.lock() {
+      f(&mut state);
+    }
+  }
+
+  /// Take a snapshot of the progress.
+  pub fn snapshot(&self) -> ResourceProgress {
+    match self.state.lock() {
+      Ok(state) => state.clone(),
+      Err(poisoned) => poisoned.into_inner().clone(),
+    }
+  }
+
+  /// Report the stage the loader is in – `"decompressing"`, `"uploading"`, etc.
+  pub fn set_stage<S>(&self, stage: S)
+  where S: Into<String> {
+    let stage = stage.into();
+    self.update(|state| state.stage = Some(stage));
+  }
+
+  /// Report the number of bytes to read.
+  pub fn set_total(&self, bytes: u64) {
+    self.update(|state| state.bytes_total = Some(bytes));
+  }
+
+  /// Report that `bytes` more bytes were read.
+  pub fn advance(&self, bytes: u64) {
+    self.update(|state| state.bytes_read += bytes);
+  }
+
+  /// Wrap a reader so that the bytes read from it are reported.
+  pub fn reader<R>(&self, reader: R) -> ProgressRead<R>
+  where R: Read {
+    ProgressRead {
+      reader,
+      progress: self.clone(),
+    }
+  }
+}
+
+/// A reader reporting the bytes read from it.
+///
+/// See `Progress::reader`.
+#[derive(Debug)]
+pub struct ProgressRead<R> {
+  reader: R,
+  progress: Progress,
+}
+
+impl<R> ProgressRead<R> {
+  /// Give the wrapped reader back.
+  pub fn into_inner(self) -> R {
+    self.reader
+  }
+}
+
+impl<R> Read for ProgressRead<R>
+where R: Read
+{
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = self.reader.read(buf)?;
+    self.progress.advance(n as u64);
+    Ok(n)
+  }
+}
+
+/// Progress of all the resources loaded since the last time it was cleared.
+///
+/// Take one with `Store::progress`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgressReport {
+  /// Progress of the resources, in the order they started loading.
+  pub resources: Vec<ResourceProgress>,
+}
+
+impl ProgressReport {
+  /// Number of resources being loaded.
+  pub fn loading(&self) -> usize {
+    self.count(LoadStatus::Loading)
+  }
+
+  /// Number of resources loaded.
+  pub fn loaded(&self) -> usize {
+    self.count(LoadStatus::Loaded)
+  }
+
+  /// Number of resources that failed to load.
+  pub fn failed(&self) -> usize {
+    self.count(LoadStatus::Failed)
+  }
+
+  /// Whether no resource is being loaded.
+  pub fn is_done(&self) -> bool {
+    self.loading() == 0
+  }
+
+  /// Overall ratio of the resources that are loaded, between `0` and `1`, or `None` if no resource
+  /// was tracked.
+  ///
+  /// Every resource weighs the same; resources being loaded with no known progress count as not
+  /// loaded at all.
+  pub fn fraction(&self) -> Option<f64> {
+    if self.resources.is_empty() {
+      return None;
+    }
+
+    let sum: f64 = self
+      .resources
+      .iter()
+      .map(|resource| resource.fraction().unwrap_or(0.))
+      .sum();
+
+    Some(sum / self.resources.len() as f64)
+  }
+
+  fn count(&self, status: LoadStatus) -> usize {
+    self
+      .resources
+      .iter()
+      .filter(|resource| resource.status == status)
+      .count()
+  }
+}
+
+/// Progress of the resources loaded by a `Storage`.
+#[derive(Debug, Default)]
+pub(crate) struct ProgressTracker {
+  // all the tracked resources, in the order they started loading
+  resources: Vec<Progress>,
+  // resources being loaded, innermost last
+  in_flight: Vec<Progress>,
+}
+
+impl ProgressTracker {
+  pub(crate) fn new() -> Self {
+    Self::default()
+  }
+
+  /// Start tracking a resource being loaded.
+  pub(crate) fn start(&mut self, key: DepKey, type_name: &'static str) {
+    let progress = Progress::new(key, type_name);
+    self.resources.push(progress.clone());
+    self.in_flight.push(progress);
+  }
+
+  /// Mark the innermost resource being loaded as finished.
+  pub(crate) fn finish(&mut self, loaded: bool) {
+    if let Some(progress) = self.in_flight.pop() {
+      let status = if loaded { LoadStatus::Loaded } else { LoadStatus::Failed };
+      progress.update(|state| state.status = status);
+    }
+  }
+
+  /// Reporter of the innermost resource being loaded, if any.
+  pub(crate) fn current(&self) -> Option<Progress> {
+    self.in_flight.last().cloned()
+  }
+
+  pub(crate) fn report(&self) -> ProgressReport {
+    ProgressReport {
+      resources: self.resources.iter().map(Progress::snapshot).collect(),
+    }
+  }
+
+  /// Forget about the finished resources.
+  pub(crate) fn clear(&mut self) {
+    self
+      .resources
+      .retain(|progress| progress.snapshot().status == LoadStatus::Loading);
+  }
+}
@@ -0,0 +1,128 @@
+//! Resource provenance.
+//!
+//! A `Storage` records, for every resource it holds, where the resource came from: the backend it
+//! was loaded from, the resolved path, a hash of the bytes it was decoded from, the loading method
+//! and the generation of the `Storage` at the time of loading. Query it with
+//! `Storage::provenance`. It’s meant to be attached to bug reports, so that they state exactly
+//! which bytes produced a misbehaving in-memory state.
+//!
+//! The backend and the hash are the ones of the bytes returned by `Storage::read` – after the
+//! read middleware and the byte transforms – so loaders must read their files through it for
+//! them to be known.
+//!
+//! It also keeps track of the lifetime of every resource – when it was loaded, when it was last
+//! reloaded and how many times – which you can query with `Storage::info`, to feed an asset
+//! inspector, for instance. Times are read from the clock of the `Store` – see
+//! `StoreOpt::set_clock`.
+
+use std::any::type_name;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use key::DepKey;
+
+/// Backend a resource was loaded from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Backend {
+  /// The resource was loaded from the filesystem.
+  Filesystem,
+  /// The resource was loaded from the bytes embedded for its path – see
+  /// `StoreOpt::set_embedded_source`.
+  Embedded,
+  /// The resource is a logical resource, computed by its loader.
+  Logical,
+}
+
+/// Where a resource came from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Provenance {
+  /// Backend the resource was loaded from.
+  pub backend: Backend,
+  /// Resolved path of the resource, if it lives on the filesystem.
+  pub path: Option<PathBuf>,
+  /// Hash of the content of the resource at the time it was (re)loaded, if it was read with
+  /// `Storage::read`.
+  ///
+  /// The hash is stable within a given build of your application only.
+  pub content_hash: Option<u64>,
+  /// Name of the type of the resource.
+  pub type_name: &'static str,
+  /// Name of the method used to load the resource, for diagnostics – see `CacheKey::method_name`.
+  pub method: &'static str,
+  /// Generation of the `Storage` when the resource was (re)loaded.
+  pub generation: u64,
+}
+
+impl Provenance {
+  /// Record the provenance of a resource of type `T` loaded with method `M`, given the backend and
+  /// the hash of its content if it was read with `Storage::read`.
+  pub(crate) fn record<T, M>(
+    dep_key: &DepKey,
+    generation: u64,
+    read: Option<(Backend, u64)>,
+  ) -> Self
+  where
+    M: ?Sized,
+  {
+    let (backend, path, content_hash) = match (dep_key.path(), read) {
+      (Some(path), Some((backend, content_hash))) => {
+        (backend, Some(path.to_owned()), Some(content_hash))
+      }
+      (Some(path), None) => (Backend::Filesystem, Some(path.to_owned()), None),
+      (None, _) => (Backend::Logical, None, None),
+    };
+
+    Provenance {
+      backend,
+      path,
+      content_hash,
+      type_name: type_name::<T>(),
+      method: type_name::<M>(),
+      generation,
+    }
+  }
+}
+
+/// Hash of the content of a resource.
+pub(crate) fn content_hash(bytes: &[u8]) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  hasher.write(bytes);
+  hasher.finish()
+}
+
+/// Bookkeeping about a resource living in a `Storage`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResInfo {
+  /// When the resource was first loaded.
+  pub loaded_at: Instant,
+  /// When the resource was last reloaded, if ever.
+  pub reloaded_at: Option<Instant>,
+  /// Number of times the resource was reloaded.
+  pub reload_count: u64,
+  /// Name of the method used to load the resource, for diagnostics – see `CacheKey::method_name`.
+  pub method: &'static str,
+  /// Resolved absolute path of the resource, if it lives on the filesystem.
+  pub path: Option<PathBuf>,
+}
+
+impl ResInfo {
+  /// Bookkeeping of a resource loaded with method `M` at a given time.
+  pub(crate) fn loaded<M>(dep_key: &DepKey, now: Instant) -> Self
+  where M: ?Sized {
+    ResInfo {
+      loaded_at: now,
+      reloaded_at: None,
+      reload_count: 0,
+      method: type_name::<M>(),
+      path: dep_key.path().map(|path| path.to_owned()),
+    }
+  }
+
+  /// Record that the resource was reloaded at a given time.
+  pub(crate) fn reloaded(&mut self, now: Instant) {
+    self.reloaded_at = Some(now);
+    self.reload_count += 1;
+  }
+}
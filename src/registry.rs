@@ -0,0 +1,259 @@
+//! Runtime loader registry.
+//!
+//! Every `Storage` holds a `LoaderRegistry`, in which loaders can be registered under a name at
+//! runtime – typically by plugins providing their own resource types. The host application can then
+//! load resources with `Storage::get_any` by giving the name of the loader and a key, without
+//! knowing the type of the resource statically. Resources are returned as `AnyRes`.
+//!
+//! Loaders can also be registered per file extension – `png` for images, `gltf` for models, etc.
+//! `Storage::get_any_path` then picks the loader from the extension of the path, so that the list
+//! of files to load can come from content – an asset manifest, for instance – rather than code.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use key::{DepKey, DirKey, FSKey, FSKeyWith, LogicalKey, TypedKey};
+use load::{Load, Storage, StoreErrorOr};
+use res::AnyRes;
+
+/// Type-erased loader.
+type AnyLoader<C> = Rc<Fn(&DepKey, &mut Storage<C>, &mut C) -> Result<AnyRes, RegistryError>>;
+
+/// Keys that can be built from a `DepKey`.
+///
+/// This is needed for resources to be loaded through a `LoaderRegistry`.
+pub trait FromDepKey: Sized {
+  /// Build the key, if the `DepKey` is of the right kind.
+  fn from_dep_key(key: &DepKey) -> Option<Self>;
+}
+
+impl FromDepKey for DepKey {
+  fn from_dep_key(key: &DepKey) -> Option<Self> {
+    Some(key.clone())
+  }
+}
+
+impl FromDepKey for FSKey {
+  fn from_dep_key(key: &DepKey) -> Option<Self> {
+    match *key {
+      DepKey::Path(ref path) => Some(FSKey::new(path)),
+      DepKey::HostPath(ref path) => Some(FSKey::absolute(path)),
+      _ => None,
+    }
+  }
+}
+
+impl FromDepKey for DirKey {
+  fn from_dep_key(key: &DepKey) -> Option<Self> {
+    match *key {
+      DepKey::Dir(ref path) => Some(DirKey::new(path)),
+      _ => None,
+    }
+  }
+}
+
+impl<P> FromDepKey for FSKeyWith<P>
+where P: Clone + 'static
+{
+  fn from_dep_key(key: &DepKey) -> Option<Self> {
+    match *key {
+      DepKey::PathWith(ref path, ref param) => {
+        param.downcast_ref::<P>().map(|param| FSKeyWith::new(path, param.clone()))
+      }
+      _ => None,
+    }
+  }
+}
+
+impl FromDepKey for LogicalKey {
+  fn from_dep_key(key: &DepKey) -> Option<Self> {
+    match *key {
+      DepKey::Logical(ref s) => Some(LogicalKey::new(s.as_str())),
+      _ => None,
+    }
+  }
+}
+
+impl<K> FromDepKey for TypedKey<K>
+where K: Clone + 'static
+{
+  fn from_dep_key(key: &DepKey) -> Option<Self> {
+    match *key {
+      DepKey::Typed(ref key) => key.downcast_ref::<K>().cloned().map(TypedKey::new),
+      _ => None,
+    }
+  }
+}
+
+/// Loaders registered by name and by file extension.
+pub struct LoaderRegistry<C> {
+  loaders: HashMap<String, AnyLoader<C>>,
+  extensions: HashMap<String, AnyLoader<C>>,
+}
+
+impl<C> LoaderRegistry<C> {
+  /// Create an empty registry.
+  pub fn new() -> Self {
+    LoaderRegistry {
+      loaders: HashMap::new(),
+      extensions: HashMap::new(),
+    }
+  }
+
+  /// Register a loader for resources of type `T` using the method `M`.
+  ///
+  /// Any loader previously registered under the same name is replaced.
+  pub fn register<T, M, N>(&mut self, name: N)
+  where
+    T: Load<C, M>,
+    T::Key: FromDepKey,
+    M: 'static,
+    N: Into<String>,
+  {
+    self.loaders.insert(name.into(), erase_loader::<C, T, M>());
+  }
+
+  /// Register a loader for resources of type `T` using the method `M`, for files with the given
+  /// extension.
+  ///
+  /// Extensions are case-insensitive and may be given with or without their leading dot. Any loader
+  /// previously registered for the same extension is replaced.
+  pub fn register_extension<T, M, E>(&mut self, extension: E)
+  where
+    T: Load<C, M>,
+    T::Key: FromDepKey,
+    M: 'static,
+    E: AsRef<str>,
+  {
+    let extension = normalize_extension(extension.as_ref());
+    self.extensions.insert(extension, erase_loader::<C, T, M>());
+  }
+
+  /// Unregister the loader of an extension and return whether it was registered.
+  pub fn unregister_extension(&mut self, extension: &str) -> bool {
+    self
+      .extensions
+      .remove(&normalize_extension(extension))
+      .is_some()
+  }
+
+  /// Check whether a loader is registered for the given extension.
+  pub fn contains_extension(&self, extension: &str) -> bool {
+    self
+      .extensions
+      .contains_key(&normalize_extension(extension))
+  }
+
+  /// Iterate over all the extensions loaders are registered for, lowercase and without their
+  /// leading dot.
+  pub fn extensions<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.extensions.keys().map(String::as_str)
+  }
+
+  /// Get the loader registered for the extension of a path.
+  pub(crate) fn loader_for_path(&self, path: &Path) -> Option<AnyLoader<C>> {
+    let extension = path.extension()?.to_str()?;
+    self.extensions.get(&normalize_extension(extension)).cloned()
+  }
+
+  /// Unregister a loader and return whether it was registered.
+  pub fn unregister(&mut self, name: &str) -> bool {
+    self.loaders.remove(name).is_some()
+  }
+
+  /// Check whether a loader is registered under the given name.
+  pub fn contains(&self, name: &str) -> bool {
+    self.loaders.contains_key(name)
+  }
+
+  /// Iterate over the names of all the registered loaders.
+  pub fn names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.loaders.keys().map(String::as_str)
+  }
+
+  /// Get a loader.
+  pub(crate) fn loader(&self, name: &str) -> Option<AnyLoader<C>> {
+    self.loaders.get(name).cloned()
+  }
+}
+
+/// Erase the type of the loader of resources of type `T` using the method `M`.
+fn erase_loader<C, T, M>() -> AnyLoader<C>
+where
+  T: Load<C, M>,
+  T::Key: FromDepKey,
+  M: 'static,
+{
+  let loader = |dep_key: &DepKey, storage: &mut Storage<C>, ctx: &mut C| {
+    let key = T::Key::from_dep_key(dep_key)
+      .ok_or_else(|| RegistryError::KeyMismatch(dep_key.clone()))?;
+
+    storage
+      .get_with::<T::Key, T, M>(&key, ctx)
+      .map(AnyRes::from)
+      .map_err(|e| match e {
+        StoreErrorOr::StoreError(e) => RegistryError::Load(Box::new(e)),
+        StoreErrorOr::ResError(e) => RegistryError::Load(Box::new(e)),
+      })
+  };
+
+  Rc::new(loader)
+}
+
+/// Lowercase an extension and strip its leading dot, if any.
+fn normalize_extension(extension: &str) -> String {
+  extension.trim_start_matches('.').to_lowercase()
+}
+
+impl<C> Default for LoaderRegistry<C> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Error that might occur while loading a resource through a `LoaderRegistry`.
+#[derive(Debug)]
+pub enum RegistryError {
+  /// No loader is registered under that name.
+  UnknownLoader(String),
+  /// No loader is registered for the extension of that path.
+  UnknownExtension(PathBuf),
+  /// The key is not of the kind the loader expects.
+  KeyMismatch(DepKey),
+  /// The resource failed to load.
+  Load(Box<Error>),
+}
+
+impl fmt::Display for RegistryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      RegistryError::UnknownLoader(ref name) => write!(f, "unknown loader: {}", name),
+      RegistryError::UnknownExtension(ref path) => {
+        write!(f, "no loader for the extension of: {}", path.display())
+      }
+      RegistryError::KeyMismatch(ref key) => write!(f, "key mismatch: {}", key),
+      RegistryError::Load(ref e) => write!(f, "loading error: {}", e),
+    }
+  }
+}
+
+impl Error for RegistryError {
+  fn description(&self) -> &str {
+    match *self {
+      RegistryError::UnknownLoader(_) => "unknown loader",
+      RegistryError::UnknownExtension(_) => "unknown extension",
+      RegistryError::KeyMismatch(_) => "key mismatch",
+      RegistryError::Load(_) => "loading error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      RegistryError::Load(ref e) => Some(&**e),
+      _ => None,
+    }
+  }
+}
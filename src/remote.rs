@@ -0,0 +1,364 @@
+//! Reload mirroring across processes.
+//!
+//! This module is only available with the `remote` feature. It enables a split where a process –
+//! typically an editor – hosts the authoritative `Store` and mirrors its reload events to other
+//! processes – typically a game running on a device – over a socket.
+//!
+//!   - On the authoritative side, use a `RemoteServer` and feed it the `SyncReport`s returned by
+//!     `Store::sync` with `RemoteServer::broadcast_report`.
+//!   - On the mirroring side, connect a `RemoteClient` and call `RemoteClient::apply` before
+//!     calling `Store::sync`.
+//!
+//! Filesystem keys are sent relative to the root of the store (i.e. as VFS paths), so both sides
+//! don’t have to share the same directory layout. If payloads are sent along, the mirroring side
+//! writes them to its own root and its watcher reloads them; otherwise, the resources are just
+//! marked dirty.
+//!
+//! # Protocol
+//!
+//! Each reload is sent as a single frame:
+//!
+//!   1. A byte giving the kind of key: `0` for a VFS path, `1` for a logical key, `2` for a host
+//!      path – see `FSKey::absolute` – and `3` for a directory.
+//!   2. The length of the key, as a big-endian 32-bit unsigned integer, followed by the UTF-8 bytes
+//!      of the key.
+//!   3. A byte set to `1` if a payload follows, `0` otherwise.
+//!   4. If a payload is present, its length as a big-endian 64-bit unsigned integer followed by
+//!      its bytes.
+//!
+//! Keys longer than `MAX_KEY_LEN` and payloads longer than `MAX_PAYLOAD_LEN` are refused on both
+//! ends, so that a single frame cannot make a peer allocate unbounded memory.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use key::DepKey;
+use load::{Storage, Store, SyncReport};
+
+/// Maximum length of a key in a frame, in bytes.
+pub const MAX_KEY_LEN: u64 = 64 * 1024;
+
+/// Maximum length of a payload in a frame, in bytes.
+pub const MAX_PAYLOAD_LEN: u64 = 256 * 1024 * 1024;
+
+/// Time after which a client not taking a frame is disconnected.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A reload notification, as sent over the wire.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReloadMessage {
+  /// Key of the reloaded resource. Filesystem keys are expressed as VFS paths.
+  pub key: DepKey,
+  /// Optional raw content of the reloaded resource.
+  pub payload: Option<Vec<u8>>,
+}
+
+impl ReloadMessage {
+  /// Build the message for a resource living in a `Storage`.
+  ///
+  /// If `with_payload` is `true` and the resource lives on the filesystem, its content is read with
+  /// `Storage::read` – embedded, transformed, etc., just like the storage loaded it – and sent
+  /// along.
+  pub fn from_storage<C>(storage: &Storage<C>, key: &DepKey, with_payload: bool) -> Self {
+    match *key {
+      DepKey::Path(ref path) => {
+        let vfs_key = match storage.vfs_path(path) {
+          Some(vfs_path) => DepKey::Path(vfs_path),
+          None => DepKey::HostPath(path.clone()),
+        };
+
+        let payload = if with_payload {
+          storage.read(path).ok()
+        } else {
+          None
+        };
+
+        ReloadMessage {
+          key: vfs_key,
+          payload,
+        }
+      }
+
+      DepKey::Dir(_) => ReloadMessage {
+        key: key.clone().unprepare_key(storage.root(), storage.mounts()),
+        payload: None,
+      },
+
+      _ => ReloadMessage {
+        key: key.clone(),
+        payload: None,
+      },
+    }
+  }
+
+  /// Write the message to a stream.
+  ///
+  /// Messages about resources living at a `DepKey::PathWith` or `DepKey::Typed` key cannot be
+  /// written, nor messages which key or payload is too long – see `MAX_KEY_LEN` and
+  /// `MAX_PAYLOAD_LEN`.
+  pub fn write_to<W>(&self, w: &mut W) -> io::Result<()>
+  where W: Write {
+    let (kind, key) = match self.key {
+      DepKey::Path(ref path) => (0, path_str(path)?),
+      DepKey::Logical(ref s) => (1, s.as_str()),
+      DepKey::HostPath(ref path) => (2, path_str(path)?),
+      DepKey::Dir(ref path) => (3, path_str(path)?),
+      _ => return Err(invalid_data("typed keys cannot be serialized")),
+    };
+
+    check_len(key.len() as u64, MAX_KEY_LEN, "key too long")?;
+
+    if let Some(ref payload) = self.payload {
+      check_len(payload.len() as u64, MAX_PAYLOAD_LEN, "payload too long")?;
+    }
+
+    w.write_all(&[kind])?;
+    w.write_all(&encode_u64(key.len() as u64)[4..])?;
+    w.write_all(key.as_bytes())?;
+
+    match self.payload {
+      Some(ref payload) => {
+        w.write_all(&[1])?;
+        w.write_all(&encode_u64(payload.len() as u64))?;
+        w.write_all(payload)?;
+      }
+
+      None => w.write_all(&[0])?,
+    }
+
+    w.flush()
+  }
+
+  /// Read a message from a stream.
+  ///
+  /// Frames which key or payload is too long – see `MAX_KEY_LEN` and `MAX_PAYLOAD_LEN` – are
+  /// rejected before anything gets allocated for them.
+  pub fn read_from<R>(r: &mut R) -> io::Result<Self>
+  where R: Read {
+    let kind = read_u8(r)?;
+
+    let mut len = [0; 8];
+    r.read_exact(&mut len[4..])?;
+    let len = check_len(decode_u64(len), MAX_KEY_LEN, "key too long")?;
+    let mut key = vec![0; len];
+    r.read_exact(&mut key)?;
+    let key = String::from_utf8(key).map_err(|_| invalid_data("non UTF-8 key"))?;
+
+    let key = match kind {
+      0 => DepKey::Path(PathBuf::from(key)),
+      1 => DepKey::Logical(key),
+      2 => DepKey::HostPath(PathBuf::from(key)),
+      3 => DepKey::Dir(PathBuf::from(key)),
+      _ => return Err(invalid_data("unknown key kind")),
+    };
+
+    let payload = match read_u8(r)? {
+      0 => None,
+      1 => {
+        let mut len = [0; 8];
+        r.read_exact(&mut len)?;
+        let len = check_len(decode_u64(len), MAX_PAYLOAD_LEN, "payload too long")?;
+        let mut payload = vec![0; len];
+        r.read_exact(&mut payload)?;
+        Some(payload)
+      }
+      _ => return Err(invalid_data("invalid payload flag")),
+    };
+
+    Ok(ReloadMessage { key, payload })
+  }
+}
+
+/// Authoritative side of the mirroring.
+///
+/// Clients are accepted lazily, each time something is broadcast. Clients not taking a frame
+/// within a second are disconnected, so that a stalled client cannot block the synchronization.
+pub struct RemoteServer {
+  listener: TcpListener,
+  clients: Vec<TcpStream>,
+}
+
+impl RemoteServer {
+  /// Listen for mirroring clients on the given address.
+  pub fn bind<A>(addr: A) -> io::Result<Self>
+  where A: ToSocketAddrs {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+
+    Ok(RemoteServer {
+      listener,
+      clients: Vec::new(),
+    })
+  }
+
+  /// Address the server is listening on.
+  pub fn local_addr(&self) -> io::Result<SocketAddr> {
+    self.listener.local_addr()
+  }
+
+  /// Number of connected clients.
+  pub fn client_count(&self) -> usize {
+    self.clients.len()
+  }
+
+  /// Accept all pending clients.
+  fn accept_pending(&mut self) {
+    while let Ok((client, _)) = self.listener.accept() {
+      let blocking = client
+        .set_nonblocking(false)
+        .and_then(|_| client.set_write_timeout(Some(WRITE_TIMEOUT)));
+
+      if blocking.is_ok() {
+        let _ = client.set_nodelay(true);
+        self.clients.push(client);
+      }
+    }
+  }
+
+  /// Send a message to all clients.
+  ///
+  /// Clients that cannot be written to anymore are disconnected.
+  pub fn broadcast(&mut self, msg: &ReloadMessage) {
+    self.accept_pending();
+
+    let mut frame = Vec::new();
+    if msg.write_to(&mut frame).is_err() {
+      return;
+    }
+
+    self.clients.retain(|client| {
+      let mut client: &TcpStream = client;
+      client.write_all(&frame).is_ok()
+    });
+  }
+
+  /// Send a message for every resource reloaded during a synchronization.
+  pub fn broadcast_report<C>(
+    &mut self,
+    storage: &Storage<C>,
+    report: &SyncReport,
+    with_payloads: bool,
+  ) {
+    for key in &report.reloaded {
+      let msg = ReloadMessage::from_storage(storage, key, with_payloads);
+      self.broadcast(&msg);
+    }
+  }
+}
+
+/// Mirroring side.
+pub struct RemoteClient {
+  rx: Receiver<ReloadMessage>,
+}
+
+impl RemoteClient {
+  /// Connect to a `RemoteServer`.
+  ///
+  /// Messages are received in the background and applied with `RemoteClient::apply`.
+  pub fn connect<A>(addr: A) -> io::Result<Self>
+  where A: ToSocketAddrs {
+    let mut stream = TcpStream::connect(addr)?;
+    let (tx, rx) = channel();
+
+    thread::spawn(move || {
+      while let Ok(msg) = ReloadMessage::read_from(&mut stream) {
+        if tx.send(msg).is_err() {
+          break;
+        }
+      }
+    });
+
+    Ok(RemoteClient { rx })
+  }
+
+  /// Apply all the received messages to a `Store` and return how many were applied.
+  ///
+  /// Payloads are written to the root of the `Store`, which watcher picks them up; resources
+  /// sent without a payload are marked dirty. Either way, a future call to `Store::sync` reloads
+  /// them – once. Without the `watcher` feature, resources which payload was written are marked
+  /// dirty as well.
+  pub fn apply<C>(&mut self, store: &mut Store<C>) -> io::Result<usize> {
+    let mut applied = 0;
+
+    for msg in self.rx.try_iter() {
+      match (&msg.key, &msg.payload) {
+        (&DepKey::Path(ref path), &Some(ref payload)) => {
+          let path = mirrored_path(store.root(), path)?;
+
+          if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+          }
+
+          File::create(&path)?.write_all(payload)?;
+
+          #[cfg(not(feature = "watcher"))]
+          store.mark_dirty(msg.key.clone());
+        }
+
+        _ => store.mark_dirty(msg.key.clone()),
+      }
+
+      applied += 1;
+    }
+
+    Ok(applied)
+  }
+}
+
+/// Compute the path a payload is written to, refusing to escape the root.
+fn mirrored_path(root: &Path, vfs_path: &Path) -> io::Result<PathBuf> {
+  let mut path = root.to_owned();
+
+  for component in vfs_path.components() {
+    match component {
+      Component::Normal(c) => path.push(c),
+      Component::RootDir | Component::CurDir => (),
+      _ => return Err(invalid_data("path escaping the root")),
+    }
+  }
+
+  Ok(path)
+}
+
+fn path_str(path: &Path) -> io::Result<&str> {
+  path.to_str().ok_or_else(|| invalid_data("non UTF-8 path"))
+}
+
+/// Check the length of a part of a frame against its maximum.
+fn check_len(len: u64, max: u64, msg: &str) -> io::Result<usize> {
+  if len > max {
+    return Err(invalid_data(msg));
+  }
+
+  Ok(len as usize)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u8<R>(r: &mut R) -> io::Result<u8>
+where R: Read {
+  let mut byte = [0];
+  r.read_exact(&mut byte)?;
+  Ok(byte[0])
+}
+
+fn encode_u64(x: u64) -> [u8; 8] {
+  let mut bytes = [0; 8];
+
+  for (i, byte) in bytes.iter_mut().enumerate() {
+    *byte = (x >> (56 - 8 * i)) as u8;
+  }
+
+  bytes
+}
+
+fn decode_u64(bytes: [u8; 8]) -> u64 {
+  bytes.iter().fold(0, |x, &byte| (x << 8) | byte as u64)
+}
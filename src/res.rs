@@ -1,14 +1,49 @@
 //! Shareable resources.
 
-use std::cell::{Ref, RefCell, RefMut};
-use std::rc::Rc;
+use std::any::{type_name, Any, TypeId};
+use std::cell::{BorrowError, BorrowMutError, Cell, Ref, RefCell, RefMut};
+use std::fmt;
+use std::mem;
+use std::rc::{Rc, Weak};
 
 /// Shareable resource type.
 ///
 /// Resources are wrapped in this type. You cannot do much with an object of this type, despite
 /// borrowing immutable or mutably its content.
 #[derive(Debug)]
-pub struct Res<T>(Rc<RefCell<T>>);
+pub struct Res<T>(Rc<ResSlot<T>>);
+
+/// State of the source of a resource.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ResState {
+  /// The source of the resource is there; the resource reloads as it changes.
+  Live,
+  /// The file backing the resource was deleted. The resource keeps its last value – unless the
+  /// reload error policy says otherwise – until a file appears at its path again.
+  SourceMissing,
+}
+
+/// The shared slot a `Res` points to.
+struct ResSlot<T> {
+  value: RefCell<T>,
+  generation: Cell<u64>,
+  // bumped each time the value is borrowed mutably, so that copies of it can tell it changed
+  version: Cell<u64>,
+  state: Cell<ResState>,
+  observers: RefCell<Vec<Box<Fn(&T)>>>,
+}
+
+impl<T> fmt::Debug for ResSlot<T>
+where T: fmt::Debug
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("ResSlot")
+      .field("value", &self.value)
+      .field("generation", &self.generation)
+      .field("state", &self.state)
+      .finish()
+  }
+}
 
 impl<T> Clone for Res<T> {
   fn clone(&self) -> Self {
@@ -19,16 +54,401 @@ impl<T> Clone for Res<T> {
 impl<T> Res<T> {
   /// Wrap a value in a shareable resource.
   pub fn new(t: T) -> Self {
-    Res(Rc::new(RefCell::new(t)))
+    Res(Rc::new(ResSlot {
+      value: RefCell::new(t),
+      generation: Cell::new(0),
+      version: Cell::new(0),
+      state: Cell::new(ResState::Live),
+      observers: RefCell::new(Vec::new()),
+    }))
   }
 
   /// Borrow a resource for as long as the return value lives.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the resource is currently mutably borrowed – by a reload callback, for instance. See
+  /// `Res::try_borrow` for a non-panicking variant.
   pub fn borrow(&self) -> Ref<T> {
-    self.0.borrow()
+    self.0.value.borrow()
   }
 
   /// Mutably borrow a resource for as long as the return value lives.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the resource is currently borrowed. See `Res::try_borrow_mut` for a non-panicking
+  /// variant.
   pub fn borrow_mut(&self) -> RefMut<T> {
-    self.0.borrow_mut()
+    let value = self.0.value.borrow_mut();
+    self.0.version.set(self.0.version.get() + 1);
+    value
+  }
+
+  /// Borrow a resource for as long as the return value lives, or return an error if it’s currently
+  /// mutably borrowed.
+  pub fn try_borrow(&self) -> Result<Ref<T>, BorrowError> {
+    self.0.value.try_borrow()
+  }
+
+  /// Mutably borrow a resource for as long as the return value lives, or return an error if it’s
+  /// currently borrowed.
+  pub fn try_borrow_mut(&self) -> Result<RefMut<T>, BorrowMutError> {
+    let value = self.0.value.try_borrow_mut()?;
+    self.0.version.set(self.0.version.get() + 1);
+    Ok(value)
+  }
+
+  /// Check whether the resource is currently borrowed, mutably or not.
+  ///
+  /// If it isn’t, `Res::borrow_mut` won’t panic.
+  pub fn is_borrowed(&self) -> bool {
+    self.0.value.try_borrow_mut().is_err()
+  }
+
+  /// Check whether the resource is currently mutably borrowed.
+  ///
+  /// If it isn’t, `Res::borrow` won’t panic.
+  pub fn is_borrowed_mut(&self) -> bool {
+    self.0.value.try_borrow().is_err()
+  }
+
+  /// Take a snapshot of the resource.
+  ///
+  /// The resource is cloned under a borrow that’s released right away, so that the snapshot can
+  /// outlive reloads or be sent to another thread.
+  pub fn snapshot(&self) -> T
+  where T: Clone {
+    self.borrow().clone()
+  }
+
+  /// Extract a summary of the resource – its size, a checksum – without holding the borrow any
+  /// longer than the extraction.
+  pub fn snapshot_with<F, U>(&self, f: F) -> U
+  where F: FnOnce(&T) -> U {
+    f(&self.borrow())
+  }
+
+  /// Get a non-owning handle to the resource.
+  pub fn downgrade(&self) -> ResWeak<T> {
+    ResWeak(Rc::downgrade(&self.0))
+  }
+
+  /// Generation of the resource.
+  ///
+  /// The generation starts at `0` and is incremented each time the resource gets reloaded. You can
+  /// compare it to a value you saw earlier to cheaply know whether the resource has changed.
+  pub fn generation(&self) -> u64 {
+    self.0.generation.get()
+  }
+
+  /// Number of times the resource was borrowed mutably – by reloads and by users alike.
+  pub(crate) fn version(&self) -> u64 {
+    self.0.version.get()
+  }
+
+  /// State of the source of the resource.
+  ///
+  /// Resources which files were deleted are `ResState::SourceMissing`; tools can surface them as
+  /// broken references.
+  pub fn state(&self) -> ResState {
+    self.0.state.get()
+  }
+
+  pub(crate) fn set_state(&self, state: ResState) {
+    self.0.state.set(state);
+  }
+
+  /// Register a function to call each time the resource gets reloaded.
+  ///
+  /// The function is called right after the resource is updated, with the new value.
+  pub fn on_reload<F>(&self, f: F)
+  where F: 'static + Fn(&T) {
+    self.0.observers.borrow_mut().push(Box::new(f));
+  }
+
+  /// Replace the content of the resource with a fully staged, reloaded value, bump its generation
+  /// and return the previous value.
+  ///
+  /// This is the only place the resource gets borrowed mutably during a reload, so that readers are
+  /// never kept waiting for the reloading work.
+  pub(crate) fn replace(&self, t: T) -> T {
+    let old = mem::replace(&mut *self.borrow_mut(), t);
+    self.0.generation.set(self.generation() + 1);
+    old
+  }
+
+  /// Put back the value replaced by a reload that got rolled back, restore the generation it had
+  /// and return the rolled back value.
+  pub(crate) fn restore(&self, t: T) -> T {
+    let rolled_back = mem::replace(&mut *self.borrow_mut(), t);
+    self.0.generation.set(self.generation() - 1);
+    rolled_back
+  }
+
+  /// Notify observers that the resource has reloaded.
+  pub(crate) fn notify_reloaded(&self) {
+    // take the observers out so that they can register new observers while being notified
+    let observers = mem::replace(&mut *self.0.observers.borrow_mut(), Vec::new());
+
+    {
+      let value = self.borrow();
+
+      for observer in &observers {
+        observer(&value);
+      }
+    }
+
+    let mut current = self.0.observers.borrow_mut();
+    let added = mem::replace(&mut *current, observers);
+    current.extend(added);
+  }
+}
+
+/// Non-owning handle to a shareable resource.
+///
+/// Holding a `ResWeak<T>` doesn’t keep the resource alive: once the `Storage` evicts it and all the
+/// `Res<T>` pointing to it are dropped, `ResWeak::upgrade` returns `None`. Get one with
+/// `Res::downgrade`.
+pub struct ResWeak<T>(Weak<ResSlot<T>>);
+
+impl<T> ResWeak<T> {
+  /// Get a `Res<T>` back, if the resource is still alive.
+  pub fn upgrade(&self) -> Option<Res<T>> {
+    self.0.upgrade().map(Res)
+  }
+}
+
+impl<T> Clone for ResWeak<T> {
+  fn clone(&self) -> Self {
+    ResWeak(self.0.clone())
+  }
+}
+
+impl<T> fmt::Debug for ResWeak<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str("ResWeak")
+  }
+}
+
+/// A view of a part of a shareable resource.
+///
+/// A view is obtained by projecting a `Res<T>` – see `Res::map` – and shares the same slot as the
+/// resource: it sees its hot reloads without cloning anything nor exposing the whole resource.
+pub struct ResView<U: ?Sized>(Rc<Project<U>>);
+
+/// Borrowing through a projection.
+trait Project<U: ?Sized> {
+  fn borrow(&self) -> Ref<U>;
+
+  fn try_borrow(&self) -> Result<Ref<U>, BorrowError>;
+
+  fn generation(&self) -> u64;
+}
+
+/// A resource along with the projection of its views.
+struct Projection<T, F> {
+  res: Res<T>,
+  f: F,
+}
+
+impl<T, U, F> Project<U> for Projection<T, F>
+where
+  U: ?Sized,
+  F: Fn(&T) -> &U,
+{
+  fn borrow(&self) -> Ref<U> {
+    Ref::map(self.res.borrow(), &self.f)
+  }
+
+  fn try_borrow(&self) -> Result<Ref<U>, BorrowError> {
+    self.res.try_borrow().map(|r| Ref::map(r, &self.f))
+  }
+
+  fn generation(&self) -> u64 {
+    self.res.generation()
+  }
+}
+
+/// A view along with a further projection.
+struct ViewProjection<U: ?Sized, F> {
+  view: ResView<U>,
+  f: F,
+}
+
+impl<U, V, F> Project<V> for ViewProjection<U, F>
+where
+  U: ?Sized,
+  V: ?Sized,
+  F: Fn(&U) -> &V,
+{
+  fn borrow(&self) -> Ref<V> {
+    Ref::map(self.view.borrow(), &self.f)
+  }
+
+  fn try_borrow(&self) -> Result<Ref<V>, BorrowError> {
+    self.view.try_borrow().map(|r| Ref::map(r, &self.f))
+  }
+
+  fn generation(&self) -> u64 {
+    self.view.generation()
+  }
+}
+
+impl<T> Res<T>
+where T: 'static
+{
+  /// Get a view of a part of the resource – a field, typically.
+  ///
+  /// ```
+  /// # use warmy::Res;
+  /// struct Config { camera: (f32, f32) }
+  ///
+  /// let config = Res::new(Config { camera: (0., 1.) });
+  /// let camera = config.map(|config| &config.camera);
+  ///
+  /// assert_eq!(*camera.borrow(), (0., 1.));
+  /// ```
+  pub fn map<U, F>(&self, f: F) -> ResView<U>
+  where
+    U: 'static + ?Sized,
+    F: 'static + Fn(&T) -> &U,
+  {
+    ResView(Rc::new(Projection {
+      res: self.clone(),
+      f,
+    }))
+  }
+}
+
+impl<U> ResView<U>
+where U: ?Sized
+{
+  /// Borrow the viewed part for as long as the return value lives.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the resource is currently mutably borrowed. See `ResView::try_borrow` for a
+  /// non-panicking variant.
+  pub fn borrow(&self) -> Ref<U> {
+    self.0.borrow()
+  }
+
+  /// Borrow the viewed part for as long as the return value lives, or return an error if the
+  /// resource is currently mutably borrowed.
+  pub fn try_borrow(&self) -> Result<Ref<U>, BorrowError> {
+    self.0.try_borrow()
+  }
+
+  /// Generation of the resource.
+  ///
+  /// See `Res::generation` for further details.
+  pub fn generation(&self) -> u64 {
+    self.0.generation()
+  }
+}
+
+impl<U> ResView<U>
+where U: 'static + ?Sized
+{
+  /// Narrow the view down to a part of the viewed part.
+  pub fn map<V, F>(&self, f: F) -> ResView<V>
+  where
+    V: 'static + ?Sized,
+    F: 'static + Fn(&U) -> &V,
+  {
+    ResView(Rc::new(ViewProjection {
+      view: self.clone(),
+      f,
+    }))
+  }
+}
+
+impl<U> Clone for ResView<U>
+where U: ?Sized
+{
+  fn clone(&self) -> Self {
+    ResView(self.0.clone())
+  }
+}
+
+impl<U> fmt::Debug for ResView<U>
+where U: ?Sized + fmt::Debug
+{
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self.try_borrow() {
+      Ok(value) => f.debug_tuple("ResView").field(&&*value).finish(),
+      Err(_) => f.write_str("ResView(<borrowed>)"),
+    }
+  }
+}
+
+/// Type-erased shareable resource.
+///
+/// This is a `Res<T>` which type is only known at runtime. It’s handy when you cannot be generic
+/// over the type of resources – plugin systems, scripting bridges, etc. Get the `Res<T>` back with
+/// `AnyRes::downcast`.
+///
+/// Get the `AnyRes` of a resource living in a `Storage` with `Storage::get_dyn`.
+#[derive(Clone)]
+pub struct AnyRes {
+  type_id: TypeId,
+  type_name: &'static str,
+  res: Rc<Any>,
+}
+
+impl AnyRes {
+  /// Erase the type of a resource.
+  pub fn new<T>(res: Res<T>) -> Self
+  where T: 'static {
+    AnyRes {
+      type_id: TypeId::of::<T>(),
+      type_name: type_name::<T>(),
+      res: Rc::new(res),
+    }
+  }
+
+  /// Erase the type of a resource stored as a `Res<T>` behind a `&Any`.
+  pub(crate) fn erase<T>(res: &Any) -> Option<Self>
+  where T: 'static {
+    res.downcast_ref::<Res<T>>().cloned().map(AnyRes::new)
+  }
+
+  /// Type of the resource.
+  pub fn type_id(&self) -> TypeId {
+    self.type_id
+  }
+
+  /// Name of the type of the resource, for diagnostics.
+  pub fn type_name(&self) -> &'static str {
+    self.type_name
+  }
+
+  /// Check whether the resource is of type `T`.
+  pub fn is<T>(&self) -> bool
+  where T: 'static {
+    self.type_id == TypeId::of::<T>()
+  }
+
+  /// Get the resource back if it’s of type `T`.
+  pub fn downcast<T>(&self) -> Option<Res<T>>
+  where T: 'static {
+    self.res.downcast_ref::<Res<T>>().cloned()
+  }
+}
+
+impl fmt::Debug for AnyRes {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("AnyRes")
+      .field("type_name", &self.type_name)
+      .finish()
+  }
+}
+
+impl<T> From<Res<T>> for AnyRes
+where T: 'static
+{
+  fn from(res: Res<T>) -> Self {
+    AnyRes::new(res)
   }
 }
@@ -0,0 +1,158 @@
+//! Scoped views of a `Storage`.
+//!
+//! Handing a whole `Store` to a subsystem – the UI, the audio engine – lets it load any asset,
+//! including the ones of other subsystems. `Storage::scoped` – available on `Store` as well –
+//! returns a `ScopedStore` instead: a view of the storage rooted at a VFS directory. Filesystem
+//! keys given to it are taken relative to that directory, and keys escaping it – with `..` – are
+//! refused.
+//!
+//! ```ignore
+//! let mut ui = store.scoped("/ui");
+//! // loads /ui/button.png
+//! let button: Res<Texture> = ui.get(&FSKey::new("/button.png"), ctx)?;
+//! ```
+//!
+//! Only filesystem keys are scoped: logical and typed keys are given to the `Storage` as-is.
+
+use std::path::{Component, Path, PathBuf};
+
+use key::{self, FSKey, FSKeyWith, Key, LogicalKey, TypedKey};
+use load::{Load, Storage, StoreError, StoreErrorOr};
+use res::Res;
+
+/// Keys that can be moved into a VFS directory.
+pub trait Scope: Key {
+  /// Move the key into a VFS directory, or return `None` if it escapes it.
+  fn scope(self, prefix: &Path) -> Option<Self>;
+}
+
+impl Scope for FSKey {
+  fn scope(self, prefix: &Path) -> Option<Self> {
+    // absolute keys don’t live in the VFS at all
+    if self.is_absolute() {
+      return None;
+    }
+
+    scope_path(self.as_path(), prefix).map(FSKey::new)
+  }
+}
+
+impl<P> Scope for FSKeyWith<P>
+where
+  P: Clone,
+  FSKeyWith<P>: Key,
+{
+  fn scope(self, prefix: &Path) -> Option<Self> {
+    let path = scope_path(self.as_path(), prefix)?;
+    Some(FSKeyWith::new(path, self.param().clone()))
+  }
+}
+
+impl Scope for LogicalKey {
+  fn scope(self, _: &Path) -> Option<Self> {
+    Some(self)
+  }
+}
+
+impl<K> Scope for TypedKey<K>
+where TypedKey<K>: Key
+{
+  fn scope(self, _: &Path) -> Option<Self> {
+    Some(self)
+  }
+}
+
+/// Move a VFS path into a VFS directory, or return `None` if it escapes it.
+fn scope_path(path: &Path, prefix: &Path) -> Option<PathBuf> {
+  let relative = path.components().filter(|c| *c != Component::RootDir);
+  let scoped = key::normalize(prefix.components().chain(relative));
+
+  if scoped.starts_with(prefix) {
+    Some(scoped)
+  } else {
+    None
+  }
+}
+
+/// A view of a `Storage` rooted at a VFS directory.
+///
+/// See the documentation of the `scope` module for further details.
+pub struct ScopedStore<'a, C: 'a> {
+  storage: &'a mut Storage<C>,
+  prefix: PathBuf,
+}
+
+impl<'a, C> ScopedStore<'a, C> {
+  /// VFS directory the view is rooted at.
+  pub fn prefix(&self) -> &Path {
+    &self.prefix
+  }
+
+  /// Get a resource from the scoped `Storage` and return an error if its loading failed.
+  ///
+  /// This function uses the default loading method. Keys escaping the scope fail with
+  /// `StoreError::KeyOutsideRoot`.
+  pub fn get<K, T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C>>
+  where
+    T: Load<C>,
+    T::Key: Scope,
+    K: Clone + Into<T::Key>,
+  {
+    self.get_by(key, ctx, ())
+  }
+
+  /// Get a resource from the scoped `Storage` by using a specific method and return an error if its
+  /// loading failed.
+  pub fn get_by<K, T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, M>>
+  where
+    T: Load<C, M>,
+    T::Key: Scope,
+    K: Clone + Into<T::Key>,
+    M: 'static,
+  {
+    let key: T::Key = key.clone().into();
+    let scoped = key
+      .clone()
+      .scope(&self.prefix)
+      .ok_or_else(|| StoreErrorOr::StoreError(StoreError::KeyOutsideRoot(key.into())))?;
+
+    self.storage.get_by::<T::Key, T, M>(&scoped, ctx, method)
+  }
+
+  /// Narrow the view down to one of its directories.
+  pub fn scoped<P>(&mut self, prefix: P) -> ScopedStore<C>
+  where P: AsRef<Path> {
+    ScopedStore {
+      storage: &mut *self.storage,
+      prefix: vfs_dir(&self.prefix.join(relative(prefix.as_ref()))),
+    }
+  }
+}
+
+impl<C> Storage<C> {
+  /// Get a view of the `Storage` rooted at a VFS directory.
+  ///
+  /// See the documentation of the `scope` module for further details.
+  pub fn scoped<P>(&mut self, prefix: P) -> ScopedStore<C>
+  where P: AsRef<Path> {
+    ScopedStore {
+      storage: self,
+      prefix: vfs_dir(prefix.as_ref()),
+    }
+  }
+}
+
+/// Normalize a VFS directory, making it absolute.
+fn vfs_dir(dir: &Path) -> PathBuf {
+  key::normalize(Path::new("/").components().chain(dir.components()))
+}
+
+/// Strip the root of a path.
+fn relative(path: &Path) -> &Path {
+  path.strip_prefix("/").unwrap_or(path)
+}
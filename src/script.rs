@@ -0,0 +1,111 @@
+//! Hot-reloadable scripts.
+//!
+//! This module is only available with the `rhai` feature. A `Script` is a [rhai] source file
+//! compiled with the engine provided by the context. When a script is reloaded, it’s recompiled and
+//! the context gets a chance to migrate any state from the old script to the new one – for
+//! instance, re-running initialization code or re-binding callbacks – through
+//! `ScriptContext::migrate_script`.
+//!
+//! [rhai]: https://crates.io/crates/rhai
+
+use rhai::{Engine, ParseError, AST};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use key::FSKey;
+use load::{Load, Loaded, Storage};
+
+/// Context needed to load scripts.
+pub trait ScriptContext {
+  /// Engine used to compile scripts.
+  fn engine(&self) -> &Engine;
+
+  /// Migrate state from a script to its freshly reloaded version.
+  ///
+  /// The default implementation does nothing.
+  fn migrate_script(&mut self, _key: &FSKey, _old: &Script, _new: &Script) {}
+}
+
+/// A compiled script.
+#[derive(Clone, Debug)]
+pub struct Script {
+  key: FSKey,
+  source: String,
+  ast: AST,
+}
+
+impl Script {
+  /// Key of the script.
+  pub fn key(&self) -> &FSKey {
+    &self.key
+  }
+
+  /// Source of the script.
+  pub fn source(&self) -> &str {
+    &self.source
+  }
+
+  /// Compiled script, ready to be evaluated by the engine.
+  pub fn ast(&self) -> &AST {
+    &self.ast
+  }
+}
+
+impl<C> Load<C> for Script
+where C: ScriptContext
+{
+  type Key = FSKey;
+
+  type Error = ScriptError;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let source = storage.read_to_string(key.as_path()).map_err(ScriptError::Io)?;
+    let ast = ctx.engine().compile(&source).map_err(ScriptError::Parse)?;
+
+    Ok(Script { key, source, ast }.into())
+  }
+
+  fn after_reload(&mut self, old: &Self, _: &mut Storage<C>, ctx: &mut C) {
+    ctx.migrate_script(&self.key, old, self);
+  }
+}
+
+/// Error that might occur while loading a script.
+#[derive(Debug)]
+pub enum ScriptError {
+  /// The script couldn’t be read.
+  Io(io::Error),
+  /// The script couldn’t be compiled.
+  Parse(ParseError),
+}
+
+impl fmt::Display for ScriptError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      ScriptError::Io(ref e) => write!(f, "I/O error: {}", e),
+      ScriptError::Parse(ref e) => write!(f, "parse error: {}", e),
+    }
+  }
+}
+
+impl Error for ScriptError {
+  fn description(&self) -> &str {
+    match *self {
+      ScriptError::Io(_) => "I/O error",
+      ScriptError::Parse(_) => "parse error",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      ScriptError::Io(ref e) => Some(e),
+      ScriptError::Parse(ref e) => Some(e),
+    }
+  }
+}
@@ -0,0 +1,187 @@
+//! Shader sources with `#include` support.
+//!
+//! A `ShaderSource` is a text resource in which lines of the form `#include "path"` are replaced by
+//! the content of the file at `path`, resolved as a VFS path – i.e. relative to the root of the
+//! store. Included files are loaded as `ShaderChunk` resources and declared as dependencies of the
+//! shader, so that it gets reloaded whenever any included chunk – even transitively – changes.
+//!
+//! The preprocessor is exposed as `preprocess`, so that you can build your own resource types on
+//! top of it.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use key::{DepKey, FSKey};
+use load::{Load, Loaded, Storage, StoreError, StoreErrorOr};
+use res::Res;
+
+/// A raw piece of text, included by shader sources.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShaderChunk(pub String);
+
+impl<C> Load<C> for ShaderChunk {
+  type Key = FSKey;
+
+  type Error = io::Error;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let content = storage.read_to_string(key.as_path())?;
+    Ok(ShaderChunk(content).into())
+  }
+}
+
+/// A shader source with all its `#include` directives resolved.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShaderSource {
+  source: String,
+  includes: Vec<FSKey>,
+}
+
+impl ShaderSource {
+  /// The preprocessed source.
+  pub fn as_str(&self) -> &str {
+    &self.source
+  }
+
+  /// Keys of all the chunks the source includes, transitively.
+  pub fn includes(&self) -> &[FSKey] {
+    &self.includes
+  }
+}
+
+impl<C> Load<C> for ShaderSource {
+  type Key = FSKey;
+
+  type Error = ShaderError;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let content = storage.read_to_string(key.as_path()).map_err(ShaderError::Io)?;
+    let (source, includes) = preprocess(&content, storage, ctx)?;
+    let deps = includes.iter().cloned().map(DepKey::from).collect();
+
+    Ok(Loaded::with_deps(ShaderSource { source, includes }, deps))
+  }
+}
+
+/// Resolve all the `#include "path"` directives of a piece of text.
+///
+/// Included files are loaded as `ShaderChunk` resources in the `Storage`. The resolved text is
+/// returned along with the keys of all the chunks it includes, transitively, which you should
+/// declare as dependencies of your resource.
+pub fn preprocess<C>(
+  content: &str,
+  storage: &mut Storage<C>,
+  ctx: &mut C,
+) -> Result<(String, Vec<FSKey>), ShaderError>
+{
+  let mut source = String::with_capacity(content.len());
+  let mut includes = Vec::new();
+  let mut stack = Vec::new();
+
+  expand(content, storage, ctx, &mut source, &mut includes, &mut stack)?;
+
+  Ok((source, includes))
+}
+
+fn expand<C>(
+  content: &str,
+  storage: &mut Storage<C>,
+  ctx: &mut C,
+  source: &mut String,
+  includes: &mut Vec<FSKey>,
+  stack: &mut Vec<FSKey>,
+) -> Result<(), ShaderError>
+{
+  for line in content.lines() {
+    let directive = line.trim();
+
+    if !directive.starts_with("#include") {
+      source.push_str(line);
+      source.push('\n');
+      continue;
+    }
+
+    let path = directive["#include".len()..].trim();
+
+    if path.len() < 2 || !path.starts_with('"') || !path.ends_with('"') {
+      return Err(ShaderError::MalformedInclude(line.to_owned()));
+    }
+
+    let key = FSKey::new(&path[1..path.len() - 1]);
+
+    if stack.contains(&key) {
+      return Err(ShaderError::IncludeCycle(key.as_path().to_owned()));
+    }
+
+    let chunk: Res<ShaderChunk> = storage.get(&key, ctx).map_err(|e| match e {
+      StoreErrorOr::StoreError(e) => ShaderError::Store(e),
+      StoreErrorOr::ResError(e) => ShaderError::Io(e),
+    })?;
+
+    if !includes.contains(&key) {
+      includes.push(key.clone());
+    }
+
+    stack.push(key);
+    let chunk_content = chunk.borrow().0.clone();
+    expand(&chunk_content, storage, ctx, source, includes, stack)?;
+    stack.pop();
+  }
+
+  Ok(())
+}
+
+/// Error that might occur while preprocessing a shader source.
+#[derive(Debug)]
+pub enum ShaderError {
+  /// A file couldn’t be read.
+  Io(io::Error),
+  /// An included chunk couldn’t be registered in the `Storage`.
+  Store(StoreError),
+  /// An `#include` directive is not of the form `#include "path"`.
+  MalformedInclude(String),
+  /// A file ends up including itself.
+  IncludeCycle(PathBuf),
+}
+
+impl fmt::Display for ShaderError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      ShaderError::Io(ref e) => write!(f, "I/O error: {}", e),
+      ShaderError::Store(ref e) => write!(f, "store error: {}", e),
+      ShaderError::MalformedInclude(ref line) => write!(f, "malformed include: {}", line),
+      ShaderError::IncludeCycle(ref path) => write!(f, "include cycle: {}", path.display()),
+    }
+  }
+}
+
+impl Error for ShaderError {
+  fn description(&self) -> &str {
+    match *self {
+      ShaderError::Io(_) => "I/O error",
+      ShaderError::Store(_) => "store error",
+      ShaderError::MalformedInclude(_) => "malformed include",
+      ShaderError::IncludeCycle(_) => "include cycle",
+    }
+  }
+
+  fn cause(&self) -> Option<&Error> {
+    match *self {
+      ShaderError::Io(ref e) => Some(e),
+      ShaderError::Store(ref e) => Some(e),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,45 @@
+//! Structured records of reload activity.
+//!
+//! A `ReloadSink` receives a `ReloadRecord` for every reload attempted during `Store::sync`. Set
+//! one with `StoreOpt::set_reload_sink` in your playtest builds and forward the records to your
+//! telemetry backend, so that you can aggregate which assets designers iterate on most and which
+//! reloads fail in the field. Tag the records with `StoreOpt::set_build_id` to tell builds apart.
+
+use std::time::Duration;
+
+use key::DepKey;
+use load::ReloadOutcome;
+
+/// Record of a reload.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReloadRecord {
+  /// Key of the resource.
+  pub key: DepKey,
+  /// Name of the type of the resource.
+  pub type_name: &'static str,
+  /// Time spent reloading the resource.
+  pub duration: Duration,
+  /// Outcome of the reload.
+  pub outcome: ReloadOutcome,
+  /// Identifier of the build the `Store` runs in, if set.
+  pub build_id: Option<String>,
+}
+
+/// Receiver of reload records.
+///
+/// Any `FnMut(&ReloadRecord)` closure is a `ReloadSink`.
+pub trait ReloadSink {
+  /// Receive the record of a reload.
+  ///
+  /// This function is called synchronously during `Store::sync`: don’t block in there – queue the
+  /// record and send it from another thread instead.
+  fn record(&mut self, record: &ReloadRecord);
+}
+
+impl<F> ReloadSink for F
+where F: FnMut(&ReloadRecord)
+{
+  fn record(&mut self, record: &ReloadRecord) {
+    self(record)
+  }
+}
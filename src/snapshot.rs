@@ -0,0 +1,158 @@
+//! Frozen, read-only views of the resources of a store.
+//!
+//! Resources are shared with `Res`, which can’t cross threads. Render or audio threads rather get a
+//! `StoreSnapshot`, created by `Storage::freeze`: it holds a copy of every resident resource of
+//! the types made freezable with `Storage::set_freezable`, behind an `Arc`. A snapshot is `Send`
+//! and `Sync` and never changes: while the main thread keeps synchronizing the store, reloads only
+//! become visible to other threads with the next snapshot.
+//!
+//! Copies are made with `Clone`, so freezable types should be cheap to clone – holding their heavy
+//! data behind an `Arc`, for instance. A resource is only copied once per reload: successive
+//! snapshots share the copies of the resources that didn’t change in between.
+//!
+//! ```ignore
+//! store.set_freezable::<Mesh>();
+//!
+//! loop {
+//!   store.sync(ctx);
+//!   render_tx.send(store.freeze())?;
+//! }
+//! ```
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cache::CacheKey;
+use key::{DepKey, Key, PathNormalization};
+use mount::Mounts;
+use res::Res;
+
+/// A frozen copy of the resources of a store.
+///
+/// See the documentation of the `snapshot` module for further details.
+#[derive(Clone)]
+pub struct StoreSnapshot {
+  root: PathBuf,
+  path_normalization: PathNormalization,
+  mounts: Mounts,
+  generation: u64,
+  resources: HashMap<CacheKey, Arc<Any + Send + Sync>>,
+}
+
+impl StoreSnapshot {
+  pub(crate) fn new(
+    root: PathBuf,
+    path_normalization: PathNormalization,
+    mounts: Mounts,
+    generation: u64,
+    resources: HashMap<CacheKey, Arc<Any + Send + Sync>>,
+  ) -> Self
+  {
+    StoreSnapshot {
+      root,
+      path_normalization,
+      mounts,
+      generation,
+      resources,
+    }
+  }
+
+  /// Generation of the store at the time the snapshot was taken – see `Storage::generation`.
+  pub fn generation(&self) -> u64 {
+    self.generation
+  }
+
+  /// Number of resources in the snapshot.
+  pub fn len(&self) -> usize {
+    self.resources.len()
+  }
+
+  /// Check whether the snapshot holds no resource at all.
+  pub fn is_empty(&self) -> bool {
+    self.resources.is_empty()
+  }
+
+  /// Get the frozen resource of type `T`, loaded with the default method, living at a key.
+  pub fn get<K, T>(&self, key: &K) -> Option<Arc<T>>
+  where
+    K: Key,
+    T: 'static + Send + Sync,
+  {
+    self.get_by::<K, T, ()>(key, ())
+  }
+
+  /// Get the frozen resource of type `T`, loaded with a specific method, living at a key.
+  pub fn get_by<K, T, M>(&self, key: &K, _: M) -> Option<Arc<T>>
+  where
+    K: Key,
+    T: 'static + Send + Sync,
+    M: 'static,
+  {
+    let key = self
+      .mounts
+      .prepare_key(key.clone(), &self.root, self.path_normalization);
+    let cache_key = CacheKey::with_method::<T, M>(key.into());
+
+    self
+      .resources
+      .get(&cache_key)
+      .and_then(|resource| resource.clone().downcast().ok())
+  }
+
+  /// Iterate over the keys of the resources in the snapshot.
+  ///
+  /// A key backing several resources of different types is yielded once per resource.
+  pub fn keys<'a>(&'a self) -> impl Iterator<Item = &'a DepKey> + 'a {
+    self.resources.keys().map(CacheKey::dep_key)
+  }
+}
+
+/// A copy of a resource made for a snapshot.
+pub(crate) struct Frozen {
+  // generation of the `Storage` when the resource was registered
+  registered: u64,
+  // version of the resource when it was copied
+  version: u64,
+  pub(crate) resource: Arc<Any + Send + Sync>,
+}
+
+/// Function copying a cached resource for a snapshot – see `freeze`.
+pub(crate) type Freezer = fn(&Any, u64, Option<Frozen>) -> Option<Frozen>;
+
+/// Copy a cached resource of type `T`, registered at a given generation of the `Storage`, for a
+/// snapshot.
+///
+/// The previous copy of the resource is reused if the resource wasn’t reloaded nor borrowed mutably
+/// since.
+pub(crate) fn freeze<T>(cached: &Any, registered: u64, previous: Option<Frozen>) -> Option<Frozen>
+where T: 'static + Clone + Send + Sync {
+  let res = cached.downcast_ref::<Res<T>>()?;
+  let version = res.version();
+
+  let resource = match previous {
+    Some(previous) if previous.registered == registered && previous.version == version => {
+      previous.resource
+    }
+
+    _ => Arc::new(res.borrow().clone()),
+  };
+
+  Some(Frozen {
+    registered,
+    version,
+    resource,
+  })
+}
+
+impl fmt::Debug for StoreSnapshot {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("StoreSnapshot")
+      .field("root", &self.root)
+      .field("generation", &self.generation)
+      .field("resources", &self.resources.keys().collect::<Vec<_>>())
+      .finish()
+  }
+}
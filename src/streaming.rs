@@ -0,0 +1,222 @@
+//! Chunked world streaming.
+//!
+//! This module is only available with the `streaming` feature. A `StreamingGrid` splits a 2D world
+//! into square cells, each of them backed by a resource living at a key you compute from the cell
+//! coordinates. As a focus point – typically, the camera or the player – moves, `update` loads the
+//! cells within a given radius around it, nearest cells first, and evicts the cells beyond that
+//! radius from the `Storage`. Loaded cells are regular resources: editing the file of a cell
+//! reloads it like any other resource.
+//!
+//! Loading is spread over several updates: at most a given number of cells – the *budget* – are
+//! loaded per update, so that calling `update` once per frame doesn’t stall when the focus point
+//! jumps far away.
+
+use std::collections::{HashMap, HashSet};
+
+use key::DepKey;
+use load::{Load, Storage};
+use res::Res;
+
+/// Coordinates of a cell.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cell {
+  /// Horizontal coordinate.
+  pub x: i32,
+  /// Vertical coordinate.
+  pub y: i32,
+}
+
+impl Cell {
+  /// Create a cell from its coordinates.
+  pub fn new(x: i32, y: i32) -> Self {
+    Cell { x, y }
+  }
+
+  /// Cell containing a world-space position, for a given cell size.
+  pub fn containing(position: [f32; 2], cell_size: f32) -> Self {
+    Cell {
+      x: (position[0] / cell_size).floor() as i32,
+      y: (position[1] / cell_size).floor() as i32,
+    }
+  }
+
+  /// Squared distance to another cell, in cells.
+  fn distance2(&self, other: &Cell) -> i64 {
+    let dx = (self.x - other.x) as i64;
+    let dy = (self.y - other.y) as i64;
+    dx * dx + dy * dy
+  }
+}
+
+/// What happened during a `StreamingGrid::update`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StreamingReport {
+  /// Cells loaded during the update, nearest first.
+  pub loaded: Vec<Cell>,
+  /// Cells evicted during the update.
+  pub evicted: Vec<Cell>,
+  /// Cells that failed to load during the update, along with the error message.
+  ///
+  /// They’re not retried until they get out of the radius and back in.
+  pub failed: Vec<(Cell, String)>,
+  /// Number of cells within the radius still waiting to be loaded.
+  pub pending: usize,
+}
+
+/// A grid of resources streamed in and out around a focus point.
+///
+/// `T` is the type of the resources backing cells and `K` the type of their keys.
+pub struct StreamingGrid<T, K> {
+  cell_size: f32,
+  radius: i32,
+  budget: usize,
+  key_of: Box<Fn(Cell) -> K>,
+  cells: HashMap<Cell, Res<T>>,
+  failed: HashSet<Cell>,
+}
+
+impl<T, K> StreamingGrid<T, K> {
+  /// Create a grid of square cells of a given world-space size, which key is given by `key_of`.
+  ///
+  /// The grid has a radius of `1` cell and a budget of `1` cell per update; change them with
+  /// `set_radius` and `set_budget`.
+  pub fn new<F>(cell_size: f32, key_of: F) -> Self
+  where F: 'static + Fn(Cell) -> K {
+    StreamingGrid {
+      cell_size,
+      radius: 1,
+      budget: 1,
+      key_of: Box::new(key_of),
+      cells: HashMap::new(),
+      failed: HashSet::new(),
+    }
+  }
+
+  /// Change the radius, in cells, around the focus point within which cells are loaded.
+  pub fn set_radius(self, radius: u32) -> Self {
+    StreamingGrid {
+      radius: radius as i32,
+      ..self
+    }
+  }
+
+  /// Change the maximum number of cells loaded per update.
+  pub fn set_budget(self, budget: usize) -> Self {
+    StreamingGrid { budget, ..self }
+  }
+
+  /// Size of the cells.
+  pub fn cell_size(&self) -> f32 {
+    self.cell_size
+  }
+
+  /// Radius, in cells, around the focus point within which cells are loaded.
+  pub fn radius(&self) -> u32 {
+    self.radius as u32
+  }
+
+  /// Maximum number of cells loaded per update.
+  pub fn budget(&self) -> usize {
+    self.budget
+  }
+
+  /// Get a loaded cell.
+  pub fn get(&self, cell: &Cell) -> Option<&Res<T>> {
+    self.cells.get(cell)
+  }
+
+  /// Iterate over all the loaded cells.
+  pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a Cell, &'a Res<T>)> + 'a {
+    self.cells.iter()
+  }
+
+  /// Move the focus point to a world-space position, evicting the cells that are now too far away
+  /// and loading the nearest missing ones, within the budget.
+  pub fn update<C>(
+    &mut self,
+    focus: [f32; 2],
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> StreamingReport
+  where
+    T: Load<C>,
+    K: Into<T::Key>,
+  {
+    let center = Cell::containing(focus, self.cell_size);
+    let radius2 = (self.radius as i64) * (self.radius as i64);
+    let mut report = StreamingReport::default();
+
+    // evict the cells beyond the radius
+    let far: Vec<_> = self
+      .cells
+      .keys()
+      .filter(|cell| cell.distance2(&center) > radius2)
+      .cloned()
+      .collect();
+
+    for cell in far {
+      self.cells.remove(&cell);
+
+      let key: T::Key = (self.key_of)(cell).into();
+      let dep_key: DepKey = key.into();
+      storage.evict(&dep_key);
+      report.evicted.push(cell);
+    }
+
+    self.failed.retain(|cell| cell.distance2(&center) <= radius2);
+
+    // find the missing cells, nearest first
+    let mut missing = Vec::new();
+
+    for y in center.y - self.radius..=center.y + self.radius {
+      for x in center.x - self.radius..=center.x + self.radius {
+        let cell = Cell::new(x, y);
+
+        if cell.distance2(&center) <= radius2
+          && !self.cells.contains_key(&cell)
+          && !self.failed.contains(&cell)
+        {
+          missing.push(cell);
+        }
+      }
+    }
+
+    missing.sort_by_key(|cell| (cell.distance2(&center), *cell));
+
+    let budget = self.budget.min(missing.len());
+    report.pending = missing.len() - budget;
+
+    for cell in missing.into_iter().take(budget) {
+      let key: T::Key = (self.key_of)(cell).into();
+
+      match storage.get::<T::Key, T>(&key, ctx) {
+        Ok(res) => {
+          self.cells.insert(cell, res);
+          report.loaded.push(cell);
+        }
+
+        Err(e) => {
+          self.failed.insert(cell);
+          report.failed.push((cell, e.to_string()));
+        }
+      }
+    }
+
+    report
+  }
+
+  /// Evict all the loaded cells from the `Storage`.
+  pub fn clear<C>(&mut self, storage: &mut Storage<C>)
+  where
+    T: Load<C>,
+    K: Into<T::Key>,
+  {
+    for (cell, _) in self.cells.drain() {
+      let key: T::Key = (self.key_of)(cell).into();
+      let dep_key: DepKey = key.into();
+      storage.evict(&dep_key);
+    }
+
+    self.failed.clear();
+  }
+}
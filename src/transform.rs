@@ -0,0 +1,135 @@
+//! Byte transforms applied to files before they reach loaders.
+//!
+//! Release assets are often shipped compressed while development ones are kept loose. A
+//! `ByteTransform` registered for an extension with `StoreOpt::add_byte_transform` sits between
+//! the source and the loaders: `Storage::read` – and thus the built-in loaders – transforms the
+//! bytes of the files with that extension. Furthermore, when a file doesn’t exist, a file with the
+//! same path and a registered extension appended is read instead: with a transform registered for
+//! `gz`, the key `/config.json` loads `config.json` in development and `config.json.gz` in
+//! release, with the very same loader code. Editing either file reloads the resource.
+//!
+//! A few transforms are built in:
+//!
+//!   - `Gzip`, with the `gzip` feature: decompresses gzip files.
+//!   - `Zstd`, with the `zstd` feature: decompresses Zstandard files.
+//!
+//! Any function from bytes to bytes can be used as a transform as well:
+//!
+//! ```ignore
+//! let opt = StoreOpt::default()
+//!   .add_byte_transform("gz", Gzip)
+//!   .add_byte_transform("xor", |bytes: Vec<u8>| {
+//!     Ok(bytes.into_iter().map(|b| b ^ 0x5a).collect())
+//!   });
+//! ```
+//!
+//! Transforms depend on extensions only. Logic applying to every file – decryption, signature
+//! verification, patching – rather belongs to the read middleware, added with
+//! `StoreOpt::add_read_middleware`, which stages run before transforms and get the key of the file.
+
+#[cfg(feature = "gzip")]
+use flate2::read::GzDecoder;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+#[cfg(feature = "gzip")]
+use std::io::Read;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "zstd")]
+use zstd;
+
+/// A transformation of the bytes of files.
+pub trait ByteTransform {
+  /// Transform the bytes of a file.
+  fn transform(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>>;
+}
+
+impl<F> ByteTransform for F
+where F: Fn(Vec<u8>) -> io::Result<Vec<u8>>
+{
+  fn transform(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    self(bytes)
+  }
+}
+
+/// Gzip decompression.
+#[cfg(feature = "gzip")]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Gzip;
+
+#[cfg(feature = "gzip")]
+impl ByteTransform for Gzip {
+  fn transform(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+  }
+}
+
+/// Zstandard decompression.
+#[cfg(feature = "zstd")]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Zstd;
+
+#[cfg(feature = "zstd")]
+impl ByteTransform for Zstd {
+  fn transform(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    zstd::stream::decode_all(&bytes[..])
+  }
+}
+
+/// Byte transforms, by extension.
+#[derive(Default)]
+pub(crate) struct ByteTransforms {
+  transforms: Vec<(String, Box<ByteTransform>)>,
+}
+
+impl ByteTransforms {
+  /// Register a transform for an extension, replacing the one already registered, if any.
+  pub(crate) fn insert(&mut self, extension: String, transform: Box<ByteTransform>) {
+    self.transforms.retain(|&(ref ext, _)| *ext != extension);
+    self.transforms.push((extension, transform));
+  }
+
+  /// Extensions with a transform, in registration order.
+  pub(crate) fn extensions<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.transforms.iter().map(|&(ref ext, _)| ext.as_str())
+  }
+
+  /// Transform registered for the extension of a path, if any.
+  pub(crate) fn get(&self, path: &Path) -> Option<&ByteTransform> {
+    let extension = path.extension().and_then(OsStr::to_str)?;
+
+    self
+      .transforms
+      .iter()
+      .find(|&&(ref ext, _)| ext == extension)
+      .map(|&(_, ref transform)| &**transform)
+  }
+
+  /// Paths of the transformed variants of a path, in registration order – e.g. `config.json.gz`
+  /// for `config.json`.
+  pub(crate) fn variants<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
+    self.extensions().map(move |ext| {
+      let mut variant = path.as_os_str().to_owned();
+      variant.push(".");
+      variant.push(ext);
+      PathBuf::from(variant)
+    })
+  }
+
+  /// Path a transformed variant stands for, if it has a registered extension – e.g. `config.json`
+  /// for `config.json.gz`.
+  pub(crate) fn plain_path(&self, path: &Path) -> Option<PathBuf> {
+    self.get(path).and_then(|_| {
+      let stem = path.file_stem()?;
+      Some(path.with_file_name(stem))
+    })
+  }
+}
+
+impl fmt::Debug for ByteTransforms {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_list().entries(self.extensions()).finish()
+  }
+}
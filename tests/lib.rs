@@ -1,10 +1,36 @@
+#[cfg(feature = "fluent")]
+extern crate fluent_bundle;
+#[cfg(feature = "async")]
+extern crate futures_core;
+#[cfg(feature = "image")]
+extern crate image;
+#[cfg(feature = "rhai")]
+extern crate rhai;
+#[cfg(any(
+  feature = "serde-bincode",
+  feature = "serde-json",
+  feature = "serde-msgpack",
+  feature = "serde-ron",
+  feature = "serde-toml",
+  feature = "serde-yaml"
+))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde-json")]
+extern crate serde_json;
+#[cfg(feature = "async")]
+extern crate tokio;
 extern crate warmy;
 
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
-use warmy::{FSKey, Load, Loaded, LogicalKey, Res, Storage, Store};
+use warmy::process::Processed as ProcessedMethod;
+use warmy::{
+  DepKey, FSKey, Load, Loaded, LogicalKey, ManualClock, Manifest, Process, ProcessParams, Res,
+  Storage, Store, StoreOpt,
+};
 
 mod utils;
 
@@ -126,6 +152,39 @@ impl<C> Load<C> for Zoo {
   }
 }
 
+/// A resource that fails to load if its file doesn’t exist.
+#[derive(Debug, Eq, PartialEq)]
+struct Strict(String);
+
+#[derive(Debug, Eq, PartialEq)]
+struct StrictErr;
+
+impl Error for StrictErr {
+  fn description(&self) -> &str {
+    "missing file"
+  }
+}
+
+impl fmt::Display for StrictErr {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str(self.description())
+  }
+}
+
+impl<C> Load<C> for Strict {
+  type Key = FSKey;
+
+  type Error = StrictErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let mut s = String::new();
+    let mut fh = File::open(key.as_path()).map_err(|_| StrictErr)?;
+    let _ = fh.read_to_string(&mut s);
+
+    Ok(Strict(s).into())
+  }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 struct LogicalFoo(String);
 
@@ -171,6 +230,7 @@ fn create_store() {
   utils::with_store(|_: Store<()>| {})
 }
 
+#[cfg(feature = "watcher")]
 #[test]
 fn foo() {
   utils::with_store(|mut store| {
@@ -215,6 +275,7 @@ fn foo() {
   })
 }
 
+#[cfg(feature = "watcher")]
 #[test]
 fn foo_with_leading_slash() {
   utils::with_store(|mut store| {
@@ -259,6 +320,7 @@ fn foo_with_leading_slash() {
   })
 }
 
+#[cfg(feature = "watcher")]
 #[test]
 fn two_same_paths_diff_types() {
   utils::with_store(|mut store| {
@@ -276,8 +338,33 @@ fn two_same_paths_diff_types() {
     let foo: Res<Foo> = store.get(&foo_key, ctx).unwrap();
     assert_eq!(foo.borrow().0.as_str(), "foobarzoo");
 
-    let bar: Result<Res<Bar>, _> = store.get(&bar_key, ctx);
-    assert!(bar.is_err());
+    let bar: Res<Bar> = store.get(&bar_key, ctx).unwrap();
+    assert_eq!(bar.borrow().0.as_str(), "bar");
+    assert_eq!(store.keys().count(), 1);
+
+    // both resources reload when the file changes
+    let bar_generation = bar.generation();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"zoobarfoo"[..]);
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if foo.borrow().0.as_str() == "zoobarfoo" && bar.generation() != bar_generation {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
   })
 }
 
@@ -290,6 +377,7 @@ fn logical_resource() {
   })
 }
 
+#[cfg(feature = "watcher")]
 #[test]
 fn logical_with_deps() {
   utils::with_store(|mut store| {
@@ -358,7 +446,7 @@ impl Load<Ctx> for FooWithCtx {
   ) -> Result<Loaded<Self>, Self::Error>
   {
     // load as if it was a Foo
-    let Loaded { res, deps } = <Foo as Load<_, ()>>::load(key, storage, ctx)?;
+    let Loaded { res, deps, .. } = <Foo as Load<_, ()>>::load(key, storage, ctx)?;
 
     // increment the counter
     ctx.count += 1;
@@ -368,6 +456,7 @@ impl Load<Ctx> for FooWithCtx {
   }
 }
 
+#[cfg(feature = "watcher")]
 #[test]
 fn foo_with_ctx() {
   utils::with_store(|mut store: Store<Ctx>| {
@@ -436,3 +525,5331 @@ fn foo_by_stupid() {
     assert_eq!(&r.borrow().0, expected);
   })
 }
+
+#[test]
+fn methods_cache_identity() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("/foo.txt");
+    let path = store.root().join("foo.txt");
+
+    let write = |content: &str| {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    write("before");
+
+    // the same key loaded with two methods backs two resources
+    let foo: Res<Foo> = store.get(&key, ctx).unwrap();
+    assert!(store.contains::<Foo>(&key));
+    assert!(!store.contains_by::<_, Foo, _>(&key, Stupid));
+    let stupid: Res<Foo> = store.get_by(&key, ctx, Stupid).unwrap();
+    assert!(store.contains_by::<_, Foo, _>(&key, Stupid));
+    assert_eq!(foo.borrow().0, "before");
+    assert_eq!(stupid.borrow().0, "stupid");
+    assert_eq!(store.get_all_dyn(&DepKey::Path(path.clone())).len(), 2);
+
+    // both are cached…
+    stupid.borrow_mut().0.push('!');
+    let foo_: Res<Foo> = store.get(&key, ctx).unwrap();
+    let stupid_: Res<Foo> = store.get_by(&key, ctx, Stupid).unwrap();
+    assert_eq!(foo_.borrow().0, "before");
+    assert_eq!(stupid_.borrow().0, "stupid!");
+
+    // … and each reloads with its own method
+    write("after");
+    let report = store.reload_type::<Foo>(ctx);
+    assert_eq!(report.reloaded, 2);
+    assert_eq!(foo.borrow().0, "after");
+    assert_eq!(stupid.borrow().0, "stupid");
+  })
+}
+
+#[test]
+fn dependency_graph() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let foo_key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let log_foo_key = LogicalKey::new(foo_key.as_path().to_str().unwrap());
+    let _: Res<LogicalFoo> = store.get(&log_foo_key, ctx).unwrap();
+
+    let graph = store.dependency_graph();
+    let foo_dep_key = DepKey::Path(path);
+    let log_foo_dep_key = DepKey::from(log_foo_key);
+
+    assert_eq!(graph.nodes().len(), 2);
+    assert_eq!(graph.edges(), &[(foo_dep_key, log_foo_dep_key)]);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains("foo.txt\" -> \"foo.txt\""));
+  })
+}
+
+#[test]
+fn dependency_queries() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let log_foo_key = LogicalKey::new("/foo.txt");
+    let foo_dep_key = DepKey::from(FSKey::new("/foo.txt"));
+    let log_foo_dep_key = DepKey::from(log_foo_key.clone());
+
+    {
+      let _: Res<LogicalFoo> = store.get(&log_foo_key, ctx).unwrap();
+
+      assert_eq!(store.dependents_of(&foo_dep_key), vec![log_foo_dep_key.clone()]);
+      assert_eq!(store.dependencies_of(&log_foo_dep_key), vec![DepKey::Path(path)]);
+      assert!(store.dependents_of(&log_foo_dep_key).is_empty());
+    }
+
+    // evicted resources depend on nothing anymore
+    store.evict(&log_foo_dep_key);
+
+    assert!(store.dependents_of(&foo_dep_key).is_empty());
+    assert!(store.dependencies_of(&log_foo_dep_key).is_empty());
+  })
+}
+
+// a file which might include another one, depending on it
+#[derive(Debug)]
+struct Including(String);
+
+impl<C> Load<C> for Including {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let content = storage.read_to_string(key.as_path()).map_err(|_| FooErr)?;
+
+    if content.starts_with("include ") {
+      let included_key = FSKey::new(&content["include ".len()..]);
+      let included: Res<Including> = storage.get(&included_key, ctx).map_err(|_| FooErr)?;
+      let content = included.borrow().0.clone();
+
+      Ok(Loaded::with_deps(Including(content), vec![included_key.into()]))
+    } else {
+      Ok(Including(content).into())
+    }
+  }
+}
+
+#[test]
+fn dependency_walks() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for &(name, content) in &[
+      ("top.txt", "include /middle.txt"),
+      ("middle.txt", "include /leaf.txt"),
+      ("leaf.txt", "leaf"),
+    ] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    }
+
+    let top: Res<Including> = store.get(&FSKey::new("/top.txt"), ctx).unwrap();
+    assert_eq!(top.borrow().0, "leaf");
+
+    let top_key = DepKey::Path(store.root().join("top.txt"));
+    let middle_key = DepKey::Path(store.root().join("middle.txt"));
+    let leaf_key = DepKey::Path(store.root().join("leaf.txt"));
+
+    // the returned keys can be queried in turn
+    let dependents = store.dependents_of(&FSKey::new("/leaf.txt").into());
+    assert_eq!(dependents, vec![middle_key.clone()]);
+    assert_eq!(store.dependents_of(&dependents[0]), vec![top_key.clone()]);
+    assert!(store.dependents_of(&top_key).is_empty());
+
+    let dependencies = store.dependencies_of(&FSKey::new("/top.txt").into());
+    assert_eq!(dependencies, vec![middle_key]);
+    assert_eq!(store.dependencies_of(&dependencies[0]), vec![leaf_key.clone()]);
+    assert!(store.dependencies_of(&leaf_key).is_empty());
+  })
+}
+
+// a texture resolution derived from the quality level held in the context
+#[derive(Debug)]
+struct Resolution(u32);
+
+impl Load<u32> for Resolution {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    _: &mut Storage<u32>,
+    ctx: &mut u32,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let loaded = Loaded::from(Resolution(256 << *ctx));
+
+    // the fixed resolution doesn’t depend on the quality level
+    if key.as_str() == "fixed" {
+      Ok(loaded)
+    } else {
+      Ok(loaded.with_ctx_tag("quality"))
+    }
+  }
+}
+
+#[test]
+fn ctx_tags() {
+  utils::with_tmp_dir(|tmp_dir| {
+    let opt = StoreOpt::default().set_root(tmp_dir).set_update_await_time_ms(0);
+    let mut store: Store<u32> = Store::new(opt).unwrap();
+    let quality = &mut 0;
+
+    let albedo: Res<Resolution> = store.get(&LogicalKey::new("albedo"), quality).unwrap();
+    let normal: Res<Resolution> = store.get(&LogicalKey::new("normal"), quality).unwrap();
+    let fixed: Res<Resolution> = store.get(&LogicalKey::new("fixed"), quality).unwrap();
+    assert_eq!(
+      store.tagged("quality"),
+      vec![LogicalKey::new("albedo").into(), LogicalKey::new("normal").into()]
+    );
+    assert!(store.tagged("locale").is_empty());
+
+    // changing the context doesn’t reload anything by itself
+    *quality = 2;
+    store.sync(quality);
+    assert_eq!(albedo.borrow().0, 256);
+
+    store.invalidate_tag("quality");
+    store.sync(quality);
+    assert_eq!(albedo.borrow().0, 1024);
+    assert_eq!(normal.borrow().0, 1024);
+    assert_eq!(fixed.borrow().0, 256);
+
+    // evicted resources aren’t tagged anymore
+    store.evict(&LogicalKey::new("normal").into());
+    assert_eq!(store.tagged("quality"), vec![LogicalKey::new("albedo").into()]);
+  })
+}
+
+#[test]
+fn resource_groups() {
+  use warmy::methods::{Bytes, Text};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let root = store.root().to_owned();
+    let write = |name: &str, content: &str| {
+      let mut fh = File::create(root.join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    for name in &["hud.txt", "forest.txt", "cave.txt"] {
+      write(name, "v1");
+    }
+
+    let hud = FSKey::new("/hud.txt");
+    let forest = FSKey::new("/forest.txt");
+    let cave = FSKey::new("/cave.txt");
+
+    // the HUD is shared by both levels
+    let hud_res: Res<Text> = store.get_in_group("level1", &hud, ctx).unwrap();
+    let forest_res: Res<Text> = store.get_in_group("level1", &forest, ctx).unwrap();
+    let _: Res<Text> = store.get_in_group("level2", &hud, ctx).unwrap();
+    let cave_res: Res<Text> = store.get_in_group("level2", &cave, ctx).unwrap();
+
+    // groups hold resources, not keys: other resources living at the same keys are left alone
+    let forest_bytes: Res<Bytes> = store.get(&forest, ctx).unwrap();
+
+    let keys = |names: &[&str]| -> Vec<DepKey> {
+      names.iter().map(|name| DepKey::Path(root.join(name))).collect()
+    };
+    assert_eq!(store.group_keys("level1"), keys(&["forest.txt", "hud.txt"]));
+    assert!(store.group_keys("level3").is_empty());
+
+    for name in &["hud.txt", "forest.txt", "cave.txt"] {
+      write(name, "v2");
+    }
+
+    let report = store.reload_group("level1", ctx);
+    assert!(report.is_ok());
+    assert_eq!(hud_res.borrow().0, "v2");
+    assert_eq!(forest_res.borrow().0, "v2");
+    assert_eq!(cave_res.borrow().0, "v1");
+    assert_eq!(forest_bytes.generation(), 0);
+
+    // shared resources survive the eviction of a group
+    assert_eq!(store.evict_group("level1"), 1);
+    assert!(store.group_keys("level1").is_empty());
+    assert!(!store.contains::<Text>(&forest));
+    assert!(store.contains::<Bytes>(&forest));
+    assert!(store.contains_key(&hud.clone().into()));
+    assert_eq!(store.group_keys("level2"), keys(&["cave.txt", "hud.txt"]));
+  })
+}
+
+#[cfg(feature = "failure-injection")]
+#[test]
+fn injected_failures() {
+  use warmy::fault::Fault;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    store.set_fault(key.clone(), Fault::error(|| FooErr).only_on_load());
+
+    let r: Result<Res<Foo>, _> = store.get(&key, ctx);
+    assert_eq!(r.err(), Some(warmy::StoreErrorOr::ResError(FooErr)));
+
+    let proxied: Res<Foo> = store
+      .get_proxied(&key, || Foo("proxy".to_owned()), ctx)
+      .unwrap();
+    assert_eq!(proxied.borrow().0.as_str(), "proxy");
+  })
+}
+
+#[test]
+fn migrations() {
+  use warmy::migrate::{MigrationError, Migrations};
+
+  let migrations = Migrations::<u32, &'static str>::new(3)
+    .register(0, |x| Ok(x + 1))
+    .register(1, |x| if x > 10 { Err("too big") } else { Ok(x * 10) });
+
+  assert_eq!(migrations.migrate(3, 42), Ok(42));
+  assert_eq!(migrations.migrate(1, 4), Err(MigrationError::MissingStep(2)));
+  assert_eq!(
+    migrations.migrate(1, 11),
+    Err(MigrationError::StepFailed {
+      from: 1,
+      error: "too big",
+    })
+  );
+  assert_eq!(
+    migrations.migrate(4, 0),
+    Err(MigrationError::FromTheFuture {
+      version: 4,
+      current: 3,
+    })
+  );
+}
+
+#[test]
+fn contains() {
+  utils::with_store(|mut store: Store<()>| {
+    let key = LogicalKey::new("mem/uid/32197");
+
+    assert!(!store.contains::<Zoo>(&key));
+    assert!(!store.contains_key(&key.clone().into()));
+
+    let _: Res<Zoo> = store.get(&key, &mut ()).unwrap();
+
+    assert!(store.contains::<Zoo>(&key));
+    assert!(!store.contains::<LogicalFoo>(&key));
+    assert!(store.contains_key(&key.into()));
+  })
+}
+
+#[test]
+fn keys() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let zoo_key = LogicalKey::new("mem/uid/32197");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let _: Res<Zoo> = store.get(&zoo_key, ctx).unwrap();
+    let _: Res<Foo> = store.get(&FSKey::new("foo.txt"), ctx).unwrap();
+
+    assert_eq!(store.keys().count(), 2);
+    assert_eq!(
+      store.keys_of::<Zoo>().collect::<Vec<_>>(),
+      vec![&DepKey::from(zoo_key)]
+    );
+    assert_eq!(
+      store.keys_of::<Foo>().collect::<Vec<_>>(),
+      vec![&DepKey::Path(path)]
+    );
+    assert_eq!(store.keys_of::<Bar>().count(), 0);
+  })
+}
+
+#[test]
+fn evict_keys() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for name in &["a.txt", "b.txt"] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(name.as_bytes());
+    }
+
+    let _: Res<Zoo> = store.get(&LogicalKey::new("mem/uid/32197"), ctx).unwrap();
+    let _: Res<Foo> = store.get(&FSKey::new("a.txt"), ctx).unwrap();
+    let _: Res<Foo> = store.get(&FSKey::new("b.txt"), ctx).unwrap();
+
+    // the keys yielded by the store can be fed back to it
+    let keys: Vec<DepKey> = store.keys().cloned().collect();
+    assert_eq!(keys.len(), 3);
+
+    for key in &keys {
+      assert!(store.contains_key(key));
+      assert!(store.evict(key));
+      assert!(!store.contains_key(key));
+    }
+
+    assert_eq!(store.keys().count(), 0);
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn sync_report() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let r: Res<Foo> = store.get(&key, ctx).unwrap();
+    assert_eq!(r.generation(), 0);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Bye!"[..]);
+    }
+
+    let start_time = ::std::time::Instant::now();
+    let mut succeeded = 0;
+    loop {
+      let report = store.sync(ctx);
+      assert_eq!(report.reloads_failed, 0);
+      succeeded += report.reloads_succeeded;
+
+      if r.borrow().0.as_str() == "Bye!" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    assert!(succeeded >= 1);
+    assert!(r.generation() >= 1);
+  })
+}
+
+#[test]
+fn custom_cache_backend() {
+  use std::any::Any;
+  use std::cell::Cell;
+  use std::rc::Rc;
+  use warmy::cache::{CacheKey, HashCache};
+  use warmy::CacheBackend;
+
+  struct CountingCache {
+    cache: HashCache,
+    saves: Rc<Cell<usize>>,
+  }
+
+  impl CacheBackend for CountingCache {
+    fn save(&mut self, key: CacheKey, value: Box<Any>) {
+      self.saves.set(self.saves.get() + 1);
+      self.cache.save(key, value)
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<&Any> {
+      self.cache.get(key)
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<Box<Any>> {
+      self.cache.remove(key)
+    }
+
+    fn keys<'a>(&'a self) -> Box<Iterator<Item = &'a CacheKey> + 'a> {
+      self.cache.keys()
+    }
+  }
+
+  let saves = Rc::new(Cell::new(0));
+  let cache_saves = saves.clone();
+
+  utils::with_store_opt(
+    move |opt| {
+      opt.set_cache_backend(CountingCache {
+        cache: HashCache::new(),
+        saves: cache_saves.clone(),
+      })
+    },
+    |mut store: Store<()>| {
+      let key = LogicalKey::new("mem/uid/32197");
+      let _: Res<Zoo> = store.get(&key, &mut ()).unwrap();
+      let _: Res<Zoo> = store.get(&key, &mut ()).unwrap();
+    },
+  );
+
+  assert_eq!(saves.get(), 1);
+}
+
+#[cfg(feature = "remote")]
+#[test]
+fn remote_mirroring() {
+  use warmy::methods::Text;
+  use warmy::remote::{ReloadMessage, RemoteClient, RemoteServer};
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let ctx = &mut ();
+    let key = FSKey::new("foo.txt");
+
+    // written before the store starts watching, so that only the mirroring reloads the resource
+    {
+      let mut fh = File::create(tmp_dir.join("foo.txt")).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    // payloads are read the way the store reads its files
+    let opt = StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_update_await_time_ms(0)
+      .add_read_middleware(|_: &DepKey, bytes: Vec<u8>| Ok(bytes.to_ascii_uppercase()));
+    let mut store: Store<()> = Store::new(opt).unwrap();
+
+    let r: Res<Text> = store.get(&key, ctx).unwrap();
+    let msg = ReloadMessage::from_storage(&store, &DepKey::Path(tmp_dir.join("foo.txt")), true);
+    assert_eq!(msg.key, FSKey::new("/foo.txt").into());
+    assert_eq!(msg.payload, Some(b"HELLO, WORLD!".to_vec()));
+
+    let mut server = RemoteServer::bind("127.0.0.1:0").unwrap();
+    let mut client = RemoteClient::connect(server.local_addr().unwrap()).unwrap();
+
+    server.broadcast(&ReloadMessage {
+      key: key.clone().into(),
+      payload: Some(b"Bye!".to_vec()),
+    });
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      client.apply(&mut store).unwrap();
+      store.sync(ctx);
+
+      if r.borrow().0.as_str() == "BYE!" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a remote event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    // the mirrored payload reloads the resource once
+    utils::settle(&mut store, ctx);
+    assert_eq!(r.generation(), 1);
+
+    // oversize frames are refused without allocating them
+    let mut frame = vec![0, 0, 0, 0, 1, b'a', 1];
+    frame.extend_from_slice(&[0xff; 8]);
+    let err = ReloadMessage::read_from(&mut &frame[..]).unwrap_err();
+    assert_eq!(err.kind(), ::std::io::ErrorKind::InvalidData);
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn on_reload_observers() {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let r: Res<Foo> = store.get(&key, ctx).unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let seen_ = seen.clone();
+    r.on_reload(move |foo| seen_.borrow_mut().push(foo.0.clone()));
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Bye!"[..]);
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if seen.borrow().last().map(String::as_str) == Some("Bye!") {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn adaptive_await_time() {
+  utils::with_store_opt(
+    |opt| opt.set_adaptive_await_time_ms(Some(1000)),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("foo.txt");
+      let path = store.root().join("foo.txt");
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(&b"Hello, world!"[..]);
+      }
+
+      let r: Res<Foo> = store.get(&key, ctx).unwrap();
+
+      for expected in &["Bye!", "Hello again!"] {
+        {
+          let mut fh = File::create(&path).unwrap();
+          let _ = fh.write_all(expected.as_bytes());
+        }
+
+        let start_time = ::std::time::Instant::now();
+        loop {
+          store.sync(ctx);
+
+          if r.borrow().0.as_str() == *expected {
+            break;
+          }
+
+          if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+            panic!(
+              "more than {} milliseconds were spent waiting for a filesystem event",
+              QUEUE_TIMEOUT_MS
+            );
+          }
+        }
+      }
+    },
+  )
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn reload_events() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let foo_key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let log_foo_key = LogicalKey::new(foo_key.as_path().to_str().unwrap());
+    let log_foo: Res<LogicalFoo> = store.get(&log_foo_key, ctx).unwrap();
+    let events = store.events();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Bye!"[..]);
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if log_foo.borrow().0.as_str() == "Bye!" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    let keys: Vec<_> = events.try_iter().map(|event| event.key).collect();
+    assert_eq!(keys[0], DepKey::Path(path));
+    assert!(keys.contains(&log_foo_key.into()));
+  })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct WeakFoo {
+  content: String,
+  notified: usize,
+}
+
+impl<C> Load<C> for WeakFoo {
+  type Key = LogicalKey;
+
+  type Error = LogicalFooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let fs_key = FSKey::new(key.as_str());
+    let foo: Res<Foo> = storage.get(&fs_key, ctx).unwrap();
+
+    let weak_foo = WeakFoo {
+      content: foo.borrow().0.clone(),
+      notified: 0,
+    };
+
+    Ok(Loaded::with_weak_deps(weak_foo, vec![fs_key.into()]))
+  }
+
+  fn dependency_reloaded(&mut self, _: &DepKey, _: &mut Storage<C>, _: &mut C) {
+    self.notified += 1;
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn weak_deps() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let foo_key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let weak_foo_key = LogicalKey::new(foo_key.as_path().to_str().unwrap());
+    let weak_foo: Res<WeakFoo> = store.get(&weak_foo_key, ctx).unwrap();
+    let foo: Res<Foo> = store.get(&foo_key, ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Bye!"[..]);
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if foo.borrow().0.as_str() == "Bye!" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    assert_eq!(weak_foo.borrow().content.as_str(), "Hello, world!");
+    assert!(weak_foo.borrow().notified >= 1);
+    assert_eq!(weak_foo.generation(), 0);
+  })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Lifecycle {
+  content: String,
+  events: Vec<&'static str>,
+}
+
+impl<C> Load<C> for Lifecycle {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let foo = <Foo as Load<C>>::load(key, storage, ctx)?.res;
+
+    let lifecycle = Lifecycle {
+      content: foo.0,
+      events: Vec::new(),
+    };
+
+    Ok(lifecycle.into())
+  }
+
+  fn before_reload(&self, _: &mut Storage<C>, _: &mut C) {
+    assert!(self.events.is_empty());
+  }
+
+  fn after_reload(&mut self, old: &Self, _: &mut Storage<C>, _: &mut C) {
+    self.events = old.events.clone();
+    self.events.push("reloaded");
+  }
+
+  fn unload(&mut self) {
+    self.events.push("unloaded");
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn lifecycle_hooks() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("foo.txt");
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Hello, world!"[..]);
+    }
+
+    let r: Res<Lifecycle> = store.get(&key, ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(&b"Bye!"[..]);
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if r.borrow().content.as_str() == "Bye!" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    assert_eq!(r.borrow().events, vec!["reloaded"]);
+
+    assert!(store.evict(&key.clone().into()));
+    assert!(!store.contains::<Lifecycle>(&key));
+    assert_eq!(r.borrow().events, vec!["reloaded", "unloaded"]);
+    assert!(!store.evict(&key.into()));
+  })
+}
+
+#[cfg(any(
+  feature = "serde-bincode",
+  feature = "serde-json",
+  feature = "serde-msgpack",
+  feature = "serde-ron",
+  feature = "serde-toml",
+  feature = "serde-yaml"
+))]
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+struct Config {
+  name: String,
+  tags: Vec<String>,
+}
+
+/// Load a `Config` written in a given format with the matching method.
+#[cfg(any(
+  feature = "serde-bincode",
+  feature = "serde-json",
+  feature = "serde-msgpack",
+  feature = "serde-ron",
+  feature = "serde-toml",
+  feature = "serde-yaml"
+))]
+fn check_config_method<M, F>(file: &str, content: &[u8], method: F)
+where
+  Config: Load<(), M, Key = FSKey>,
+  F: Fn() -> M,
+  M: 'static, {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new(file);
+    let path = store.root().join(file);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(content);
+    }
+
+    let config: Res<Config> = store.get_by(&key, ctx, method()).unwrap();
+
+    assert_eq!(
+      *config.borrow(),
+      Config {
+        name: "warmy".to_owned(),
+        tags: vec!["hot".to_owned(), "reload".to_owned()],
+      }
+    );
+  })
+}
+
+#[cfg(feature = "serde-json")]
+#[test]
+fn json_method() {
+  use warmy::methods::Json;
+
+  check_config_method(
+    "config.json",
+    br#"{ "name": "warmy", "tags": ["hot", "reload"] }"#,
+    || Json,
+  );
+}
+
+// version 0 spelled the color "colour", version 1 renamed it and version 2 upper-cases it
+#[cfg(feature = "serde-json")]
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+struct Paint {
+  color: String,
+  version: u32,
+}
+
+#[cfg(feature = "serde-json")]
+impl warmy::migrate::Versioned<serde_json::Value> for Paint {
+  type Error = &'static str;
+
+  fn migrations() -> warmy::migrate::Migrations<serde_json::Value, Self::Error> {
+    warmy::migrate::Migrations::new(2)
+      .register(0, |mut doc: serde_json::Value| {
+        let colour = doc
+          .as_object_mut()
+          .and_then(|fields| fields.remove("colour"))
+          .ok_or("no colour")?;
+        doc["color"] = colour;
+        Ok(doc)
+      })
+      .register(1, |mut doc: serde_json::Value| {
+        let color = doc["color"].as_str().ok_or("no color")?.to_uppercase();
+        doc["color"] = color.into();
+        Ok(doc)
+      })
+  }
+}
+
+#[cfg(feature = "serde-json")]
+#[test]
+fn versioned_json_method() {
+  use warmy::methods::{FormatError, VersionedJson};
+  use warmy::migrate::{MigrationError, VersionedError};
+  use warmy::StoreErrorOr;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for &(name, content) in &[
+      ("old.json", r#"{ "colour": "red" }"#),
+      ("v1.json", r#"{ "version": 1, "color": "blue" }"#),
+      ("current.json", r#"{ "version": 2, "color": "Green" }"#),
+      ("future.json", r#"{ "version": 3, "color": "?" }"#),
+    ] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    }
+
+    let paint = |store: &mut Store<()>, ctx: &mut (), name: &str| {
+      store.get_by::<_, Paint, _>(&FSKey::new(name), ctx, VersionedJson)
+    };
+
+    // older documents are migrated up to the current version
+    let old = paint(&mut store, ctx, "old.json").unwrap();
+    assert_eq!(
+      *old.borrow(),
+      Paint {
+        color: "RED".to_owned(),
+        version: 2,
+      }
+    );
+
+    let v1 = paint(&mut store, ctx, "v1.json").unwrap();
+    assert_eq!(v1.borrow().color, "BLUE");
+
+    let current = paint(&mut store, ctx, "current.json").unwrap();
+    assert_eq!(current.borrow().color, "Green");
+
+    let future = paint(&mut store, ctx, "future.json");
+    assert!(match future {
+      Err(StoreErrorOr::ResError(FormatError::Format(VersionedError::Migration(
+        MigrationError::FromTheFuture { version: 3, .. },
+      )))) => true,
+      _ => false,
+    });
+  })
+}
+
+// the former name keeps working, both as a type and as a value
+#[cfg(feature = "serde-json")]
+#[allow(deprecated)]
+#[test]
+fn deprecated_json_method() {
+  use warmy::methods::JSON;
+
+  check_config_method::<JSON, _>(
+    "config.json",
+    br#"{ "name": "warmy", "tags": ["hot", "reload"] }"#,
+    || JSON,
+  );
+}
+
+#[cfg(feature = "serde-json")]
+#[test]
+fn auto_method() {
+  use warmy::methods::Auto;
+
+  check_config_method(
+    "config.json",
+    br#"{ "name": "warmy", "tags": ["hot", "reload"] }"#,
+    || Auto,
+  );
+}
+
+#[cfg(feature = "serde-json")]
+#[test]
+fn auto_unsupported_extension() {
+  use warmy::methods::{Auto, AutoError, FormatError};
+  use warmy::StoreErrorOr;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("config.ini");
+
+    {
+      let mut fh = File::create(store.root().join("config.ini")).unwrap();
+      let _ = fh.write_all(b"name = warmy");
+    }
+
+    match store.get_by::<_, Config, _>(&key, ctx, Auto) {
+      Err(StoreErrorOr::ResError(FormatError::Format(AutoError::UnsupportedExtension(ext)))) => {
+        assert_eq!(ext, "ini")
+      }
+      _ => panic!("the extension should be unsupported"),
+    }
+  })
+}
+
+#[cfg(all(feature = "derive", feature = "serde-json"))]
+#[derive(Debug, Deserialize, Eq, Load, PartialEq)]
+#[warmy(format = "json")]
+struct DerivedConfig {
+  name: String,
+}
+
+#[cfg(all(feature = "derive", feature = "serde-json"))]
+#[test]
+fn derive_json() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("derived.json");
+
+    {
+      let mut fh = File::create(store.root().join("derived.json")).unwrap();
+      let _ = fh.write_all(br#"{ "name": "warmy" }"#);
+    }
+
+    let config: Res<DerivedConfig> = store.get(&key, ctx).unwrap();
+
+    assert_eq!(
+      *config.borrow(),
+      DerivedConfig {
+        name: "warmy".to_owned(),
+      }
+    );
+  })
+}
+
+#[cfg(feature = "serde-bincode")]
+#[test]
+fn bincode_method() {
+  use warmy::methods::Bincode;
+
+  let content = [
+    5, 0, 0, 0, 0, 0, 0, 0, b'w', b'a', b'r', b'm', b'y', // name
+    2, 0, 0, 0, 0, 0, 0, 0, // tags
+    3, 0, 0, 0, 0, 0, 0, 0, b'h', b'o', b't', // tags[0]
+    6, 0, 0, 0, 0, 0, 0, 0, b'r', b'e', b'l', b'o', b'a', b'd', // tags[1]
+  ];
+
+  check_config_method("config.bin", &content, || Bincode);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn image_method() {
+  use image::RgbaImage;
+  use warmy::methods::ImageRgba8;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("pixel.tga");
+
+    // a 1×1, uncompressed, 32-bit true-color TGA image
+    let content = [
+      0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 32, 8, // header
+      0x30, 0x20, 0x10, 0xff, // BGRA pixel
+    ];
+
+    {
+      let mut fh = File::create(store.root().join("pixel.tga")).unwrap();
+      let _ = fh.write_all(&content);
+    }
+
+    let image: Res<RgbaImage> = store.get_by(&key, ctx, ImageRgba8).unwrap();
+
+    assert_eq!(image.borrow().dimensions(), (1, 1));
+    assert_eq!(image.borrow().get_pixel(0, 0).data, [0x10, 0x20, 0x30, 0xff]);
+  })
+}
+
+#[test]
+fn raw_resources() {
+  use warmy::methods::{Bytes, Text};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("raw.txt")).unwrap();
+      let _ = fh.write_all(b"Hello, world!");
+      let mut fh = File::create(store.root().join("raw.bin")).unwrap();
+      let _ = fh.write_all(&[0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    let text: Res<Text> = store.get(&FSKey::new("/raw.txt"), ctx).unwrap();
+    assert_eq!(text.borrow().0, "Hello, world!");
+
+    let bytes: Res<Bytes> = store.get(&FSKey::new("/raw.bin"), ctx).unwrap();
+    assert_eq!(bytes.borrow().0, vec![0xde, 0xad, 0xbe, 0xef]);
+
+    // the same file can be grabbed both ways, but only valid UTF-8 is text
+    let bytes: Res<Bytes> = store.get(&FSKey::new("/raw.txt"), ctx).unwrap();
+    assert_eq!(bytes.borrow().0, b"Hello, world!".to_vec());
+    assert!(store.get::<FSKey, Text>(&FSKey::new("/raw.bin"), ctx).is_err());
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn byte_transforms() {
+  use std::io;
+  use warmy::methods::Text;
+
+  let xor = |bytes: Vec<u8>| -> io::Result<Vec<u8>> {
+    Ok(bytes.into_iter().map(|b| b ^ 0x5a).collect())
+  };
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let write = |content: &str| {
+      let scrambled: Vec<u8> = content.bytes().map(|b| b ^ 0x5a).collect();
+      let mut fh = File::create(tmp_dir.join("config.txt.xor")).unwrap();
+      let _ = fh.write_all(&scrambled);
+    };
+
+    write("Hello, world!");
+
+    let opt = StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_update_await_time_ms(0)
+      .add_byte_transform("xor", xor);
+    assert_eq!(opt.byte_transform_extensions().collect::<Vec<_>>(), vec!["xor"]);
+
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    // the plain path falls back to the transformed variant; the variant is transformed as well
+    let text: Res<Text> = store.get(&FSKey::new("/config.txt"), ctx).unwrap();
+    assert_eq!(text.borrow().0, "Hello, world!");
+    let direct: Res<Text> = store.get(&FSKey::new("/config.txt.xor"), ctx).unwrap();
+    assert_eq!(direct.borrow().0, "Hello, world!");
+
+    // editing the transformed file reloads the resource living at the plain path
+    write("Bye, world!");
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if text.borrow().0 == "Bye, world!" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[test]
+fn read_middleware() {
+  use std::io;
+  use warmy::methods::Text;
+  use warmy::StoreErrorOr;
+
+  utils::with_tmp_dir(|tmp_dir| {
+    for &(name, content) in &[("a.txt", "Hello, world!"), ("tampered.txt", "Bye, world!")] {
+      let scrambled: Vec<u8> = content.bytes().map(|b| b ^ 0x5a).collect();
+      let mut fh = File::create(tmp_dir.join(name)).unwrap();
+      let _ = fh.write_all(&scrambled);
+    }
+
+    let opt = StoreOpt::default()
+      .set_root(tmp_dir)
+      .add_read_middleware(|key: &DepKey, bytes: Vec<u8>| match *key {
+        DepKey::Path(ref path) if path.ends_with("tampered.txt") => {
+          Err(io::Error::new(io::ErrorKind::InvalidData, "bad signature"))
+        }
+        _ => Ok(bytes),
+      })
+      .add_read_middleware(|_: &DepKey, bytes: Vec<u8>| {
+        Ok(bytes.into_iter().map(|b| b ^ 0x5a).collect())
+      });
+    assert_eq!(opt.read_middleware_len(), 2);
+
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    let text: Res<Text> = store.get(&FSKey::new("/a.txt"), ctx).unwrap();
+    assert_eq!(text.borrow().0, "Hello, world!");
+
+    match store.get::<FSKey, Text>(&FSKey::new("/tampered.txt"), ctx) {
+      Err(StoreErrorOr::ResError(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+      Err(StoreErrorOr::StoreError(e)) => panic!("unexpected store error: {}", e),
+      Ok(_) => panic!("the middleware rejects the file"),
+    }
+  })
+}
+
+#[cfg(all(feature = "fluent", feature = "watcher"))]
+#[test]
+fn fluent_method() {
+  use fluent_bundle::{FluentBundle, FluentResource};
+  use warmy::methods::{Fluent, FluentKey};
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let ctx = &mut ();
+    let key = FluentKey::new("/locales", "main.ftl", &["fr-CA", "fr", "en-US"]);
+    let locales = tmp_dir.join("locales");
+
+    let write = |locale: &str, content: &str| {
+      let mut fh = File::create(locales.join(locale).join("main.ftl")).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    // the directories must exist before the store starts watching them, otherwise the watches on
+    // them might be added too late to see the edits below
+    for locale in &["fr-CA", "fr", "en-US"] {
+      ::std::fs::create_dir_all(locales.join(locale)).unwrap();
+    }
+
+    // fr-CA has no file and is skipped
+    write("fr", "hello = Bonjour");
+    write("en-US", "hello = Hello\nbye = Bye");
+
+    let opt = StoreOpt::default().set_root(tmp_dir).set_update_await_time_ms(0);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+
+    let bundle: Res<FluentBundle<FluentResource>> = store.get_by(&key, ctx, Fluent).unwrap();
+
+    let format = |id: &str| {
+      let bundle = bundle.borrow();
+      let message = bundle.get_message(id).unwrap();
+      let mut errors = Vec::new();
+      let value = bundle.format_pattern(message.value().unwrap(), None, &mut errors);
+      value.into_owned()
+    };
+
+    assert_eq!(format("hello"), "Bonjour");
+    assert_eq!(format("bye"), "Bye");
+
+    // editing a fallback reloads the bundle
+    write("en-US", "hello = Hello\nbye = Goodbye");
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if format("bye") == "Goodbye" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    assert_eq!(format("hello"), "Bonjour");
+
+    // creating the file of a skipped locale reloads the bundle as well
+    write("fr-CA", "hello = Allô");
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if format("hello") == "Allô" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    assert_eq!(format("bye"), "Goodbye");
+  })
+}
+
+#[cfg(feature = "serde-msgpack")]
+#[test]
+fn msgpack_method() {
+  use warmy::methods::MessagePack;
+
+  let content = [
+    0x92, 0xa5, b'w', b'a', b'r', b'm', b'y', // name
+    0x92, 0xa3, b'h', b'o', b't', 0xa6, b'r', b'e', b'l', b'o', b'a', b'd', // tags
+  ];
+
+  check_config_method("config.msgpack", &content, || MessagePack);
+}
+
+#[cfg(feature = "serde-ron")]
+#[test]
+fn ron_method() {
+  use warmy::methods::Ron;
+
+  check_config_method(
+    "config.ron",
+    br#"(name: "warmy", tags: ["hot", "reload"])"#,
+    || Ron,
+  );
+}
+
+#[cfg(feature = "serde-toml")]
+#[test]
+fn toml_method() {
+  use warmy::methods::Toml;
+
+  check_config_method(
+    "config.toml",
+    b"name = \"warmy\"\ntags = [\"hot\", \"reload\"]\n",
+    || Toml,
+  );
+}
+
+#[cfg(feature = "serde-yaml")]
+#[test]
+fn yaml_method() {
+  use warmy::methods::Yaml;
+
+  check_config_method("config.yaml", b"name: warmy\ntags:\n  - hot\n  - reload\n", || Yaml);
+}
+
+// the former name keeps working, both as a type and as a value
+#[cfg(feature = "serde-yaml")]
+#[allow(deprecated)]
+#[test]
+fn deprecated_yaml_method() {
+  use warmy::methods::YAML;
+
+  check_config_method::<YAML, _>(
+    "config.yaml",
+    b"name: warmy\ntags:\n  - hot\n  - reload\n",
+    || YAML,
+  );
+}
+
+#[test]
+fn preflight() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let present = FSKey::new("present.txt");
+    let missing = FSKey::new("missing.txt");
+
+    {
+      let mut fh = File::create(store.root().join("present.txt")).unwrap();
+      let _ = fh.write_all(b"present");
+    }
+
+    let manifest = Manifest::new()
+      .add::<_, Strict>(present.clone())
+      .add::<_, Strict>(missing.clone())
+      .add::<_, Zoo>(LogicalKey::new("zoo"));
+
+    let report = store.preflight(&manifest, ctx);
+
+    assert!(!report.is_ok());
+    assert_eq!(report.loaded, 2);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].key, missing.into());
+    assert_eq!(report.failures[0].error, "missing file");
+    assert!(store.contains::<Strict>(&present));
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn cancellation() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("cancel.txt");
+    let path = store.root().join("cancel.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let strict: Res<Strict> = store.get(&key, ctx).unwrap();
+    let token = store.cancellation_token().clone();
+    token.cancel();
+    assert!(store.cancellation_token().is_cancelled());
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    // reloads are held back while cancelled
+    let start_time = ::std::time::Instant::now();
+    while start_time.elapsed() < ::std::time::Duration::from_millis(500) {
+      store.sync(ctx);
+    }
+
+    assert_eq!(strict.borrow().0, "before");
+
+    token.reset();
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if strict.borrow().0 == "after" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[test]
+fn root_recreation() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("root.txt");
+    let root = store.root().to_owned();
+
+    {
+      let mut fh = File::create(root.join("root.txt")).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let strict: Res<Strict> = store.get(&key, ctx).unwrap();
+
+    // delete the root and let the store notice it
+    ::std::fs::remove_dir_all(&root).unwrap();
+    assert!(!store.sync(ctx).root_rewatched);
+
+    // recreate it; the watch is re-established and the resource rescanned
+    ::std::fs::create_dir(&root).unwrap();
+
+    {
+      let mut fh = File::create(root.join("root.txt")).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    assert!(store.sync(ctx).root_rewatched);
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if strict.borrow().0 == "after" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn provenance() {
+  use warmy::methods::Text;
+  use warmy::provenance::Backend;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("provenance.txt");
+    let path = store.root().join("provenance.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let _: Res<Text> = store.get(&key, ctx).unwrap();
+    let _: Res<Zoo> = store.get(&LogicalKey::new("zoo"), ctx).unwrap();
+
+    let before = store.provenance(&key.clone().into()).unwrap().clone();
+    assert_eq!(before.backend, Backend::Filesystem);
+    assert_eq!(before.path, Some(path.clone()));
+    assert!(before.content_hash.is_some());
+    assert!(before.type_name.ends_with("Text"));
+    assert_eq!(before.method, "()");
+    assert_eq!(before.generation, 1);
+
+    let zoo = store.provenance(&LogicalKey::new("zoo").into()).unwrap();
+    assert_eq!(zoo.backend, Backend::Logical);
+    assert_eq!(zoo.path, None);
+    assert_eq!(zoo.generation, 2);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      if !store.sync(ctx).reloaded.is_empty() {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    let after = store.provenance(&key.into()).unwrap();
+    assert_ne!(after.content_hash, before.content_hash);
+    assert_eq!(after.generation, 3);
+    assert_eq!(store.generation(), 3);
+  })
+}
+
+#[test]
+fn resource_info() {
+  let clock = ManualClock::new();
+
+  utils::with_store_opt(
+    |opt| opt.set_clock(clock.clone()),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("info.txt");
+      let path = store.root().join("info.txt");
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"info");
+      }
+
+      let _: Res<Foo> = store.get(&key, ctx).unwrap();
+
+      // each resource living at a key has its own bookkeeping
+      let loaded = store.info::<_, Foo>(&key).unwrap().clone();
+      assert_eq!(loaded.reloaded_at, None);
+      assert_eq!(loaded.reload_count, 0);
+      assert_eq!(loaded.method, "()");
+      assert_eq!(loaded.path, Some(path));
+      assert!(store.info_by::<_, Foo, _>(&key, Stupid).is_none());
+
+      // times are read from the clock of the store
+      clock.advance(::std::time::Duration::from_secs(1));
+      store.reload_type::<Foo>(ctx);
+      store.reload_type::<Foo>(ctx);
+
+      let reloaded = store.info::<_, Foo>(&key).unwrap();
+      assert_eq!(reloaded.loaded_at, loaded.loaded_at);
+      assert_eq!(
+        reloaded.reloaded_at,
+        Some(loaded.loaded_at + ::std::time::Duration::from_secs(1))
+      );
+      assert_eq!(reloaded.reload_count, 2);
+
+      store.evict(&key.clone().into());
+      assert!(store.info::<_, Foo>(&key).is_none());
+    },
+  )
+}
+
+#[test]
+fn store_snapshot() {
+  use std::thread;
+  use warmy::methods::{Bytes, Text};
+
+  fn is_send_sync<T: Send + Sync>(_: &T) {}
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("frozen.txt");
+    let write = |content: &str| {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    write("before");
+
+    let key = FSKey::new("/frozen.txt");
+    let text: Res<Text> = store.get(&key, ctx).unwrap();
+    let _: Res<Bytes> = store.get(&key, ctx).unwrap();
+
+    // only freezable types are part of snapshots
+    assert!(store.freeze().is_empty());
+    store.set_freezable::<Text>();
+
+    let before = store.freeze();
+    is_send_sync(&before);
+    assert_eq!(before.len(), 1);
+    assert_eq!(before.generation(), store.generation());
+    assert!(before.get::<_, Bytes>(&key).is_none());
+
+    // unchanged resources aren’t copied again
+    let again = store.freeze();
+    let copy = |snapshot: &warmy::StoreSnapshot| snapshot.get::<_, Text>(&key).unwrap();
+    assert!(::std::sync::Arc::ptr_eq(&copy(&before), &copy(&again)));
+
+    // resources mutated in place are copied again
+    text.borrow_mut().0.push('!');
+    assert_eq!(store.freeze().get::<_, Text>(&key).unwrap().0, "before!");
+
+    // reloads only show in the next snapshots
+    write("after");
+    store.mark_dirty(key.clone());
+    store.sync(ctx);
+    assert_eq!(text.borrow().0, "after");
+
+    let after = store.freeze();
+    let (old, new) = thread::spawn(move || {
+      let old = before.get::<_, Text>(&FSKey::new("frozen.txt")).unwrap();
+      let new = after.get::<_, Text>(&FSKey::new("frozen.txt")).unwrap();
+      (old.0.clone(), new.0.clone())
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(old, "before");
+    assert_eq!(new, "after");
+  })
+}
+
+#[test]
+fn generation_diff() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let a = LogicalKey::new("a");
+    let b = LogicalKey::new("b");
+    let c = LogicalKey::new("c");
+
+    let _: Res<Zoo> = store.get(&a, ctx).unwrap();
+    let _: Res<Zoo> = store.get(&b, ctx).unwrap();
+    let start = store.generation();
+
+    store.mark_dirty(a.clone());
+    assert_eq!(store.sync(ctx).reloads_succeeded, 1);
+    assert!(store.evict(&b.clone().into()));
+    let _: Res<Zoo> = store.get(&c, ctx).unwrap();
+    let now = store.generation();
+
+    let diff = store.diff(start, now);
+    assert_eq!(diff.changed, vec![DepKey::from(a.clone())]);
+    assert_eq!(diff.appeared, vec![DepKey::from(c.clone())]);
+    assert_eq!(diff.disappeared, vec![DepKey::from(b.clone())]);
+
+    let reversed = store.diff(now, start);
+    assert_eq!(reversed.changed, diff.changed);
+    assert_eq!(reversed.appeared, diff.disappeared);
+    assert_eq!(reversed.disappeared, diff.appeared);
+
+    assert!(store.diff(now, now).is_empty());
+    assert_eq!(store.diff(0, start).appeared.len(), 2);
+  })
+}
+
+#[test]
+fn sync_hooks() {
+  let opt = |opt: StoreOpt<Vec<&'static str>>| {
+    opt
+      .set_before_sync(|ctx: &mut Vec<_>| ctx.push("before"))
+      .set_after_sync(|ctx: &mut Vec<_>| ctx.push("after"))
+  };
+
+  utils::with_store_opt(opt, |mut store: Store<Vec<&'static str>>| {
+    let mut ctx = Vec::new();
+
+    store.sync(&mut ctx);
+    store.sync(&mut ctx);
+
+    assert_eq!(ctx, vec!["before", "after", "before", "after"]);
+  })
+}
+
+#[test]
+fn recent_syncs() {
+  utils::with_store_opt(
+    |opt| opt.set_sync_history_len(2),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("history.txt");
+
+      {
+        let mut fh = File::create(store.root().join("history.txt")).unwrap();
+        let _ = fh.write_all(b"history");
+      }
+
+      let _: Res<Strict> = store.get(&key, ctx).unwrap();
+      assert_eq!(store.recent_syncs().count(), 0);
+
+      store.sync(ctx);
+      store.mark_dirty(key.clone());
+      store.sync(ctx);
+      store.sync(ctx);
+
+      // only the last two reports are kept
+      let reports = store.recent_syncs().collect::<Vec<_>>();
+      assert_eq!(reports.len(), 2);
+      assert_eq!(reports[0].reloaded, vec![DepKey::Path(store.root().join("history.txt"))]);
+      assert!(reports[1].reloaded.is_empty());
+    },
+  )
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn resource_map() {
+  use warmy::{ResourceMap, ResourceMapKey};
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let ctx = &mut ();
+    let dir = tmp_dir.join("strings");
+    let write = |name: &str, content: &[u8]| {
+      let mut fh = File::create(dir.join(name)).unwrap();
+      let _ = fh.write_all(content);
+    };
+
+    // the whole tree must exist before the store starts watching it, otherwise the watch on the
+    // new directory might be added too late to see the edits below
+    ::std::fs::create_dir_all(dir.join("nested")).unwrap();
+    write("a.txt", b"a");
+    write("nested/b.txt", b"b");
+    write("ignored.bin", b"ignored");
+
+    let opt = StoreOpt::default().set_root(tmp_dir).set_update_await_time_ms(0);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+
+    let key = ResourceMapKey::new("/strings").with_extension("txt");
+    let map: Res<ResourceMap<Strict>> = store.get(&key, ctx).unwrap();
+    let a = map.borrow().get(&FSKey::new("/strings/a.txt")).unwrap().clone();
+
+    assert_eq!(map.borrow().len(), 2);
+    assert_eq!(a.borrow().0, "a");
+    assert_eq!(map.borrow().get(&FSKey::new("/strings/nested/b.txt")).unwrap().borrow().0, "b");
+
+    // modify an entry, add one and remove one
+    write("a.txt", b"a2");
+    write("c.txt", b"c");
+    ::std::fs::remove_file(dir.join("nested/b.txt")).unwrap();
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      let map = map.borrow();
+      let added = map.get(&FSKey::new("/strings/c.txt")).is_some();
+      let removed = map.get(&FSKey::new("/strings/nested/b.txt")).is_none();
+
+      if a.borrow().0 == "a2" && added && removed {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    // the removed entry was evicted from the store
+    assert!(!store.contains::<Strict>(&FSKey::new("/strings/nested/b.txt")));
+    assert_eq!(map.borrow().len(), 2);
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn shader_includes() {
+  use warmy::shader::ShaderSource;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let root = store.root().to_owned();
+    let write = |name: &str, content: &str| {
+      let mut fh = File::create(root.join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    write("main.glsl", "#include \"lighting.glsl\"\nvoid main() {}\n");
+    write("lighting.glsl", "#include \"common.glsl\"\nfloat light;\n");
+    write("common.glsl", "float pi;\n");
+
+    let key = FSKey::new("main.glsl");
+    let shader: Res<ShaderSource> = store.get(&key, ctx).unwrap();
+
+    assert_eq!(shader.borrow().as_str(), "float pi;\nfloat light;\nvoid main() {}\n");
+    assert_eq!(shader.borrow().includes().len(), 2);
+
+    // changing a transitively included chunk reloads the shader
+    write("common.glsl", "float tau;\n");
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if shader.borrow().as_str() == "float tau;\nfloat light;\nvoid main() {}\n" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[cfg(feature = "rhai")]
+struct ScriptCtx {
+  engine: rhai::Engine,
+  migrations: Vec<(String, String)>,
+}
+
+#[cfg(feature = "rhai")]
+impl warmy::script::ScriptContext for ScriptCtx {
+  fn engine(&self) -> &rhai::Engine {
+    &self.engine
+  }
+
+  fn migrate_script(
+    &mut self,
+    _: &FSKey,
+    old: &warmy::script::Script,
+    new: &warmy::script::Script,
+  )
+  {
+    self
+      .migrations
+      .push((old.source().to_owned(), new.source().to_owned()));
+  }
+}
+
+#[cfg(all(feature = "rhai", feature = "watcher"))]
+#[test]
+fn scripts() {
+  use warmy::script::Script;
+
+  utils::with_store(|mut store: Store<ScriptCtx>| {
+    let ctx = &mut ScriptCtx {
+      engine: rhai::Engine::new(),
+      migrations: Vec::new(),
+    };
+    let key = FSKey::new("script.rhai");
+    let path = store.root().join("script.rhai");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"let x = 1;");
+    }
+
+    let script: Res<Script> = store.get(&key, ctx).unwrap();
+    assert_eq!(script.borrow().source(), "let x = 1;");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"let x = 2;");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if script.borrow().source() == "let x = 2;" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    assert_eq!(
+      ctx.migrations,
+      vec![("let x = 1;".to_owned(), "let x = 2;".to_owned())]
+    );
+  })
+}
+
+#[test]
+fn loader_registry() {
+  use warmy::AnyRes;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    // a plugin registers its loaders
+    store.registry_mut().register::<Zoo, (), _>("zoo");
+    store.registry_mut().register::<Strict, (), _>("strict");
+    assert!(store.registry().contains("zoo"));
+
+    // the host loads resources without knowing their types
+    let key: DepKey = LogicalKey::new("animal").into();
+    let any: AnyRes = store.get_any("zoo", &key, ctx).unwrap();
+
+    assert!(any.is::<Zoo>());
+    assert!(any.downcast::<Strict>().is_none());
+    assert_eq!(any.downcast::<Zoo>().unwrap().borrow().0, "animal");
+
+    // the resource is shared with typed accesses
+    let zoo: Res<Zoo> = store.get(&LogicalKey::new("animal"), ctx).unwrap();
+    assert_eq!(zoo.generation(), any.downcast::<Zoo>().unwrap().generation());
+
+    assert!(store.get_any("strict", &key, ctx).is_err());
+    assert!(store.get_any("unknown", &key, ctx).is_err());
+  })
+}
+
+#[cfg(feature = "libloading")]
+#[test]
+fn dylib_invalid_library() {
+  use warmy::dylib::Dylib;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("libplugin.so");
+    let copies = || {
+      let prefix = format!("warmy-{}-", ::std::process::id());
+
+      ::std::fs::read_dir(::std::env::temp_dir())
+        .unwrap()
+        .filter(|entry| {
+          let name = entry.as_ref().unwrap().file_name();
+          name.to_string_lossy().starts_with(&prefix)
+        })
+        .count()
+    };
+
+    {
+      let mut fh = File::create(store.root().join("libplugin.so")).unwrap();
+      let _ = fh.write_all(b"not a library");
+    }
+
+    let before = copies();
+    assert!(store.get::<_, Dylib>(&key, ctx).is_err());
+
+    // the temporary copy doesn’t leak
+    assert_eq!(copies(), before);
+  })
+}
+
+/// A resource checking, when reloaded, that the current value is still readable.
+struct Staged(String);
+
+impl Load<Option<Res<Staged>>> for Staged {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<Option<Res<Staged>>>,
+    ctx: &mut Option<Res<Staged>>,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let foo = <Foo as Load<_>>::load(key, storage, ctx)?.res;
+    Ok(Staged(foo.0).into())
+  }
+
+  fn after_reload(
+    &mut self,
+    old: &Self,
+    _: &mut Storage<Option<Res<Staged>>>,
+    ctx: &mut Option<Res<Staged>>,
+  )
+  {
+    // the resource is not borrowed mutably while the new value is being staged
+    let current = ctx.as_ref().unwrap().borrow();
+    assert_eq!(current.0, old.0);
+    assert_ne!(current.0, self.0);
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn staged_reloads() {
+  utils::with_store(|mut store: Store<Option<Res<Staged>>>| {
+    let mut ctx = None;
+    let key = FSKey::new("staged.txt");
+    let path = store.root().join("staged.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let staged: Res<Staged> = store.get(&key, &mut ctx).unwrap();
+    ctx = Some(staged.clone());
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(&mut ctx);
+
+      if staged.borrow().0 == "after" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+/// A resource getting itself back from the storage when reloaded.
+struct Reentrant {
+  value: String,
+  seen: Option<String>,
+}
+
+impl<C> Load<C> for Reentrant {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let foo = <Foo as Load<C>>::load(key, storage, ctx)?.res;
+    let reentrant = Reentrant {
+      value: foo.0,
+      seen: None,
+    };
+
+    Ok(reentrant.into())
+  }
+
+  fn after_reload(&mut self, _: &Self, storage: &mut Storage<C>, ctx: &mut C) {
+    let current: Res<Reentrant> = storage.get(&FSKey::new("reentrant.txt"), ctx).unwrap();
+    self.seen = Some(current.borrow().value.clone());
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn reentrant_after_reload() {
+  utils::with_tmp_dir(|tmp_dir| {
+    let path = tmp_dir.join("reentrant.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let opt = StoreOpt::default().set_root(tmp_dir).set_update_await_time_ms(0);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+    let key = FSKey::new("reentrant.txt");
+
+    let reentrant: Res<Reentrant> = store.get(&key, ctx).unwrap();
+    assert_eq!(reentrant.borrow().seen, None);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if reentrant.borrow().value == "after" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    // after_reload runs before the replacement: the storage still hands back the previous value
+    assert_eq!(reentrant.borrow().seen, Some("before".to_owned()));
+  })
+}
+
+/// A resource keyed by a typed payload.
+#[derive(Debug, Eq, PartialEq)]
+struct Tile(u32);
+
+impl<C> Load<C> for Tile {
+  type Key = warmy::TypedKey<(u32, u32)>;
+
+  type Error = ZooErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let (x, y) = *key.payload();
+    Ok(Tile(x * 100 + y).into())
+  }
+}
+
+#[test]
+fn typed_keys() {
+  use warmy::TypedKey;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = TypedKey::new((3, 4));
+
+    let tile: Res<Tile> = store.get(&key, ctx).unwrap();
+    assert_eq!(tile.borrow().0, 304);
+
+    let _: Res<Tile> = store.get(&TypedKey::new((3, 4)), ctx).unwrap();
+    let other: Res<Tile> = store.get(&TypedKey::new((4, 3)), ctx).unwrap();
+    assert_eq!(other.borrow().0, 403);
+    assert_eq!(store.keys().count(), 2);
+
+    let dep_key = DepKey::from(key);
+    assert!(store.contains_key(&dep_key));
+    assert_ne!(dep_key, DepKey::from(TypedKey::new((3u64, 4u64))));
+
+    match dep_key {
+      DepKey::Typed(ref erased) => assert_eq!(erased.downcast_ref::<(u32, u32)>(), Some(&(3, 4))),
+      _ => panic!("expected a typed key"),
+    }
+  })
+}
+
+/// A logical resource declaring a dependency on the file its key names.
+struct Dependent;
+
+impl<C> Load<C> for Dependent {
+  type Key = LogicalKey;
+
+  type Error = ZooErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let dep = FSKey::new(key.as_str()).into();
+    Ok(Loaded::with_deps(Dependent, vec![dep]))
+  }
+}
+
+#[test]
+fn dependency_check() {
+  use warmy::{DependencyCheck, StoreError, StoreErrorOr};
+
+  let check = |opt: StoreOpt| opt.set_dependency_check(DependencyCheck::Deny);
+
+  utils::with_store_opt(check, |mut store: Store<()>| {
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("present.txt")).unwrap();
+      let _ = fh.write_all(b"present");
+    }
+
+    let present: Result<Res<Dependent>, _> = store.get(&LogicalKey::new("/present.txt"), ctx);
+    assert!(present.is_ok());
+
+    let key = LogicalKey::new("/typo.txt");
+    let missing: Result<Res<Dependent>, _> = store.get(&key, ctx);
+
+    match missing {
+      Err(StoreErrorOr::StoreError(StoreError::MissingDependency(dependent, dep))) => {
+        assert_eq!(dependent, key.clone().into());
+        assert_eq!(dep, DepKey::Path(store.root().join("typo.txt")));
+      }
+
+      _ => panic!("expected a missing dependency error"),
+    }
+
+    assert!(!store.contains_key(&key.into()));
+  })
+}
+
+/// A file repeated as many times as its key’s parameter says.
+struct Repeated(String);
+
+impl<C> Load<C> for Repeated {
+  type Key = warmy::FSKeyWith<usize>;
+
+  type Error = StrictErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let mut s = String::new();
+    let mut fh = File::open(key.as_path()).map_err(|_| StrictErr)?;
+    let _ = fh.read_to_string(&mut s);
+
+    Ok(Repeated(s.repeat(*key.param())).into())
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn parameterized_fs_keys() {
+  use warmy::FSKeyWith;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("repeated.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"a");
+    }
+
+    let once: Res<Repeated> = store.get(&FSKeyWith::new("repeated.txt", 1), ctx).unwrap();
+    let twice: Res<Repeated> = store.get(&FSKeyWith::new("repeated.txt", 2), ctx).unwrap();
+    assert_eq!(once.borrow().0, "a");
+    assert_eq!(twice.borrow().0, "aa");
+    assert_eq!(store.keys().count(), 2);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"b");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if once.borrow().0 == "b" && twice.borrow().0 == "bb" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[test]
+fn reload_sink() {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+  use warmy::{ReloadOutcome, ReloadRecord};
+
+  let records = Rc::new(RefCell::new(Vec::new()));
+  let sink = |opt: StoreOpt| {
+    let records = records.clone();
+
+    opt
+      .set_build_id("playtest-42")
+      .set_reload_sink(move |record: &ReloadRecord| records.borrow_mut().push(record.clone()))
+  };
+
+  utils::with_store_opt(sink, |mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = LogicalKey::new("sunk");
+
+    let _: Res<Zoo> = store.get(&key, ctx).unwrap();
+    assert!(records.borrow().is_empty());
+
+    store.mark_dirty(key.clone());
+    store.sync(ctx);
+
+    let records = records.borrow();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].key, key.into());
+    assert!(records[0].type_name.ends_with("Zoo"));
+    assert_eq!(records[0].outcome, ReloadOutcome::Reloaded);
+    assert_eq!(records[0].build_id, Some("playtest-42".to_owned()));
+  })
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+fn streaming_grid() {
+  use warmy::streaming::{Cell, StreamingGrid};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key_of = |cell: Cell| LogicalKey::new(format!("{},{}", cell.x, cell.y));
+    let mut grid: StreamingGrid<Zoo, LogicalKey> = StreamingGrid::new(10., key_of)
+      .set_radius(1)
+      .set_budget(3);
+
+    // the center and its four neighbors are within the radius
+    let report = grid.update([5., 5.], &mut store, ctx);
+    assert_eq!(report.loaded[0], Cell::new(0, 0));
+    assert_eq!(report.loaded.len(), 3);
+    assert_eq!(report.pending, 2);
+
+    let report = grid.update([5., 5.], &mut store, ctx);
+    assert_eq!(report.loaded.len(), 2);
+    assert_eq!(report.pending, 0);
+    assert_eq!(store.keys().count(), 5);
+    assert_eq!(grid.get(&Cell::new(1, 0)).unwrap().borrow().0, "1,0");
+
+    // moving right by a cell evicts the left column
+    let report = grid.update([15., 5.], &mut store, ctx);
+    assert_eq!(report.evicted.len(), 3);
+    assert!(!store.contains_key(&LogicalKey::new("-1,0").into()));
+    assert_eq!(report.loaded.len(), 3);
+
+    grid.clear(&mut store);
+    assert_eq!(store.keys().count(), 0);
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn dead_dependents() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = LogicalKey::new("dead.txt");
+    let path = store.root().join("dead.txt");
+
+    let write = |content: &[u8]| {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(content);
+    };
+
+    let wait = |store: &mut Store<()>, ctx: &mut (), done: &Fn() -> bool| {
+      let start_time = ::std::time::Instant::now();
+      loop {
+        store.sync(ctx);
+
+        if done() {
+          break;
+        }
+
+        if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+          panic!(
+            "more than {} milliseconds were spent waiting for a filesystem event",
+            QUEUE_TIMEOUT_MS
+          );
+        }
+      }
+    };
+
+    write(b"1");
+
+    // only the resource declaring the dependency reloads, not the other one sharing its key
+    let logical: Res<LogicalFoo> = store.get(&key, ctx).unwrap();
+    let zoo: Res<Zoo> = store.get(&key, ctx).unwrap();
+    let foo: Res<Foo> = store.get(&FSKey::new("dead.txt"), ctx).unwrap();
+    let zoo_generation = zoo.generation();
+
+    write(b"2");
+    wait(&mut store, ctx, &|| logical.borrow().0 == "2");
+    assert_eq!(zoo.generation(), zoo_generation);
+
+    // once evicted, a resource doesn’t reload, even if its key gets registered again
+    assert!(store.evict(&key.clone().into()));
+    let zoo: Res<Zoo> = store.get(&key, ctx).unwrap();
+    let zoo_generation = zoo.generation();
+
+    write(b"3");
+    wait(&mut store, ctx, &|| foo.borrow().0 == "3");
+    assert_eq!(logical.borrow().0, "2");
+    assert_eq!(zoo.generation(), zoo_generation);
+
+    // registering it again brings the dependency back
+    let logical: Res<LogicalFoo> = store.get(&key, ctx).unwrap();
+    assert_eq!(logical.borrow().0, "3");
+
+    write(b"4");
+    wait(&mut store, ctx, &|| logical.borrow().0 == "4");
+    assert_eq!(zoo.generation(), zoo_generation);
+  })
+}
+
+/// A logical resource reading the content of the `Foo` behind an alias.
+struct Player(String);
+
+impl<C> Load<C> for Player {
+  type Key = LogicalKey;
+
+  type Error = ZooErr;
+
+  fn load(
+    _: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let alias = DepKey::from(LogicalKey::new("player_texture"));
+    let texture: Res<Foo> = storage.get_via(&alias, ctx).map_err(|_| ZooErr)?;
+    let content = texture.borrow().0.clone();
+
+    Ok(Loaded::with_deps(Player(content), vec![alias]))
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn aliases() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for &(file, content) in &[("a.txt", "a"), ("b.txt", "b")] {
+      let mut fh = File::create(store.root().join(file)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    }
+
+    let alias = LogicalKey::new("player_texture");
+    store.alias(alias.clone(), FSKey::new("a.txt")).unwrap();
+    assert_eq!(store.resolve(&alias.clone().into()), DepKey::Path(store.root().join("a.txt")));
+
+    let player: Res<Player> = store.get(&LogicalKey::new("player"), ctx).unwrap();
+    assert_eq!(player.borrow().0, "a");
+
+    let texture: Res<Foo> = store.get(&FSKey::new("a.txt"), ctx).unwrap();
+    let via: Res<Foo> = store.get_via(&alias.clone().into(), ctx).unwrap();
+    assert_eq!(texture.generation(), via.generation());
+
+    // the target reloading reloads the observers of the alias
+    {
+      let mut fh = File::create(store.root().join("a.txt")).unwrap();
+      let _ = fh.write_all(b"a2");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if player.borrow().0 == "a2" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    // remapping the alias switches the observers to the new target
+    store.alias(alias.clone(), FSKey::new("b.txt")).unwrap();
+    store.sync(ctx);
+    assert_eq!(player.borrow().0, "b");
+
+    assert!(store.alias(LogicalKey::new("player"), FSKey::new("b.txt")).is_err());
+  })
+}
+
+#[test]
+fn alias_remap_settles() {
+  utils::with_store_opt(
+    |opt| opt.set_update_await_time_ms(50),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+
+      for &(file, content) in &[("a.txt", "a"), ("b.txt", "b")] {
+        let mut fh = File::create(store.root().join(file)).unwrap();
+        let _ = fh.write_all(content.as_bytes());
+      }
+
+      let alias = LogicalKey::new("player_texture");
+      store.alias(alias.clone(), FSKey::new("a.txt")).unwrap();
+      let player: Res<Player> = store.get(&LogicalKey::new("player"), ctx).unwrap();
+
+      // syncing more often than the update await time must not keep the alias settling forever
+      store.alias(alias, FSKey::new("b.txt")).unwrap();
+
+      let start_time = ::std::time::Instant::now();
+      while player.borrow().0 != "b" {
+        store.sync(ctx);
+        ::std::thread::sleep(::std::time::Duration::from_millis(5));
+
+        assert!(start_time.elapsed() < ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS));
+      }
+    },
+  )
+}
+
+#[test]
+fn domains() {
+  let domains = |opt: StoreOpt| opt.add_domain("config", "/config").add_domain("art", "/art");
+
+  utils::with_store_opt(domains, |mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for dir in &["config", "art"] {
+      ::std::fs::create_dir(store.root().join(dir)).unwrap();
+      let mut fh = File::create(store.root().join(dir).join("a.txt")).unwrap();
+      let _ = fh.write_all(dir.as_bytes());
+    }
+
+    let config_key = FSKey::new("/config/a.txt");
+    let art_key = FSKey::new("/art/a.txt");
+    let config: Res<Foo> = store.get(&config_key, ctx).unwrap();
+    let art: Res<Foo> = store.get(&art_key, ctx).unwrap();
+
+    assert_eq!(store.domain_of(&config_key.clone().into()), Some("config"));
+    assert_eq!(store.domain_of(&art_key.clone().into()), Some("art"));
+    assert_eq!(store.domain_of(&LogicalKey::new("config").into()), None);
+
+    let (config_generation, art_generation) = (config.generation(), art.generation());
+    store.mark_dirty(config_key);
+    store.mark_dirty(art_key);
+
+    // only the synchronized domain reloads
+    store.sync_domain("config", ctx);
+    assert_ne!(config.generation(), config_generation);
+    assert_eq!(art.generation(), art_generation);
+
+    store.sync_domain("art", ctx);
+    assert_ne!(art.generation(), art_generation);
+  })
+}
+
+#[test]
+fn keys_outside_root() {
+  use warmy::{StoreError, StoreErrorOr};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("inside.txt")).unwrap();
+      let _ = fh.write_all(b"inside");
+    }
+
+    // traversals staying inside the root are fine
+    let inside: Res<Foo> = store.get(&FSKey::new("/dir/../inside.txt"), ctx).unwrap();
+    assert_eq!(inside.borrow().0, "inside");
+
+    let outside: Result<Res<Foo>, _> = store.get(&FSKey::new("../../etc/passwd"), ctx);
+
+    match outside {
+      Err(StoreErrorOr::StoreError(StoreError::KeyOutsideRoot(_))) => (),
+      _ => panic!("expected a key outside root error"),
+    }
+  });
+
+  let allow = |opt: StoreOpt| opt.set_allow_outside_root(true);
+
+  utils::with_store_opt(allow, |mut store: Store<()>| {
+    let ctx = &mut ();
+    let name = format!("warmy-outside-{}.txt", ::std::process::id());
+    let path = store.root().parent().unwrap().join(&name);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"outside");
+    }
+
+    let outside: Result<Res<Foo>, _> = store.get(&FSKey::new(format!("../{}", name)), ctx);
+    let _ = ::std::fs::remove_file(&path);
+    assert_eq!(outside.unwrap().borrow().0, "outside");
+  })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Sprite {
+  Raw(Vec<u8>),
+  Text(String),
+}
+
+impl warmy::methods::Sniffable for Sprite {
+  fn candidates() -> Vec<warmy::methods::Candidate<Self>> {
+    use warmy::methods::Candidate;
+
+    vec![
+      Candidate::with_magic("raw", b"RAW\0", |bytes| Ok(Sprite::Raw(bytes[4..].to_owned()))),
+      Candidate::new(
+        "text",
+        |bytes| bytes.first() == Some(&b'{'),
+        |bytes| {
+          let s = ::std::str::from_utf8(bytes)?;
+          Ok(Sprite::Text(s.to_owned()))
+        },
+      ),
+    ]
+  }
+}
+
+#[test]
+fn sniffed_methods() {
+  use warmy::methods::{FormatError, Sniff, SniffError};
+  use warmy::StoreErrorOr;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for &(name, content) in &[
+      ("3fa9c1", &b"RAW\0\x01\x02"[..]),
+      ("b7e2d0", &b"{ sprite }"[..]),
+      ("0c44aa", &b"GIF89a"[..]),
+    ] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(content);
+    }
+
+    let raw: Res<Sprite> = store.get_by(&FSKey::new("/3fa9c1"), ctx, Sniff).unwrap();
+    assert_eq!(*raw.borrow(), Sprite::Raw(vec![1, 2]));
+
+    let text: Res<Sprite> = store.get_by(&FSKey::new("/b7e2d0"), ctx, Sniff).unwrap();
+    assert_eq!(*text.borrow(), Sprite::Text("{ sprite }".to_owned()));
+
+    let unknown: Result<Res<Sprite>, _> = store.get_by(&FSKey::new("/0c44aa"), ctx, Sniff);
+
+    match unknown {
+      Err(StoreErrorOr::ResError(FormatError::Format(SniffError::Unrecognized))) => (),
+      _ => panic!("expected an unrecognized content error"),
+    }
+
+    // built-in methods can be declared in manifests
+    let manifest = Manifest::new().add_by::<_, Sprite, _>(FSKey::new("/3fa9c1"), Sniff);
+    assert!(store.preflight(&manifest, ctx).is_ok());
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn ignore_patterns() {
+  use warmy::ignore::glob_matches;
+
+  assert!(glob_matches("*.swp", ".foo.txt.swp"));
+  assert!(glob_matches("*~", "foo.txt~"));
+  assert!(!glob_matches("*.swp", "foo.txt"));
+  assert!(!glob_matches("levels/*.json", "levels/a/b.json"));
+
+  let ignore = |opt: StoreOpt| opt.add_ignore_pattern("*.bak");
+
+  utils::with_store_opt(ignore, |mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for name in &["kept.txt", "ignored.bak"] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let kept: Res<Foo> = store.get(&FSKey::new("/kept.txt"), ctx).unwrap();
+    let ignored: Res<Foo> = store.get(&FSKey::new("/ignored.bak"), ctx).unwrap();
+
+    for name in &["ignored.bak", "kept.txt"] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    while kept.borrow().0 != "after" {
+      store.sync(ctx);
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    store.sync(ctx);
+    assert_eq!(ignored.borrow().0, "before");
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn non_recursive_watch() {
+  use warmy::WatchMode;
+
+  let non_recursive = |opt: StoreOpt| opt.set_watch_mode(WatchMode::NonRecursive);
+
+  utils::with_store_opt(non_recursive, |mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("levels").join("a.txt");
+    ::std::fs::create_dir(store.root().join("levels")).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    // the directory of the resource is watched as it gets requested
+    let level: Res<Foo> = store.get(&FSKey::new("/levels/a.txt"), ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    while level.borrow().0 != "after" {
+      store.sync(ctx);
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[derive(Debug)]
+struct NoReload(String);
+
+impl<C> Load<C> for NoReload {
+  type Key = FSKey;
+
+  type Error = StrictErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let mut s = String::new();
+    let mut fh = File::open(key.as_path()).map_err(|_| StrictErr)?;
+    let _ = fh.read_to_string(&mut s);
+
+    Ok(NoReload(s).into())
+  }
+
+  fn reload(&self, _: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Self, Self::Error> {
+    Err(StrictErr)
+  }
+}
+
+#[test]
+fn check_reloads() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for name in &["a.txt", "b.txt"] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(name.as_bytes());
+    }
+
+    let _: Res<Foo> = store.get(&FSKey::new("/a.txt"), ctx).unwrap();
+    let b: Res<NoReload> = store.get(&FSKey::new("/b.txt"), ctx).unwrap();
+
+    let report = store.check_reloads(ctx);
+
+    assert!(!report.is_ok());
+    assert_eq!(report.reloaded, 1);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].key, DepKey::Path(store.root().join("b.txt")));
+    assert_eq!(report.failures[0].error, "missing file");
+
+    // the resource that failed to reload is still around
+    assert_eq!(b.borrow().0, "b.txt");
+  })
+}
+
+#[test]
+fn fx_definitions() {
+  use warmy::fx::{Fx, FxError};
+  use warmy::StoreErrorOr;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let write = |store: &Store<()>, name: &str, content: &str| {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    write(&store, "spark.png", "pixels");
+    write(&store, "size.curve", "0 0\n1 1\n");
+    write(&store, "smoke.fx", "rate 5\n");
+    write(
+      &store,
+      "spark.fx",
+      "# sparks\nrate 40\ntexture /spark.png\ncurve size /size.curve\nsub_emitter /smoke.fx\n",
+    );
+
+    // the texture is loaded by the renderer
+    let _: Res<Foo> = store.get(&FSKey::new("/spark.png"), ctx).unwrap();
+    let spark: Res<Fx> = store.get(&FSKey::new("/spark.fx"), ctx).unwrap();
+
+    assert_eq!(spark.borrow().rate(), 40.);
+    assert_eq!(spark.borrow().curve("size").unwrap()[0], 0.);
+    assert_eq!(spark.borrow().sub_emitters()[0].borrow().rate(), 5.);
+
+    // curves are strong dependencies
+    write(&store, "size.curve", "0 1\n1 1\n");
+    store.mark_dirty(FSKey::new("/size.curve"));
+    store.sync(ctx);
+    assert_eq!(spark.borrow().curve("size").unwrap()[0], 1.);
+
+    // textures and sub-emitters are weak dependencies
+    store.mark_dirty(FSKey::new("/spark.png"));
+    write(&store, "smoke.fx", "rate 10\n");
+    store.mark_dirty(FSKey::new("/smoke.fx"));
+    store.sync(ctx);
+
+    assert_eq!(spark.borrow_mut().take_stale_textures(), vec![FSKey::new("/spark.png")]);
+    assert_eq!(spark.borrow_mut().take_changed_sub_emitters(), vec![0]);
+    assert_eq!(spark.borrow().sub_emitters()[0].borrow().rate(), 10.);
+
+    write(&store, "a.fx", "sub_emitter /b.fx\n");
+    write(&store, "b.fx", "sub_emitter /a.fx\n");
+
+    match store.get::<_, Fx>(&FSKey::new("/a.fx"), ctx) {
+      Err(StoreErrorOr::ResError(FxError::SubEmitterCycle(_))) => (),
+      _ => panic!("expected a sub-emitter cycle"),
+    }
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn absolute_keys() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let dir = store.root().parent().unwrap().join(format!("warmy-abs-{}", ::std::process::id()));
+    let path = dir.join("settings.txt");
+    ::std::fs::create_dir_all(&dir).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    // absolute keys bypass the root and are accepted without allowing keys outside of it
+    let settings: Res<Foo> = store.get(&FSKey::absolute(&path), ctx).unwrap();
+    assert_eq!(settings.borrow().0, "before");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    while settings.borrow().0 != "after" {
+      store.sync(ctx);
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        let _ = ::std::fs::remove_dir_all(&dir);
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    let _ = ::std::fs::remove_dir_all(&dir);
+  })
+}
+
+#[derive(Debug)]
+struct Weighed(String);
+
+impl<C> Load<C> for Weighed {
+  type Key = FSKey;
+
+  type Error = StrictErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let mut s = String::new();
+    let mut fh = File::open(key.as_path()).map_err(|_| StrictErr)?;
+    let _ = fh.read_to_string(&mut s);
+
+    Ok(Weighed(s).into())
+  }
+
+  fn memory_footprint(&self) -> usize {
+    self.0.len()
+  }
+}
+
+#[test]
+fn memory_budget() {
+  use warmy::{StoreError, StoreErrorOr};
+
+  let budget = |opt: StoreOpt| opt.set_memory_budget(Some(10));
+
+  utils::with_store_opt(budget, |mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for &(name, content) in &[("small.txt", "12345"), ("big.txt", "1234567")] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    }
+
+    let _: Res<Weighed> = store.get(&FSKey::new("/small.txt"), ctx).unwrap();
+    assert_eq!(store.memory_used(), 5);
+
+    match store.get::<_, Weighed>(&FSKey::new("/big.txt"), ctx) {
+      Err(StoreErrorOr::StoreError(StoreError::BudgetExceeded {
+        requested: 7,
+        available: 5,
+      })) => (),
+      _ => panic!("expected a budget exceeded error"),
+    }
+
+    assert!(!store.contains::<Weighed>(&FSKey::new("/big.txt")));
+
+    // evicting frees memory
+    store.evict(&FSKey::new("/small.txt").into());
+    assert_eq!(store.memory_used(), 0);
+
+    let _: Res<Weighed> = store.get(&FSKey::new("/big.txt"), ctx).unwrap();
+    assert_eq!(store.memory_used(), 7);
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn poll_watcher_backend() {
+  use std::time::Duration;
+  use warmy::WatcherBackend;
+
+  let backend = WatcherBackend::Poll(Duration::from_millis(5));
+  let poll = |opt: StoreOpt| opt.set_watcher_backend(backend);
+
+  utils::with_store_opt(poll, |mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("polled.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let polled: Res<Foo> = store.get(&FSKey::new("/polled.txt"), ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let start_time = ::std::time::Instant::now();
+    while polled.borrow().0 != "after" {
+      store.sync(ctx);
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[derive(Debug)]
+struct Decoded(String);
+
+impl<C> Load<C> for Decoded {
+  type Key = FSKey;
+
+  type Error = StrictErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let prepared = <Decoded as warmy::preload::Prepare<C>>::prepare(&key)?;
+    <Decoded as warmy::preload::Prepare<C>>::finish(prepared, key, storage, ctx)
+  }
+}
+
+impl<C> warmy::preload::Prepare<C> for Decoded {
+  type Prepared = String;
+
+  fn prepare(key: &FSKey) -> Result<String, StrictErr> {
+    let mut s = String::new();
+    let mut fh = File::open(key.as_path()).map_err(|_| StrictErr)?;
+    let _ = fh.read_to_string(&mut s);
+
+    if s == "panic" {
+      panic!("the decoder has a bug");
+    }
+
+    Ok(s)
+  }
+
+  fn finish(
+    prepared: String,
+    _: FSKey,
+    _: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, StrictErr>
+  {
+    Ok(Decoded(prepared.to_uppercase()).into())
+  }
+}
+
+#[derive(Debug)]
+struct Bundle(String);
+
+impl<C> Load<C> for Bundle {
+  type Key = LogicalKey;
+
+  type Error = StrictErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let mut content = String::new();
+    let mut deps = Vec::new();
+
+    for name in key.as_str().split(',') {
+      let part_key = FSKey::new(name);
+      let part: Res<Decoded> = storage.get(&part_key, ctx).map_err(|_| StrictErr)?;
+      content.push_str(&part.borrow().0);
+      deps.push(part_key.into());
+    }
+
+    Ok(Loaded::with_deps(Bundle(content), deps))
+  }
+}
+
+#[test]
+fn parallel_preload() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for name in &["a.txt", "b.txt", "c.txt"] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(name.as_bytes());
+    }
+
+    let bundle = LogicalKey::new("/a.txt,/b.txt");
+    let broken = LogicalKey::new("/missing.txt");
+    let manifest = Manifest::new()
+      .set_preload_threads(2)
+      .set_type_limit::<Decoded>(1)
+      .add::<_, Bundle>(bundle.clone())
+      .add::<_, Bundle>(broken.clone())
+      .add_prepared::<_, Decoded>(FSKey::new("/a.txt"))
+      .add_prepared::<_, Decoded>(FSKey::new("/b.txt"))
+      .add_prepared::<_, Decoded>(FSKey::new("/c.txt"))
+      .add_prepared::<_, Decoded>(FSKey::new("/missing.txt"))
+      .add_dep(bundle.clone(), FSKey::new("/a.txt"))
+      .add_dep(bundle.clone(), FSKey::new("/b.txt"))
+      .add_dep(broken.clone(), FSKey::new("/missing.txt"));
+
+    let report = store.preload(&manifest, ctx);
+
+    assert_eq!(report.loaded, 4);
+    assert_eq!(report.failures.len(), 2);
+    assert_eq!(report.failures[0].key, broken.into());
+    assert_eq!(report.failures[0].error, "dependency failed: /missing.txt");
+    assert_eq!(report.failures[1].error, "missing file");
+
+    let bundle: Res<Bundle> = store.get(&bundle, ctx).unwrap();
+    assert_eq!(bundle.borrow().0, "A.TXTB.TXT");
+    assert!(store.contains::<Decoded>(&FSKey::new("/c.txt")));
+  })
+}
+
+#[test]
+fn get_many() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    for &(name, content) in &[("a.txt", "a.txt"), ("b.txt", "b.txt"), ("buggy.txt", "panic")] {
+      let mut fh = File::create(store.root().join(name)).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    }
+
+    let a: Res<Decoded> = store.get(&FSKey::new("/a.txt"), ctx).unwrap();
+    let keys = [
+      FSKey::new("/a.txt"),
+      FSKey::new("/b.txt"),
+      FSKey::new("/missing.txt"),
+      FSKey::new("/buggy.txt"),
+    ];
+    let results = store.get_many::<_, Decoded>(&keys, 2, ctx);
+
+    assert_eq!(results.len(), 4);
+    // resources already living in the store are shared
+    a.borrow_mut().0.push('!');
+    assert_eq!(results[0].as_ref().unwrap().borrow().0, "A.TXT!");
+    assert_eq!(results[1].as_ref().unwrap().borrow().0, "B.TXT");
+    assert!(results[2].is_err());
+
+    // panics aren’t swallowed
+    let buggy = DepKey::Path(store.root().join("buggy.txt"));
+    match results[3] {
+      Err(warmy::StoreErrorOr::StoreError(warmy::StoreError::PreparePanicked(ref key))) => {
+        assert_eq!(*key, buggy)
+      }
+      _ => panic!("the preparation panicked"),
+    }
+
+    // resources failing to prepare aren’t loaded again
+    assert_eq!(store.metrics().cache_misses, 2);
+  })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Lazy(String);
+
+impl<C> Load<C> for Lazy {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let mut s = String::new();
+    let mut fh = File::open(key.as_path()).unwrap();
+    let _ = fh.read_to_string(&mut s);
+
+    Ok(Lazy(s).into())
+  }
+
+  fn reload_delay() -> Option<::std::time::Duration> {
+    Some(::std::time::Duration::from_secs(3600))
+  }
+}
+
+#[test]
+fn reload_delay_overrides() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("lazy.txt");
+    let path = store.root().join("lazy.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let r: Res<Lazy> = store.get(&key, ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    // the delay of the type holds the reload back
+    store.mark_dirty(key.clone());
+    let start_time = ::std::time::Instant::now();
+    while start_time.elapsed() < ::std::time::Duration::from_millis(300) {
+      store.sync(ctx);
+      assert_eq!(r.borrow().0, "before");
+    }
+
+    // the override of the key takes precedence
+    store.set_await_time_for(key.clone(), Some(0));
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if r.borrow().0 == "after" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+  })
+}
+
+#[test]
+fn read_metrics() {
+  utils::with_store_opt(
+    |opt| opt.set_read_metrics(true),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("metrics.txt");
+
+      {
+        let mut fh = File::create(store.root().join("metrics.txt")).unwrap();
+        let _ = fh.write_all(b"counted");
+      }
+
+      let _: Res<Foo> = store.get(&key, ctx).unwrap();
+      let _: Res<Foo> = store.get(&key, ctx).unwrap();
+      let _: Res<Foo> = store.get_labeled(&key, ctx, "hud").unwrap();
+
+      let type_name = ::std::any::type_name::<Foo>();
+      let metrics = store.read_metrics().unwrap();
+      let unlabeled = metrics.by_label(type_name, None);
+      assert_eq!((unlabeled.hits, unlabeled.misses), (1, 1));
+      assert_eq!(metrics.by_label(type_name, Some("hud")).hits, 1);
+      assert_eq!(metrics.by_type(type_name).hit_ratio(), Some(2. / 3.));
+
+      store.clear_read_metrics();
+      assert_eq!(store.read_metrics().unwrap().iter().count(), 0);
+    },
+  )
+}
+
+#[test]
+fn pause_sync() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("paused.txt");
+    let path = store.root().join("paused.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"v0");
+    }
+
+    let r: Res<Foo> = store.get(&key, ctx).unwrap();
+    store.pause_sync();
+    assert!(store.is_sync_paused());
+
+    for content in &["v1", "v2", "v3"] {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    }
+
+    store.mark_dirty(key.clone());
+    let start_time = ::std::time::Instant::now();
+    while start_time.elapsed() < ::std::time::Duration::from_millis(300) {
+      assert_eq!(store.sync(ctx).reloads_attempted, 0);
+      assert_eq!(r.borrow().0, "v0");
+    }
+
+    // everything that piled up is reloaded in a single pass
+    store.resume_sync();
+    let report = store.sync(ctx);
+    assert_eq!(report.reloaded, vec![DepKey::Path(store.root().join("paused.txt"))]);
+    assert_eq!(r.borrow().0, "v3");
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn wait_for_reload() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("waited.txt");
+    let path = store.root().join("waited.txt");
+    let timeout = ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let r: Res<Foo> = store.get(&key, ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    assert!(store.wait_for_reload(key.clone(), timeout, ctx));
+    assert_eq!(r.borrow().0, "after");
+
+    // nothing changes anymore, once the trailing events of the write are drained
+    utils::settle(&mut store, ctx);
+    let short = ::std::time::Duration::from_millis(100);
+    assert!(!store.wait_for_reload(key, short, ctx));
+  })
+}
+
+#[test]
+fn manual_clock() {
+  let clock = ManualClock::new();
+  let clock_ = clock.clone();
+
+  utils::with_store_opt(
+    move |opt| {
+      // only mark_dirty touches the resource; filesystem events would touch it at random times
+      opt
+        .set_update_await_time_ms(1000)
+        .add_ignore_pattern("clocked.txt")
+        .set_clock(clock_.clone())
+    },
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("clocked.txt");
+      let path = store.root().join("clocked.txt");
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"before");
+      }
+
+      let r: Res<Foo> = store.get(&key, ctx).unwrap();
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"after");
+      }
+
+      store.mark_dirty(key.clone());
+      clock.advance(::std::time::Duration::from_millis(999));
+      assert_eq!(store.sync(ctx).reloads_attempted, 0);
+      assert_eq!(r.borrow().0, "before");
+
+      clock.advance(::std::time::Duration::from_millis(1));
+      store.sync(ctx);
+      assert_eq!(r.borrow().0, "after");
+    },
+  )
+}
+
+#[test]
+fn scoped_store() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let ui_dir = store.root().join("ui");
+    ::std::fs::create_dir(&ui_dir).unwrap();
+
+    {
+      let mut fh = File::create(ui_dir.join("button.txt")).unwrap();
+      let _ = fh.write_all(b"button");
+    }
+
+    {
+      let mut fh = File::create(store.root().join("secret.txt")).unwrap();
+      let _ = fh.write_all(b"secret");
+    }
+
+    {
+      let mut ui = store.scoped("/ui/");
+      assert_eq!(ui.prefix(), ::std::path::Path::new("/ui"));
+
+      let button: Res<Foo> = ui.get(&FSKey::new("/button.txt"), ctx).unwrap();
+      assert_eq!(button.borrow().0, "button");
+
+      let escaped = ui.get::<_, Foo>(&FSKey::new("../secret.txt"), ctx);
+      assert!(escaped.is_err());
+    }
+
+    assert!(store.contains::<Foo>(&FSKey::new("/ui/button.txt")));
+  })
+}
+
+#[test]
+fn fallback_stores() {
+  utils::with_tmp_dir(|overrides_dir| {
+    utils::with_tmp_dir(|data_dir| {
+      let ctx = &mut ();
+
+      for &(dir, file, content) in &[
+        (overrides_dir, "both.txt", "override"),
+        (data_dir, "both.txt", "data"),
+        (data_dir, "data.txt", "data only"),
+      ] {
+        let mut fh = File::create(dir.join(file)).unwrap();
+        let _ = fh.write_all(content.as_bytes());
+      }
+
+      let overrides = Store::new(StoreOpt::default().set_root(overrides_dir)).unwrap();
+      let data = Store::new(StoreOpt::default().set_root(data_dir)).unwrap();
+      let mut store = warmy::FallbackStore::new(overrides).add_fallback(data);
+
+      let both: Res<Foo> = store.get(&FSKey::new("both.txt"), ctx).unwrap();
+      let data_only: Res<Foo> = store.get(&FSKey::new("data.txt"), ctx).unwrap();
+      assert_eq!(both.borrow().0, "override");
+      assert_eq!(data_only.borrow().0, "data only");
+      assert!(store.stores()[1].contains::<Foo>(&FSKey::new("data.txt")));
+      assert!(!store.stores()[0].contains::<Foo>(&FSKey::new("data.txt")));
+    })
+  })
+}
+
+#[test]
+fn embedded_source() {
+  use warmy::fx::Curve;
+  use warmy::provenance::Backend;
+  use warmy::EmbeddedSource;
+
+  let embedded = EmbeddedSource::new(&[
+    ("/curves/fade.curve", b"0 1\n1 0"),
+    ("/ramp.curve", b"0 1"),
+  ]);
+
+  utils::with_store_opt(
+    move |opt| opt.set_embedded_source(embedded.clone()),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+
+      let fade: Res<Curve> = store.get(&FSKey::new("/curves/fade.curve"), ctx).unwrap();
+      assert_eq!(fade.borrow().points(), &[(0., 1.), (1., 0.)]);
+
+      // files win over embedded resources
+      {
+        let mut fh = File::create(store.root().join("ramp.curve")).unwrap();
+        let _ = fh.write_all(b"0 0\n1 1");
+      }
+
+      let ramp: Res<Curve> = store.get(&FSKey::new("/ramp.curve"), ctx).unwrap();
+      assert_eq!(ramp.borrow().points(), &[(0., 0.), (1., 1.)]);
+
+      // provenance tells where the bytes came from
+      let backend = |key: &str| store.provenance(&FSKey::new(key).into()).unwrap().backend;
+      assert_eq!(backend("/curves/fade.curve"), Backend::Embedded);
+      assert_eq!(backend("/ramp.curve"), Backend::Filesystem);
+    },
+  )
+}
+
+#[derive(Debug)]
+struct Processed(String, bool);
+
+impl<C> Load<C> for Processed {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let source = storage.read(key.as_path()).unwrap();
+
+    if let Some(cache) = storage.artifact_cache() {
+      if let Some(artifact) = cache.get(key.as_path(), &source, "v1") {
+        return Ok(Processed(String::from_utf8(artifact).unwrap(), true).into());
+      }
+    }
+
+    let processed = String::from_utf8(source.clone()).unwrap().to_uppercase();
+
+    if let Some(cache) = storage.artifact_cache() {
+      cache.put(key.as_path(), &source, "v1", processed.as_bytes()).unwrap();
+    }
+
+    Ok(Processed(processed, false).into())
+  }
+}
+
+#[test]
+fn artifact_cache() {
+  utils::with_tmp_dir(|root| {
+    let ctx = &mut ();
+    let key = FSKey::new("mesh.txt");
+    let cache = warmy::ArtifactCache::new(root.join("artifacts"));
+
+    {
+      let mut fh = File::create(root.join("mesh.txt")).unwrap();
+      let _ = fh.write_all(b"mesh");
+    }
+
+    // a first run processes the resource, the next ones use the artifact
+    for &from_artifact in &[false, true] {
+      let opt = StoreOpt::default()
+        .set_root(root)
+        .set_artifact_cache(Some(cache.clone()));
+      let mut store: Store<()> = Store::new(opt).unwrap();
+      let r: Res<Processed> = store.get(&key, ctx).unwrap();
+
+      assert_eq!(r.borrow().0, "MESH");
+      assert_eq!(r.borrow().1, from_artifact);
+    }
+
+    cache.clear().unwrap();
+    assert!(!cache.dir().exists());
+  })
+}
+
+// counts its imports in the context
+#[derive(Debug)]
+struct Imported(String);
+
+impl Load<u32> for Imported {
+  type Key = FSKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<u32>,
+    ctx: &mut u32,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    *ctx += 1;
+    let source = storage.read_to_string(key.as_path()).map_err(|_| FooErr)?;
+    Ok(Imported(source).into())
+  }
+}
+
+// the imported string, repeated
+#[derive(Debug)]
+struct Repeats(String);
+
+impl ProcessParams for Repeats {
+  type Params = usize;
+}
+
+impl Process<u32> for Repeats {
+  type Intermediate = Imported;
+
+  type Error = FooErr;
+
+  fn process(
+    intermediate: &Imported,
+    count: &usize,
+    _: &mut Storage<u32>,
+    _: &mut u32,
+  ) -> Result<Self, Self::Error>
+  {
+    Ok(Repeats(intermediate.0.repeat(*count)))
+  }
+}
+
+#[test]
+fn processed_resources() {
+  utils::with_tmp_dir(|root| {
+    let ctx = &mut 0;
+    let key = FSKey::new("/text.txt");
+
+    {
+      let mut fh = File::create(root.join("text.txt")).unwrap();
+      let _ = fh.write_all(b"ab");
+    }
+
+    let mut store: Store<u32> = Store::new(StoreOpt::default().set_root(root)).unwrap();
+    let r: Res<Repeats> = store.get_by(&key, ctx, ProcessedMethod).unwrap();
+
+    assert_eq!(r.borrow().0, "");
+    assert_eq!(*ctx, 1);
+
+    // changing the parameters processes again without importing again
+    store.set_process_params::<_, Repeats>(&key, 3, ctx).unwrap();
+
+    assert_eq!(r.borrow().0, "ababab");
+    assert_eq!(*ctx, 1);
+  })
+}
+
+#[test]
+fn handles() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("foo.txt")).unwrap();
+      let _ = fh.write_all(b"foo");
+    }
+
+    let handle = store.handle::<_, Foo>(&FSKey::new("/foo.txt")).unwrap();
+    let a = store.get_handle(&handle, ctx).unwrap();
+    let b: Res<Foo> = store.get(&FSKey::new("/foo.txt"), ctx).unwrap();
+
+    // handles and keys refer to the same resource
+    a.borrow_mut().0.push('!');
+    assert_eq!(b.borrow().0, "foo!");
+    assert_eq!(store.get_handle(&handle, ctx).unwrap().borrow().0, "foo!");
+
+    // other methods load resources of their own
+    let stupid = store.get_handle_by(&handle, ctx, Stupid).unwrap();
+    assert_eq!(stupid.borrow().0, "stupid");
+
+    let stupid_handle = store.handle_by::<_, Foo, _>(&FSKey::new("/foo.txt"), Stupid).unwrap();
+    stupid.borrow_mut().0.push('!');
+    let stupid = store.get_handle_by(&stupid_handle, ctx, Stupid).unwrap();
+    assert_eq!(stupid.borrow().0, "stupid!");
+
+    assert!(store.handle::<_, Foo>(&FSKey::new("/../escaping.txt")).is_err());
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn store_metrics() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("measured.txt");
+    let path = store.root().join("measured.txt");
+    let timeout = ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let _: Res<Foo> = store.get(&key, ctx).unwrap();
+    let _: Res<Foo> = store.get(&key, ctx).unwrap();
+
+    let metrics = store.metrics();
+    assert_eq!(metrics.resident, 1);
+    assert_eq!((metrics.cache_hits, metrics.cache_misses), (1, 1));
+    assert_eq!(metrics.hit_ratio(), Some(0.5));
+    assert!(metrics.last_reload_durations.is_empty());
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    assert!(store.wait_for_reload(key.clone(), timeout, ctx));
+
+    let metrics = store.metrics();
+    assert!(metrics.reloads_succeeded >= 1);
+    assert_eq!(metrics.reloads_failed, 0);
+    assert!(metrics.last_reload_durations.contains_key(&store.resolve(&key.into())));
+  })
+}
+
+#[test]
+fn inserted_resources() {
+  utils::with_store_opt(
+    |opt| opt.set_dependency_check(warmy::DependencyCheck::Deny),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("/generated.txt");
+
+      let generated = store.insert(&key, Foo("generated".to_owned()).into()).unwrap();
+      assert!(store.insert(&key, Foo("again".to_owned()).into()).is_err());
+
+      let got: Res<Foo> = store.get(&key, ctx).unwrap();
+      assert_eq!(got.borrow().0, "generated");
+
+      let got = store
+        .get_or_insert_with(&key, || -> Loaded<Foo> { unreachable!() })
+        .unwrap();
+      generated.borrow_mut().0.push('!');
+      assert_eq!(got.borrow().0, "generated!");
+
+      // other resources can depend on inserted ones
+      let dependent = LogicalKey::new("dependent");
+      let loaded = Loaded::with_deps(LogicalFoo("dependent".to_owned()), vec![key.clone().into()]);
+      store.insert(&dependent, loaded).unwrap();
+
+      let graph = store.dependency_graph();
+      let dep_key = store.resolve(&key.into());
+      assert!(graph.edges().contains(&(dep_key, dependent.into())));
+    },
+  )
+}
+
+#[test]
+fn reload_all() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("foo.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let log_foo: Res<LogicalFoo> = store.get(&LogicalKey::new("/foo.txt"), ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    // the dependency is reloaded first, so that its dependent sees its new content
+    let report = store.reload_all(ctx);
+    assert!(report.is_ok());
+    assert_eq!(report.reloaded, 2);
+    assert_eq!(log_foo.borrow().0, "after");
+
+    let report = store.reload_type::<Foo>(ctx);
+    assert_eq!(report.reloaded, 1);
+  })
+}
+
+// refuses to load when its dependency is broken
+#[derive(Debug)]
+struct Picky(String);
+
+impl<C> Load<C> for Picky {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let fs_key = FSKey::new(key.as_str());
+    let foo: Res<Foo> = storage.get(&fs_key, ctx).map_err(|_| FooErr)?;
+    let content = foo.borrow().0.clone();
+
+    if content == "broken" {
+      return Err(FooErr);
+    }
+
+    Ok(Loaded::with_deps(Picky(content), vec![fs_key.into()]))
+  }
+}
+
+#[test]
+fn transactional_reloads() {
+  use std::cell::Cell;
+  use std::rc::Rc;
+  use warmy::ReloadOutcome;
+
+  utils::with_store_opt(
+    |opt| opt.set_transactional_reloads(true),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("/picked.txt");
+      let path = store.root().join("picked.txt");
+
+      for content in &["good", "better"] {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(content.as_bytes());
+        drop(fh);
+
+        let picky: Res<Picky> = store.get(&LogicalKey::new("/picked.txt"), ctx).unwrap();
+        store.mark_dirty(key.clone());
+        store.sync(ctx);
+
+        assert_eq!(picky.borrow().0, *content);
+      }
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"broken");
+      }
+
+      let foo: Res<Foo> = store.get(&key, ctx).unwrap();
+      let generation = foo.generation();
+      let provenance = store.provenance(&key.clone().into()).cloned();
+      let storage_generation = store.generation();
+      let notified = Rc::new(Cell::new(0));
+      let notified_ = notified.clone();
+      foo.on_reload(move |_| notified_.set(notified_.get() + 1));
+      let events = store.events();
+
+      // the dependent fails to reload, so the dependency gets its old value back
+      store.mark_dirty(key.clone());
+      let report = store.sync(ctx);
+
+      assert_eq!(report.rollbacks, 1);
+      assert_eq!(report.reloads_failed, 1);
+      assert_eq!(report.reloads_rolled_back, 1);
+      assert!(report.reloaded.is_empty());
+      assert_eq!(foo.borrow().0, "better");
+
+      // the rolled back reload never happened
+      assert_eq!(foo.generation(), generation);
+      assert_eq!(notified.get(), 0);
+      assert_eq!(store.provenance(&key.clone().into()).cloned(), provenance);
+      assert!(store.diff(storage_generation, store.generation()).changed.is_empty());
+
+      let outcomes: Vec<_> = events.try_iter().map(|event| event.outcome).collect();
+      assert_eq!(outcomes.len(), 2);
+      assert_eq!(outcomes[0], ReloadOutcome::RolledBack);
+      assert!(match outcomes[1] {
+        ReloadOutcome::Failed(_) => true,
+        _ => false,
+      });
+
+      // committed cascades notify the observers once
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"best");
+      }
+
+      store.mark_dirty(key.clone());
+      let report = store.sync(ctx);
+
+      assert_eq!(report.rollbacks, 0);
+      assert_eq!(report.reloaded.len(), 2);
+      assert_eq!(foo.generation(), generation + 1);
+      assert_eq!(notified.get(), 1);
+    },
+  )
+}
+
+#[test]
+fn reload_error_policy() {
+  use warmy::ReloadErrorPolicy;
+
+  for &policy in &[ReloadErrorPolicy::ReplaceWithProxy, ReloadErrorPolicy::Evict] {
+    utils::with_store_opt(
+      |opt| opt.set_reload_error_policy(policy),
+      |mut store: Store<()>| {
+        let ctx = &mut ();
+        let key = FSKey::new("/deleted.txt");
+        let path = store.root().join("deleted.txt");
+
+        {
+          let mut fh = File::create(&path).unwrap();
+          let _ = fh.write_all(b"present");
+        }
+
+        let r: Res<Decoded> = store.get(&key, ctx).unwrap();
+        store.set_proxy(|| Decoded("proxy".to_owned()));
+
+        // deleting the file makes the reload fail
+        ::std::fs::remove_file(&path).unwrap();
+        store.mark_dirty(key.clone());
+        let report = store.sync(ctx);
+        assert_eq!(report.reloads_failed, 1);
+
+        match policy {
+          ReloadErrorPolicy::ReplaceWithProxy => assert_eq!(r.borrow().0, "proxy"),
+          _ => assert!(!store.contains::<Decoded>(&key)),
+        }
+      },
+    )
+  }
+}
+
+#[test]
+fn default_fallback() {
+  use warmy::methods::Text;
+
+  utils::with_store_opt(
+    |opt| opt.set_fallback_to_defaults(true),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = FSKey::new("/missing.txt");
+      store.register_default(|| Decoded("magenta".to_owned()));
+
+      // types without a default still fail
+      assert!(store.get::<_, Text>(&key, ctx).is_err());
+
+      let r: Res<Decoded> = store.get(&key, ctx).unwrap();
+      assert_eq!(r.borrow().0, "magenta");
+
+      // the actual resource replaces the default once its source is there
+      {
+        let mut fh = File::create(store.root().join("missing.txt")).unwrap();
+        let _ = fh.write_all(b"present");
+      }
+
+      store.mark_dirty(key.clone());
+      store.sync(ctx);
+      assert_eq!(r.borrow().0, "PRESENT");
+    },
+  )
+}
+
+#[test]
+fn default_without_fallback() {
+  utils::with_store_opt(
+    |opt| opt.set_fallback_to_defaults(false),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      store.register_default(|| Decoded("magenta".to_owned()));
+
+      // defaults are only used for failed reloads unless told otherwise
+      assert!(store.get::<_, Decoded>(&FSKey::new("/missing.txt"), ctx).is_err());
+    },
+  )
+}
+
+#[test]
+fn try_borrow() {
+  let r = Res::new(Foo("foo".to_owned()));
+  assert!(!r.is_borrowed());
+
+  {
+    let _read = r.borrow();
+    assert!(r.is_borrowed());
+    assert!(!r.is_borrowed_mut());
+    assert!(r.try_borrow().is_ok());
+    assert!(r.try_borrow_mut().is_err());
+  }
+
+  {
+    let _write = r.borrow_mut();
+    assert!(r.is_borrowed_mut());
+    assert!(r.try_borrow().is_err());
+  }
+
+  assert_eq!(r.try_borrow_mut().unwrap().0, "foo");
+}
+
+#[test]
+fn res_views() {
+  let r = Res::new(Foo("foo".to_owned()));
+  let content = r.map(|foo| &foo.0);
+  let first = content.map(|content| &content[..1]);
+
+  assert_eq!(*content.borrow(), "foo");
+  assert_eq!(&*first.borrow(), "f");
+
+  // views share the slot of the resource
+  *r.borrow_mut() = Foo("bar".to_owned());
+  assert_eq!(&*first.borrow(), "b");
+
+  let _write = r.borrow_mut();
+  assert!(content.try_borrow().is_err());
+}
+
+#[test]
+fn weak_res() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("/weak.txt");
+    let weak = store.insert(&key, Foo("weak".to_owned()).into()).unwrap().downgrade();
+
+    // the store keeps the resource alive
+    assert_eq!(weak.upgrade().unwrap().borrow().0, "weak");
+
+    let strong: Res<Foo> = store.get(&key, ctx).unwrap();
+    store.evict(&key.into());
+    assert!(weak.upgrade().is_some());
+
+    drop(strong);
+    assert!(weak.upgrade().is_none());
+  })
+}
+
+#[test]
+fn snapshots() {
+  let r = Res::new(vec![1, 2, 3]);
+  let snapshot = r.snapshot();
+  let len = r.snapshot_with(|v| v.len());
+
+  r.borrow_mut().push(4);
+
+  // snapshots don’t borrow the resource
+  assert_eq!(snapshot, vec![1, 2, 3]);
+  assert_eq!(len, 3);
+  assert!(!r.is_borrowed());
+}
+
+#[test]
+fn self_contained() {
+  utils::with_tmp_dir(|tmp_dir| {
+    // written before the store starts watching, so that only mark_dirty reloads the resource
+    {
+      let mut fh = File::create(tmp_dir.join("counted.txt")).unwrap();
+      let _ = fh.write_all(b"counted");
+    }
+
+    let opt = StoreOpt::default().set_root(tmp_dir).set_update_await_time_ms(0);
+    let store: Store<Ctx> = Store::new(opt).unwrap();
+    let mut store = store.with_context(Ctx { count: 0 });
+    let key = FSKey::new("/counted.txt");
+    let r: Res<FooWithCtx> = store.get(&key).unwrap();
+    let _: Res<FooWithCtx> = store.get(&key).unwrap();
+
+    assert_eq!(r.borrow().0, "counted");
+    assert_eq!(store.context().count, 1);
+
+    store.sync();
+    store.with(|store, _| store.mark_dirty(FSKey::new("/counted.txt")));
+    store.sync();
+
+    let (_, ctx) = store.into_inner();
+    assert_eq!(ctx.count, 2);
+  })
+}
+
+#[derive(Debug)]
+struct Fetched(usize);
+
+impl<C> Load<C> for Fetched
+where C: warmy::Fetch
+{
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(_: Self::Key, _: &mut Storage<C>, ctx: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    let counter = ctx.fetch::<usize>().ok_or(FooErr)?;
+    *counter += 1;
+    Ok(Fetched(*counter).into())
+  }
+}
+
+#[test]
+fn type_map_context() {
+  utils::with_store(|mut store: Store<warmy::TypeMapContext>| {
+    let mut ctx = warmy::TypeMapContext::new().with(41usize).with("unrelated");
+
+    let r: Res<Fetched> = store.get(&LogicalKey::new("fetched"), &mut ctx).unwrap();
+    assert_eq!(r.borrow().0, 42);
+    assert_eq!(ctx.get::<&str>(), Some(&"unrelated"));
+    assert_eq!(ctx.len(), 2);
+
+    // loaders report missing values
+    ctx.remove::<usize>();
+    assert!(store.get::<_, Fetched>(&LogicalKey::new("missing"), &mut ctx).is_err());
+  })
+}
+
+struct Reported;
+
+impl<C> Load<C> for Reported {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    use std::io::Read;
+
+    let progress = storage.load_progress().unwrap();
+    assert_eq!(progress.snapshot().status, warmy::LoadStatus::Loading);
+
+    let bytes = b"0123456789";
+    progress.set_total(bytes.len() as u64);
+
+    let mut buf = [0; 4];
+    progress.reader(&bytes[..]).read_exact(&mut buf).unwrap();
+    progress.set_stage("decoding");
+
+    assert_eq!(progress.snapshot().fraction(), Some(0.4));
+
+    if key.as_str() == "broken" {
+      Err(FooErr)
+    } else {
+      Ok(Reported.into())
+    }
+  }
+}
+
+#[test]
+fn load_progress() {
+  utils::with_store_opt(
+    |opt| opt.set_progress_tracking(true),
+    |mut store: Store<()>| {
+      assert_eq!(store.load_progress().map(|p| p.snapshot()), None);
+      assert_eq!(store.progress().fraction(), None);
+
+      let _: Res<Reported> = store.get(&LogicalKey::new("ok"), &mut ()).unwrap();
+      assert!(store.get::<_, Reported>(&LogicalKey::new("broken"), &mut ()).is_err());
+
+      let report = store.progress();
+      assert_eq!(report.resources.len(), 2);
+      assert_eq!(report.resources[0].key, LogicalKey::new("ok").into());
+      assert_eq!(report.resources[0].stage, Some("decoding".to_owned()));
+      assert_eq!(report.resources[0].bytes_read, 4);
+      assert_eq!(report.resources[0].bytes_total, Some(10));
+      assert_eq!((report.loaded(), report.failed(), report.loading()), (1, 1, 0));
+      assert!(report.is_done());
+      assert_eq!(report.fraction(), Some(1.));
+
+      store.clear_progress();
+      assert!(store.progress().resources.is_empty());
+    },
+  )
+}
+
+struct Abortable;
+
+impl<C> Load<C> for Abortable {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    if key.as_str() == "abort" {
+      // simulate the player backing out while loading
+      storage.cancellation_token().cancel();
+    }
+
+    if storage.load_cancelled() {
+      Err(FooErr)
+    } else {
+      Ok(Abortable.into())
+    }
+  }
+}
+
+#[test]
+fn cancelable_loads() {
+  utils::with_store(|mut store: Store<()>| {
+    let a = store.request::<_, Abortable>(&LogicalKey::new("a"));
+    let b = store.request::<_, Abortable>(&LogicalKey::new("b"));
+    let c = store.request::<_, Abortable>(&LogicalKey::new("c"));
+    assert_eq!(store.pending_requests(), 3);
+    assert!(a.is_pending() && b.is_pending() && c.is_pending());
+
+    // cancelled requests are removed from the queue
+    b.cancel();
+    assert!(b.is_cancelled() && !b.is_pending());
+    assert_eq!(store.pending_requests(), 2);
+
+    assert_eq!(store.load_requests(&mut (), 1), 1);
+    assert!(a.take().unwrap().is_ok());
+    assert!(a.take().is_none());
+    assert!(c.is_pending());
+
+    assert_eq!(store.load_requests(&mut (), 10), 1);
+    assert!(c.take().unwrap().is_ok());
+    assert!(b.take().is_none());
+    assert_eq!(store.pending_requests(), 0);
+    assert!(store.get::<_, Abortable>(&LogicalKey::new("a"), &mut ()).is_ok());
+
+    // loaders see cancellations while loading
+    let aborted = store.request::<_, Abortable>(&LogicalKey::new("abort"));
+    store.load_requests(&mut (), 1);
+    store.cancellation_token().reset();
+    assert!(aborted.take().unwrap().is_err());
+  })
+}
+
+#[test]
+fn load_once() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("once.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"once");
+    }
+
+    let once: LogicalFoo = store.load_once(&LogicalKey::new("once.txt"), ctx).unwrap();
+    assert_eq!(once.0, "once");
+
+    // only the dependency fetched by the loader is kept around
+    assert_eq!(store.metrics().resident, 1);
+    assert!(!store.contains::<LogicalFoo>(&LogicalKey::new("once.txt")));
+  })
+}
+
+#[test]
+fn dyn_resources() {
+  use std::any::TypeId;
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    let zoo: Res<Zoo> = store.get(&LogicalKey::new("animal"), ctx).unwrap();
+    let key: DepKey = LogicalKey::new("animal").into();
+
+    // script bindings only know about keys and type ids
+    let any = store.get_dyn(&key, TypeId::of::<Zoo>()).unwrap();
+    assert!(any.is::<Zoo>());
+    assert!(any.type_name().ends_with("Zoo"));
+    assert_eq!(any.downcast::<Zoo>().unwrap().borrow().0, "animal");
+
+    zoo.borrow_mut().0 = "changed".to_owned();
+    assert_eq!(any.downcast::<Zoo>().unwrap().borrow().0, "changed");
+
+    assert!(store.get_dyn(&key, TypeId::of::<Strict>()).is_none());
+    assert!(store.get_dyn(&LogicalKey::new("none").into(), TypeId::of::<Zoo>()).is_none());
+
+    let all = store.get_all_dyn(&key);
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].type_id(), TypeId::of::<Zoo>());
+  })
+}
+
+#[test]
+fn loaders_by_extension() {
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("hero.txt")).unwrap();
+      let _ = fh.write_all(b"hero");
+    }
+
+    store.registry_mut().register_extension::<Foo, (), _>(".TXT");
+    assert!(store.registry().contains_extension("txt"));
+    assert_eq!(store.registry().extensions().collect::<Vec<_>>(), vec!["txt"]);
+
+    // the file list comes from content, not code
+    let any = store.get_any_path("/hero.txt", ctx).unwrap();
+    assert_eq!(any.downcast::<Foo>().unwrap().borrow().0, "hero");
+
+    // the resource is shared with typed accesses
+    let foo: Res<Foo> = store.get(&FSKey::new("/hero.txt"), ctx).unwrap();
+    foo.borrow_mut().0 = "villain".to_owned();
+    assert_eq!(any.downcast::<Foo>().unwrap().borrow().0, "villain");
+
+    assert!(store.get_any_path("/hero.gltf", ctx).is_err());
+    assert!(store.get_any_path("/hero", ctx).is_err());
+
+    assert!(store.registry_mut().unregister_extension("txt"));
+    assert!(store.get_any_path("/hero.txt", ctx).is_err());
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn wake_up() {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
+
+  let wake_ups = Arc::new(AtomicUsize::new(0));
+  let wake_ups_ = wake_ups.clone();
+
+  utils::with_store_opt(
+    move |opt| {
+      let wake_ups = wake_ups_.clone();
+      opt.set_waker(move || {
+        wake_ups.fetch_add(1, Ordering::SeqCst);
+      })
+    },
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let path = store.root().join("woken.txt");
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"before");
+      }
+
+      let r: Res<Foo> = store.get(&FSKey::new("woken.txt"), ctx).unwrap();
+      utils::settle(&mut store, ctx);
+      assert!(!store.sync_needed());
+      wake_ups.store(0, Ordering::SeqCst);
+
+      {
+        let mut fh = File::create(&path).unwrap();
+        let _ = fh.write_all(b"after");
+      }
+
+      // an event loop would sleep until woken up
+      let start_time = ::std::time::Instant::now();
+      while wake_ups.load(Ordering::SeqCst) == 0 {
+        if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+          panic!(
+            "more than {} milliseconds were spent waiting for a filesystem event",
+            QUEUE_TIMEOUT_MS
+          );
+        }
+
+        ::std::thread::sleep(::std::time::Duration::from_millis(1));
+      }
+
+      assert!(store.sync_needed());
+
+      let report = store.sync(ctx);
+      assert!(report.events_dequeued > 0);
+      assert_eq!(r.borrow().0, "after");
+
+      // the write might still be yielding events
+      utils::settle(&mut store, ctx);
+      assert!(!store.sync_needed());
+
+      // resources marked dirty by hand need a synchronization too
+      store.mark_dirty(FSKey::new("woken.txt"));
+      assert!(store.sync_needed());
+    },
+  )
+}
+
+#[test]
+fn next_sync_deadline() {
+  utils::with_store_opt(
+    |opt| opt.set_update_await_time_ms(50),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = LogicalKey::new("settling");
+
+      let _: Res<Zoo> = store.get(&key, ctx).unwrap();
+      assert_eq!(store.next_sync_deadline(), None);
+
+      // a settling resource is due once its update await time has elapsed
+      let touched = ::std::time::Instant::now();
+      store.mark_dirty(key);
+      let report = store.sync(ctx);
+      assert_eq!(report.reloads_attempted, 0);
+
+      let deadline = store.next_sync_deadline().unwrap();
+      assert!(deadline >= touched + ::std::time::Duration::from_millis(50));
+
+      let now = ::std::time::Instant::now();
+      if deadline > now {
+        ::std::thread::sleep(deadline - now);
+      }
+
+      let report = store.sync(ctx);
+      assert_eq!(report.reloads_succeeded, 1);
+      assert_eq!(store.next_sync_deadline(), None);
+    },
+  )
+}
+
+#[cfg(all(feature = "async", feature = "watcher"))]
+#[test]
+fn sync_stream() {
+  use futures_core::Stream;
+  use std::pin::Pin;
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::sync::Arc;
+  use std::task::{Context, Poll, Wake, Waker};
+
+  struct Woken(AtomicBool);
+
+  impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+      self.0.store(true, Ordering::SeqCst);
+    }
+  }
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("streamed.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let r: Res<Foo> = store.get(&FSKey::new("streamed.txt"), ctx).unwrap();
+    utils::settle(&mut store, ctx);
+
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut reports = store.sync_stream(ctx);
+
+    // nothing to reload yet
+    assert!(Pin::new(&mut reports).poll_next(&mut cx).is_pending());
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    // an executor would only poll the stream again once woken up by the watcher
+    let start_time = ::std::time::Instant::now();
+    let report = loop {
+      if woken.0.swap(false, Ordering::SeqCst) {
+        if let Poll::Ready(report) = Pin::new(&mut reports).poll_next(&mut cx) {
+          break report.expect("endless stream");
+        }
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+
+      ::std::thread::sleep(::std::time::Duration::from_millis(1));
+    };
+
+    assert_eq!(report.reloads_succeeded, 1);
+    assert_eq!(r.borrow().0, "after");
+  })
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn sync_stream_settle() {
+  use futures_core::Stream;
+  use std::future;
+  use std::pin::Pin;
+
+  utils::with_store_opt(
+    |opt| opt.set_update_await_time_ms(50),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let key = LogicalKey::new("settling");
+      let r: Res<Zoo> = store.get(&key, ctx).unwrap();
+
+      let touched = ::std::time::Instant::now();
+      store.mark_dirty(key);
+
+      // the stream sleeps until the resource has settled instead of yielding an empty report
+      let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+      let mut reports = store.sync_stream(ctx);
+      let report = runtime
+        .block_on(future::poll_fn(|cx| Pin::new(&mut reports).poll_next(cx)))
+        .expect("endless stream");
+
+      assert_eq!(report.reloads_succeeded, 1);
+      assert!(touched.elapsed() >= ::std::time::Duration::from_millis(50));
+      assert_eq!(r.generation(), 1);
+    },
+  )
+}
+
+#[cfg(all(feature = "async", feature = "watcher"))]
+#[test]
+fn run_sync_loop() {
+  use std::future::{self, Future};
+  use std::pin::Pin;
+  use std::task::Poll;
+  use std::time::{Duration, Instant};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let path = store.root().join("looped.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"before");
+    }
+
+    let r: Res<Foo> = store.get(&FSKey::new("looped.txt"), ctx).unwrap();
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"after");
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .unwrap();
+    let mut sync_loop = store.run_sync_loop(ctx, Duration::from_millis(10));
+    let start_time = Instant::now();
+
+    // the loop never completes; drive it until the change shows up
+    runtime.block_on(future::poll_fn(|cx| {
+      assert!(Pin::new(&mut sync_loop).poll(cx).is_pending());
+
+      if r.borrow().0 == "after" {
+        return Poll::Ready(());
+      }
+
+      if start_time.elapsed() >= Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+
+      Poll::Pending
+    }));
+  })
+}
+
+struct Urgent;
+
+impl Load<Vec<&'static str>> for Urgent {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    _: Self::Key,
+    _: &mut Storage<Vec<&'static str>>,
+    ctx: &mut Vec<&'static str>,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    ctx.push("urgent");
+    Ok(Urgent.into())
+  }
+
+  fn reload_priority() -> i32 {
+    10
+  }
+}
+
+struct Idle;
+
+impl Load<Vec<&'static str>> for Idle {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    _: Self::Key,
+    _: &mut Storage<Vec<&'static str>>,
+    ctx: &mut Vec<&'static str>,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    ctx.push("idle");
+    Ok(Idle.into())
+  }
+}
+
+#[test]
+fn reload_priorities() {
+  utils::with_store_opt(
+    |opt| opt,
+    |mut store: Store<Vec<&'static str>>| {
+      let mut ctx = Vec::new();
+
+      let _: Res<Idle> = store.get(&LogicalKey::new("a"), &mut ctx).unwrap();
+      let _: Res<Urgent> = store.get(&LogicalKey::new("b"), &mut ctx).unwrap();
+      ctx.clear();
+
+      // the type with the highest priority reloads first
+      store.mark_dirty(LogicalKey::new("a"));
+      store.mark_dirty(LogicalKey::new("b"));
+      store.sync(&mut ctx);
+      assert_eq!(ctx, vec!["urgent", "idle"]);
+      ctx.clear();
+
+      // overrides take precedence
+      store.set_reload_priority_for(LogicalKey::new("a"), Some(20));
+      store.mark_dirty(LogicalKey::new("b"));
+      store.mark_dirty(LogicalKey::new("a"));
+      store.sync(&mut ctx);
+      assert_eq!(ctx, vec!["idle", "urgent"]);
+      ctx.clear();
+
+      store.set_reload_priority_for(LogicalKey::new("a"), None);
+      store.mark_dirty(LogicalKey::new("a"));
+      store.mark_dirty(LogicalKey::new("b"));
+      store.sync(&mut ctx);
+      assert_eq!(ctx, vec!["urgent", "idle"]);
+    },
+  )
+}
+
+#[test]
+fn preload_manifest() {
+  use warmy::ManifestFile;
+
+  utils::with_store_opt(
+    |opt| opt,
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let root = store.root().to_owned();
+
+      ::std::fs::create_dir(root.join("C:")).unwrap();
+
+      for name in &["a.txt", "b.txt", "c.txt", "C:/d.txt"] {
+        let mut fh = File::create(root.join(name)).unwrap();
+        let _ = fh.write_all(name.as_bytes());
+      }
+
+      // colons only delimit loaders when followed by a space
+      {
+        let mut fh = File::create(root.join("assets.list")).unwrap();
+        let _ = fh.write_all(b"# text files\n/a.txt\n\nfoo: /b.txt\n/hero.gltf\nC:/d.txt\n");
+      }
+
+      store.registry_mut().register::<Foo, (), _>("foo");
+      store.registry_mut().register_extension::<Foo, (), _>("txt");
+
+      let manifest: Res<ManifestFile> =
+        store.preload_manifest(&FSKey::new("/assets.list"), ctx).unwrap();
+
+      {
+        let manifest = manifest.borrow();
+        let entries = manifest.entries();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1].loader, Some("foo".to_owned()));
+        assert_eq!(entries[3].loader, None);
+        assert!(entries[0].outcome.is_ok() && entries[1].outcome.is_ok());
+        assert!(entries[3].outcome.is_ok());
+        assert_eq!(
+          manifest.failures().map(|e| e.path.clone()).collect::<Vec<_>>(),
+          vec![::std::path::PathBuf::from("/hero.gltf")]
+        );
+      }
+
+      assert!(store.contains::<Foo>(&FSKey::new("/a.txt")));
+      assert!(store.contains::<Foo>(&FSKey::new("/b.txt")));
+      assert!(!store.contains::<Foo>(&FSKey::new("/c.txt")));
+
+      // editing the manifest loads the newly listed resources
+      {
+        let mut fh = File::create(root.join("assets.list")).unwrap();
+        let _ = fh.write_all(b"/a.txt\n/c.txt\n");
+      }
+
+      store.mark_dirty(FSKey::new("/assets.list"));
+      store.sync(ctx);
+
+      assert!(manifest.borrow().is_ok());
+      assert!(store.contains::<Foo>(&FSKey::new("/c.txt")));
+    },
+  )
+}
+
+// concatenation of the files of a directory, in name order
+#[derive(Debug)]
+struct Listing(String);
+
+fn read_dir_sorted(dir: &::std::path::Path) -> String {
+  let mut paths: Vec<_> = ::std::fs::read_dir(dir)
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .collect();
+  paths.sort();
+
+  paths
+    .iter()
+    .map(|path| ::std::fs::read_to_string(path).unwrap())
+    .collect()
+}
+
+impl<C> Load<C> for Listing {
+  type Key = warmy::DirKey;
+
+  type Error = FooErr;
+
+  fn load(key: Self::Key, _: &mut Storage<C>, _: &mut C) -> Result<Loaded<Self>, Self::Error> {
+    Ok(Listing(read_dir_sorted(key.as_path())).into())
+  }
+}
+
+// number of files of a directory, only depending on it
+#[derive(Debug)]
+struct FileCount(usize);
+
+impl<C> Load<C> for FileCount {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    key: Self::Key,
+    storage: &mut Storage<C>,
+    _: &mut C,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    let dir = storage.root().join(key.as_str());
+    let count = ::std::fs::read_dir(dir).unwrap().count();
+    let dep = warmy::DirKey::new(key.as_str()).into();
+
+    Ok(Loaded::with_deps(FileCount(count), vec![dep]))
+  }
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn dir_resources() {
+  use std::time::Duration;
+  use warmy::DirKey;
+
+  utils::with_store_opt(
+    |opt| opt,
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let dir = store.root().join("levels");
+      ::std::fs::create_dir(&dir).unwrap();
+
+      let write = |name: &str, content: &str| {
+        let mut fh = File::create(dir.join(name)).unwrap();
+        let _ = fh.write_all(content.as_bytes());
+      };
+
+      write("1.lvl", "a");
+
+      let listing: Res<Listing> = store.get(&DirKey::new("/levels"), ctx).unwrap();
+      let count: Res<FileCount> = store.get(&LogicalKey::new("levels"), ctx).unwrap();
+      assert_eq!(listing.borrow().0, "a");
+      assert_eq!(count.borrow().0, 1);
+
+      // writes might be seen in several steps; wait for the last one
+      let mut settle = |store: &mut Store<()>, done: &Fn() -> bool| {
+        let start_time = ::std::time::Instant::now();
+
+        while !done() {
+          if start_time.elapsed() >= Duration::from_millis(QUEUE_TIMEOUT_MS) {
+            panic!(
+              "more than {} milliseconds were spent waiting for a filesystem event",
+              QUEUE_TIMEOUT_MS
+            );
+          }
+
+          store.sync(ctx);
+        }
+      };
+
+      // modified files
+      write("1.lvl", "b");
+      settle(&mut store, &|| listing.borrow().0 == "b");
+
+      // created files
+      write("2.lvl", "c");
+      settle(&mut store, &|| listing.borrow().0 == "bc" && count.borrow().0 == 2);
+    },
+  )
+}
+
+#[test]
+fn literal_glob() {
+  use warmy::{GlobKey, ResourceSet};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("a.txt")).unwrap();
+      let _ = fh.write_all(b"a");
+    }
+
+    // a pattern without wildcards selects the single file it names
+    let key = GlobKey::new("/a.txt");
+    assert_eq!(key.base(), ::std::path::Path::new("/"));
+
+    let set: Res<ResourceSet<Foo>> = store.get_all(&key, ctx).unwrap();
+    let a = set.borrow().get(&FSKey::new("/a.txt")).cloned();
+    assert_eq!(set.borrow().len(), 1);
+    assert_eq!(a.map(|a| a.borrow().0.clone()), Some("a".to_owned()));
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn glob_resources() {
+  use std::time::Duration;
+  use warmy::{GlobKey, ResourceSet};
+
+  utils::with_store_opt(
+    |opt| opt,
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let dir = store.root().join("textures");
+      ::std::fs::create_dir_all(dir.join("hero")).unwrap();
+
+      let write = |name: &str, content: &str| {
+        let mut fh = File::create(dir.join(name)).unwrap();
+        let _ = fh.write_all(content.as_bytes());
+      };
+
+      write("sky.png", "sky");
+      write("hero/idle.png", "idle");
+      write("readme.txt", "not a texture");
+
+      let textures: Res<ResourceSet<Foo>> = store
+        .get_all(&GlobKey::new("/textures/**/*.png"), ctx)
+        .unwrap();
+      let top: Res<ResourceSet<Foo>> = store
+        .get_all(&GlobKey::new("/textures/*.png"), ctx)
+        .unwrap();
+
+      let members = |set: &Res<ResourceSet<Foo>>| -> Vec<String> {
+        set.borrow().iter().map(|(_, res)| res.borrow().0.clone()).collect()
+      };
+
+      assert_eq!(members(&textures), vec!["idle", "sky"]);
+      assert_eq!(members(&top), vec!["sky"]);
+
+      // members are shared with the store
+      let sky: Res<Foo> = store.get(&FSKey::new("/textures/sky.png"), ctx).unwrap();
+      let member = textures.borrow().get(&FSKey::new("/textures/sky.png")).cloned();
+      assert_eq!(member.map(|res| res.borrow().0.clone()), Some(sky.borrow().0.clone()));
+
+      // writes might be seen in several steps; wait for the last one
+      let mut settle = |store: &mut Store<()>, done: &Fn() -> bool| {
+        let start_time = ::std::time::Instant::now();
+
+        while !done() {
+          if start_time.elapsed() >= Duration::from_millis(QUEUE_TIMEOUT_MS) {
+            panic!(
+              "more than {} milliseconds were spent waiting for a filesystem event",
+              QUEUE_TIMEOUT_MS
+            );
+          }
+
+          store.sync(ctx);
+        }
+      };
+
+      // created files
+      write("hero/run.png", "run");
+      settle(&mut store, &|| members(&textures) == vec!["idle", "run", "sky"]);
+      assert_eq!(members(&top), vec!["sky"]);
+
+      // modified members
+      write("sky.png", "night");
+      settle(&mut store, &|| members(&textures) == vec!["idle", "run", "night"]);
+      assert_eq!(sky.borrow().0, "night");
+    },
+  )
+}
+
+#[test]
+fn source_missing() {
+  use warmy::{ReloadOutcome, ResState};
+
+  utils::with_store(|mut store: Store<()>| {
+    let ctx = &mut ();
+    let key = FSKey::new("/broken.txt");
+    let path = store.root().join("broken.txt");
+    let write = |content: &[u8]| {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(content);
+    };
+
+    write(b"before");
+
+    let events = store.events();
+    let r: Res<Foo> = store.get(&key, ctx).unwrap();
+    assert_eq!(r.state(), ResState::Live);
+
+    // the stale value lives on, flagged
+    ::std::fs::remove_file(&path).unwrap();
+    store.mark_dirty(key.clone());
+    let report = store.sync(ctx);
+    assert_eq!(report.reloads_failed, 1);
+    assert_eq!(r.state(), ResState::SourceMissing);
+    assert_eq!(r.borrow().0, "before");
+    assert_eq!(store.missing_sources().len(), 1);
+    assert_eq!(events.try_recv().unwrap().outcome, ReloadOutcome::SourceMissing);
+
+    // the file comes back
+    write(b"after");
+    store.mark_dirty(key.clone());
+    store.sync(ctx);
+    assert_eq!(r.state(), ResState::Live);
+    assert_eq!(r.borrow().0, "after");
+    assert!(store.missing_sources().is_empty());
+    assert_eq!(events.try_recv().unwrap().outcome, ReloadOutcome::Reloaded);
+  })
+}
+
+#[test]
+fn path_normalization() {
+  use warmy::PathNormalization;
+
+  utils::with_store_opt(
+    |opt| opt.set_path_normalization(PathNormalization::all()),
+    |mut store: Store<()>| {
+      let ctx = &mut ();
+      let dir = store.root().join("textures");
+      ::std::fs::create_dir(&dir).unwrap();
+
+      let write = |content: &str| {
+        let mut fh = File::create(dir.join("hero.txt")).unwrap();
+        let _ = fh.write_all(content.as_bytes());
+      };
+
+      write("hero");
+
+      // the same file, written differently, is loaded once
+      let a: Res<Foo> = store.get(&FSKey::new("/textures/hero.txt"), ctx).unwrap();
+      let b: Res<Foo> = store.get(&FSKey::new("\\Textures\\HERO.txt"), ctx).unwrap();
+      a.borrow_mut().0 = "shared".to_owned();
+      assert_eq!(b.borrow().0, "shared");
+      assert!(store.contains::<Foo>(&FSKey::new("/TEXTURES/Hero.TXT")));
+
+      write("reloaded");
+      store.mark_dirty(FSKey::new("\\textures/Hero.txt"));
+      store.sync(ctx);
+      assert_eq!(a.borrow().0, "reloaded");
+    },
+  )
+}
+
+#[cfg(all(unix, feature = "watcher"))]
+#[test]
+fn symlinked_resources() {
+  use std::os::unix::fs::symlink;
+  use std::path::Path;
+  use std::time::Duration;
+
+  utils::with_tmp_dir(|outside| {
+    let outside = outside.canonicalize().unwrap();
+    ::std::fs::create_dir(outside.join("levels")).unwrap();
+
+    let write = |path: &Path, content: &str| {
+      let mut fh = File::create(path).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    utils::with_store(|mut store: Store<()>| {
+      let ctx = &mut ();
+      let hero = outside.join("hero.txt");
+      let level = outside.join("levels").join("1.lvl");
+      write(&hero, "hero");
+      write(&level, "level");
+
+      // a symlinked file and a file living in a symlinked directory
+      symlink(&hero, store.root().join("hero.txt")).unwrap();
+      symlink(outside.join("levels"), store.root().join("levels")).unwrap();
+
+      let a: Res<Foo> = store.get(&FSKey::new("/hero.txt"), ctx).unwrap();
+      let b: Res<Foo> = store.get(&FSKey::new("/levels/1.lvl"), ctx).unwrap();
+
+      // writes might be seen in several steps; wait for the last one
+      let mut settle = |store: &mut Store<()>, done: &Fn() -> bool| {
+        let start_time = ::std::time::Instant::now();
+
+        while !done() {
+          if start_time.elapsed() >= Duration::from_millis(QUEUE_TIMEOUT_MS) {
+            panic!(
+              "more than {} milliseconds were spent waiting for a filesystem event",
+              QUEUE_TIMEOUT_MS
+            );
+          }
+
+          store.sync(ctx);
+        }
+      };
+
+      write(&hero, "hero, modified");
+      settle(&mut store, &|| a.borrow().0 == "hero, modified");
+
+      write(&level, "level, modified");
+      settle(&mut store, &|| b.borrow().0 == "level, modified");
+    })
+  })
+}
+
+#[cfg(unix)]
+#[test]
+fn lexical_root() {
+  use std::os::unix::fs::symlink;
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let real = tmp_dir.join("real");
+    let link = tmp_dir.join("link");
+    ::std::fs::create_dir(&real).unwrap();
+    symlink(&real, &link).unwrap();
+
+    let write = |content: &str| {
+      let mut fh = File::create(real.join("a.txt")).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    write("a");
+
+    // the root keeps its link, only normalized lexically
+    let opt = StoreOpt::default()
+      .set_root(link.join(".").join("missing").join(".."))
+      .set_canonicalize(false)
+      .set_update_await_time_ms(0);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    assert_eq!(store.root(), link.as_path());
+
+    let ctx = &mut ();
+    let a: Res<Foo> = store.get(&FSKey::new("/a.txt"), ctx).unwrap();
+    assert_eq!(a.borrow().0, "a");
+
+    write("b");
+    store.mark_dirty(FSKey::new("/a.txt"));
+    store.sync(ctx);
+    assert_eq!(a.borrow().0, "b");
+
+    // a missing root is still an error
+    let opt = StoreOpt::<()>::default()
+      .set_root(tmp_dir.join("nope"))
+      .set_canonicalize(false);
+    assert!(Store::new(opt).is_err());
+  })
+}
+
+#[test]
+fn store_error_sources() {
+  use std::io;
+  use warmy::StoreError;
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("missing");
+
+    for &canonicalize in &[true, false] {
+      let opt = StoreOpt::<()>::default()
+        .set_root(&root)
+        .set_canonicalize(canonicalize);
+
+      match Store::new(opt) {
+        Err(ref e @ StoreError::RootNotFound { .. }) => {
+          let source = e.source().expect("source of the error");
+          let source = source.downcast_ref::<io::Error>().expect("io::Error");
+          assert_eq!(source.kind(), io::ErrorKind::NotFound);
+          assert!(e.to_string().contains(&root.display().to_string()));
+        }
+
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("the root doesn’t exist"),
+      }
+    }
+  })
+}
+
+#[test]
+fn create_root() {
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("config").join("game");
+
+    let opt = StoreOpt::<()>::default()
+      .set_root(&root)
+      .set_create_root(true)
+      .set_update_await_time_ms(0);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    assert!(root.is_dir());
+
+    // the freshly created root is used as any other
+    {
+      let mut fh = File::create(root.join("a.txt")).unwrap();
+      let _ = fh.write_all(b"a");
+    }
+
+    let ctx = &mut ();
+    let a: Res<Foo> = store.get(&FSKey::new("/a.txt"), ctx).unwrap();
+    assert_eq!(a.borrow().0, "a");
+  })
+}
+
+#[cfg(feature = "watcher")]
+#[test]
+fn mount_points() {
+  use std::path::Path;
+  use warmy::methods::Text;
+  use warmy::StoreError;
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("assets");
+    let exports = tmp_dir.join("exports");
+    let ui = tmp_dir.join("ui");
+
+    for dir in &[&root, &exports, &ui] {
+      ::std::fs::create_dir(dir).unwrap();
+    }
+
+    let write = |path: &Path, content: &str| {
+      let mut fh = File::create(path).unwrap();
+      let _ = fh.write_all(content.as_bytes());
+    };
+
+    write(&root.join("config.txt"), "config");
+    write(&exports.join("hero.txt"), "hero");
+    write(&ui.join("button.txt"), "button");
+
+    let opt = StoreOpt::default()
+      .set_root(&root)
+      .set_update_await_time_ms(0)
+      .add_mount("/textures", &exports)
+      .add_mount("/textures/ui", &ui);
+    assert_eq!(opt.mounts().count(), 2);
+
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    // keys are routed to the deepest mount point, other ones stay under the root
+    let config: Res<Text> = store.get(&FSKey::new("/config.txt"), ctx).unwrap();
+    let hero: Res<Text> = store.get(&FSKey::new("/textures/hero.txt"), ctx).unwrap();
+    let button: Res<Text> = store.get(&FSKey::new("/textures/ui/button.txt"), ctx).unwrap();
+    assert_eq!(config.borrow().0, "config");
+    assert_eq!(hero.borrow().0, "hero");
+    assert_eq!(button.borrow().0, "button");
+
+    // mounted directories are watched
+    write(&exports.join("hero.txt"), "villain");
+
+    let start_time = ::std::time::Instant::now();
+    loop {
+      store.sync(ctx);
+
+      if hero.borrow().0 == "villain" {
+        break;
+      }
+
+      if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+        panic!(
+          "more than {} milliseconds were spent waiting for a filesystem event",
+          QUEUE_TIMEOUT_MS
+        );
+      }
+    }
+
+    // a missing mounted directory is an error
+    let opt = StoreOpt::<()>::default()
+      .set_root(&root)
+      .add_mount("/sounds", tmp_dir.join("nope"));
+
+    match Store::new(opt) {
+      Err(StoreError::MountNotFound { path, .. }) => assert_eq!(path, tmp_dir.join("nope")),
+      Err(e) => panic!("unexpected error: {}", e),
+      Ok(_) => panic!("the mounted directory doesn’t exist"),
+    }
+  })
+}
+
+#[test]
+fn mounted_snapshot() {
+  use warmy::methods::Text;
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("assets");
+    let exports = tmp_dir.join("exports");
+
+    for dir in &[&root, &exports] {
+      ::std::fs::create_dir(dir).unwrap();
+    }
+
+    {
+      let mut fh = File::create(exports.join("hero.txt")).unwrap();
+      let _ = fh.write_all(b"hero");
+    }
+
+    let opt = StoreOpt::default()
+      .set_root(&root)
+      .add_mount("/textures", &exports);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    store.set_freezable::<Text>();
+    let _: Res<Text> = store.get(&FSKey::new("/textures/hero.txt"), ctx).unwrap();
+
+    // snapshots route keys the same way the store does
+    let snapshot = store.freeze();
+    let hero = snapshot.get::<_, Text>(&FSKey::new("/textures/hero.txt"));
+    assert_eq!(hero.map(|hero| hero.0.clone()), Some("hero".to_owned()));
+  })
+}
+
+#[test]
+fn mounted_preload() {
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("assets");
+    let exports = tmp_dir.join("exports");
+
+    for dir in &[&root, &exports] {
+      ::std::fs::create_dir(dir).unwrap();
+    }
+
+    {
+      let mut fh = File::create(exports.join("hero.txt")).unwrap();
+      let _ = fh.write_all(b"hero");
+    }
+
+    let opt = StoreOpt::default()
+      .set_root(&root)
+      .add_mount("/textures", &exports);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    // worker threads prepare mounted keys from their mounted directories
+    let manifest = Manifest::new().add_prepared::<_, Decoded>(FSKey::new("/textures/hero.txt"));
+    let report = store.preload(&manifest, ctx);
+    assert_eq!(report.loaded, 1);
+    assert!(report.failures.is_empty());
+
+    let hero: Res<Decoded> = store.get(&FSKey::new("/textures/hero.txt"), ctx).unwrap();
+    assert_eq!(hero.borrow().0, "HERO");
+  })
+}
+
+#[test]
+fn mounted_glob() {
+  use warmy::methods::Text;
+  use warmy::{GlobKey, ResourceMap, ResourceMapKey, ResourceSet};
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("assets");
+    let exports = tmp_dir.join("exports");
+
+    for dir in &[&root, &exports] {
+      ::std::fs::create_dir(dir).unwrap();
+    }
+
+    {
+      let mut fh = File::create(exports.join("hero.txt")).unwrap();
+      let _ = fh.write_all(b"hero");
+    }
+
+    let opt = StoreOpt::default()
+      .set_root(&root)
+      .add_mount("/textures", &exports);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    // files found in mounted directories are keyed by their VFS paths
+    let set: Res<ResourceSet<Text>> =
+      store.get_all(&GlobKey::new("/textures/*.txt"), ctx).unwrap();
+    let hero = set.borrow().get(&FSKey::new("/textures/hero.txt")).cloned();
+    assert_eq!(set.borrow().len(), 1);
+    assert_eq!(hero.map(|hero| hero.borrow().0.clone()), Some("hero".to_owned()));
+
+    let key = ResourceMapKey::new("/textures").with_extension("txt");
+    let map: Res<ResourceMap<Text>> = store.get(&key, ctx).unwrap();
+    let hero = map.borrow().get(&FSKey::new("/textures/hero.txt")).cloned();
+    assert_eq!(map.borrow().len(), 1);
+    assert_eq!(hero.map(|hero| hero.borrow().0.clone()), Some("hero".to_owned()));
+  })
+}
+
+#[cfg(feature = "remote")]
+#[test]
+fn mounted_remote_message() {
+  use warmy::methods::Text;
+  use warmy::remote::ReloadMessage;
+
+  utils::with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("assets");
+    let exports = tmp_dir.join("exports");
+
+    for dir in &[&root, &exports] {
+      ::std::fs::create_dir(dir).unwrap();
+    }
+
+    {
+      let mut fh = File::create(exports.join("hero.txt")).unwrap();
+      let _ = fh.write_all(b"hero");
+    }
+
+    let opt = StoreOpt::default()
+      .set_root(&root)
+      .add_mount("/textures", &exports);
+    let mut store: Store<()> = Store::new(opt).unwrap();
+    let ctx = &mut ();
+
+    let _: Res<Text> = store.get(&FSKey::new("/textures/hero.txt"), ctx).unwrap();
+
+    // mounted resources are sent with their VFS paths, not the paths of the server
+    let dep_key = store.keys_of::<Text>().next().unwrap().clone();
+    let msg = ReloadMessage::from_storage(&store, &dep_key, true);
+    assert_eq!(msg.key, FSKey::new("/textures/hero.txt").into());
+    assert_eq!(msg.payload, Some(b"hero".to_vec()));
+  })
+}
+
+// depends on several files at once and counts its loads in the context
+#[derive(Debug)]
+struct Asset(String);
+
+impl Load<u32> for Asset {
+  type Key = LogicalKey;
+
+  type Error = FooErr;
+
+  fn load(
+    _: Self::Key,
+    storage: &mut Storage<u32>,
+    ctx: &mut u32,
+  ) -> Result<Loaded<Self>, Self::Error>
+  {
+    *ctx += 1;
+
+    let mut content = String::new();
+    let mut deps = Vec::new();
+
+    for name in &["/mesh.txt", "/material.txt", "/texture.txt"] {
+      let fs_key = FSKey::new(name);
+      let foo: Res<Foo> = storage.get(&fs_key, ctx).map_err(|_| FooErr)?;
+      content.push_str(&foo.borrow().0);
+      deps.push(fs_key.into());
+    }
+
+    Ok(Loaded::with_deps(Asset(content), deps))
+  }
+}
+
+#[test]
+fn coalesce_reloads() {
+  utils::with_store_opt(
+    |opt| opt.set_coalesce_reloads(true).set_update_await_time_ms(0),
+    |mut store: Store<u32>| {
+      let ctx = &mut 0;
+      let names = ["mesh.txt", "material.txt", "texture.txt"];
+
+      for name in &names {
+        let mut fh = File::create(store.root().join(name)).unwrap();
+        let _ = fh.write_all(b"a");
+      }
+
+      let asset: Res<Asset> = store.get(&LogicalKey::new("/asset"), ctx).unwrap();
+      assert_eq!(*ctx, 1);
+
+      for name in &names {
+        let mut fh = File::create(store.root().join(name)).unwrap();
+        let _ = fh.write_all(b"b");
+        drop(fh);
+
+        store.mark_dirty(FSKey::new(&format!("/{}", name)));
+      }
+
+      // the asset is reloaded once, after its three dependencies
+      let report = store.sync(ctx);
+      assert_eq!(*ctx, 2);
+      assert_eq!(report.reloads_coalesced, 2);
+      assert_eq!(asset.borrow().0, "bbb");
+
+      // even when it’s dirty itself
+      for name in &names {
+        let mut fh = File::create(store.root().join(name)).unwrap();
+        let _ = fh.write_all(b"c");
+        drop(fh);
+
+        store.mark_dirty(FSKey::new(&format!("/{}", name)));
+      }
+
+      store.mark_dirty(LogicalKey::new("/asset"));
+      let report = store.sync(ctx);
+      assert_eq!(*ctx, 3);
+      assert_eq!(report.reloads_coalesced, 3);
+      assert_eq!(asset.borrow().0, "ccc");
+    },
+  )
+}
@@ -6,6 +6,11 @@ extern crate warmy;
 use self::tempdir::TempDir;
 pub use std::fs::File;
 use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time without any filesystem event after which a store is considered settled.
+const QUIET_MS: u64 = 200;
 
 pub fn with_tmp_dir<F, B>(f: F)
 where F: Fn(&Path) -> B {
@@ -16,12 +21,38 @@ where F: Fn(&Path) -> B {
 
 pub fn with_store<F, B, C>(f: F)
 where F: Fn(warmy::Store<C>) -> B {
+  with_store_opt(|opt| opt, f)
+}
+
+pub fn with_store_opt<O, F, B, C>(o: O, f: F)
+where
+  O: Fn(warmy::StoreOpt<C>) -> warmy::StoreOpt<C>,
+  F: Fn(warmy::Store<C>) -> B,
+{
   with_tmp_dir(|tmp_dir| {
     let opt = warmy::StoreOpt::default()
       .set_root(tmp_dir.to_owned())
       .set_update_await_time_ms(0);
 
-    let store = warmy::Store::new(opt).expect("create store");
+    let store = warmy::Store::new(o(opt)).expect("create store");
     f(store)
   })
 }
+
+/// Synchronize the store until no filesystem event has shown up for a while.
+///
+/// A single write yields several events (create, modify, close…) that might still be arriving after
+/// the first reload; call this before asserting that nothing else happens.
+pub fn settle<C>(store: &mut warmy::Store<C>, ctx: &mut C) {
+  let quiet = Duration::from_millis(QUIET_MS);
+  let mut last_event = Instant::now();
+
+  while last_event.elapsed() < quiet {
+    if store.sync_needed() {
+      store.sync(ctx);
+      last_event = Instant::now();
+    } else {
+      thread::sleep(Duration::from_millis(10));
+    }
+  }
+}
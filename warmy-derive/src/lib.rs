@@ -0,0 +1,146 @@
+//! Custom derive for warmy’s `Load` trait.
+//!
+//! This crate is re-exported by warmy with its `derive` feature; you shouldn’t have to depend on
+//! it directly.
+//!
+//! `#[derive(Load)]` implements `Load<C>` – i.e. with the default method – for any type that can
+//! already be loaded with one of the serde-backed methods of `warmy::methods`, by delegating to it.
+//! The format is picked with the `#[warmy(format = "…")]` attribute:
+//!
+//! ```ignore
+//! #[derive(Deserialize, Load)]
+//! #[warmy(format = "json")]
+//! struct Config {
+//!   name: String,
+//! }
+//! ```
+//!
+//! The key type is `warmy::FSKey` and the error type is the one of the chosen method. Recognized
+//! formats are `auto`, `bincode`, `json`, `msgpack`, `ron`, `toml` and `yaml`; the matching
+//! feature must be enabled on warmy. Generic types are not supported.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+#[proc_macro_derive(Load, attributes(warmy))]
+pub fn derive_load(input: TokenStream) -> TokenStream {
+  let code = match expand(input) {
+    Ok(code) => code,
+    Err(e) => format!("compile_error!({:?});", e),
+  };
+
+  code.parse().unwrap()
+}
+
+/// Generate the implementation of `Load<C>` for the input type.
+fn expand(input: TokenStream) -> Result<String, String> {
+  let mut tokens = input.into_iter().peekable();
+  let mut format = None;
+  let mut name = None;
+
+  while let Some(token) = tokens.next() {
+    match token {
+      // attribute
+      TokenTree::Punct(ref p) if p.as_char() == '#' => {
+        if let Some(TokenTree::Group(group)) = tokens.next() {
+          if let Some(f) = parse_warmy_attr(group.stream())? {
+            format = Some(f);
+          }
+        }
+      }
+
+      TokenTree::Ident(ref ident) if is_item_keyword(&ident.to_string()) => {
+        match tokens.next() {
+          Some(TokenTree::Ident(ident)) => name = Some(ident.to_string()),
+          _ => return Err("expected a type name".to_owned()),
+        }
+
+        if let Some(&TokenTree::Punct(ref p)) = tokens.peek() {
+          if p.as_char() == '<' {
+            return Err("#[derive(Load)] doesn’t support generic types".to_owned());
+          }
+        }
+
+        break;
+      }
+
+      _ => (),
+    }
+  }
+
+  let name = name.ok_or_else(|| "#[derive(Load)] expects a struct or an enum".to_owned())?;
+  let format = format.ok_or_else(|| {
+    "#[derive(Load)] requires a format; add #[warmy(format = \"…\")]".to_owned()
+  })?;
+  let method = method_of_format(&format)?;
+
+  Ok(format!(
+    "impl<C> ::warmy::Load<C> for {name} {{
+       type Key = ::warmy::FSKey;
+
+       type Error = <Self as ::warmy::Load<C, {method}>>::Error;
+
+       fn load(
+         key: Self::Key,
+         storage: &mut ::warmy::Storage<C>,
+         ctx: &mut C,
+       ) -> ::std::result::Result<::warmy::Loaded<Self>, Self::Error> {{
+         <Self as ::warmy::Load<C, {method}>>::load(key, storage, ctx)
+       }}
+     }}",
+    name = name,
+    method = method
+  ))
+}
+
+fn is_item_keyword(s: &str) -> bool {
+  s == "struct" || s == "enum"
+}
+
+/// Parse the inside of an attribute and return the format if it’s a `warmy` attribute.
+fn parse_warmy_attr(attr: TokenStream) -> Result<Option<String>, String> {
+  let mut tokens = attr.into_iter();
+
+  match tokens.next() {
+    Some(TokenTree::Ident(ref ident)) if ident.to_string() == "warmy" => (),
+    _ => return Ok(None),
+  }
+
+  let args = match tokens.next() {
+    Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => {
+      group.stream().into_iter().collect::<Vec<_>>()
+    }
+    _ => return Err("expected #[warmy(format = \"…\")]".to_owned()),
+  };
+
+  match args.as_slice() {
+    [TokenTree::Ident(ref key), TokenTree::Punct(ref eq), TokenTree::Literal(ref lit)]
+      if key.to_string() == "format" && eq.as_char() == '=' =>
+    {
+      let lit = lit.to_string();
+
+      if lit.len() >= 2 && lit.starts_with('"') && lit.ends_with('"') {
+        Ok(Some(lit[1..lit.len() - 1].to_owned()))
+      } else {
+        Err("the format must be a string literal".to_owned())
+      }
+    }
+
+    _ => Err("expected #[warmy(format = \"…\")]".to_owned()),
+  }
+}
+
+/// Path to the method type matching a format.
+fn method_of_format(format: &str) -> Result<&'static str, String> {
+  match format {
+    "auto" => Ok("::warmy::methods::Auto"),
+    "bincode" => Ok("::warmy::methods::Bincode"),
+    "json" => Ok("::warmy::methods::Json"),
+    "msgpack" => Ok("::warmy::methods::MessagePack"),
+    "ron" => Ok("::warmy::methods::Ron"),
+    "toml" => Ok("::warmy::methods::Toml"),
+    "yaml" => Ok("::warmy::methods::Yaml"),
+    _ => Err(format!("unknown format: {}", format)),
+  }
+}